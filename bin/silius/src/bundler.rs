@@ -6,7 +6,7 @@ use crate::{
     utils::unwrap_path_or_home,
 };
 use alloy_chains::{Chain, NamedChain};
-use ethers::{providers::Middleware, types::Address};
+use ethers::{providers::Middleware, signers::Signer, types::Address};
 use parking_lot::RwLock;
 use silius_bundler::{ConditionalClient, EthereumClient, FlashbotsClient};
 use silius_contracts::EntryPoint;
@@ -16,9 +16,9 @@ use silius_grpc::{
 };
 use silius_mempool::{
     init_env,
-    validate::validator::{new_canonical, new_canonical_unsafe},
-    CodeHashes, DatabaseTable, EntitiesReputation, Mempool, Reputation, UserOperations,
-    UserOperationsByEntity, UserOperationsBySender, WriteMap,
+    validate::validator::{new_alternative, new_canonical, new_canonical_unsafe},
+    BundleHistory, CodeHashes, DatabaseTable, EntitiesReputation, Mempool, Reputation,
+    UserOperations, UserOperationsByEntity, UserOperationsBySender, WriteMap,
 };
 use silius_metrics::{launch_metrics_exporter, mempool::MetricsHandler};
 use silius_primitives::{
@@ -31,14 +31,20 @@ use silius_primitives::{
             BAN_SLACK, MIN_INCLUSION_RATE_DENOMINATOR, MIN_UNSTAKE_DELAY, THROTTLING_SLACK,
         },
     },
+    entry_point::EntryPointVersion,
+    mempool::load_alt_mempools,
     provider::BlockStream,
     reputation::ReputationEntry,
     simulation::CodeHash,
+    validation::{load_validation_rules, ValidationRules},
     UserOperationHash, UserOperationSigned, Wallet,
 };
 use silius_rpc::{
+    admin_api::{AdminApiServer, AdminApiServerImpl},
     debug_api::{DebugApiServer, DebugApiServerImpl},
     eth_api::{EthApiServer, EthApiServerImpl},
+    eth_pubsub_api::{EthPubSubApiServer, EthPubSubApiServerImpl},
+    silius_api::{SiliusApiServer, SiliusApiServerImpl},
     web3_api::{Web3ApiServer, Web3ApiServerImpl},
     JsonRpcServer, JsonRpcServerType,
 };
@@ -63,12 +69,14 @@ pub async fn launch_bundler<M>(
 where
     M: Middleware + Clone + 'static,
 {
+    let entry_points = common_args.entry_points_with_versions()?;
+
     launch_uopool(
         uopool_args.clone(),
         eth_client.clone(),
         block_streams,
         common_args.chain,
-        common_args.entry_points.clone(),
+        entry_points.clone(),
         metrics_args.clone(),
     )
     .await?;
@@ -77,7 +85,7 @@ where
         bundler_args.clone(),
         eth_client.clone(),
         common_args.chain,
-        common_args.entry_points,
+        entry_points,
         format!("http://{:?}:{:?}", uopool_args.uopool_addr, uopool_args.uopool_port),
         metrics_args.clone(),
     )
@@ -102,7 +110,7 @@ pub async fn launch_bundling<M>(
     args: BundlerArgs,
     eth_client: Arc<M>,
     chain: Option<NamedChain>,
-    entry_points: Vec<Address>,
+    entry_points: Vec<(Address, EntryPointVersion)>,
     uopool_grpc_listen_address: String,
     metrics_args: MetricsArgs,
 ) -> eyre::Result<()>
@@ -122,14 +130,41 @@ where
 
     let wallet: Wallet;
     if args.send_bundle_mode == SendStrategy::Flashbots {
-        wallet = Wallet::from_file(args.mnemonic_file.into(), chain_id, true)
-            .map_err(|error| eyre::format_err!("Could not load mnemonic file: {}", error))?;
+        if args.mnemonic_indices.len() > 1 || !args.key_files.is_empty() {
+            return Err(eyre::format_err!("Flashbots mode supports only a single signer"));
+        }
+        // the signer is derived at the same index as in the other modes
+        wallet = Wallet::from_file_with_indices(
+            args.mnemonic_file.clone().into(),
+            args.mnemonic_indices.clone(),
+            chain_id,
+            true,
+        )
+        .map_err(|error| eyre::format_err!("Could not load mnemonic file: {}", error))?;
         info!("Wallet Signer {:?}", wallet.signer);
         info!("Flashbots Signer {:?}", wallet.flashbots_signer);
     } else {
-        wallet = Wallet::from_file(args.mnemonic_file.into(), chain_id, false)
-            .map_err(|error| eyre::format_err!("Could not load mnemonic file: {}", error))?;
-        info!("{:?}", wallet.signer);
+        wallet = Wallet::from_file_with_indices(
+            args.mnemonic_file.clone().into(),
+            args.mnemonic_indices.clone(),
+            chain_id,
+            false,
+        )
+        .and_then(|wallet| {
+            wallet
+                .with_key_files(args.key_files.iter().cloned().map(Into::into).collect(), chain_id)
+        })
+        .map_err(|error| eyre::format_err!("Could not load wallet: {}", error))?;
+        for signer in wallet.signers() {
+            info!("{:?}", signer);
+        }
+    }
+
+    let funding_policy = args
+        .funding_policy(chain_id)
+        .map_err(|error| eyre::format_err!("Could not load treasury key file: {}", error))?;
+    if let Some(policy) = &funding_policy {
+        info!("Treasury {:?}", policy.treasury.address());
     }
 
     info!("Connecting to uopool gRPC service...");
@@ -152,6 +187,10 @@ where
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
+                args.profit_policy(),
+                args.tracker_config(),
+                funding_policy,
+                args.watch_policy(),
             );
         }
         SendStrategy::Conditional => {
@@ -169,6 +208,10 @@ where
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
+                args.profit_policy(),
+                args.tracker_config(),
+                funding_policy,
+                args.watch_policy(),
             );
         }
         SendStrategy::Flashbots => {
@@ -206,6 +249,10 @@ where
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
+                args.profit_policy(),
+                args.tracker_config(),
+                funding_policy,
+                args.watch_policy(),
             );
         }
     }
@@ -220,7 +267,7 @@ pub async fn launch_uopool<M>(
     eth_client: Arc<M>,
     block_streams: Vec<BlockStream>,
     chain: Option<NamedChain>,
-    entry_points: Vec<Address>,
+    entry_points: Vec<(Address, EntryPointVersion)>,
     metrics_args: MetricsArgs,
 ) -> eyre::Result<()>
 where
@@ -260,7 +307,10 @@ where
                 Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
                 Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
                 Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
-            );
+            )
+            .with_bundle_history(Box::new(
+                DatabaseTable::<WriteMap, BundleHistory>::new(env.clone()),
+            ));
             let mut reputation = Reputation::new(
                 MIN_INCLUSION_RATE_DENOMINATOR,
                 THROTTLING_SLACK,
@@ -279,21 +329,7 @@ where
             (mempool, reputation)
         }
         StorageType::Memory => {
-            let mempool = Mempool::new(
-                Box::new(Arc::new(RwLock::new(MetricsHandler::new(HashMap::<
-                    UserOperationHash,
-                    UserOperationSigned,
-                >::default())))),
-                Box::new(Arc::new(RwLock::new(
-                    HashMap::<Address, HashSet<UserOperationHash>>::default(),
-                ))),
-                Box::new(Arc::new(RwLock::new(
-                    HashMap::<Address, HashSet<UserOperationHash>>::default(),
-                ))),
-                Box::new(Arc::new(RwLock::new(
-                    HashMap::<UserOperationHash, Vec<CodeHash>>::default(),
-                ))),
-            );
+            let mempool = memory_mempool();
             let reputation = Reputation::new(
                 MIN_INCLUSION_RATE_DENOMINATOR,
                 THROTTLING_SLACK,
@@ -310,14 +346,58 @@ where
             (mempool, reputation)
         }
     };
+    let rules = match args.validation_rules {
+        Some(path) => load_validation_rules(&path)?,
+        None => ValidationRules::default(),
+    };
+    if !rules.disabled().is_empty() {
+        warn!("Validation rules {:?} are disabled", rules.disabled());
+    }
+
+    // alternative mempools of other chains or entry points are ignored
+    let alt_mempools = match args.alt_mempools {
+        Some(path) => load_alt_mempools(&path)?
+            .into_iter()
+            .filter(|alt_mempool| {
+                let supported = alt_mempool.chain_id == chain.id() &&
+                    entry_points.iter().any(|(ep, _)| *ep == alt_mempool.entry_point);
+                if !supported {
+                    warn!(
+                        "Alternative mempool {} (chain {}, entry point {:?}) is not supported",
+                        alt_mempool.id, alt_mempool.chain_id, alt_mempool.entry_point
+                    );
+                }
+                supported
+            })
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
+
     match args.uopool_mode {
         silius_primitives::UoPoolMode::Standard => {
+            // alternative mempools are kept in memory
+            let alt_mempools = alt_mempools
+                .into_iter()
+                .map(|alt_mempool| {
+                    let validator = new_alternative(
+                        entrypoint_api.clone(),
+                        chain,
+                        args.max_verification_gas,
+                        args.min_priority_fee_per_gas,
+                        &alt_mempool,
+                    )
+                    .with_rules(rules.clone());
+                    (alt_mempool, validator, memory_mempool())
+                })
+                .collect();
+
             let validator = new_canonical(
                 entrypoint_api,
                 chain,
                 args.max_verification_gas,
                 args.min_priority_fee_per_gas,
-            );
+            )
+            .with_rules(rules);
 
             uopool_service_run(
                 SocketAddr::new(args.uopool_addr, args.uopool_port),
@@ -327,22 +407,31 @@ where
                 block_streams,
                 chain,
                 args.max_verification_gas,
+                args.min_priority_fee_per_gas,
                 mempool,
                 reputation,
                 validator,
+                alt_mempools,
                 p2p_config,
                 metrics_args.enable_metrics,
+                args.bundle_strategy,
+                args.execution_gas_margin,
             )
             .await?;
             info!("Started uopool gRPC service at {:?}:{:?}", args.uopool_addr, args.uopool_port);
         }
         silius_primitives::UoPoolMode::Unsafe => {
+            if !alt_mempools.is_empty() {
+                warn!("Alternative mempools are not supported in the unsafe mode");
+            }
+
             let validator = new_canonical_unsafe(
                 entrypoint_api,
                 chain,
                 args.max_verification_gas,
                 args.min_priority_fee_per_gas,
-            );
+            )
+            .with_rules(rules);
             uopool_service_run(
                 SocketAddr::new(args.uopool_addr, args.uopool_port),
                 args.uopool_mode,
@@ -351,11 +440,15 @@ where
                 block_streams,
                 chain,
                 args.max_verification_gas,
+                args.min_priority_fee_per_gas,
                 mempool,
                 reputation,
                 validator,
+                vec![],
                 p2p_config,
                 metrics_args.enable_metrics,
+                args.bundle_strategy,
+                args.execution_gas_margin,
             )
             .await?;
             info!("Started uopool gRPC service at {:?}:{:?}", args.uopool_addr, args.uopool_port);
@@ -365,6 +458,19 @@ where
     Ok(())
 }
 
+/// Creates a mempool that is kept in memory
+fn memory_mempool() -> Mempool {
+    Mempool::new(
+        Box::new(Arc::new(RwLock::new(MetricsHandler::new(HashMap::<
+            UserOperationHash,
+            UserOperationSigned,
+        >::default())))),
+        Box::new(Arc::new(RwLock::new(HashMap::<Address, HashSet<UserOperationHash>>::default()))),
+        Box::new(Arc::new(RwLock::new(HashMap::<Address, HashSet<UserOperationHash>>::default()))),
+        Box::new(Arc::new(RwLock::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()))),
+    )
+}
+
 pub async fn launch_rpc(
     args: RpcArgs,
     uopool_grpc_listen_address: String,
@@ -423,6 +529,11 @@ pub async fn launch_rpc(
                 EthApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }.into_rpc(),
                 JsonRpcServerType::Ws,
             )?;
+            server.add_methods(
+                EthPubSubApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }
+                    .into_rpc(),
+                JsonRpcServerType::Ws,
+            )?;
         }
     }
 
@@ -444,7 +555,41 @@ pub async fn launch_rpc(
 
         if ws_api.contains("debug") {
             server.add_methods(
-                DebugApiServerImpl { uopool_grpc_client, bundler_grpc_client }.into_rpc(),
+                DebugApiServerImpl {
+                    uopool_grpc_client: uopool_grpc_client.clone(),
+                    bundler_grpc_client,
+                }
+                .into_rpc(),
+                JsonRpcServerType::Ws,
+            )?;
+        }
+    }
+
+    if args.is_api_method_enabled("admin") {
+        if http_api.contains("admin") {
+            server.add_methods(
+                AdminApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }.into_rpc(),
+                JsonRpcServerType::Http,
+            )?;
+        }
+        if ws_api.contains("admin") {
+            server.add_methods(
+                AdminApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }.into_rpc(),
+                JsonRpcServerType::Ws,
+            )?;
+        }
+    }
+
+    if args.is_api_method_enabled("silius") {
+        if http_api.contains("silius") {
+            server.add_methods(
+                SiliusApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }.into_rpc(),
+                JsonRpcServerType::Http,
+            )?;
+        }
+        if ws_api.contains("silius") {
+            server.add_methods(
+                SiliusApiServerImpl { uopool_grpc_client }.into_rpc(),
                 JsonRpcServerType::Ws,
             )?;
         }
@@ -1,30 +1,41 @@
 use crate::utils::{
-    parse_address, parse_duration, parse_enr, parse_label_value, parse_send_bundle_mode,
-    parse_u256, parse_uopool_mode,
+    parse_address, parse_bundle_strategy, parse_duration, parse_enr, parse_entry_point_version,
+    parse_label_value, parse_range, parse_send_bundle_mode, parse_u256, parse_uopool_mode,
 };
 use alloy_chains::{Chain, NamedChain};
 use clap::{Parser, ValueEnum};
 use discv5::Enr;
-use ethers::types::{Address, U256};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, U256},
+};
 use expanded_pathbuf::ExpandedPathBuf;
+use silius_bundler::{FundingPolicy, ProfitPolicy, TransactionTrackerConfig, WatchPolicy};
 use silius_metrics::label::LabelValue;
 use silius_p2p::{
     config::{gossipsub_config, Config, ConfigBuilder},
     listen_addr::{ListenAddr, ListenAddress},
 };
 use silius_primitives::{
-    bundler::SendStrategy,
+    bundler::{BundleStrategy, SendStrategy},
     chain::ChainSpec,
     constants::{
-        bundler::BUNDLE_INTERVAL,
+        bundler::{
+            BUMP_AFTER_BLOCKS, BUNDLE_INTERVAL, FEE_BUMP_PERC, MAX_FEE_BUMPS, WATCH_INTERVAL,
+        },
         grpc::{BUNDLER_PORT, MEMPOOL_PORT},
-        p2p::{NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME},
+        mempool::EXECUTION_GAS_MARGIN_PERC,
+        p2p::{NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME, NODE_PEERS_FILE_NAME},
         rpc::{HTTP_PORT, WS_PORT},
+        validation::reputation::MIN_UNSTAKE_DELAY,
     },
+    entry_point::EntryPointVersion,
     UoPoolMode,
 };
 use std::{
+    fs,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::Range,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -50,6 +61,16 @@ pub struct BundlerArgs {
     #[clap(long)]
     pub mnemonic_file: PathBuf,
 
+    /// The derivation path indices of the signers derived from the mnemonic (e.g., `0..4`).
+    ///
+    /// Bundles are sent by the signers in turns. By default, this option is set to `0..1`.
+    #[clap(long, default_value = "0..1", value_parser=parse_range)]
+    pub mnemonic_indices: Range<u32>,
+
+    /// Paths to files with private keys of additional signers.
+    #[clap(long, value_delimiter = ',')]
+    pub key_files: Vec<PathBuf>,
+
     /// The bundler beneficiary address.
     #[clap(long, value_parser=parse_address)]
     pub beneficiary: Address,
@@ -75,6 +96,145 @@ pub struct BundlerArgs {
     /// Indicates whether the access list is enabled.
     #[clap(long)]
     pub enable_access_list: bool,
+
+    /// Minimum simulated profit of the bundle (in wei).
+    ///
+    /// User operations that make the bundle less profitable are dropped from the bundle and stay
+    /// in the mempool. By default, the profitability is not checked.
+    #[clap(long, value_parser=parse_u256)]
+    pub min_bundle_profit: Option<U256>,
+
+    /// Minimum simulated profit of the bundle relative to its cost (in percentage).
+    #[clap(long)]
+    pub min_bundle_profit_margin: Option<u64>,
+
+    /// Number of blocks after which a pending bundle transaction is replaced with higher fees.
+    ///
+    /// By default, this option is set to `3`.
+    #[clap(long, default_value_t = BUMP_AFTER_BLOCKS)]
+    pub bump_after_blocks: u64,
+
+    /// Percentage the fees are increased by when a pending bundle transaction is replaced.
+    ///
+    /// By default, this option is set to `10`.
+    #[clap(long, default_value_t = FEE_BUMP_PERC)]
+    pub fee_bump_perc: u64,
+
+    /// Maximum number of fee bumps of a bundle transaction.
+    ///
+    /// By default, this option is set to `5`.
+    #[clap(long, default_value_t = MAX_FEE_BUMPS)]
+    pub max_fee_bumps: u64,
+
+    /// Path to the file with the private key of the treasury the signers are refunded from.
+    ///
+    /// Signers whose balance drops below the minimum balance are topped up with plain transfers.
+    /// By default, a signer with low balance is the beneficiary of its own bundles instead.
+    #[clap(long)]
+    pub treasury_key_file: Option<PathBuf>,
+
+    /// The balance the signers are topped up to from the treasury.
+    ///
+    /// By default, this option is set to twice the minimum balance.
+    #[clap(long, value_parser=parse_u256)]
+    pub signer_target_balance: Option<U256>,
+
+    /// The treasury balance below which warnings are logged.
+    ///
+    /// By default, this option is set to `1000000000000000000`.
+    #[clap(long, default_value = "1000000000000000000", value_parser=parse_u256)]
+    pub min_treasury_balance: U256,
+
+    /// Addresses of the paymasters and factories whose deposits and stakes are watched.
+    ///
+    /// The deposits, stakes and unstake delays are exported as metrics and warnings are logged
+    /// when they drop below the thresholds.
+    #[clap(long, value_delimiter=',', value_parser=parse_address)]
+    pub watch_entities: Vec<Address>,
+
+    /// The deposit of a watched entity below which warnings are logged.
+    ///
+    /// By default, this option is set to `100000000000000000`.
+    #[clap(long, default_value = "100000000000000000", value_parser=parse_u256)]
+    pub watch_min_deposit: U256,
+
+    /// The stake of a watched entity below which warnings are logged.
+    ///
+    /// By default, this option is set to `1`.
+    #[clap(long, default_value = "1", value_parser=parse_u256)]
+    pub watch_min_stake: U256,
+
+    /// The unstake delay (in seconds) of a watched entity below which warnings are logged.
+    ///
+    /// By default, this option is set to `86400`.
+    #[clap(long, default_value_t = MIN_UNSTAKE_DELAY)]
+    pub watch_min_unstake_delay: u64,
+
+    /// Time interval of checking the watched entities (in seconds).
+    ///
+    /// By default, this option is set to `60`.
+    #[clap(long, default_value_t = WATCH_INTERVAL)]
+    pub watch_interval: u64,
+}
+
+impl BundlerArgs {
+    /// The profit policy of the bundler, `None` if neither the minimum profit nor the minimum
+    /// profit margin is set.
+    pub fn profit_policy(&self) -> Option<ProfitPolicy> {
+        if self.min_bundle_profit.is_none() && self.min_bundle_profit_margin.is_none() {
+            return None;
+        }
+
+        Some(ProfitPolicy {
+            min_profit: self.min_bundle_profit.unwrap_or_default(),
+            min_margin_perc: self.min_bundle_profit_margin.unwrap_or_default(),
+        })
+    }
+
+    /// The configuration of the tracker that replaces stuck bundle transactions.
+    pub fn tracker_config(&self) -> TransactionTrackerConfig {
+        TransactionTrackerConfig {
+            bump_after_blocks: self.bump_after_blocks,
+            fee_bump_perc: self.fee_bump_perc,
+            max_fee_bumps: self.max_fee_bumps,
+            ..Default::default()
+        }
+    }
+
+    /// The policy of watching the paymasters and factories, `None` if no entity is watched.
+    pub fn watch_policy(&self) -> Option<WatchPolicy> {
+        if self.watch_entities.is_empty() {
+            return None;
+        }
+
+        Some(WatchPolicy {
+            entities: self.watch_entities.clone(),
+            min_deposit: self.watch_min_deposit,
+            min_stake: self.watch_min_stake,
+            min_unstake_delay: self.watch_min_unstake_delay,
+            interval: Duration::from_secs(self.watch_interval.max(1)),
+        })
+    }
+
+    /// The policy of refunding the signers from the treasury, `None` if the treasury key file is
+    /// not set.
+    pub fn funding_policy(&self, chain_id: u64) -> eyre::Result<Option<FundingPolicy>> {
+        let Some(path) = &self.treasury_key_file else {
+            return Ok(None);
+        };
+
+        let treasury = fs::read_to_string(ExpandedPathBuf::from(path.clone()))?
+            .trim()
+            .parse::<LocalWallet>()?
+            .with_chain_id(chain_id);
+        Ok(Some(FundingPolicy {
+            treasury,
+            target_balance: self
+                .signer_target_balance
+                .unwrap_or_else(|| self.min_balance.saturating_mul(2.into())),
+            min_treasury_balance: self.min_treasury_balance,
+        }))
+    }
 }
 
 /// UoPool CLI args
@@ -117,6 +277,33 @@ pub struct UoPoolArgs {
     #[clap(long, default_value = "standard", value_parser=parse_uopool_mode)]
     pub uopool_mode: UoPoolMode,
 
+    /// Path of the YAML file with the definitions of the alternative mempools.
+    ///
+    /// Every alternative mempool gets its own validation rules, mempool partition and gossip topic
+    /// (only in the `standard` mode).
+    #[clap(long)]
+    pub alt_mempools: Option<ExpandedPathBuf>,
+
+    /// Path of the YAML file that enables or disables the validation rules (ERC-7562) by their
+    /// ids, e.g. `OP-041: false` under `rules`.
+    ///
+    /// By default, all validation rules are enforced.
+    #[clap(long)]
+    pub validation_rules: Option<ExpandedPathBuf>,
+
+    /// Sets the strategy that picks user operations into the bundle.
+    ///
+    /// By default, this option is set to `default` (`maximize-profit`, `fifo` and `knapsack` are
+    /// also available).
+    #[clap(long, default_value = "default", value_parser=parse_bundle_strategy)]
+    pub bundle_strategy: BundleStrategy,
+
+    /// Percentage added to the gas a user operation used in the simulation when packing bundles.
+    ///
+    /// By default, this option is set to `10`.
+    #[clap(long, default_value_t = EXECUTION_GAS_MARGIN_PERC)]
+    pub execution_gas_margin: u64,
+
     /// P2P configuration
     #[clap(flatten)]
     pub p2p_opts: P2PArgs,
@@ -137,6 +324,12 @@ pub struct BundlerAndUoPoolArgs {
     #[clap(long, value_delimiter=',', value_parser=parse_address)]
     pub entry_points: Vec<Address>,
 
+    /// Versions of the entry points (`v0.6` or `v0.7`), in the same order as `--entry-points`.
+    ///
+    /// By default, the version is determined from the entry point address.
+    #[clap(long, value_delimiter=',', value_parser=parse_entry_point_version)]
+    pub entry_point_versions: Vec<EntryPointVersion>,
+
     /// Poll interval event filters and pending transactions in milliseconds.
     #[clap(long, default_value = "500", value_parser= parse_duration)]
     pub poll_interval: Duration,
@@ -169,7 +362,7 @@ pub struct RpcArgs {
     pub http_port: u16,
 
     /// Configures the HTTP RPC API modules.
-    #[clap(long = "http.api", value_delimiter=',', default_value = "eth", value_parser = ["eth", "debug", "web3"])]
+    #[clap(long = "http.api", value_delimiter=',', default_value = "eth", value_parser = ["eth", "debug", "web3", "admin", "silius"])]
     pub http_api: Vec<String>,
 
     /// Configures the allowed CORS domains.
@@ -199,7 +392,7 @@ pub struct RpcArgs {
     pub ws_port: u16,
 
     /// Configures the WS RPC API modules.
-    #[clap(long = "ws.api", value_delimiter=',', default_value = "eth", value_parser = ["eth", "debug", "web3"])]
+    #[clap(long = "ws.api", value_delimiter=',', default_value = "eth", value_parser = ["eth", "debug", "web3", "admin", "silius"])]
     pub ws_api: Vec<String>,
 
     /// Configures the allowed WS origins.
@@ -285,6 +478,33 @@ pub struct P2PArgs {
     pub node_enr: Option<PathBuf>,
 }
 
+impl BundlerAndUoPoolArgs {
+    /// The entry points with their versions (determined from the addresses if no version is set),
+    /// an error if the number of the versions doesn't match the number of the entry points.
+    pub fn entry_points_with_versions(&self) -> eyre::Result<Vec<(Address, EntryPointVersion)>> {
+        if self.entry_point_versions.is_empty() {
+            return Ok(self
+                .entry_points
+                .iter()
+                .map(|ep| (*ep, EntryPointVersion::from_address(ep)))
+                .collect());
+        }
+        if self.entry_point_versions.len() != self.entry_points.len() {
+            return Err(eyre::format_err!(
+                "Expected {} entry point versions, got {}",
+                self.entry_points.len(),
+                self.entry_point_versions.len()
+            ));
+        }
+        Ok(self
+            .entry_points
+            .iter()
+            .copied()
+            .zip(self.entry_point_versions.iter().copied())
+            .collect())
+    }
+}
+
 impl P2PArgs {
     /// Convert the P2PArgs to [silius_p2p::config::Config]
     pub fn to_config(&self, chain: &Chain, datadir: &Path) -> Config {
@@ -305,6 +525,7 @@ impl P2PArgs {
             } else {
                 datadir.join(NODE_ENR_FILE_NAME)
             })
+            .peers_file(datadir.join(NODE_PEERS_FILE_NAME))
             .listen_addr(listen_addr.clone())
             .ipv4_addr(self.p2p_broadcast_address)
             .enr_tcp4_port(Some(self.tcp4_port))
@@ -367,6 +588,8 @@ mod tests {
                 mnemonic_file: PathBuf::from(
                     "~/.silius/0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990"
                 ),
+                mnemonic_indices: 0..1,
+                key_files: vec![],
                 beneficiary: Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990")
                     .unwrap(),
                 min_balance: U256::from(100000000000000000_u64),
@@ -375,6 +598,19 @@ mod tests {
                 bundler_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 bundler_port: 3002,
                 enable_access_list: false,
+                min_bundle_profit: None,
+                min_bundle_profit_margin: None,
+                bump_after_blocks: 3,
+                fee_bump_perc: 10,
+                max_fee_bumps: 5,
+                treasury_key_file: None,
+                signer_target_balance: None,
+                min_treasury_balance: U256::from(1000000000000000000_u64),
+                watch_entities: vec![],
+                watch_min_deposit: U256::from(100000000000000000_u64),
+                watch_min_stake: U256::from(1),
+                watch_min_unstake_delay: 86400,
+                watch_interval: 60,
             },
             BundlerArgs::try_parse_from(args).unwrap()
         );
@@ -400,6 +636,7 @@ mod tests {
                 entry_points: vec![
                     Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990").unwrap()
                 ],
+                entry_point_versions: vec![],
                 poll_interval: Duration::from_millis(5000),
                 metrics: MetricsArgs {
                     enable_metrics: false,
@@ -412,6 +649,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bundler_and_uopool_args_entry_point_versions() {
+        let args = vec![
+            "bundleranduopoolargs",
+            "--entry-points",
+            "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789,0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990",
+            "--entry-point-versions",
+            "v0.6,v0.7",
+        ];
+        let args = BundlerAndUoPoolArgs::try_parse_from(args).unwrap();
+        assert_eq!(
+            args.entry_points_with_versions().unwrap(),
+            vec![
+                (
+                    Address::from_str("0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789").unwrap(),
+                    EntryPointVersion::V0_6
+                ),
+                (
+                    Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990").unwrap(),
+                    EntryPointVersion::V0_7
+                ),
+            ]
+        );
+
+        let args = vec![
+            "bundleranduopoolargs",
+            "--entry-points",
+            "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789,0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990",
+            "--entry-point-versions",
+            "v0.7",
+        ];
+        assert!(BundlerAndUoPoolArgs::try_parse_from(args)
+            .unwrap()
+            .entry_points_with_versions()
+            .is_err());
+    }
+
     #[test]
     fn rpc_args_when_http_and_ws_flag() {
         let args = vec![
@@ -104,7 +104,7 @@ impl BundlerCommand {
                 self.bundler,
                 eth_client,
                 self.common.chain,
-                self.common.entry_points,
+                self.common.entry_points_with_versions()?,
                 self.uopool_grpc_listen_address,
                 self.common.metrics,
             )
@@ -115,7 +115,7 @@ impl BundlerCommand {
                 self.bundler,
                 eth_client,
                 self.common.chain,
-                self.common.entry_points,
+                self.common.entry_points_with_versions()?,
                 self.uopool_grpc_listen_address,
                 self.common.metrics,
             )
@@ -153,7 +153,7 @@ impl UoPoolCommand {
                 eth_client,
                 block_streams,
                 self.common.chain,
-                self.common.entry_points,
+                self.common.entry_points_with_versions()?,
                 self.common.metrics,
             )
             .await?;
@@ -166,7 +166,7 @@ impl UoPoolCommand {
                 eth_client,
                 block_streams,
                 self.common.chain,
-                self.common.entry_points,
+                self.common.entry_points_with_versions()?,
                 self.common.metrics,
             )
             .await?;
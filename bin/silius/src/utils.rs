@@ -4,8 +4,12 @@ use ethers::types::{Address, U256};
 use expanded_pathbuf::ExpandedPathBuf;
 use pin_utils::pin_mut;
 use silius_metrics::label::LabelValue;
-use silius_primitives::{bundler::SendStrategy, UoPoolMode};
-use std::{future::Future, str::FromStr, time::Duration};
+use silius_primitives::{
+    bundler::{BundleStrategy, SendStrategy},
+    entry_point::EntryPointVersion,
+    UoPoolMode,
+};
+use std::{future::Future, ops::Range, str::FromStr, time::Duration};
 use tracing::info;
 
 /// Unwrap path or returns home directory
@@ -30,16 +34,39 @@ pub fn parse_u256(s: &str) -> Result<U256, String> {
     U256::from_str_radix(s, 10).map_err(|_| format!("String {s} is not a valid U256"))
 }
 
+/// Parses a range of indices (e.g., `0..4`) from string
+pub fn parse_range(s: &str) -> Result<Range<u32>, String> {
+    let (start, end) =
+        s.split_once("..").ok_or_else(|| format!("String {s} is not a valid range"))?;
+    let start = start.parse::<u32>().map_err(|_| format!("String {s} is not a valid range"))?;
+    let end = end.parse::<u32>().map_err(|_| format!("String {s} is not a valid range"))?;
+    if start >= end {
+        return Err(format!("Range {s} is empty"));
+    }
+    Ok(start..end)
+}
+
 /// Parses SendBundleMode from string
 pub fn parse_send_bundle_mode(s: &str) -> Result<SendStrategy, String> {
     SendStrategy::from_str(s).map_err(|_| format!("String {s} is not a valid SendBundleMode"))
 }
 
+/// Parses BundleStrategy from string
+pub fn parse_bundle_strategy(s: &str) -> Result<BundleStrategy, String> {
+    BundleStrategy::from_str(s).map_err(|_| format!("String {s} is not a valid BundleStrategy"))
+}
+
 /// Parses UoPoolMode from string
 pub fn parse_uopool_mode(s: &str) -> Result<UoPoolMode, String> {
     UoPoolMode::from_str(s).map_err(|_| format!("String {s} is not a valid UoPoolMode"))
 }
 
+/// Parses EntryPointVersion from string
+pub fn parse_entry_point_version(s: &str) -> Result<EntryPointVersion, String> {
+    EntryPointVersion::from_str(s)
+        .map_err(|_| format!("String {s} is not a valid EntryPointVersion"))
+}
+
 /// Parses ENR record
 pub fn parse_enr(enr: &str) -> Result<Enr, String> {
     Enr::from_str(enr).map_err(|_| format!("Enr {enr} is not a valid enr."))
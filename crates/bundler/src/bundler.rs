@@ -1,15 +1,34 @@
+use crate::{
+    funding::{FundingPolicy, Treasury},
+    profit::{BundleProfit, ProfitPolicy},
+    signers::SignerRotation,
+    tracker::{BundleOutcome, PendingBundle, TransactionTracker, TransactionTrackerConfig},
+};
 use alloy_chains::Chain;
 use ethers::{
+    abi::RawLog,
+    contract::EthLogDecode,
     providers::Middleware,
     signers::Signer,
     types::{
-        transaction::eip2718::TypedTransaction, Address, Eip1559TransactionRequest, H256, U256, U64,
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        TransactionReceipt, H256, U256, U64,
     },
 };
-use silius_contracts::entry_point::EntryPointAPI;
-use silius_primitives::{simulation::StorageMap, UserOperation, UserOperationHash, Wallet};
-use std::sync::Arc;
-use tracing::{info, trace};
+use silius_contracts::{EntryPoint, UserOperationEventFilter};
+use silius_metrics::bundler::record_signer_balance;
+use silius_primitives::{
+    bundler::{BundleRecord, BundleStatus},
+    entry_point::EntryPointVersion,
+    simulation::StorageMap,
+    UserOperation, UserOperationHash, UserOperationsPerAggregator, Wallet,
+};
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{mpsc::UnboundedSender, Mutex};
+use tracing::{info, trace, warn};
 
 /// A trait for sending the bundler of user operations
 #[async_trait::async_trait]
@@ -27,6 +46,13 @@ pub trait SendBundleOp: Send + Sync + 'static {
         bundle: TypedTransaction,
         storage_map: StorageMap,
     ) -> eyre::Result<H256>;
+
+    /// Whether the returned hash is a transaction hash that stays in the mempool until it's mined
+    /// (in which case the transaction is tracked by the
+    /// [TransactionTracker](crate::TransactionTracker) and replaced if it gets stuck)
+    fn is_trackable(&self) -> bool {
+        true
+    }
 }
 
 /// The `Bundler` struct is used to represent a bundler with necessary properties
@@ -42,6 +68,8 @@ where
     pub beneficiary: Address,
     /// Entry point contract address
     pub entry_point: Address,
+    /// Version of the entry point contract
+    pub entry_point_version: EntryPointVersion,
     /// Chain the bundler is running on
     pub chain: Chain,
     /// Minimum balance required
@@ -52,6 +80,20 @@ where
     pub client: Arc<S>,
     /// Whether add access list into tx
     pub enable_access_list: bool,
+    /// Profitability the bundle has to reach before it's sent (disabled if `None`)
+    pub profit_policy: Option<ProfitPolicy>,
+    /// Tracker of the bundle transactions that are not mined yet
+    pub tracker: TransactionTracker<M>,
+    /// Round-robin rotation of the wallet signers
+    pub signers: SignerRotation,
+    /// Treasury the signers are refunded from (if `None`, a signer with low balance is the
+    /// beneficiary of its own bundles)
+    pub treasury: Option<Treasury<M>>,
+    /// Records of the sent bundles, reported before the bundle transactions are tracked
+    pub submitted_bundles: Option<UnboundedSender<BundleRecord>>,
+    /// Serializes creating and sending of the bundles (the sent bundles are tracked concurrently),
+    /// shared among the bundlers that share the signers
+    bundling: Arc<Mutex<()>>,
 }
 
 impl<M, S> Bundler<M, S>
@@ -74,86 +116,304 @@ where
         client: Arc<S>,
         enable_access_list: bool,
     ) -> Self {
+        let signers = SignerRotation::new(wallet.signers().iter().map(|s| s.address()).collect());
         Self {
             wallet,
             beneficiary,
             entry_point,
+            entry_point_version: EntryPointVersion::from_address(&entry_point),
             chain,
             min_balance,
-            eth_client,
+            eth_client: eth_client.clone(),
             client,
             enable_access_list,
+            profit_policy: None,
+            tracker: TransactionTracker::new(eth_client, TransactionTrackerConfig::default()),
+            signers,
+            treasury: None,
+            submitted_bundles: None,
+            bundling: Default::default(),
         }
     }
 
-    /// Functions that generates a bundle of user operations (i.e.,
-    /// [TypedTransaction](TypedTransaction)).
+    /// Sets the version of the entry point (determined from the entry point address by default)
+    pub fn with_entry_point_version(mut self, version: EntryPointVersion) -> Self {
+        self.entry_point_version = version;
+        self
+    }
+
+    /// Sets the configuration of the tracker that replaces stuck bundle transactions
+    pub fn with_tracker_config(mut self, config: TransactionTrackerConfig) -> Self {
+        self.tracker = TransactionTracker::new(self.eth_client.clone(), config);
+        self
+    }
+
+    /// Shares the signers with other bundlers (e.g., of other entry points), so that the bundlers
+    /// that run concurrently never pick the same signer and nonce
     ///
     /// # Arguments
-    /// * `uos` - Slice of [UserOperations](UserOperation)
+    /// * `signers` - The [rotation](SignerRotation) of the shared signers
+    /// * `tracker` - The [tracker](TransactionTracker) of the pending bundles of the shared signers
+    /// * `bundling` - The lock that serializes creating and sending of the bundles of all bundlers
+    pub fn with_shared_signers(
+        mut self,
+        signers: SignerRotation,
+        tracker: TransactionTracker<M>,
+        bundling: Arc<Mutex<()>>,
+    ) -> Self {
+        self.signers = signers;
+        self.tracker = tracker;
+        self.bundling = bundling;
+        self
+    }
+
+    /// Sets the profitability the bundle has to reach before it's sent
+    ///
+    /// User operations that make the bundle unprofitable are dropped from the bundle (they stay in
+    /// the mempool and may be bundled later).
+    pub fn with_profit_policy(mut self, profit_policy: ProfitPolicy) -> Self {
+        self.profit_policy = Some(profit_policy);
+        self
+    }
+
+    /// Sets the policy of refunding the signers from the treasury
+    pub fn with_funding_policy(mut self, funding_policy: FundingPolicy) -> Self {
+        self.treasury = Some(Treasury::new(self.eth_client.clone(), funding_policy));
+        self
+    }
+
+    /// Sets the treasury the signers are refunded from (the treasury may be shared among bundlers)
+    pub fn with_treasury(mut self, treasury: Treasury<M>) -> Self {
+        self.treasury = Some(treasury);
+        self
+    }
+
+    /// Sets the channel the records of the sent bundles are reported to (the record returned from
+    /// [send_bundle](Bundler::send_bundle) is known only once the bundle transaction is mined)
+    pub fn with_submitted_bundles(
+        mut self,
+        submitted_bundles: UnboundedSender<BundleRecord>,
+    ) -> Self {
+        self.submitted_bundles = Some(submitted_bundles);
+        self
+    }
+
+    /// Whether any of the signers can send a bundle (doesn't have a pending bundle)
+    pub fn has_available_signer(&self) -> bool {
+        self.signers.addresses().iter().any(|a| !self.tracker.has_pending(a))
+    }
+
+    /// Picks the signer of the next bundle
+    ///
+    /// The nonces and balances of all signers are refreshed first and the signers below
+    /// `min_balance` are refunded from the treasury (if it's set). The signers with at least
+    /// `min_balance` that don't have a pending bundle are picked round-robin. If there is no such
+    /// signer and no treasury is set, the available signer with the highest balance sends the
+    /// bundle and is also its beneficiary (so that it gets refunded).
     ///
     /// # Returns
-    /// * `TypedTransaction` - A [TypedTransaction](TypedTransaction)
-    async fn create_bundle(&self, uos: &[UserOperation]) -> eyre::Result<TypedTransaction> {
-        let ep = EntryPointAPI::new(self.entry_point, self.eth_client.clone());
-
-        let nonce =
-            self.eth_client.get_transaction_count(self.wallet.signer.address(), None).await?;
-        let balance = self.eth_client.get_balance(self.wallet.signer.address(), None).await?;
-        let beneficiary = if balance < self.min_balance {
-            self.wallet.signer.address()
-        } else {
-            self.beneficiary
-        };
+    /// * `Option<(Address, U256, Address)>` - The signer, its nonce and the beneficiary, `None` if
+    ///   no signer is available
+    async fn select_signer(&self) -> eyre::Result<Option<(Address, U256, Address)>> {
+        for address in self.signers.addresses() {
+            let nonce = self.eth_client.get_transaction_count(address, None).await?;
+            let balance = self.eth_client.get_balance(address, None).await?;
+            self.signers.update(&address, nonce, balance, self.min_balance);
+            record_signer_balance(address, balance);
+        }
 
-        let mut tx: TypedTransaction = ep
-            .handle_ops(
-                uos.iter().cloned().map(|uo| uo.user_operation.into()).collect(),
+        if let Some(treasury) = &self.treasury {
+            if let Err(err) = treasury.refund(&self.signers.signers(), self.min_balance).await {
+                warn!(
+                    "Failed to refund the signers from the treasury {:?}: {err:?}",
+                    treasury.address()
+                );
+            }
+        }
+
+        if let Some(signer) = self.signers.next(|a| !self.tracker.has_pending(a)) {
+            let nonce = self.tracker.next_nonce(&signer.address, signer.nonce);
+            return Ok(Some((signer.address, nonce, self.beneficiary)));
+        }
+
+        if self.treasury.is_some() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .signers
+            .signers()
+            .into_iter()
+            .filter(|s| !self.tracker.has_pending(&s.address))
+            .max_by_key(|s| s.balance)
+            .map(|s| (s.address, self.tracker.next_nonce(&s.address, s.nonce), s.address)))
+    }
+
+    /// Creates the `handleOps` or `handleAggregatedOps` transaction for the user operations
+    fn handle_ops_tx(
+        &self,
+        ep: &EntryPoint<M>,
+        uos_per_aggregator: &[UserOperationsPerAggregator],
+        beneficiary: Address,
+    ) -> TypedTransaction {
+        if uos_per_aggregator.iter().any(|uos| uos.is_aggregated()) {
+            ep.handle_aggregated_ops_tx(uos_per_aggregator.to_vec(), beneficiary)
+        } else {
+            ep.handle_ops_tx(
+                uos_per_aggregator
+                    .iter()
+                    .flat_map(|uos| uos.user_operations.iter().cloned())
+                    .map(|uo| uo.user_operation)
+                    .collect(),
                 beneficiary,
             )
-            .tx;
+        }
+    }
 
-        let accesslist = if self.enable_access_list {
-            let accesslist = self.eth_client.create_access_list(&tx, None).await?.access_list;
-            tx.set_access_list(accesslist.clone());
-            accesslist
-        } else {
-            Default::default()
+    /// Functions that generates a bundle of user operations (i.e.,
+    /// [TypedTransaction](TypedTransaction)).
+    ///
+    /// The bundle calls `handleOps` if none of the user operations uses a signature aggregator,
+    /// otherwise `handleAggregatedOps`. If the [profit policy](ProfitPolicy) is set, the least
+    /// profitable user operations are dropped until the bundle satisfies it.
+    ///
+    /// # Arguments
+    /// * `uos_per_aggregator` - Slice of [UserOperations](UserOperation) grouped per aggregator
+    ///
+    /// # Returns
+    /// * `Option<(TypedTransaction, Vec<UserOperation>, Address)>` - A
+    ///   [TypedTransaction](TypedTransaction), the user operations included in it and the
+    ///   beneficiary, `None` if no profitable bundle can be created or no signer is available
+    async fn create_bundle(
+        &self,
+        uos_per_aggregator: &[UserOperationsPerAggregator],
+    ) -> eyre::Result<Option<(TypedTransaction, Vec<UserOperation>, Address)>> {
+        let ep = EntryPoint::new_with_version(
+            self.eth_client.clone(),
+            self.entry_point,
+            self.entry_point_version,
+        );
+
+        let (signer, nonce, beneficiary) = match self.select_signer().await? {
+            Some(signer) => signer,
+            None => {
+                info!("Skipping creating a new bundle, no signer is available");
+                return Ok(None);
+            }
         };
-        let estimated_gas = self.eth_client.estimate_gas(&tx, None).await?;
 
         let (max_fee_per_gas, max_priority_fee) =
             self.eth_client.estimate_eip1559_fees(None).await?;
+        let base_fee = self
+            .eth_client
+            .get_block(BlockNumber::Latest)
+            .await?
+            .and_then(|b| b.base_fee_per_gas)
+            .unwrap_or_default();
+
+        let mut uos_per_aggregator = uos_per_aggregator.to_vec();
+        loop {
+            let mut tx = self.handle_ops_tx(&ep, &uos_per_aggregator, beneficiary);
+            tx.set_from(signer);
+
+            let accesslist = if self.enable_access_list {
+                let accesslist = self.eth_client.create_access_list(&tx, None).await?.access_list;
+                tx.set_access_list(accesslist.clone());
+                accesslist
+            } else {
+                Default::default()
+            };
+            let estimated_gas = self.eth_client.estimate_gas(&tx, None).await?;
+
+            let profit = BundleProfit::estimate(
+                &uos_per_aggregator,
+                estimated_gas,
+                max_fee_per_gas,
+                max_priority_fee,
+                base_fee,
+            );
+            info!(
+                "Simulated bundle profit: {}, revenue: {:?}, cost: {:?}",
+                profit.profit(),
+                profit.revenue(),
+                profit.cost()
+            );
+
+            if let Some(policy) = self.profit_policy {
+                if !policy.is_satisfied(&profit) {
+                    let dropped = profit.drop_least_profitable(&mut uos_per_aggregator);
+                    warn!(
+                        "Bundle doesn't satisfy the profit policy {policy:?}, dropping user operations: {:?}",
+                        dropped.iter().map(|uo| uo.hash).collect::<Vec<UserOperationHash>>()
+                    );
+                    if uos_per_aggregator.is_empty() {
+                        return Ok(None);
+                    }
+                    continue;
+                }
+            }
 
-        tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
-            to: tx.to().cloned(),
-            from: Some(self.wallet.signer.address()),
-            data: tx.data().cloned(),
-            chain_id: Some(U64::from(self.chain.id())),
-            max_priority_fee_per_gas: Some(max_priority_fee),
-            max_fee_per_gas: Some(max_fee_per_gas),
-            gas: Some(estimated_gas),
-            nonce: Some(nonce),
-            value: None,
-            access_list: accesslist,
-        });
-
-        Ok(tx)
+            let tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to: tx.to().cloned(),
+                from: Some(signer),
+                data: tx.data().cloned(),
+                chain_id: Some(U64::from(self.chain.id())),
+                max_priority_fee_per_gas: Some(max_priority_fee),
+                max_fee_per_gas: Some(max_fee_per_gas),
+                gas: Some(estimated_gas),
+                nonce: Some(nonce),
+                value: None,
+                access_list: accesslist,
+            });
+
+            return Ok(Some((
+                tx,
+                uos_per_aggregator.into_iter().flat_map(|uos| uos.user_operations).collect(),
+                beneficiary,
+            )));
+        }
     }
 
     /// Send a bundle of [UserOperations](UserOperation)
     ///
+    /// User operations that are already in a pending bundle transaction are skipped. If the client
+    /// sends the bundle as a regular transaction, the function waits until the transaction (or its
+    /// replacement with bumped fees) is mined. If the bundle is cancelled or dropped instead, its
+    /// user operations stay in the mempool.
+    ///
     /// # Arguments
-    /// * `uos` - An array of [UserOperations](UserOperation)
+    /// * `uos_per_aggregator` - An array of [UserOperations](UserOperation) grouped per aggregator
     /// * `storage_map` - Storage map
     ///
     /// # Returns
-    /// * `H256` - The hash
+    /// * `Option<(BundleRecord, Vec<UserOperation>)>` - The [record](BundleRecord) of the sent
+    ///   bundle and the user operations in it, `None` if no bundle was sent
     pub async fn send_bundle(
         &self,
-        uos: &Vec<UserOperation>,
+        uos_per_aggregator: &[UserOperationsPerAggregator],
         storage_map: StorageMap,
-    ) -> eyre::Result<Option<H256>> {
+    ) -> eyre::Result<Option<(BundleRecord, Vec<UserOperation>)>> {
+        let bundling = self.bundling.lock().await;
+
+        let uos_per_aggregator = uos_per_aggregator
+            .iter()
+            .filter_map(|uos| {
+                let mut uos = uos.clone();
+                if uos.is_aggregated() {
+                    // the aggregated signature covers all user operations of the aggregator
+                    if uos.user_operations.iter().any(|uo| self.tracker.is_pending(&uo.hash)) {
+                        return None;
+                    }
+                } else {
+                    uos.user_operations.retain(|uo| !self.tracker.is_pending(&uo.hash));
+                }
+                (!uos.user_operations.is_empty()).then_some(uos)
+            })
+            .collect::<Vec<_>>();
+        let uos = uos_per_aggregator
+            .iter()
+            .flat_map(|uos| uos.user_operations.iter())
+            .collect::<Vec<&UserOperation>>();
         if uos.is_empty() {
             info!("Skipping creating a new bundle, no user operations");
             return Ok(None);
@@ -166,17 +426,104 @@ where
         );
         trace!("Bundle content: {uos:?}");
 
-        let bundle = self.create_bundle(uos).await?;
-        let hash = self.client.send_bundle(bundle, storage_map).await?;
+        let (bundle, uos, beneficiary) = match self.create_bundle(&uos_per_aggregator).await? {
+            Some(bundle) => bundle,
+            None => {
+                info!("Skipping sending the bundle");
+                return Ok(None);
+            }
+        };
+        let hash = self.client.send_bundle(bundle.clone(), storage_map.clone()).await?;
 
         info!(
             "Bundle successfully sent, hash: {:?}, account: {:?}, entry point: {:?}, beneficiary: {:?}",
             hash,
-            self.wallet.signer.address(),
+            bundle.from().cloned().unwrap_or_default(),
             self.entry_point,
-            self.beneficiary
+            beneficiary
         );
 
-        Ok(Some(hash))
+        let record = BundleRecord {
+            transaction_hash: hash,
+            entry_point: self.entry_point,
+            user_operations: uos.iter().map(|uo| uo.hash.into()).collect(),
+            beneficiary,
+            status: BundleStatus::Submitted.into(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            ..Default::default()
+        };
+
+        if !self.client.is_trackable() {
+            return Ok(Some((record, uos)));
+        }
+
+        if let Some(ref submitted_bundles) = self.submitted_bundles {
+            let _ = submitted_bundles.send(record.clone());
+        }
+
+        let pending = PendingBundle {
+            entry_point: self.entry_point,
+            entry_point_version: self.entry_point_version,
+            nonce: bundle.nonce().cloned().unwrap_or_default(),
+            tx: bundle,
+            tx_hashes: vec![hash],
+            cancel_tx_hashes: vec![],
+            uos: uos.clone(),
+            storage_map,
+            sent_at_block: self.eth_client.get_block_number().await?,
+            fee_bumps: 0,
+        };
+        // once the bundle is tracked, its user operations and nonce are not reused, so other
+        // bundles can be created while this one is pending
+        self.tracker.add(pending.clone());
+        drop(bundling);
+
+        let record = match self.tracker.track(self.client.as_ref(), pending).await {
+            BundleOutcome::Mined { tx_hash, receipt } => {
+                trace!("Bundle transaction receipt: {receipt:?}");
+                let gas_used = receipt.gas_used.unwrap_or_default();
+                BundleRecord {
+                    transaction_hash: tx_hash,
+                    block_number: receipt.block_number.unwrap_or_default().as_u64(),
+                    gas_used,
+                    fees_paid: gas_used
+                        .saturating_mul(receipt.effective_gas_price.unwrap_or_default()),
+                    revenue: beneficiary_revenue(&receipt, self.entry_point),
+                    status: if receipt.status == Some(U64::one()) {
+                        BundleStatus::Included.into()
+                    } else {
+                        BundleStatus::Reverted.into()
+                    },
+                    ..record
+                }
+            }
+            BundleOutcome::Cancelled { tx_hash } => {
+                info!("Bundle transaction cancelled with {tx_hash:?}, user operations released");
+                BundleRecord { status: BundleStatus::Cancelled.into(), ..record }
+            }
+            BundleOutcome::Dropped => {
+                warn!("Bundle transaction {hash:?} dropped, user operations released");
+                BundleRecord { status: BundleStatus::Dropped.into(), ..record }
+            }
+        };
+
+        Ok(Some((record, uos)))
     }
 }
+
+/// Fees the beneficiary received from the user operations of the mined bundle (sum of
+/// `actualGasCost` of the `UserOperationEvent`s)
+fn beneficiary_revenue(receipt: &TransactionReceipt, entry_point: Address) -> U256 {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == entry_point)
+        .filter_map(|log| {
+            UserOperationEventFilter::decode_log(&RawLog::from((
+                log.topics.clone(),
+                log.data.to_vec(),
+            )))
+            .ok()
+        })
+        .fold(U256::zero(), |acc, event| acc.saturating_add(event.actual_gas_cost))
+}
@@ -1,4 +1,7 @@
-use crate::bundler::SendBundleOp;
+use crate::{
+    bundler::SendBundleOp,
+    ethereum::{signer_client, signer_clients},
+};
 use ethers::{
     middleware::SignerMiddleware,
     providers::Middleware,
@@ -12,12 +15,12 @@ use ethers::{
     },
 };
 use silius_primitives::{simulation::StorageMap, Wallet};
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc};
 use tracing::trace;
 
-/// A type alias for the Ethereum Conditional Signer client
+/// A type alias for the Ethereum Conditional Signer clients (one per bundler signer)
 #[derive(Clone)]
-pub struct ConditionalClient<M>(pub SignerMiddleware<Arc<M>, LocalWallet>);
+pub struct ConditionalClient<M>(pub Vec<SignerMiddleware<Arc<M>, LocalWallet>>);
 
 #[async_trait::async_trait]
 impl<M> SendBundleOp for ConditionalClient<M>
@@ -32,7 +35,8 @@ where
     /// * 'storage_map' - Storage map
     ///
     /// # Returns
-    /// * `H256` - The transaction hash
+    /// * `H256` - The transaction hash (the transaction is tracked by the
+    ///   [TransactionTracker](crate::TransactionTracker) until it's mined)
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
@@ -50,21 +54,18 @@ where
             known_accounts.insert(k, AccountStorage::SlotValues(v));
         }
 
-        let signed_tx = self.0.sign_transaction(bundle).await?;
+        let client = signer_client(&self.0, &bundle)?;
+        let signed_tx = client.sign_transaction(bundle).await?;
 
-        let tx = self
-            .0
+        let tx = client
             .send_raw_transaction_conditional(
                 signed_tx,
                 ConditionalOptions { known_accounts, ..Default::default() },
             )
-            .await?
-            .interval(Duration::from_millis(75));
+            .await?;
         let tx_hash = tx.tx_hash();
 
-        let tx_receipt = tx.await?;
-
-        trace!("Transaction receipt: {tx_receipt:?}");
+        trace!("Transaction sent: {tx_hash:?}");
 
         Ok(tx_hash)
     }
@@ -83,7 +84,6 @@ where
     /// # Returns
     /// * `ConditionalClient` - A [Ethereum Signer Middleware](ConditionalClient)
     pub fn new(eth_client: Arc<M>, wallet: Wallet) -> Self {
-        let signer = SignerMiddleware::new(eth_client, wallet.signer);
-        Self(signer)
+        Self(signer_clients(eth_client, &wallet))
     }
 }
@@ -2,16 +2,36 @@ use crate::bundler::SendBundleOp;
 use ethers::{
     middleware::SignerMiddleware,
     providers::Middleware,
-    signers::LocalWallet,
+    signers::{LocalWallet, Signer},
     types::{transaction::eip2718::TypedTransaction, H256},
 };
 use silius_primitives::{simulation::StorageMap, Wallet};
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 use tracing::trace;
 
-/// A type alias for the Ethereum Signer client
+/// Creates a signer client for every signer of the wallet
+pub(crate) fn signer_clients<M: Middleware>(
+    eth_client: Arc<M>,
+    wallet: &Wallet,
+) -> Vec<SignerMiddleware<Arc<M>, LocalWallet>> {
+    wallet.signers().into_iter().map(|s| SignerMiddleware::new(eth_client.clone(), s)).collect()
+}
+
+/// Returns the signer client of the bundle sender
+pub(crate) fn signer_client<'a, M: Middleware>(
+    clients: &'a [SignerMiddleware<Arc<M>, LocalWallet>],
+    bundle: &TypedTransaction,
+) -> eyre::Result<&'a SignerMiddleware<Arc<M>, LocalWallet>> {
+    let from = bundle.from().cloned().unwrap_or_default();
+    clients
+        .iter()
+        .find(|c| c.signer().address() == from)
+        .ok_or_else(|| eyre::eyre!("No signer found for the bundle sender {from:?}"))
+}
+
+/// A type alias for the Ethereum Signer clients (one per bundler signer)
 #[derive(Clone)]
-pub struct EthereumClient<M>(pub SignerMiddleware<Arc<M>, LocalWallet>);
+pub struct EthereumClient<M>(pub Vec<SignerMiddleware<Arc<M>, LocalWallet>>);
 
 #[async_trait::async_trait]
 impl<M> SendBundleOp for EthereumClient<M>
@@ -25,7 +45,8 @@ where
     /// * 'storage_map' - Storage map
     ///
     /// # Returns
-    /// * `H256` - The transaction hash
+    /// * `H256` - The transaction hash (the transaction is tracked by the
+    ///   [TransactionTracker](crate::TransactionTracker) until it's mined)
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
@@ -33,12 +54,10 @@ where
     ) -> eyre::Result<H256> {
         trace!("Sending transaction to the execution client: {bundle:?}");
 
-        let tx = self.0.send_transaction(bundle, None).await?.interval(Duration::from_millis(75));
+        let tx = signer_client(&self.0, &bundle)?.send_transaction(bundle, None).await?;
         let tx_hash = tx.tx_hash();
 
-        let tx_receipt = tx.await?;
-
-        trace!("Transaction receipt: {tx_receipt:?}");
+        trace!("Transaction sent: {tx_hash:?}");
 
         Ok(tx_hash)
     }
@@ -57,7 +76,6 @@ where
     /// # Returns
     /// * `EthereumClient` - A [Ethereum Signer Middleware](EthereumClient)
     pub fn new(eth_client: Arc<M>, wallet: Wallet) -> Self {
-        let signer = SignerMiddleware::new(eth_client, wallet.signer);
-        Self(signer)
+        Self(signer_clients(eth_client, &wallet))
     }
 }
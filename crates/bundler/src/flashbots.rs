@@ -47,6 +47,11 @@ where
 
         Ok(bundle_hash)
     }
+
+    /// The Flashbots bundle is either included in the target block or not at all
+    fn is_trackable(&self) -> bool {
+        false
+    }
 }
 
 impl<M> FlashbotsClient<M>
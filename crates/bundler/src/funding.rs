@@ -0,0 +1,214 @@
+//! Refunding of the bundler signers from the treasury account
+use crate::signers::SignerState;
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, BlockNumber, TransactionRequest, H256, U256},
+};
+use parking_lot::RwLock;
+use silius_metrics::bundler::{
+    record_signer_balance, record_signer_refund, record_treasury_balance,
+    record_treasury_insufficient_balance,
+};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Policy of refunding the bundler signers
+#[derive(Clone, Debug)]
+pub struct FundingPolicy {
+    /// The treasury key the signers are refunded from
+    pub treasury: LocalWallet,
+    /// Balance the signers are topped up to once their balance drops below the minimum balance
+    pub target_balance: U256,
+    /// Balance of the treasury below which warnings are raised
+    pub min_treasury_balance: U256,
+}
+
+/// Plans the refunds of the signers whose balance is below the minimum balance
+///
+/// Every such signer is topped up to the target balance as long as the treasury balance (minus the
+/// previous refunds) covers it.
+///
+/// # Returns
+/// * `(Vec<(Address, U256)>, Vec<Address>)` - The signers with the refunded amounts and the signers
+///   the treasury can't afford to refund
+pub fn plan_refunds(
+    signers: &[SignerState],
+    min_balance: U256,
+    target_balance: U256,
+    treasury_balance: U256,
+) -> (Vec<(Address, U256)>, Vec<Address>) {
+    let mut available = treasury_balance;
+    let mut refunds = vec![];
+    let mut unaffordable = vec![];
+    for signer in signers.iter().filter(|s| s.balance < min_balance) {
+        let amount = target_balance.max(min_balance).saturating_sub(signer.balance);
+        if amount > available {
+            unaffordable.push(signer.address);
+            continue;
+        }
+        available -= amount;
+        refunds.push((signer.address, amount));
+    }
+    (refunds, unaffordable)
+}
+
+/// Treasury that tops up the bundler signers with plain transfers
+#[derive(Clone, Debug)]
+pub struct Treasury<M: Middleware + 'static> {
+    /// Ethereum execution client
+    eth_client: Arc<M>,
+    /// Client that signs the refund transfers with the treasury key
+    client: SignerMiddleware<Arc<M>, LocalWallet>,
+    /// Balance the signers are topped up to
+    target_balance: U256,
+    /// Balance of the treasury below which warnings are raised
+    min_treasury_balance: U256,
+    /// Hashes of the refund transfers that are not mined yet (per signer)
+    pending: Arc<RwLock<HashMap<Address, H256>>>,
+    /// Serializes the refunds (the treasury is shared among the bundlers, so that concurrent
+    /// refunds don't top up the same signers twice or reuse the treasury nonce)
+    refunding: Arc<Mutex<()>>,
+}
+
+impl<M: Middleware + 'static> Treasury<M> {
+    /// Creates a new treasury
+    ///
+    /// # Arguments
+    /// * `eth_client` - Connection to the Ethereum execution client
+    /// * `policy` - The [funding policy](FundingPolicy)
+    pub fn new(eth_client: Arc<M>, policy: FundingPolicy) -> Self {
+        Self {
+            eth_client: eth_client.clone(),
+            client: SignerMiddleware::new(eth_client, policy.treasury),
+            target_balance: policy.target_balance,
+            min_treasury_balance: policy.min_treasury_balance,
+            pending: Default::default(),
+            refunding: Default::default(),
+        }
+    }
+
+    /// Address of the treasury
+    pub fn address(&self) -> Address {
+        self.client.signer().address()
+    }
+
+    /// Whether the refund transfer to the signer is not mined yet
+    pub fn is_refunding(&self, signer: &Address) -> bool {
+        self.pending.read().contains_key(signer)
+    }
+
+    /// Forgets the refund transfers that are mined or dropped (the balance of the refunded signers
+    /// is recorded from the chain)
+    async fn prune_pending(&self) -> eyre::Result<()> {
+        let pending = self.pending.read().clone();
+        for (signer, tx_hash) in pending {
+            if let Some(receipt) = self.eth_client.get_transaction_receipt(tx_hash).await? {
+                if receipt.status.map_or(false, |s| s.is_zero()) {
+                    warn!("Refund transfer {tx_hash:?} to the signer {signer:?} failed");
+                } else {
+                    let balance = self.eth_client.get_balance(signer, None).await?;
+                    record_signer_balance(signer, balance);
+                }
+            } else if self.eth_client.get_transaction(tx_hash).await?.is_some() {
+                continue;
+            } else {
+                warn!("Refund transfer {tx_hash:?} to the signer {signer:?} dropped");
+            }
+            self.pending.write().remove(&signer);
+        }
+        Ok(())
+    }
+
+    /// Tops up the signers whose balance is below the minimum balance to the target balance
+    ///
+    /// Signers with a pending refund transfer are skipped. Concurrent refunds wait for each other.
+    /// A warning is logged (and a metric
+    /// raised) if the treasury balance is below the minimum treasury balance or if it can't cover
+    /// a refund.
+    ///
+    /// # Arguments
+    /// * `signers` - States of the bundler signers
+    /// * `min_balance` - Minimum balance of the signers
+    ///
+    /// # Returns
+    /// * `Vec<H256>` - Hashes of the sent refund transfers
+    pub async fn refund(
+        &self,
+        signers: &[SignerState],
+        min_balance: U256,
+    ) -> eyre::Result<Vec<H256>> {
+        let _refunding = self.refunding.lock().await;
+        self.prune_pending().await?;
+
+        let treasury = self.address();
+        let balance = self.eth_client.get_balance(treasury, None).await?;
+        let low = balance < self.min_treasury_balance;
+        record_treasury_balance(treasury, balance, low);
+        if low {
+            warn!(
+                "Treasury {treasury:?} balance {balance:?} is below the minimum treasury balance {:?}",
+                self.min_treasury_balance
+            );
+        }
+
+        let signers =
+            signers.iter().filter(|s| !self.is_refunding(&s.address)).cloned().collect::<Vec<_>>();
+        let (refunds, unaffordable) =
+            plan_refunds(&signers, min_balance, self.target_balance, balance);
+        for signer in unaffordable {
+            warn!("Treasury {treasury:?} balance {balance:?} is too low to refund the signer {signer:?}");
+            record_treasury_insufficient_balance(treasury, signer);
+        }
+        if refunds.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut nonce = self
+            .eth_client
+            .get_transaction_count(treasury, Some(BlockNumber::Pending.into()))
+            .await?;
+        let mut tx_hashes = vec![];
+        for (signer, amount) in refunds {
+            let tx = TransactionRequest::new().from(treasury).to(signer).value(amount).nonce(nonce);
+            let tx_hash = self.client.send_transaction(tx, None).await?.tx_hash();
+            info!("Refunding the signer {signer:?} with {amount:?} from the treasury, transaction: {tx_hash:?}");
+            record_signer_refund(signer);
+            self.pending.write().insert(signer, tx_hash);
+            tx_hashes.push(tx_hash);
+            nonce += U256::one();
+        }
+        Ok(tx_hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(id: u64, balance: u64) -> SignerState {
+        SignerState {
+            address: Address::from_low_u64_be(id),
+            balance: balance.into(),
+            active: balance >= 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn refunds_up_to_treasury_balance() {
+        let signers = vec![signer(1, 2), signer(2, 20), signer(3, 5), signer(4, 0)];
+        let (refunds, unaffordable) = plan_refunds(&signers, 10.into(), 30.into(), 60.into());
+
+        assert_eq!(
+            refunds,
+            vec![
+                (Address::from_low_u64_be(1), 28.into()),
+                (Address::from_low_u64_be(3), 25.into())
+            ]
+        );
+        assert_eq!(unaffordable, vec![Address::from_low_u64_be(4)]);
+    }
+}
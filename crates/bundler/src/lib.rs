@@ -5,8 +5,20 @@ mod bundler;
 mod conditional;
 mod ethereum;
 mod flashbots;
+mod funding;
+mod monitor;
+mod profit;
+mod signers;
+mod tracker;
 
 pub use bundler::{Bundler, SendBundleOp};
 pub use conditional::ConditionalClient;
 pub use ethereum::EthereumClient;
 pub use flashbots::FlashbotsClient;
+pub use funding::{plan_refunds, FundingPolicy, Treasury};
+pub use monitor::{check_entity, EntityAlert, EntityDeposit, EntityMonitor, WatchPolicy};
+pub use profit::{BundleProfit, ProfitPolicy, UserOperationProfit};
+pub use signers::{SignerRotation, SignerState};
+pub use tracker::{
+    bump_fee, BundleOutcome, PendingBundle, TransactionTracker, TransactionTrackerConfig,
+};
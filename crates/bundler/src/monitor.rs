@@ -0,0 +1,190 @@
+//! Monitoring of the deposits and stakes of the watched entities (e.g., own paymasters)
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use silius_contracts::EntryPoint;
+use silius_metrics::bundler::{record_entity_alerts, record_entity_deposit};
+use silius_primitives::entry_point::EntryPointVersion;
+use std::{sync::Arc, time::Duration};
+use tracing::warn;
+
+/// Policy of watching the paymasters and factories
+#[derive(Clone, Debug)]
+pub struct WatchPolicy {
+    /// Addresses of the watched entities
+    pub entities: Vec<Address>,
+    /// Deposit below which alerts are raised
+    pub min_deposit: U256,
+    /// Stake below which alerts are raised
+    pub min_stake: U256,
+    /// Unstake delay (in seconds) below which alerts are raised
+    pub min_unstake_delay: u64,
+    /// How often the deposits and stakes are checked
+    pub interval: Duration,
+}
+
+/// Deposit and stake of an entity in the entry point
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntityDeposit {
+    pub address: Address,
+    pub deposit: U256,
+    pub staked: bool,
+    pub stake: U256,
+    /// Unstake delay (in seconds)
+    pub unstake_delay: u64,
+    /// Time the stake can be withdrawn at (zero if the stake is locked)
+    pub withdraw_time: u64,
+}
+
+/// Alert raised for a watched entity
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntityAlert {
+    /// The deposit is below the minimum deposit
+    LowDeposit { deposit: U256, min_deposit: U256 },
+    /// The stake is below the minimum stake
+    LowStake { stake: U256, min_stake: U256 },
+    /// The unstake delay is below the minimum unstake delay
+    LowUnstakeDelay { unstake_delay: u64, min_unstake_delay: u64 },
+    /// The stake is unlocked (and can be withdrawn once the withdraw time passes)
+    Unstaking { withdraw_time: u64 },
+}
+
+/// Checks the deposit and stake of the entity against the thresholds of the policy
+pub fn check_entity(deposit: &EntityDeposit, policy: &WatchPolicy) -> Vec<EntityAlert> {
+    let mut alerts = vec![];
+    if deposit.deposit < policy.min_deposit {
+        alerts.push(EntityAlert::LowDeposit {
+            deposit: deposit.deposit,
+            min_deposit: policy.min_deposit,
+        });
+    }
+    if deposit.stake < policy.min_stake {
+        alerts.push(EntityAlert::LowStake { stake: deposit.stake, min_stake: policy.min_stake });
+    }
+    if deposit.unstake_delay < policy.min_unstake_delay {
+        alerts.push(EntityAlert::LowUnstakeDelay {
+            unstake_delay: deposit.unstake_delay,
+            min_unstake_delay: policy.min_unstake_delay,
+        });
+    }
+    if deposit.withdraw_time != 0 {
+        alerts.push(EntityAlert::Unstaking { withdraw_time: deposit.withdraw_time });
+    }
+    alerts
+}
+
+/// Monitor of the deposits and stakes of the watched entities in the entry point
+#[derive(Clone)]
+pub struct EntityMonitor<M: Middleware + 'static> {
+    /// Entry point the entities deposit to
+    entry_point: EntryPoint<M>,
+    /// The [watch policy](WatchPolicy)
+    policy: WatchPolicy,
+}
+
+impl<M: Middleware + 'static> EntityMonitor<M> {
+    /// Creates a new monitor
+    ///
+    /// # Arguments
+    /// * `eth_client` - Connection to the Ethereum execution client
+    /// * `entry_point` - Address of the entry point
+    /// * `version` - Version of the entry point
+    /// * `policy` - The [watch policy](WatchPolicy)
+    pub fn new(
+        eth_client: Arc<M>,
+        entry_point: Address,
+        version: EntryPointVersion,
+        policy: WatchPolicy,
+    ) -> Self {
+        Self { entry_point: EntryPoint::new_with_version(eth_client, entry_point, version), policy }
+    }
+
+    /// The [watch policy](WatchPolicy)
+    pub fn policy(&self) -> &WatchPolicy {
+        &self.policy
+    }
+
+    /// Fetches the deposits and stakes of the watched entities, exports them as metrics and logs
+    /// a warning for every alert
+    ///
+    /// # Returns
+    /// * `Vec<(EntityDeposit, Vec<EntityAlert>)>` - The deposits of the entities with their alerts
+    pub async fn check(&self) -> eyre::Result<Vec<(EntityDeposit, Vec<EntityAlert>)>> {
+        let ep = self.entry_point.address();
+        let mut res = vec![];
+        for entity in self.policy.entities.iter() {
+            let info = self.entry_point.get_deposit_info(entity).await?;
+            let deposit = EntityDeposit {
+                address: *entity,
+                deposit: U256::from(info.deposit),
+                staked: info.staked,
+                stake: U256::from(info.stake),
+                unstake_delay: info.unstake_delay_sec.into(),
+                withdraw_time: info.withdraw_time.into(),
+            };
+            record_entity_deposit(
+                ep,
+                *entity,
+                deposit.deposit,
+                deposit.stake,
+                deposit.unstake_delay,
+            );
+
+            let alerts = check_entity(&deposit, &self.policy);
+            record_entity_alerts(
+                ep,
+                *entity,
+                alerts.iter().any(|a| matches!(a, EntityAlert::LowDeposit { .. })),
+                alerts.iter().any(|a| matches!(a, EntityAlert::LowStake { .. })),
+                alerts.iter().any(|a| matches!(a, EntityAlert::LowUnstakeDelay { .. })),
+                alerts.iter().any(|a| matches!(a, EntityAlert::Unstaking { .. })),
+            );
+            for alert in alerts.iter() {
+                warn!("Watched entity {entity:?} on entry point {ep:?}: {alert:?}");
+            }
+            res.push((deposit, alerts));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_below_thresholds() {
+        let policy = WatchPolicy {
+            entities: vec![Address::from_low_u64_be(1)],
+            min_deposit: 100.into(),
+            min_stake: 10.into(),
+            min_unstake_delay: 86400,
+            interval: Duration::from_secs(60),
+        };
+        let deposit = EntityDeposit {
+            address: Address::from_low_u64_be(1),
+            deposit: 200.into(),
+            staked: true,
+            stake: 10.into(),
+            unstake_delay: 86400,
+            withdraw_time: 0,
+        };
+        assert!(check_entity(&deposit, &policy).is_empty());
+
+        let deposit = EntityDeposit {
+            deposit: 50.into(),
+            unstake_delay: 3600,
+            withdraw_time: 1_700_000_000,
+            ..deposit
+        };
+        assert_eq!(
+            check_entity(&deposit, &policy),
+            vec![
+                EntityAlert::LowDeposit { deposit: 50.into(), min_deposit: 100.into() },
+                EntityAlert::LowUnstakeDelay { unstake_delay: 3600, min_unstake_delay: 86400 },
+                EntityAlert::Unstaking { withdraw_time: 1_700_000_000 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Simulated economics of a bundle
+use ethers::types::{I256, U256};
+use silius_primitives::{UserOperation, UserOperationsPerAggregator};
+
+/// Policy the bundle has to satisfy before it's sent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProfitPolicy {
+    /// Minimum profit of the bundle (in wei)
+    pub min_profit: U256,
+    /// Minimum profit of the bundle relative to its cost (in percentage)
+    pub min_margin_perc: u64,
+}
+
+impl ProfitPolicy {
+    /// Whether the bundle with the given profit may be sent
+    pub fn is_satisfied(&self, profit: &BundleProfit) -> bool {
+        let p = profit.profit();
+        p >= I256::from_raw(self.min_profit) &&
+            p.saturating_mul(I256::from(100)) >=
+                I256::from_raw(profit.cost().saturating_mul(self.min_margin_perc.into()))
+    }
+}
+
+/// Gas and fees of a single user operation in the bundle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UserOperationProfit {
+    /// Share of the bundle gas attributed to the user operation
+    pub gas: U256,
+    /// Fee the beneficiary receives from the user operation
+    pub revenue: U256,
+}
+
+/// Simulated profit of a bundle
+///
+/// The estimated gas of the bundle transaction is split among the user operations proportionally
+/// to their gas limits. Every user operation pays `min(maxFeePerGas, maxPriorityFeePerGas +
+/// baseFee)` for its share to the beneficiary, while the bundler pays `min(maxFeePerGas,
+/// maxPriorityFeePerGas + baseFee)` of the bundle transaction for the whole gas.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleProfit {
+    /// Gas and fees of the user operations (grouped the same way as the bundle)
+    pub user_operations: Vec<Vec<UserOperationProfit>>,
+    /// Estimated gas of the bundle transaction
+    pub gas: U256,
+    /// Effective gas price of the bundle transaction
+    pub gas_price: U256,
+}
+
+/// Gas price that is effectively paid with EIP-1559 fees
+pub fn effective_gas_price(max_fee_per_gas: U256, max_priority_fee: U256, base_fee: U256) -> U256 {
+    max_fee_per_gas.min(max_priority_fee.saturating_add(base_fee))
+}
+
+fn gas_limit(uo: &UserOperation) -> U256 {
+    uo.pre_verification_gas
+        .saturating_add(uo.verification_gas_limit)
+        .saturating_add(uo.call_gas_limit)
+}
+
+impl BundleProfit {
+    /// Estimates the profit of the bundle
+    ///
+    /// # Arguments
+    /// * `bundle` - The user operations grouped per aggregator
+    /// * `gas` - The estimated gas of the bundle transaction
+    /// * `max_fee_per_gas` - Max fee per gas of the bundle transaction
+    /// * `max_priority_fee` - Max priority fee per gas of the bundle transaction
+    /// * `base_fee` - The current base fee per gas
+    pub fn estimate(
+        bundle: &[UserOperationsPerAggregator],
+        gas: U256,
+        max_fee_per_gas: U256,
+        max_priority_fee: U256,
+        base_fee: U256,
+    ) -> Self {
+        let gas_limit_total = bundle
+            .iter()
+            .flat_map(|uos| uos.user_operations.iter())
+            .fold(U256::zero(), |acc, uo| acc.saturating_add(gas_limit(uo)));
+
+        let user_operations = bundle
+            .iter()
+            .map(|uos| {
+                uos.user_operations
+                    .iter()
+                    .map(|uo| {
+                        let gas = if gas_limit_total.is_zero() {
+                            U256::zero()
+                        } else {
+                            gas.saturating_mul(gas_limit(uo)) / gas_limit_total
+                        };
+                        let gas_price = effective_gas_price(
+                            uo.max_fee_per_gas,
+                            uo.max_priority_fee_per_gas,
+                            base_fee,
+                        );
+                        UserOperationProfit { gas, revenue: gas.saturating_mul(gas_price) }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            user_operations,
+            gas,
+            gas_price: effective_gas_price(max_fee_per_gas, max_priority_fee, base_fee),
+        }
+    }
+
+    /// Fees the beneficiary receives from all user operations
+    pub fn revenue(&self) -> U256 {
+        self.user_operations
+            .iter()
+            .flatten()
+            .fold(U256::zero(), |acc, uo| acc.saturating_add(uo.revenue))
+    }
+
+    /// Fees the bundler pays for the bundle transaction
+    pub fn cost(&self) -> U256 {
+        self.gas.saturating_mul(self.gas_price)
+    }
+
+    /// Revenue minus cost (negative if the bundle is sent at a loss)
+    pub fn profit(&self) -> I256 {
+        I256::from_raw(self.revenue()) - I256::from_raw(self.cost())
+    }
+
+    fn user_operation_profit(&self, uo: &UserOperationProfit) -> I256 {
+        I256::from_raw(uo.revenue) - I256::from_raw(uo.gas.saturating_mul(self.gas_price))
+    }
+
+    /// Removes the least profitable part of the bundle: a single user operation that doesn't use
+    /// a signature aggregator, or a whole aggregator group (the aggregated signature covers all of
+    /// its user operations)
+    ///
+    /// # Returns
+    /// * `Vec<UserOperation>` - The dropped user operations
+    pub fn drop_least_profitable(
+        &self,
+        bundle: &mut Vec<UserOperationsPerAggregator>,
+    ) -> Vec<UserOperation> {
+        // (group index, user operation index if not aggregated, profit)
+        let mut least: Option<(usize, Option<usize>, I256)> = None;
+        for (i, (uos, profits)) in bundle.iter().zip(self.user_operations.iter()).enumerate() {
+            let units: Vec<(Option<usize>, I256)> = if uos.is_aggregated() {
+                vec![(
+                    None,
+                    profits.iter().fold(I256::zero(), |acc, p| acc + self.user_operation_profit(p)),
+                )]
+            } else {
+                profits
+                    .iter()
+                    .enumerate()
+                    .map(|(j, p)| (Some(j), self.user_operation_profit(p)))
+                    .collect()
+            };
+
+            for (j, p) in units {
+                if least.map_or(true, |(_, _, l)| p < l) {
+                    least = Some((i, j, p));
+                }
+            }
+        }
+
+        match least {
+            Some((i, Some(j), _)) => {
+                let dropped = bundle[i].user_operations.remove(j);
+                if bundle[i].user_operations.is_empty() {
+                    bundle.remove(i);
+                }
+                vec![dropped]
+            }
+            Some((i, None, _)) => bundle.remove(i).user_operations,
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, H256};
+    use silius_primitives::UserOperationSigned;
+
+    fn uo(id: u64, priority_fee: u64) -> UserOperation {
+        UserOperation::from_user_operation_signed(
+            H256::from_low_u64_be(id).into(),
+            UserOperationSigned {
+                sender: Address::from_low_u64_be(id),
+                pre_verification_gas: 50_000.into(),
+                verification_gas_limit: 100_000.into(),
+                call_gas_limit: 50_000.into(),
+                max_fee_per_gas: 100.into(),
+                max_priority_fee_per_gas: priority_fee.into(),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn bundle(uos: Vec<UserOperation>) -> Vec<UserOperationsPerAggregator> {
+        vec![UserOperationsPerAggregator { user_operations: uos, ..Default::default() }]
+    }
+
+    #[test]
+    fn bundle_profit() {
+        let bundle = bundle(vec![uo(1, 5), uo(2, 1)]);
+        // bundle transaction pays base fee 10 + priority fee 2
+        let profit =
+            BundleProfit::estimate(&bundle, 200_000.into(), 100.into(), 2.into(), 10.into());
+
+        assert_eq!(profit.revenue(), U256::from(100_000 * 15 + 100_000 * 11));
+        assert_eq!(profit.cost(), U256::from(200_000 * 12));
+        assert_eq!(profit.profit(), I256::from(200_000));
+
+        let policy = ProfitPolicy { min_profit: 100_000.into(), min_margin_perc: 10 };
+        assert!(!policy.is_satisfied(&profit));
+        let policy = ProfitPolicy { min_profit: 100_000.into(), min_margin_perc: 5 };
+        assert!(policy.is_satisfied(&profit));
+    }
+
+    #[test]
+    fn drop_least_profitable_user_operation() {
+        let mut bundle = bundle(vec![uo(1, 5), uo(2, 1), uo(3, 3)]);
+        let profit =
+            BundleProfit::estimate(&bundle, 300_000.into(), 100.into(), 2.into(), 10.into());
+
+        let dropped = profit.drop_least_profitable(&mut bundle);
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].sender, Address::from_low_u64_be(2));
+        assert_eq!(bundle[0].user_operations.len(), 2);
+    }
+}
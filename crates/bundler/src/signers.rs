@@ -0,0 +1,112 @@
+//! Round-robin rotation of the bundler signers
+use ethers::types::{Address, U256};
+use parking_lot::RwLock;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// State of a bundler signer
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignerState {
+    /// Address of the signer
+    pub address: Address,
+    /// Transaction count of the signer
+    pub nonce: U256,
+    /// Balance of the signer
+    pub balance: U256,
+    /// Whether the signer is in the rotation (its balance is at least the minimum balance)
+    pub active: bool,
+}
+
+/// Round-robin rotation of the bundler signers
+///
+/// Signers whose balance drops below the minimum balance are taken out of the rotation until they
+/// are refunded.
+#[derive(Clone, Debug)]
+pub struct SignerRotation {
+    /// States of the signers (in the rotation order)
+    signers: Arc<RwLock<Vec<SignerState>>>,
+    /// Index of the signer the rotation continues with
+    next: Arc<RwLock<usize>>,
+}
+
+impl SignerRotation {
+    /// Creates a new rotation of the given signers
+    pub fn new(addresses: Vec<Address>) -> Self {
+        Self {
+            signers: Arc::new(RwLock::new(
+                addresses
+                    .into_iter()
+                    .map(|address| SignerState { address, active: true, ..Default::default() })
+                    .collect(),
+            )),
+            next: Default::default(),
+        }
+    }
+
+    /// Returns the addresses of all signers
+    pub fn addresses(&self) -> Vec<Address> {
+        self.signers.read().iter().map(|s| s.address).collect()
+    }
+
+    /// Returns the states of all signers
+    pub fn signers(&self) -> Vec<SignerState> {
+        self.signers.read().clone()
+    }
+
+    /// Returns the state of the signer
+    pub fn get(&self, address: &Address) -> Option<SignerState> {
+        self.signers.read().iter().find(|s| s.address == *address).cloned()
+    }
+
+    /// Updates the nonce and balance of the signer, the signer is taken out of the rotation if its
+    /// balance is below the minimum balance (and put back once it's refunded)
+    pub fn update(&self, address: &Address, nonce: U256, balance: U256, min_balance: U256) {
+        let mut signers = self.signers.write();
+        if let Some(signer) = signers.iter_mut().find(|s| s.address == *address) {
+            let active = balance >= min_balance;
+            if signer.active && !active {
+                warn!(
+                    "Signer {address:?} balance {balance:?} is below the minimum balance {min_balance:?}, taking it out of the rotation"
+                );
+            } else if !signer.active && active {
+                info!("Signer {address:?} is refunded, putting it back into the rotation");
+            }
+            *signer = SignerState { address: *address, nonce, balance, active };
+        }
+    }
+
+    /// Returns the next active signer for which `available` holds (e.g., it doesn't have a
+    /// pending bundle) and moves the rotation forward
+    pub fn next<F: Fn(&Address) -> bool>(&self, available: F) -> Option<SignerState> {
+        let signers = self.signers.read();
+        let mut next = self.next.write();
+        for i in 0..signers.len() {
+            let idx = (*next + i) % signers.len();
+            if signers[idx].active && available(&signers[idx].address) {
+                *next = (idx + 1) % signers.len();
+                return Some(signers[idx].clone());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin_skips_inactive_signers() {
+        let addresses = (1..=3).map(Address::from_low_u64_be).collect::<Vec<_>>();
+        let rotation = SignerRotation::new(addresses.clone());
+        rotation.update(&addresses[1], U256::zero(), 5.into(), 10.into());
+
+        assert_eq!(rotation.next(|_| true).map(|s| s.address), Some(addresses[0]));
+        assert_eq!(rotation.next(|_| true).map(|s| s.address), Some(addresses[2]));
+        assert_eq!(rotation.next(|a| *a != addresses[0]).map(|s| s.address), Some(addresses[2]));
+
+        rotation.update(&addresses[1], U256::zero(), 10.into(), 10.into());
+        assert_eq!(rotation.next(|_| true).map(|s| s.address), Some(addresses[0]));
+        assert_eq!(rotation.next(|_| true).map(|s| s.address), Some(addresses[1]));
+    }
+}
@@ -0,0 +1,537 @@
+//! Tracking of the bundle transactions until they are mined
+use crate::bundler::SendBundleOp;
+use ethers::{
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        TransactionReceipt, H256, U256, U64,
+    },
+};
+use parking_lot::RwLock;
+use silius_contracts::{EntryPoint, EntryPointError};
+use silius_primitives::{
+    constants::bundler::{BUMP_AFTER_BLOCKS, FEE_BUMP_PERC, MAX_FEE_BUMPS, MAX_TRACKER_ERRORS},
+    entry_point::EntryPointVersion,
+    simulation::StorageMap,
+    UserOperation, UserOperationHash,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+/// Gas of the zero-value self-transfer that cancels a bundle transaction
+const CANCEL_TX_GAS: u64 = 21000;
+
+/// Configuration of the [TransactionTracker](TransactionTracker)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransactionTrackerConfig {
+    /// Number of blocks after which a pending bundle transaction is replaced with higher fees
+    pub bump_after_blocks: u64,
+    /// Percentage the fees are increased by when the bundle transaction is replaced
+    pub fee_bump_perc: u64,
+    /// Maximum number of fee bumps of a bundle transaction (the bundle is given up if it's not
+    /// mined after the last bump)
+    pub max_fee_bumps: u64,
+    /// Number of consecutive failed checks of a bundle transaction after which it's given up
+    pub max_errors: u64,
+    /// How often the bundle transaction is checked
+    pub poll_interval: Duration,
+}
+
+impl Default for TransactionTrackerConfig {
+    fn default() -> Self {
+        Self {
+            bump_after_blocks: BUMP_AFTER_BLOCKS,
+            fee_bump_perc: FEE_BUMP_PERC,
+            max_fee_bumps: MAX_FEE_BUMPS,
+            max_errors: MAX_TRACKER_ERRORS,
+            poll_interval: Duration::from_millis(75),
+        }
+    }
+}
+
+/// A bundle transaction that is not mined yet
+#[derive(Clone, Debug)]
+pub struct PendingBundle {
+    /// Entry point the bundle is sent to
+    pub entry_point: Address,
+    /// Version of the entry point the bundle is sent to
+    pub entry_point_version: EntryPointVersion,
+    /// The latest transaction sent with the nonce (the bundle or its replacement)
+    pub tx: TypedTransaction,
+    /// Hashes of all transactions sent with the nonce (any of them may be mined)
+    pub tx_hashes: Vec<H256>,
+    /// Hashes of the zero-value self-transfers if the bundle was cancelled (the cancellation is
+    /// replaced with bumped fees like the bundle)
+    pub cancel_tx_hashes: Vec<H256>,
+    /// Nonce of the bundle transaction
+    pub nonce: U256,
+    /// User operations in the bundle
+    pub uos: Vec<UserOperation>,
+    /// Storage map of the bundle (used when the bundle is replaced)
+    pub storage_map: StorageMap,
+    /// Block number at which the latest transaction was sent
+    pub sent_at_block: U64,
+    /// Number of times the fees were bumped
+    pub fee_bumps: u64,
+}
+
+impl PendingBundle {
+    /// Hash of the latest transaction sent with the nonce
+    pub fn tx_hash(&self) -> H256 {
+        self.tx_hashes.last().cloned().unwrap_or_default()
+    }
+
+    /// Signer of the bundle transaction
+    pub fn from(&self) -> Address {
+        self.tx.from().cloned().unwrap_or_default()
+    }
+
+    /// Whether the bundle was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        !self.cancel_tx_hashes.is_empty()
+    }
+
+    /// Creates the replacement of the latest transaction with the given fees
+    ///
+    /// # Arguments
+    /// * `max_fee_per_gas` - Max fee per gas of the replacement
+    /// * `max_priority_fee` - Max priority fee per gas of the replacement
+    /// * `cancel` - Whether the bundle is cancelled with a zero-value self-transfer (once
+    ///   cancelled, every replacement is the self-transfer)
+    ///
+    /// # Returns
+    /// * `(TypedTransaction, StorageMap, bool)` - The replacement, its storage map and whether it's
+    ///   a cancellation
+    pub fn replacement(
+        &self,
+        max_fee_per_gas: U256,
+        max_priority_fee: U256,
+        cancel: bool,
+    ) -> (TypedTransaction, StorageMap, bool) {
+        if cancel || self.is_cancelled() {
+            let from = self.from();
+            let tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to: Some(from.into()),
+                from: Some(from),
+                data: None,
+                chain_id: self.tx.chain_id(),
+                max_priority_fee_per_gas: Some(max_priority_fee),
+                max_fee_per_gas: Some(max_fee_per_gas),
+                gas: Some(CANCEL_TX_GAS.into()),
+                nonce: Some(self.nonce),
+                value: Some(U256::zero()),
+                access_list: Default::default(),
+            });
+            (tx, StorageMap::default(), true)
+        } else {
+            let mut tx = self.tx.clone();
+            if let TypedTransaction::Eip1559(ref mut tx) = tx {
+                tx.max_fee_per_gas = Some(max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(max_priority_fee);
+            }
+            (tx, self.storage_map.clone(), false)
+        }
+    }
+
+    /// Records the replacement that was sent
+    pub fn replaced(&mut self, tx: TypedTransaction, tx_hash: H256, cancel: bool, block: U64) {
+        if cancel {
+            self.cancel_tx_hashes.push(tx_hash);
+        }
+        self.tx = tx;
+        self.tx_hashes.push(tx_hash);
+        self.sent_at_block = block;
+        self.fee_bumps += 1;
+    }
+
+    /// The final state of the bundle given that the transaction with the hash was mined
+    pub fn outcome(&self, tx_hash: H256, receipt: TransactionReceipt) -> BundleOutcome {
+        if self.cancel_tx_hashes.contains(&tx_hash) {
+            BundleOutcome::Cancelled { tx_hash }
+        } else {
+            BundleOutcome::Mined { tx_hash, receipt }
+        }
+    }
+}
+
+/// Final state of a tracked bundle transaction
+#[derive(Clone, Debug)]
+pub enum BundleOutcome {
+    /// The bundle transaction (or one of its replacements) was mined
+    Mined {
+        /// Hash of the mined transaction
+        tx_hash: H256,
+        /// Receipt of the mined transaction
+        receipt: TransactionReceipt,
+    },
+    /// The bundle was cancelled with a zero-value self-transfer because all of its user
+    /// operations became invalid
+    Cancelled {
+        /// Hash of the mined self-transfer
+        tx_hash: H256,
+    },
+    /// The nonce was used by a transaction the tracker doesn't know about, or the tracker gave up
+    /// on the bundle (it's not mined after the last fee bump or it can't be checked)
+    Dropped,
+}
+
+/// Increases the fee by the given percentage (rounded up)
+pub fn bump_fee(fee: U256, perc: u64) -> U256 {
+    let fee = fee.saturating_mul(U256::from(100).saturating_add(perc.into()));
+    let (q, r) = fee.div_mod(100.into());
+    if r.is_zero() {
+        q
+    } else {
+        q.saturating_add(U256::one())
+    }
+}
+
+impl TransactionTrackerConfig {
+    /// Whether the bundle is given up (it's not mined after the last fee bump)
+    pub fn is_exhausted(&self, bundle: &PendingBundle, block: U64) -> bool {
+        bundle.fee_bumps >= self.max_fee_bumps &&
+            block >= bundle.sent_at_block + self.bump_after_blocks
+    }
+}
+
+/// Tracks the bundle transactions sent to the execution client until they are mined
+///
+/// A pending bundle transaction that is not mined after
+/// [bump_after_blocks](TransactionTrackerConfig::bump_after_blocks) is replaced with the same
+/// transaction with bumped EIP-1559 fees. If all user operations of the bundle became invalid in
+/// the meantime, the bundle is cancelled with a zero-value self-transfer with the same nonce
+/// instead. The bundle is given up (and its signer freed) if it's not mined after
+/// [max_fee_bumps](TransactionTrackerConfig::max_fee_bumps) or it can't be checked
+/// [max_errors](TransactionTrackerConfig::max_errors) times in a row.
+#[derive(Clone, Debug)]
+pub struct TransactionTracker<M: Middleware + 'static> {
+    /// Ethereum execution client
+    eth_client: Arc<M>,
+    /// Configuration of the tracker
+    config: TransactionTrackerConfig,
+    /// Pending bundles by signer and nonce
+    pending: Arc<RwLock<HashMap<(Address, U256), PendingBundle>>>,
+}
+
+impl<M: Middleware + 'static> TransactionTracker<M> {
+    /// Creates a new transaction tracker (it may be shared among the bundlers of different entry
+    /// points that use the same signers)
+    ///
+    /// # Arguments
+    /// * `eth_client` - Connection to the Ethereum execution client
+    /// * `config` - The [TransactionTrackerConfig](TransactionTrackerConfig)
+    ///
+    /// # Returns
+    /// * `Self` - A new `TransactionTracker` instance
+    pub fn new(eth_client: Arc<M>, config: TransactionTrackerConfig) -> Self {
+        Self { eth_client, config, pending: Default::default() }
+    }
+
+    /// Returns the configuration of the tracker
+    pub fn config(&self) -> TransactionTrackerConfig {
+        self.config
+    }
+
+    /// Returns all pending bundles
+    pub fn pending(&self) -> Vec<PendingBundle> {
+        self.pending.read().values().cloned().collect()
+    }
+
+    /// Whether the user operation is in one of the pending bundles
+    pub fn is_pending(&self, uo_hash: &UserOperationHash) -> bool {
+        self.pending.read().values().any(|b| b.uos.iter().any(|uo| uo.hash == *uo_hash))
+    }
+
+    /// Whether the signer has a pending bundle
+    pub fn has_pending(&self, signer: &Address) -> bool {
+        self.pending.read().keys().any(|(from, _)| from == signer)
+    }
+
+    /// Returns the nonce of the next bundle transaction of the signer (the nonces of its pending
+    /// bundles are skipped)
+    ///
+    /// # Arguments
+    /// * `signer` - The address of the signer
+    /// * `nonce` - The transaction count of the signer
+    pub fn next_nonce(&self, signer: &Address, nonce: U256) -> U256 {
+        self.pending
+            .read()
+            .keys()
+            .filter(|(from, _)| from == signer)
+            .map(|(_, n)| n)
+            .max()
+            .map(|n| nonce.max(n.saturating_add(U256::one())))
+            .unwrap_or(nonce)
+    }
+
+    /// Starts tracking the bundle (before [track](TransactionTracker::track) is awaited, so that
+    /// its user operations and nonce are not reused in the meantime)
+    pub fn add(&self, bundle: PendingBundle) {
+        self.pending.write().insert((bundle.from(), bundle.nonce), bundle);
+    }
+
+    /// Tracks the bundle transaction until it's mined, cancelled or dropped
+    ///
+    /// # Arguments
+    /// * `client` - The client the bundle was sent with (replacements are sent with it too)
+    /// * `bundle` - The [PendingBundle](PendingBundle) that was just sent
+    ///
+    /// # Returns
+    /// * `BundleOutcome` - The final state of the bundle transaction
+    pub async fn track<S: SendBundleOp>(
+        &self,
+        client: &S,
+        mut bundle: PendingBundle,
+    ) -> BundleOutcome {
+        let key = (bundle.from(), bundle.nonce);
+        self.pending.write().insert(key, bundle.clone());
+
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+        let mut errors = 0;
+        let outcome = loop {
+            interval.tick().await;
+
+            match self.poll(client, &mut bundle).await {
+                Ok(Some(outcome)) => break outcome,
+                Ok(None) => {
+                    errors = 0;
+                    self.pending.write().insert(key, bundle.clone());
+                }
+                Err(err) => {
+                    warn!(
+                        "Checking bundle transaction {:?} failed with error: {err:?}",
+                        bundle.tx_hash()
+                    );
+                    errors += 1;
+                    if errors >= self.config.max_errors {
+                        warn!(
+                            "Giving up on bundle transaction {:?} after {errors} failed checks",
+                            bundle.tx_hash()
+                        );
+                        break BundleOutcome::Dropped;
+                    }
+                }
+            }
+        };
+
+        self.pending.write().remove(&key);
+        outcome
+    }
+
+    /// Checks the pending bundle once and replaces it if it's stuck
+    async fn poll<S: SendBundleOp>(
+        &self,
+        client: &S,
+        bundle: &mut PendingBundle,
+    ) -> eyre::Result<Option<BundleOutcome>> {
+        let from = bundle.from();
+        // the nonce is fetched before the receipts, so a mined transaction is never reported as
+        // dropped
+        let nonce =
+            self.eth_client.get_transaction_count(from, Some(BlockNumber::Latest.into())).await?;
+
+        for tx_hash in bundle.tx_hashes.iter().rev() {
+            if let Some(receipt) = self.eth_client.get_transaction_receipt(*tx_hash).await? {
+                return Ok(Some(bundle.outcome(*tx_hash, receipt)));
+            }
+        }
+
+        if nonce > bundle.nonce {
+            return Ok(Some(BundleOutcome::Dropped));
+        }
+
+        let block = self.eth_client.get_block_number().await?;
+        if self.config.is_exhausted(bundle, block) {
+            warn!(
+                "Giving up on bundle transaction {:?}, not mined after {} fee bumps",
+                bundle.tx_hash(),
+                bundle.fee_bumps
+            );
+            return Ok(Some(BundleOutcome::Dropped));
+        }
+        if block < bundle.sent_at_block + self.config.bump_after_blocks {
+            return Ok(None);
+        }
+
+        let (max_fee_per_gas, max_priority_fee) =
+            self.eth_client.estimate_eip1559_fees(None).await?;
+        let max_fee_per_gas = bump_fee(
+            bundle.tx.as_eip1559_ref().and_then(|tx| tx.max_fee_per_gas).unwrap_or_default(),
+            self.config.fee_bump_perc,
+        )
+        .max(max_fee_per_gas);
+        let max_priority_fee = bump_fee(
+            bundle
+                .tx
+                .as_eip1559_ref()
+                .and_then(|tx| tx.max_priority_fee_per_gas)
+                .unwrap_or_default(),
+            self.config.fee_bump_perc,
+        )
+        .max(max_priority_fee);
+
+        let cancel = !bundle.is_cancelled() &&
+            self.is_invalid(bundle.entry_point, bundle.entry_point_version, &bundle.uos).await;
+        if cancel {
+            info!(
+                "All user operations of the bundle transaction {:?} are invalid, cancelling it",
+                bundle.tx_hash()
+            );
+        }
+        let (tx, storage_map, cancel) =
+            bundle.replacement(max_fee_per_gas, max_priority_fee, cancel);
+
+        let tx_hash = client.send_bundle(tx.clone(), storage_map).await?;
+        info!(
+            "Bundle transaction {:?} is not mined after {} blocks, replaced with {:?} (max fee per gas: {:?}, max priority fee per gas: {:?})",
+            bundle.tx_hash(),
+            block - bundle.sent_at_block,
+            tx_hash,
+            max_fee_per_gas,
+            max_priority_fee
+        );
+
+        bundle.replaced(tx, tx_hash, cancel, block);
+
+        Ok(None)
+    }
+
+    /// Whether all user operations fail the validation
+    async fn is_invalid(
+        &self,
+        entry_point: Address,
+        version: EntryPointVersion,
+        uos: &[UserOperation],
+    ) -> bool {
+        let ep = EntryPoint::new_with_version(self.eth_client.clone(), entry_point, version);
+        for uo in uos {
+            match ep.simulate_validation(uo.user_operation.clone()).await {
+                Err(EntryPointError::FailedOp(_)) => continue,
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bump_fee_rounds_up() {
+        assert_eq!(bump_fee(100.into(), 10), U256::from(110));
+        assert_eq!(bump_fee(15.into(), 10), U256::from(17));
+        assert_eq!(bump_fee(U256::zero(), 10), U256::zero());
+    }
+
+    struct NoopClient;
+
+    #[async_trait::async_trait]
+    impl SendBundleOp for NoopClient {
+        async fn send_bundle(
+            &self,
+            _bundle: TypedTransaction,
+            _storage_map: StorageMap,
+        ) -> eyre::Result<H256> {
+            Ok(H256::zero())
+        }
+    }
+
+    fn pending_bundle() -> PendingBundle {
+        let from = Address::random();
+        PendingBundle {
+            entry_point: Address::random(),
+            entry_point_version: EntryPointVersion::V0_6,
+            tx: TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to: Some(Address::random().into()),
+                from: Some(from),
+                data: Some(vec![1, 2, 3].into()),
+                chain_id: Some(1.into()),
+                max_priority_fee_per_gas: Some(10.into()),
+                max_fee_per_gas: Some(100.into()),
+                gas: Some(1_000_000.into()),
+                nonce: Some(7.into()),
+                value: None,
+                access_list: Default::default(),
+            }),
+            tx_hashes: vec![H256::random()],
+            cancel_tx_hashes: vec![],
+            nonce: 7.into(),
+            uos: vec![],
+            storage_map: StorageMap::default(),
+            sent_at_block: 10.into(),
+            fee_bumps: 0,
+        }
+    }
+
+    #[test]
+    fn cancelled_bundle_stays_cancelled() {
+        let mut bundle = pending_bundle();
+        let from = bundle.from();
+        let bundle_tx_hash = bundle.tx_hash();
+
+        let (tx, storage_map, cancel) = bundle.replacement(110.into(), 11.into(), true);
+        assert!(cancel);
+        assert_eq!(tx.to(), Some(&from.into()));
+        assert_eq!(tx.gas(), Some(&CANCEL_TX_GAS.into()));
+        assert_eq!(tx.nonce(), Some(&7.into()));
+        assert!(storage_map.root_hashes.is_empty() && storage_map.slots.is_empty());
+        let first_cancel = H256::random();
+        bundle.replaced(tx, first_cancel, cancel, 13.into());
+
+        // the bumped replacement of the cancellation is a cancellation too
+        let (tx, _, cancel) = bundle.replacement(121.into(), 13.into(), false);
+        assert!(cancel);
+        assert_eq!(tx.to(), Some(&from.into()));
+        assert_eq!(tx.as_eip1559_ref().and_then(|tx| tx.max_fee_per_gas), Some(121.into()));
+        let second_cancel = H256::random();
+        bundle.replaced(tx, second_cancel, cancel, 16.into());
+
+        assert_eq!(bundle.cancel_tx_hashes, vec![first_cancel, second_cancel]);
+        assert_eq!(bundle.tx_hash(), second_cancel);
+        assert_eq!(bundle.fee_bumps, 2);
+        for tx_hash in [first_cancel, second_cancel] {
+            assert!(matches!(
+                bundle.outcome(tx_hash, TransactionReceipt::default()),
+                BundleOutcome::Cancelled { tx_hash: h } if h == tx_hash
+            ));
+        }
+        assert!(matches!(
+            bundle.outcome(bundle_tx_hash, TransactionReceipt::default()),
+            BundleOutcome::Mined { tx_hash, .. } if tx_hash == bundle_tx_hash
+        ));
+    }
+
+    #[test]
+    fn bundle_is_exhausted_after_last_fee_bump() {
+        let config = TransactionTrackerConfig::default();
+        let mut bundle = pending_bundle();
+        assert!(!config.is_exhausted(&bundle, 100.into()));
+
+        bundle.fee_bumps = config.max_fee_bumps;
+        assert!(!config.is_exhausted(&bundle, 11.into()));
+        assert!(config.is_exhausted(&bundle, (10 + config.bump_after_blocks).into()));
+    }
+
+    #[tokio::test]
+    async fn tracker_gives_up_after_failed_checks() {
+        // the mocked provider has no responses, so every check fails
+        let (provider, _mock) = ethers::providers::Provider::mocked();
+        let tracker = TransactionTracker::new(
+            Arc::new(provider),
+            TransactionTrackerConfig {
+                max_errors: 3,
+                poll_interval: Duration::from_millis(1),
+                ..Default::default()
+            },
+        );
+        let bundle = pending_bundle();
+        let signer = bundle.from();
+        tracker.add(bundle.clone());
+        assert!(tracker.has_pending(&signer));
+
+        assert!(matches!(tracker.track(&NoopClient, bundle).await, BundleOutcome::Dropped));
+        assert!(!tracker.has_pending(&signer));
+    }
+}
@@ -46,6 +46,37 @@ fn compile_aa_smart_contracts() -> eyre::Result<()> {
     Ok(())
 }
 
+fn compile_aa_smart_contracts_v07() -> eyre::Result<()> {
+    let root =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("thirdparty/account-abstraction-v0.7");
+    let target = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR not set")).join("v0_7");
+    let build_info = root.join("contracts").join("build-info");
+
+    // compile interfaces
+    compile(&root, &root.join("contracts").join("interfaces"), &build_info, &target)?;
+
+    // compile sender creator smart contract
+    compile(
+        &root,
+        &root.join("contracts").join("core").join("SenderCreator.sol"),
+        &build_info,
+        &target,
+    )?;
+
+    // compile entry point simulations smart contract (deployed bytecode is used for state
+    // overrides)
+    compile(
+        &root,
+        &root.join("contracts").join("core").join("EntryPointSimulations.sol"),
+        &build_info,
+        &target,
+    )?;
+
+    Ok(())
+}
+
 fn main() {
     compile_aa_smart_contracts().expect("Compiling ERC-4337 smart contracts should pass.");
+    compile_aa_smart_contracts_v07()
+        .expect("Compiling ERC-4337 smart contracts (v0.7) should pass.");
 }
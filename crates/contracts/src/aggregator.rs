@@ -0,0 +1,116 @@
+use super::gen::{v0_7::aggregator_api::AggregatorAPI as AggregatorAPIV07, AggregatorAPI};
+use crate::error::EntryPointError;
+use ethers::{
+    prelude::ContractError,
+    providers::Middleware,
+    types::{Address, Bytes},
+};
+use silius_primitives::{entry_point::EntryPointVersion, UserOperationSigned};
+use std::sync::Arc;
+
+/// Signature aggregator smart contract (`IAggregator`)
+#[derive(Clone)]
+pub struct Aggregator<M: Middleware + 'static> {
+    address: Address,
+    version: EntryPointVersion,
+    aggregator_api: AggregatorAPI<M>,
+    aggregator_api_v07: AggregatorAPIV07<M>,
+}
+
+impl<M: Middleware + 'static> Aggregator<M> {
+    /// Creates a new aggregator that validates user operations of the given entry point version
+    pub fn new(eth_client: Arc<M>, address: Address, version: EntryPointVersion) -> Self {
+        let aggregator_api = AggregatorAPI::new(address, eth_client.clone());
+        let aggregator_api_v07 = AggregatorAPIV07::new(address, eth_client);
+        Self { address, version, aggregator_api, aggregator_api_v07 }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    fn map_error(method: &str, err: ContractError<M>) -> EntryPointError {
+        match err {
+            ContractError::ProviderError { e } => {
+                EntryPointError::Provider { inner: format!("{method} error: {e:?}") }
+            }
+            ContractError::MiddlewareError { e } => {
+                EntryPointError::Provider { inner: format!("{method} error: {e:?}") }
+            }
+            _ => EntryPointError::Other { inner: format!("{method} error: {err:?}") },
+        }
+    }
+
+    /// Validates the signature of a single user operation and returns the signature that should
+    /// be used for aggregation (`validateUserOpSignature`)
+    pub async fn validate_user_op_signature<U: Into<UserOperationSigned>>(
+        &self,
+        uo: U,
+    ) -> Result<Bytes, EntryPointError> {
+        let uo: UserOperationSigned = uo.into();
+        let res = match self.version {
+            EntryPointVersion::V0_6 => {
+                self.aggregator_api.validate_user_op_signature(uo.into()).call().await
+            }
+            EntryPointVersion::V0_7 => {
+                self.aggregator_api_v07.validate_user_op_signature(uo.into()).call().await
+            }
+        };
+        res.map_err(|e| Self::map_error("validate user op signature", e))
+    }
+
+    /// Aggregates the signatures of the user operations into a single signature
+    /// (`aggregateSignatures`)
+    pub async fn aggregate_signatures<U: Into<UserOperationSigned>>(
+        &self,
+        uos: Vec<U>,
+    ) -> Result<Bytes, EntryPointError> {
+        let uos = uos.into_iter().map(|u| u.into());
+        let res = match self.version {
+            EntryPointVersion::V0_6 => {
+                self.aggregator_api
+                    .aggregate_signatures(uos.map(|u: UserOperationSigned| u.into()).collect())
+                    .call()
+                    .await
+            }
+            EntryPointVersion::V0_7 => {
+                self.aggregator_api_v07
+                    .aggregate_signatures(uos.map(|u: UserOperationSigned| u.into()).collect())
+                    .call()
+                    .await
+            }
+        };
+        res.map_err(|e| Self::map_error("aggregate signatures", e))
+    }
+
+    /// Validates the aggregated signature of the user operations (`validateSignatures`), the call
+    /// reverts if the signature is invalid
+    pub async fn validate_signatures<U: Into<UserOperationSigned>>(
+        &self,
+        uos: Vec<U>,
+        signature: Bytes,
+    ) -> Result<(), EntryPointError> {
+        let uos = uos.into_iter().map(|u| u.into());
+        let res = match self.version {
+            EntryPointVersion::V0_6 => {
+                self.aggregator_api
+                    .validate_signatures(
+                        uos.map(|u: UserOperationSigned| u.into()).collect(),
+                        signature,
+                    )
+                    .call()
+                    .await
+            }
+            EntryPointVersion::V0_7 => {
+                self.aggregator_api_v07
+                    .validate_signatures(
+                        uos.map(|u: UserOperationSigned| u.into()).collect(),
+                        signature,
+                    )
+                    .call()
+                    .await
+            }
+        };
+        res.map_err(|e| Self::map_error("validate signatures", e))
+    }
+}
@@ -8,22 +8,44 @@ pub use super::{
 use super::{
     gen::{
         entry_point_api::{
-            EntryPointAPIErrors, SenderAddressResult, UserOperation, ValidationResult,
+            EntryPointAPIErrors, SenderAddressResult, ValidationResult,
             ValidationResultWithAggregation,
         },
         stake_manager_api::DepositInfo,
+        v0_7::{
+            entry_point_api::{EntryPointAPI as EntryPointAPIV07, FailedOpWithRevert},
+            entry_point_simulations_api::{
+                self, EntryPointSimulationsAPI, ENTRYPOINTSIMULATIONSAPI_DEPLOYED_BYTECODE,
+            },
+        },
     },
     tracer::JS_TRACER,
 };
-use crate::{error::decode_revert_error, executor_tracer::EXECUTOR_TRACER, gen::ExecutionResult};
+use crate::{
+    error::{decode_revert_error, failed_op_with_revert},
+    executor_tracer::EXECUTOR_TRACER,
+    gen::{
+        l2::{GasPriceOracleAPI, NodeInterfaceAPI},
+        ExecutionResult, FailedOp,
+    },
+    utils::{
+        parse_aggregators_from_input_data, parse_aggregators_from_input_data_v07,
+        parse_from_input_data, parse_from_input_data_v07,
+    },
+};
 use ethers::{
+    abi::AbiDecode,
     prelude::{ContractError, Event},
-    providers::Middleware,
+    providers::{Middleware, RawCall},
     types::{
         spoof, transaction::eip2718::TypedTransaction, Address, Bytes, GethDebugTracerType,
         GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, TransactionRequest, U256,
     },
 };
+use silius_primitives::{
+    chain::L1GasOracle, entry_point::EntryPointVersion, UserOperationSigned,
+    UserOperationsPerAggregator,
+};
 use std::sync::Arc;
 
 const UINT96_MAX: u128 = 5192296858534827628530496329220095;
@@ -34,25 +56,152 @@ pub enum SimulateValidationResult {
     ValidationResultWithAggregation(ValidationResultWithAggregation),
 }
 
+/// Signature validation failed (`validationData` of the entry point v0.7)
+const SIG_VALIDATION_FAILED: u64 = 1;
+/// Maximum value of `validUntil` (uint48)
+const UINT48_MAX: u64 = 281474976710655;
+
+/// Parses the `validationData` returned from the entry point v0.7 into the aggregator (or the
+/// signature validation failed flag), `validAfter` and `validUntil`
+fn parse_validation_data(validation_data: U256) -> (Address, u64, u64) {
+    let mut buf = [0u8; 32];
+    validation_data.to_big_endian(&mut buf);
+    let aggregator = Address::from_slice(&buf[12..32]);
+    let valid_until = U256::from_big_endian(&buf[6..12]).as_u64();
+    let valid_after = U256::from_big_endian(&buf[0..6]).as_u64();
+    (aggregator, valid_after, if valid_until == 0 { UINT48_MAX } else { valid_until })
+}
+
+impl From<entry_point_simulations_api::ValidationResult> for SimulateValidationResult {
+    fn from(res: entry_point_simulations_api::ValidationResult) -> Self {
+        let (aggregator, account_valid_after, account_valid_until) =
+            parse_validation_data(res.return_info.account_validation_data);
+        let (paymaster_sig, paymaster_valid_after, paymaster_valid_until) =
+            parse_validation_data(res.return_info.paymaster_validation_data);
+        let sig_failed = aggregator == Address::from_low_u64_be(SIG_VALIDATION_FAILED) ||
+            paymaster_sig == Address::from_low_u64_be(SIG_VALIDATION_FAILED);
+
+        let return_info = (
+            res.return_info.pre_op_gas,
+            res.return_info.prefund,
+            sig_failed,
+            account_valid_after.max(paymaster_valid_after),
+            account_valid_until.min(paymaster_valid_until),
+            res.return_info.paymaster_context,
+        );
+        let sender_info = (res.sender_info.stake, res.sender_info.unstake_delay_sec);
+        let factory_info = (res.factory_info.stake, res.factory_info.unstake_delay_sec);
+        let paymaster_info = (res.paymaster_info.stake, res.paymaster_info.unstake_delay_sec);
+
+        if aggregator.is_zero() || aggregator == Address::from_low_u64_be(SIG_VALIDATION_FAILED) {
+            SimulateValidationResult::ValidationResult(ValidationResult {
+                return_info,
+                sender_info,
+                factory_info,
+                paymaster_info,
+            })
+        } else {
+            SimulateValidationResult::ValidationResultWithAggregation(
+                ValidationResultWithAggregation {
+                    return_info,
+                    sender_info,
+                    factory_info,
+                    paymaster_info,
+                    aggregator_info: (
+                        res.aggregator_info.aggregator,
+                        (
+                            res.aggregator_info.stake_info.stake,
+                            res.aggregator_info.stake_info.unstake_delay_sec,
+                        ),
+                    ),
+                },
+            )
+        }
+    }
+}
+
+impl From<entry_point_simulations_api::ExecutionResult> for ExecutionResult {
+    fn from(res: entry_point_simulations_api::ExecutionResult) -> Self {
+        let (_, account_valid_after, account_valid_until) =
+            parse_validation_data(res.account_validation_data);
+        let (_, paymaster_valid_after, paymaster_valid_until) =
+            parse_validation_data(res.paymaster_validation_data);
+
+        Self {
+            pre_op_gas: res.pre_op_gas,
+            paid: res.paid,
+            valid_after: account_valid_after.max(paymaster_valid_after),
+            valid_until: account_valid_until.min(paymaster_valid_until),
+            target_success: res.target_success,
+            target_result: res.target_result,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EntryPoint<M: Middleware + 'static> {
     eth_client: Arc<M>,
     address: Address,
+    version: EntryPointVersion,
     entry_point_api: EntryPointAPI<M>,
+    entry_point_api_v07: EntryPointAPIV07<M>,
+    entry_point_simulations_api_v07: EntryPointSimulationsAPI<M>,
     stake_manager_api: StakeManagerAPI<M>,
 }
 
 impl<M: Middleware + 'static> EntryPoint<M> {
+    /// Creates a new entry point, the version is determined from the address (see
+    /// [EntryPointVersion::from_address])
     pub fn new(eth_client: Arc<M>, address: Address) -> Self {
+        Self::new_with_version(eth_client, address, EntryPointVersion::from_address(&address))
+    }
+
+    /// Creates a new entry point of the given version
+    pub fn new_with_version(
+        eth_client: Arc<M>,
+        address: Address,
+        version: EntryPointVersion,
+    ) -> Self {
         let entry_point_api = EntryPointAPI::new(address, eth_client.clone());
+        let entry_point_api_v07 = EntryPointAPIV07::new(address, eth_client.clone());
+        let entry_point_simulations_api_v07 =
+            EntryPointSimulationsAPI::new(address, eth_client.clone());
         let stake_manager_api = StakeManagerAPI::new(address, eth_client.clone());
-        Self { eth_client, address, entry_point_api, stake_manager_api }
+        Self {
+            eth_client,
+            address,
+            version,
+            entry_point_api,
+            entry_point_api_v07,
+            entry_point_simulations_api_v07,
+            stake_manager_api,
+        }
     }
 
     pub fn entry_point_api(&self) -> &EntryPointAPI<M> {
         &self.entry_point_api
     }
 
+    pub fn version(&self) -> EntryPointVersion {
+        self.version
+    }
+
+    /// State override that replaces the code of the entry point v0.7 with the
+    /// `EntryPointSimulations` smart contract
+    fn simulations_state_override(&self) -> spoof::State {
+        spoof::code(self.address, ENTRYPOINTSIMULATIONSAPI_DEPLOYED_BYTECODE.clone())
+    }
+
+    /// Merges the requested state override with the `EntryPointSimulations` code override (for
+    /// the entry point v0.7)
+    fn merge_state_override(&self, state_override: Option<&spoof::State>) -> spoof::State {
+        let mut state = state_override.cloned().unwrap_or_default();
+        if self.version == EntryPointVersion::V0_7 {
+            state.account(self.address).code(ENTRYPOINTSIMULATIONSAPI_DEPLOYED_BYTECODE.clone());
+        }
+        state
+    }
+
     pub fn events(&self) -> Event<Arc<M>, M, EntryPointAPIEvents> {
         self.entry_point_api.events()
     }
@@ -80,10 +229,30 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
-    pub async fn simulate_validation<U: Into<UserOperation>>(
+    pub async fn simulate_validation<U: Into<UserOperationSigned>>(
         &self,
         uo: U,
     ) -> Result<SimulateValidationResult, EntryPointError> {
+        let uo: UserOperationSigned = uo.into();
+
+        if self.version == EntryPointVersion::V0_7 {
+            return self
+                .entry_point_simulations_api_v07
+                .simulate_validation(uo.into())
+                .call_raw()
+                .state(&self.simulations_state_override())
+                .await
+                .map(|res| res.into())
+                .or_else(|e| {
+                    Self::deserialize_error_msg(e).and_then(|op| match op {
+                        EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
+                        _ => Err(EntryPointError::Other {
+                            inner: format!("simulate validation error: {op:?}"),
+                        }),
+                    })
+                });
+        }
+
         let res = self.entry_point_api.simulate_validation(uo.into()).await;
 
         match res {
@@ -103,16 +272,25 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
-    pub async fn simulate_validation_trace<U: Into<UserOperation>>(
+    pub async fn simulate_validation_trace<U: Into<UserOperationSigned>>(
         &self,
         uo: U,
     ) -> Result<GethTrace, EntryPointError> {
-        let call = self.entry_point_api.simulate_validation(uo.into());
+        let uo: UserOperationSigned = uo.into();
+        let (tx, state_overrides): (TypedTransaction, _) = match self.version {
+            EntryPointVersion::V0_6 => {
+                (self.entry_point_api.simulate_validation(uo.into()).tx, None)
+            }
+            EntryPointVersion::V0_7 => (
+                self.entry_point_simulations_api_v07.simulate_validation(uo.into()).tx,
+                Some(self.simulations_state_override()),
+            ),
+        };
 
         let res = self
             .eth_client
             .debug_trace_call(
-                call.tx,
+                tx,
                 None,
                 GethDebugTracingCallOptions {
                     tracing_options: GethDebugTracingOptions {
@@ -124,7 +302,7 @@ impl<M: Middleware + 'static> EntryPoint<M> {
                         tracer_config: None,
                         timeout: None,
                     },
-                    state_overrides: None,
+                    state_overrides,
                     block_overrides: None,
                 },
             )
@@ -136,14 +314,27 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         Ok(res)
     }
 
-    pub async fn simulate_handle_op_trace<U: Into<UserOperation>>(
+    pub async fn simulate_handle_op_trace<U: Into<UserOperationSigned>>(
         &self,
         uo: U,
+        state_override: Option<&spoof::State>,
     ) -> Result<GethTrace, EntryPointError> {
-        let uo = uo.into();
+        let uo: UserOperationSigned = uo.into();
         let max_fee_per_gas = uo.max_fee_per_gas;
-        let call = self.entry_point_api.simulate_handle_op(uo, Address::zero(), Bytes::default());
-        let mut tx: TypedTransaction = call.tx;
+        let mut tx: TypedTransaction = match self.version {
+            EntryPointVersion::V0_6 => {
+                self.entry_point_api
+                    .simulate_handle_op(uo.into(), Address::zero(), Bytes::default())
+                    .tx
+            }
+            EntryPointVersion::V0_7 => {
+                self.entry_point_simulations_api_v07
+                    .simulate_handle_op(uo.into(), Address::zero(), Bytes::default())
+                    .tx
+            }
+        };
+        let mut state_overrides = self.merge_state_override(state_override);
+        state_overrides.account(Address::zero()).balance(UINT96_MAX.into());
         tx.set_from(Address::zero());
         tx.set_gas_price(max_fee_per_gas);
         tx.set_gas(u64::MAX);
@@ -162,7 +353,7 @@ impl<M: Middleware + 'static> EntryPoint<M> {
                         tracer_config: None,
                         timeout: None,
                     },
-                    state_overrides: Some(spoof::balance(Address::zero(), UINT96_MAX.into())),
+                    state_overrides: Some(state_overrides),
                     block_overrides: None,
                 },
             )
@@ -174,21 +365,120 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         Ok(res)
     }
 
-    pub async fn handle_ops<U: Into<UserOperation>>(
+    /// Builds the `handleOps` transaction (calldata depends on the version of the entry point)
+    pub fn handle_ops_tx<U: Into<UserOperationSigned>>(
+        &self,
+        uos: Vec<U>,
+        beneficiary: Address,
+    ) -> TypedTransaction {
+        let uos = uos.into_iter().map(|u| u.into());
+        match self.version {
+            EntryPointVersion::V0_6 => {
+                self.entry_point_api.handle_ops(uos.map(|u| u.into()).collect(), beneficiary).tx
+            }
+            EntryPointVersion::V0_7 => {
+                self.entry_point_api_v07.handle_ops(uos.map(|u| u.into()).collect(), beneficiary).tx
+            }
+        }
+    }
+
+    /// Builds the `handleAggregatedOps` transaction for the user operations grouped per
+    /// aggregator
+    pub fn handle_aggregated_ops_tx(
+        &self,
+        uos_per_aggregator: Vec<UserOperationsPerAggregator>,
+        beneficiary: Address,
+    ) -> TypedTransaction {
+        match self.version {
+            EntryPointVersion::V0_6 => {
+                self.entry_point_api
+                    .handle_aggregated_ops(
+                        uos_per_aggregator.into_iter().map(|u| u.into()).collect(),
+                        beneficiary,
+                    )
+                    .tx
+            }
+            EntryPointVersion::V0_7 => {
+                self.entry_point_api_v07
+                    .handle_aggregated_ops(
+                        uos_per_aggregator.into_iter().map(|u| u.into()).collect(),
+                        beneficiary,
+                    )
+                    .tx
+            }
+        }
+    }
+
+    /// Decodes the aggregators from the input data of the `handleAggregatedOps` transaction
+    pub fn decode_aggregators(&self, data: Bytes) -> Vec<Address> {
+        match self.version {
+            EntryPointVersion::V0_6 => parse_aggregators_from_input_data(data),
+            EntryPointVersion::V0_7 => parse_aggregators_from_input_data_v07(data),
+        }
+    }
+
+    /// Decodes user operations from the input data of the `handleOps` (or `handleAggregatedOps`)
+    /// transaction
+    pub fn decode_handle_ops(&self, data: Bytes) -> Option<Vec<UserOperationSigned>> {
+        match self.version {
+            EntryPointVersion::V0_6 => parse_from_input_data(data),
+            EntryPointVersion::V0_7 => parse_from_input_data_v07(data),
+        }
+    }
+
+    /// Decodes the output of the `simulateHandleOp` call (e.g., from a trace)
+    ///
+    /// Entry point v0.6 reverts with `ExecutionResult`, while `EntryPointSimulations` v0.7
+    /// returns it.
+    pub fn decode_simulate_handle_op_output(
+        &self,
+        output: &Bytes,
+    ) -> Result<ExecutionResult, EntryPointError> {
+        let decoded = match self.version {
+            EntryPointVersion::V0_6 => ExecutionResult::decode(output.as_ref()).ok(),
+            EntryPointVersion::V0_7 => {
+                entry_point_simulations_api::SimulateHandleOpReturn::decode(output.as_ref())
+                    .ok()
+                    .map(|res| res.0.into())
+            }
+        };
+        if let Some(decoded) = decoded {
+            return Ok(decoded);
+        }
+
+        if let Ok(decoded) = FailedOp::decode(output.as_ref()) {
+            return Err(EntryPointError::FailedOp(decoded));
+        };
+
+        if let Ok(decoded) = FailedOpWithRevert::decode(output.as_ref()) {
+            return Err(EntryPointError::FailedOp(failed_op_with_revert(decoded)));
+        };
+
+        Err(EntryPointError::Other {
+            inner: "output of parse simulate handle op is not valid".to_string(),
+        })
+    }
+
+    pub async fn handle_ops<U: Into<UserOperationSigned>>(
         &self,
         uos: Vec<U>,
         beneficiary: Address,
     ) -> Result<(), EntryPointError> {
-        self.entry_point_api
-            .handle_ops(uos.into_iter().map(|u| u.into()).collect(), beneficiary)
-            .call()
-            .await
-            .or_else(|e| {
-                Self::deserialize_error_msg(e).and_then(|op| match op {
-                    EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
-                    _ => Err(EntryPointError::Other { inner: format!("handle ops error: {op:?}") }),
-                })
+        let uos = uos.into_iter().map(|u| u.into());
+        let call = match self.version {
+            EntryPointVersion::V0_6 => {
+                self.entry_point_api.handle_ops(uos.map(|u| u.into()).collect(), beneficiary)
+            }
+            EntryPointVersion::V0_7 => {
+                self.entry_point_api_v07.handle_ops(uos.map(|u| u.into()).collect(), beneficiary)
+            }
+        };
+        call.call().await.or_else(|e| {
+            Self::deserialize_error_msg(e).and_then(|op| match op {
+                EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
+                _ => Err(EntryPointError::Other { inner: format!("handle ops error: {op:?}") }),
             })
+        })
     }
 
     pub async fn get_deposit_info(&self, addr: &Address) -> Result<DepositInfo, EntryPointError> {
@@ -220,6 +510,62 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
+    /// Estimates the L1 data fee of a bundle with the user operation and converts it to L2 gas
+    ///
+    /// # Arguments
+    /// * `uo` - The user operation
+    /// * `oracle` - The [oracle](L1GasOracle) of the L1 data fee
+    /// * `gas_price` - The L2 gas price the fee is converted with (only used by the OP Stack)
+    ///
+    /// # Returns
+    /// * `U256` - The L1 data fee in L2 gas (zero on chains without the L1 data fee)
+    pub async fn l1_gas<U: Into<UserOperationSigned>>(
+        &self,
+        uo: U,
+        oracle: L1GasOracle,
+        gas_price: U256,
+    ) -> Result<U256, EntryPointError> {
+        // beneficiary with non-zero bytes so the calldata is not under-priced
+        let data = self
+            .handle_ops_tx(vec![uo], Address::repeat_byte(0xff))
+            .data()
+            .cloned()
+            .unwrap_or_default();
+
+        match oracle {
+            L1GasOracle::None => Ok(U256::zero()),
+            L1GasOracle::OpStack(address) => {
+                let res = GasPriceOracleAPI::new(address, self.eth_client.clone())
+                    .get_l1_fee(data)
+                    .call()
+                    .await;
+
+                match res {
+                    Ok(fee) => {
+                        let (gas, rem) = fee.div_mod(gas_price.max(U256::one()));
+                        Ok(if rem.is_zero() { gas } else { gas.saturating_add(U256::one()) })
+                    }
+                    Err(err) => {
+                        Err(EntryPointError::Other { inner: format!("get l1 fee error: {err:?}") })
+                    }
+                }
+            }
+            L1GasOracle::Arbitrum(address) => {
+                let res = NodeInterfaceAPI::new(address, self.eth_client.clone())
+                    .gas_estimate_l1_component(self.address, false, data)
+                    .call()
+                    .await;
+
+                match res {
+                    Ok((gas, _, _)) => Ok(gas.into()),
+                    Err(err) => Err(EntryPointError::Other {
+                        inner: format!("gas estimate l1 component error: {err:?}"),
+                    }),
+                }
+            }
+        }
+    }
+
     pub async fn get_sender_address(
         &self,
         init_code: Bytes,
@@ -238,11 +584,11 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
-    pub async fn simulate_execution<U: Into<UserOperation>>(
+    pub async fn simulate_execution<U: Into<UserOperationSigned>>(
         &self,
         uo: U,
     ) -> Result<Bytes, EntryPointError> {
-        let uo: UserOperation = uo.into();
+        let uo: UserOperationSigned = uo.into();
 
         self.eth_client
             .call(
@@ -257,14 +603,43 @@ impl<M: Middleware + 'static> EntryPoint<M> {
             .map_err(|err| EntryPointError::Provider { inner: err.to_string() })
     }
 
-    pub async fn simulate_handle_op<U: Into<UserOperation>>(
+    /// Simulates the user operation with `simulateHandleOp`
+    ///
+    /// # Arguments
+    /// * `uo` - The user operation
+    /// * `state_override` - Geth-style state override (balance, code, storage) applied to the call,
+    ///   e.g., to fund a counterfactual account
+    pub async fn simulate_handle_op<U: Into<UserOperationSigned>>(
         &self,
         uo: U,
+        state_override: Option<&spoof::State>,
     ) -> Result<ExecutionResult, EntryPointError> {
-        let res = self
-            .entry_point_api
-            .simulate_handle_op(uo.into(), Address::zero(), Bytes::default())
-            .await;
+        let uo: UserOperationSigned = uo.into();
+
+        if self.version == EntryPointVersion::V0_7 {
+            return self
+                .entry_point_simulations_api_v07
+                .simulate_handle_op(uo.into(), Address::zero(), Bytes::default())
+                .call_raw()
+                .state(&self.merge_state_override(state_override))
+                .await
+                .map(|res| res.into())
+                .or_else(|e| {
+                    Self::deserialize_error_msg(e).and_then(|op| match op {
+                        EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
+                        _ => Err(EntryPointError::Other {
+                            inner: format!("Simulate handle op error: {op:?}"),
+                        }),
+                    })
+                });
+        }
+
+        let call =
+            self.entry_point_api.simulate_handle_op(uo.into(), Address::zero(), Bytes::default());
+        let res = match state_override {
+            Some(state) => call.call_raw().state(state).await,
+            None => call.await,
+        };
 
         match res {
             Ok(_) => Err(EntryPointError::NoRevert { function: "simulate_handle_op".into() }),
@@ -278,20 +653,69 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
-    pub async fn handle_aggregated_ops<U: Into<UserOperation>>(
+    pub async fn handle_aggregated_ops(
         &self,
-        _uos_per_aggregator: Vec<U>,
-        _beneficiary: Address,
+        uos_per_aggregator: Vec<UserOperationsPerAggregator>,
+        beneficiary: Address,
     ) -> Result<(), EntryPointError> {
-        todo!()
+        let call = match self.version {
+            EntryPointVersion::V0_6 => self.entry_point_api.handle_aggregated_ops(
+                uos_per_aggregator.into_iter().map(|u| u.into()).collect(),
+                beneficiary,
+            ),
+            EntryPointVersion::V0_7 => self.entry_point_api_v07.handle_aggregated_ops(
+                uos_per_aggregator.into_iter().map(|u| u.into()).collect(),
+                beneficiary,
+            ),
+        };
+        call.call().await.or_else(|e| {
+            Self::deserialize_error_msg(e).and_then(|op| match op {
+                EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
+                EntryPointAPIErrors::SignatureValidationFailed(err) => {
+                    Err(EntryPointError::Other {
+                        inner: format!(
+                            "signature validation failed for aggregator {:?}",
+                            err.aggregator
+                        ),
+                    })
+                }
+                _ => Err(EntryPointError::Other {
+                    inner: format!("handle aggregated ops error: {op:?}"),
+                }),
+            })
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gen::entry_point_api::UserOperation;
     use ethers::providers::{Http, Provider};
 
+    #[test]
+    fn merge_state_override() {
+        let (provider, _) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let address = Address::random();
+
+        let mut state_override = spoof::State::default();
+        state_override.account(Address::random()).balance(U256::from(1_000_000));
+        state_override.account(address).balance(U256::from(1));
+
+        // the code of the entry point v0.7 is replaced with the `EntryPointSimulations`
+        let ep = EntryPoint::new_with_version(eth_client.clone(), address, EntryPointVersion::V0_7);
+        let mut expected = state_override.clone();
+        expected.account(address).code(ENTRYPOINTSIMULATIONSAPI_DEPLOYED_BYTECODE.clone());
+        assert_eq!(ep.merge_state_override(Some(&state_override)), expected);
+        assert_eq!(ep.merge_state_override(None), ep.simulations_state_override());
+
+        // the requested state override is passed as is for the entry point v0.6
+        let ep = EntryPoint::new_with_version(eth_client, address, EntryPointVersion::V0_6);
+        assert_eq!(ep.merge_state_override(Some(&state_override)), state_override);
+        assert_eq!(ep.merge_state_override(None), spoof::State::default());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn simulate_validation() {
@@ -326,4 +750,49 @@ mod tests {
 
         assert!(matches!(trace, GethTrace::Unknown { .. },));
     }
+    #[tokio::test]
+    async fn l1_gas_op_stack() {
+        let (provider, mock) = Provider::mocked();
+        // `getL1Fee` returns the L1 data fee in wei
+        mock.push(Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Uint(1001.into())])))
+            .unwrap();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+
+        // the fee is converted to L2 gas and rounded up
+        let gas = ep
+            .l1_gas(UserOperationSigned::default(), L1GasOracle::op_stack(), 10.into())
+            .await
+            .unwrap();
+        assert_eq!(gas, 101.into());
+    }
+
+    #[tokio::test]
+    async fn l1_gas_arbitrum() {
+        let (provider, mock) = Provider::mocked();
+        // `gasEstimateL1Component` returns the L1 gas, the base fee and the L1 base fee estimate
+        mock.push(Bytes::from(ethers::abi::encode(&[
+            ethers::abi::Token::Uint(2500.into()),
+            ethers::abi::Token::Uint(100.into()),
+            ethers::abi::Token::Uint(30.into()),
+        ])))
+        .unwrap();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+
+        let gas = ep
+            .l1_gas(UserOperationSigned::default(), L1GasOracle::arbitrum(), U256::zero())
+            .await
+            .unwrap();
+        assert_eq!(gas, 2500.into());
+    }
+
+    #[tokio::test]
+    async fn l1_gas_without_oracle() {
+        // no call is made, the mocked provider has no responses
+        let (provider, _mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+
+        let gas =
+            ep.l1_gas(UserOperationSigned::default(), L1GasOracle::None, 10.into()).await.unwrap();
+        assert!(gas.is_zero());
+    }
 }
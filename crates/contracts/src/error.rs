@@ -1,4 +1,4 @@
-use crate::gen::{EntryPointAPIErrors, FailedOp};
+use crate::gen::{v0_7::entry_point_api::FailedOpWithRevert, EntryPointAPIErrors, FailedOp};
 use ethers::{
     abi::AbiDecode,
     providers::{JsonRpcError, Middleware, MiddlewareError, ProviderError},
@@ -146,11 +146,27 @@ pub fn decode_revert_string(data: Bytes) -> Option<String> {
     }
 }
 
+/// Converts the `FailedOpWithRevert` error of the entry point v0.7 to a [FailedOp], the revert
+/// reason of the inner call (e.g., of the paymaster) is appended to the reason
+pub fn failed_op_with_revert(err: FailedOpWithRevert) -> FailedOp {
+    let inner = if err.inner.len() >= 4 { decode_revert_string(err.inner.clone()) } else { None };
+    let reason = match inner {
+        Some(inner) => format!("{} {inner}", err.reason),
+        None if err.inner.is_empty() => err.reason,
+        None => format!("{} {}", err.reason, err.inner),
+    };
+    FailedOp { op_index: err.op_index, reason }
+}
+
 pub fn decode_revert_error(data: Bytes) -> Result<EntryPointAPIErrors, EntryPointError> {
     let decoded = EntryPointAPIErrors::decode(data.as_ref());
     match decoded {
         Ok(res) => Ok(res),
         Err(e) => {
+            if let Ok(err) = FailedOpWithRevert::decode(data.as_ref()) {
+                return Ok(EntryPointAPIErrors::FailedOp(failed_op_with_revert(err)));
+            }
+
             if let Some(error_str) = decode_revert_string(data) {
                 return Ok(EntryPointAPIErrors::RevertString(error_str));
             };
@@ -167,6 +183,7 @@ pub fn decode_revert_error(data: Bytes) -> Result<EntryPointAPIErrors, EntryPoin
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ethers::{abi::AbiEncode, types::U256};
 
     #[test]
     fn deserialize_error_msg() -> eyre::Result<()> {
@@ -200,4 +217,21 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn deserialize_failed_op_with_revert() -> eyre::Result<()> {
+        // `Error(string)` revert of the paymaster
+        let inner = Bytes::from_str("0x08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000086e6f742061626c65000000000000000000000000000000000000000000000000")?;
+        let err_msg =
+            FailedOpWithRevert { op_index: U256::from(1), reason: "AA33 reverted".into(), inner }
+                .encode();
+        match decode_revert_error(err_msg.into())? {
+            EntryPointAPIErrors::FailedOp(f) => {
+                assert_eq!(f.op_index, U256::from(1));
+                assert_eq!(f.reason, "AA33 reverted not able")
+            }
+            _ => panic!("Invalid error message"),
+        }
+        Ok(())
+    }
 }
@@ -6,11 +6,45 @@ use lazy_static::lazy_static;
 use std::collections::HashMap;
 
 abigen!(AccountAPI, "$OUT_DIR/IAccount.sol/IAccount.json");
+abigen!(AggregatorAPI, "$OUT_DIR/IAggregator.sol/IAggregator.json");
 abigen!(EntryPointAPI, "$OUT_DIR/IEntryPoint.sol/IEntryPoint.json");
 abigen!(PaymasterAPI, "$OUT_DIR/IPaymaster.sol/IPaymaster.json");
 abigen!(SenderCreatorAPI, "$OUT_DIR/SenderCreator.sol/SenderCreator.json");
 abigen!(StakeManagerAPI, "$OUT_DIR/IStakeManager.sol/IStakeManager.json");
 
+/// Smart contract interfaces of the entry point v0.7
+pub mod v0_7 {
+    use ethers::contract::abigen;
+
+    abigen!(AccountAPI, "$OUT_DIR/v0_7/IAccount.sol/IAccount.json");
+    abigen!(AggregatorAPI, "$OUT_DIR/v0_7/IAggregator.sol/IAggregator.json");
+    abigen!(EntryPointAPI, "$OUT_DIR/v0_7/IEntryPoint.sol/IEntryPoint.json");
+    abigen!(PaymasterAPI, "$OUT_DIR/v0_7/IPaymaster.sol/IPaymaster.json");
+    abigen!(SenderCreatorAPI, "$OUT_DIR/v0_7/SenderCreator.sol/SenderCreator.json");
+    abigen!(
+        EntryPointSimulationsAPI,
+        "$OUT_DIR/v0_7/EntryPointSimulations.sol/EntryPointSimulations.json"
+    );
+}
+
+/// Interfaces of the L2 system contracts that price the L1 data fee
+pub mod l2 {
+    use ethers::contract::abigen;
+
+    abigen!(
+        GasPriceOracleAPI,
+        r#"[
+            function getL1Fee(bytes memory _data) external view returns (uint256)
+        ]"#
+    );
+    abigen!(
+        NodeInterfaceAPI,
+        r#"[
+            function gasEstimateL1Component(address to, bool contractCreation, bytes calldata data) external payable returns (uint64 gasEstimateForL1, uint256 baseFee, uint256 l1BaseFeeEstimate)
+        ]"#
+    );
+}
+
 lazy_static! {
     pub static ref SELECTORS_NAMES: HashMap<Selector, String> = {
         let mut map = HashMap::new();
@@ -34,6 +68,16 @@ lazy_static! {
         map.insert(account_api::ValidateUserOpCall::selector(), account_api::ValidateUserOpCall::function_name().into());
         // paymaster
         map.insert(paymaster_api::ValidatePaymasterUserOpCall::selector(), paymaster_api::ValidatePaymasterUserOpCall::function_name().into());
+        // entry point (v0.7)
+        map.insert(v0_7::entry_point_api::HandleAggregatedOpsCall::selector(), v0_7::entry_point_api::HandleAggregatedOpsCall::function_name().into());
+        map.insert(v0_7::entry_point_api::HandleOpsCall::selector(), v0_7::entry_point_api::HandleOpsCall::function_name().into());
+        map.insert(v0_7::entry_point_api::GetUserOpHashCall::selector(), v0_7::entry_point_api::GetUserOpHashCall::function_name().into());
+        map.insert(v0_7::entry_point_simulations_api::SimulateHandleOpCall::selector(), v0_7::entry_point_simulations_api::SimulateHandleOpCall::function_name().into());
+        map.insert(v0_7::entry_point_simulations_api::SimulateValidationCall::selector(), v0_7::entry_point_simulations_api::SimulateValidationCall::function_name().into());
+        // account (v0.7)
+        map.insert(v0_7::account_api::ValidateUserOpCall::selector(), v0_7::account_api::ValidateUserOpCall::function_name().into());
+        // paymaster (v0.7)
+        map.insert(v0_7::paymaster_api::ValidatePaymasterUserOpCall::selector(), v0_7::paymaster_api::ValidatePaymasterUserOpCall::function_name().into());
         map
     };
     pub static ref SELECTORS_INDICES: HashMap<Selector, usize> = {
@@ -42,8 +86,10 @@ lazy_static! {
         map.insert(sender_creator_api::CreateSenderCall::selector(), 0);
         // sender/account
         map.insert(account_api::ValidateUserOpCall::selector(), 1);
+        map.insert(v0_7::account_api::ValidateUserOpCall::selector(), 1);
         // paymaster
         map.insert(paymaster_api::ValidatePaymasterUserOpCall::selector(), 2);
+        map.insert(v0_7::paymaster_api::ValidatePaymasterUserOpCall::selector(), 2);
         map
     };
 }
@@ -1,39 +1,128 @@
-use crate::gen::entry_point_api::{self, EntryPointAPICalls};
-use ethers::{abi::AbiDecode, types::Bytes};
-use silius_primitives::UserOperationSigned;
+use crate::gen::{
+    aggregator_api,
+    entry_point_api::{self, EntryPointAPICalls},
+    v0_7::{
+        aggregator_api as aggregator_api_v07, entry_point_api as entry_point_api_v07,
+        entry_point_simulations_api,
+    },
+};
+use ethers::{
+    abi::AbiDecode,
+    types::{Address, Bytes},
+};
+use silius_primitives::{PackedUserOperation, UserOperationSigned, UserOperationsPerAggregator};
 
-impl From<UserOperationSigned> for entry_point_api::UserOperation {
-    fn from(uo: UserOperationSigned) -> Self {
+macro_rules! impl_user_operation_conversions {
+    ($($uo:path),*) => {
+        $(
+            impl From<UserOperationSigned> for $uo {
+                fn from(uo: UserOperationSigned) -> Self {
+                    Self {
+                        sender: uo.sender,
+                        nonce: uo.nonce,
+                        init_code: uo.init_code,
+                        call_data: uo.call_data,
+                        call_gas_limit: uo.call_gas_limit,
+                        verification_gas_limit: uo.verification_gas_limit,
+                        pre_verification_gas: uo.pre_verification_gas,
+                        max_fee_per_gas: uo.max_fee_per_gas,
+                        max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
+                        paymaster_and_data: uo.paymaster_and_data,
+                        signature: uo.signature,
+                    }
+                }
+            }
+
+            impl From<$uo> for UserOperationSigned {
+                fn from(uo: $uo) -> Self {
+                    Self {
+                        sender: uo.sender,
+                        nonce: uo.nonce,
+                        init_code: uo.init_code,
+                        call_data: uo.call_data,
+                        call_gas_limit: uo.call_gas_limit,
+                        verification_gas_limit: uo.verification_gas_limit,
+                        pre_verification_gas: uo.pre_verification_gas,
+                        max_fee_per_gas: uo.max_fee_per_gas,
+                        max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
+                        paymaster_and_data: uo.paymaster_and_data,
+                        signature: uo.signature,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_user_operation_conversions!(entry_point_api::UserOperation, aggregator_api::UserOperation);
+
+macro_rules! impl_packed_user_operation_conversions {
+    ($($packed:path),*) => {
+        $(
+            impl From<PackedUserOperation> for $packed {
+                fn from(uo: PackedUserOperation) -> Self {
+                    Self {
+                        sender: uo.sender,
+                        nonce: uo.nonce,
+                        init_code: uo.init_code,
+                        call_data: uo.call_data,
+                        account_gas_limits: uo.account_gas_limits,
+                        pre_verification_gas: uo.pre_verification_gas,
+                        gas_fees: uo.gas_fees,
+                        paymaster_and_data: uo.paymaster_and_data,
+                        signature: uo.signature,
+                    }
+                }
+            }
+
+            impl From<UserOperationSigned> for $packed {
+                fn from(uo: UserOperationSigned) -> Self {
+                    PackedUserOperation::from(uo).into()
+                }
+            }
+
+            impl From<$packed> for UserOperationSigned {
+                fn from(uo: $packed) -> Self {
+                    PackedUserOperation {
+                        sender: uo.sender,
+                        nonce: uo.nonce,
+                        init_code: uo.init_code,
+                        call_data: uo.call_data,
+                        account_gas_limits: uo.account_gas_limits,
+                        pre_verification_gas: uo.pre_verification_gas,
+                        gas_fees: uo.gas_fees,
+                        paymaster_and_data: uo.paymaster_and_data,
+                        signature: uo.signature,
+                    }
+                    .into()
+                }
+            }
+        )*
+    };
+}
+
+impl_packed_user_operation_conversions!(
+    entry_point_api_v07::PackedUserOperation,
+    entry_point_simulations_api::PackedUserOperation,
+    aggregator_api_v07::PackedUserOperation
+);
+
+impl From<UserOperationsPerAggregator> for entry_point_api::UserOpsPerAggregator {
+    fn from(uos: UserOperationsPerAggregator) -> Self {
         Self {
-            sender: uo.sender,
-            nonce: uo.nonce,
-            init_code: uo.init_code,
-            call_data: uo.call_data,
-            call_gas_limit: uo.call_gas_limit,
-            verification_gas_limit: uo.verification_gas_limit,
-            pre_verification_gas: uo.pre_verification_gas,
-            max_fee_per_gas: uo.max_fee_per_gas,
-            max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
-            paymaster_and_data: uo.paymaster_and_data,
-            signature: uo.signature,
+            user_ops: uos.user_operations.into_iter().map(|uo| uo.user_operation.into()).collect(),
+            aggregator: uos.aggregator,
+            signature: uos.signature,
         }
     }
 }
 
-impl From<entry_point_api::UserOperation> for UserOperationSigned {
-    fn from(uo: entry_point_api::UserOperation) -> Self {
+impl From<UserOperationsPerAggregator> for entry_point_api_v07::UserOpsPerAggregator {
+    fn from(uos: UserOperationsPerAggregator) -> Self {
         Self {
-            sender: uo.sender,
-            nonce: uo.nonce,
-            init_code: uo.init_code,
-            call_data: uo.call_data,
-            call_gas_limit: uo.call_gas_limit,
-            verification_gas_limit: uo.verification_gas_limit,
-            pre_verification_gas: uo.pre_verification_gas,
-            max_fee_per_gas: uo.max_fee_per_gas,
-            max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
-            paymaster_and_data: uo.paymaster_and_data,
-            signature: uo.signature,
+            user_ops: uos.user_operations.into_iter().map(|uo| uo.user_operation.into()).collect(),
+            aggregator: uos.aggregator,
+            signature: uos.signature,
         }
     }
 }
@@ -43,10 +132,52 @@ pub fn parse_from_input_data(data: Bytes) -> Option<Vec<UserOperationSigned>> {
         EntryPointAPICalls::HandleOps(ops) => {
             Some(ops.ops.into_iter().map(|op| op.into()).collect())
         }
+        EntryPointAPICalls::HandleAggregatedOps(ops) => Some(
+            ops.ops_per_aggregator
+                .into_iter()
+                .flat_map(|ops| ops.user_ops.into_iter().map(|op| op.into()))
+                .collect(),
+        ),
         _ => None,
     })
 }
 
+/// Parses user operations from the input data of the `handleOps` call (entry point v0.7)
+pub fn parse_from_input_data_v07(data: Bytes) -> Option<Vec<UserOperationSigned>> {
+    entry_point_api_v07::EntryPointAPICalls::decode(data).ok().and_then(|call| match call {
+        entry_point_api_v07::EntryPointAPICalls::HandleOps(ops) => {
+            Some(ops.ops.into_iter().map(|op| op.into()).collect())
+        }
+        entry_point_api_v07::EntryPointAPICalls::HandleAggregatedOps(ops) => Some(
+            ops.ops_per_aggregator
+                .into_iter()
+                .flat_map(|ops| ops.user_ops.into_iter().map(|op| op.into()))
+                .collect(),
+        ),
+        _ => None,
+    })
+}
+
+/// Parses the aggregators from the input data of the `handleAggregatedOps` call
+pub fn parse_aggregators_from_input_data(data: Bytes) -> Vec<Address> {
+    match EntryPointAPICalls::decode(data) {
+        Ok(EntryPointAPICalls::HandleAggregatedOps(ops)) => {
+            ops.ops_per_aggregator.into_iter().map(|ops| ops.aggregator).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Parses the aggregators from the input data of the `handleAggregatedOps` call (entry point v0.7)
+pub fn parse_aggregators_from_input_data_v07(data: Bytes) -> Vec<Address> {
+    match entry_point_api_v07::EntryPointAPICalls::decode(data) {
+        Ok(entry_point_api_v07::EntryPointAPICalls::HandleAggregatedOps(ops)) => {
+            ops.ops_per_aggregator.into_iter().map(|ops| ops.aggregator).collect()
+        }
+        _ => vec![],
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,7 +1,7 @@
 use crate::{
     proto::{
         bundler::*,
-        uopool::{GetSortedRequest, RemoveRequest},
+        uopool::{AddBundleRequest, GetSortedRequest, RemoveRequest},
     },
     uo_pool_client::UoPoolClient,
 };
@@ -9,15 +9,22 @@ use alloy_chains::Chain;
 use async_trait::async_trait;
 use ethers::{
     providers::Middleware,
+    signers::Signer,
     types::{Address, H256, U256},
 };
 use parking_lot::Mutex;
-use silius_bundler::{Bundler, SendBundleOp};
+use silius_bundler::{
+    Bundler, EntityMonitor, FundingPolicy, ProfitPolicy, SendBundleOp, SignerRotation,
+    TransactionTracker, TransactionTrackerConfig, Treasury, WatchPolicy,
+};
 use silius_metrics::grpc::MetricsLayer;
-use silius_primitives::{simulation::StorageMap, UserOperation, Wallet};
+use silius_primitives::{
+    bundler::BundleRecord, entry_point::EntryPointVersion, simulation::StorageMap, UserOperation,
+    UserOperationsPerAggregator, Wallet,
+};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tonic::{Request, Response, Status};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 pub struct BundlerService<M, S>
 where
@@ -49,19 +56,41 @@ where
     async fn get_user_operations(
         uopool_grpc_client: &UoPoolClient<tonic::transport::Channel>,
         ep: &Address,
-    ) -> eyre::Result<(Vec<UserOperation>, StorageMap)> {
+    ) -> eyre::Result<(Vec<UserOperationsPerAggregator>, StorageMap)> {
         let req = Request::new(GetSortedRequest { ep: Some((*ep).into()) });
         let res = uopool_grpc_client.clone().get_sorted_user_operations(req).await?;
 
         let res = res.into_inner();
 
-        let uos: Vec<UserOperation> = res.uos.into_iter().map(|u| u.into()).collect();
+        let uos_per_aggregator: Vec<UserOperationsPerAggregator> =
+            if res.uos_per_aggregator.is_empty() && !res.uos.is_empty() {
+                vec![UserOperationsPerAggregator {
+                    user_operations: res.uos.into_iter().map(|u| u.into()).collect(),
+                    ..Default::default()
+                }]
+            } else {
+                res.uos_per_aggregator.into_iter().map(|u| u.into()).collect()
+            };
         let map = match res.storage_map {
             Some(map) => map.into(),
             None => StorageMap::default(),
         };
 
-        Ok((uos, map))
+        Ok((uos_per_aggregator, map))
+    }
+
+    /// Stores the record of the sent bundle in the bundle history of the uopool
+    async fn add_bundle_record(
+        uopool_grpc_client: &UoPoolClient<tonic::transport::Channel>,
+        record: BundleRecord,
+    ) {
+        let req = Request::new(AddBundleRequest {
+            ep: Some(record.entry_point.into()),
+            record: Some(record.into()),
+        });
+        if let Err(e) = uopool_grpc_client.clone().add_bundle(req).await {
+            error!("Error while storing the bundle record: {e:?}");
+        }
     }
 
     pub async fn send_bundles(&self) -> eyre::Result<(Vec<UserOperation>, Option<H256>)> {
@@ -69,12 +98,27 @@ where
         let mut user_operations: Vec<Vec<UserOperation>> = vec![];
 
         for bundler in self.bundlers.iter() {
-            let (uos, map) =
+            let (uos_per_aggregator, map) =
                 Self::get_user_operations(&self.uopool_grpc_client, &bundler.entry_point).await?;
-            let tx_hash = bundler.send_bundle(&uos, map).await?;
-
-            tx_hashes.push(tx_hash);
-            user_operations.push(uos);
+            // user operations dropped from the bundle (e.g., unprofitable ones) stay in the mempool
+            match bundler.send_bundle(&uos_per_aggregator, map).await? {
+                Some((record, uos)) => {
+                    let released = record.is_released();
+                    let tx_hash = record.transaction_hash;
+                    Self::add_bundle_record(&self.uopool_grpc_client, record).await;
+                    if !released {
+                        tx_hashes.push(Some(tx_hash));
+                        user_operations.push(uos);
+                    } else {
+                        tx_hashes.push(None);
+                        user_operations.push(vec![]);
+                    }
+                }
+                None => {
+                    tx_hashes.push(None);
+                    user_operations.push(vec![]);
+                }
+            }
         }
 
         // FIXME: Because currently the bundler support multiple bundler and
@@ -118,6 +162,11 @@ where
                             break;
                         }
 
+                        // every signer has at most one pending bundle
+                        if !bundler_own.has_available_signer() {
+                            continue;
+                        }
+
                         match Self::get_user_operations(
                             &uopool_grpc_client,
                             &bundler_own.entry_point,
@@ -125,9 +174,21 @@ where
                         .await
                         {
                             Ok((bundle, map)) => {
-                                if let Err(e) = bundler_own.send_bundle(&bundle, map).await {
-                                    error!("Error while sending bundle: {e:?}");
-                                }
+                                // bundles of different signers are pending at the same time
+                                let bundler = bundler_own.clone();
+                                let uopool_grpc_client = uopool_grpc_client.clone();
+                                tokio::spawn(async move {
+                                    match bundler.send_bundle(&bundle, map).await {
+                                        Ok(Some((record, _))) => {
+                                            Self::add_bundle_record(&uopool_grpc_client, record)
+                                                .await;
+                                        }
+                                        Ok(None) => {}
+                                        Err(e) => {
+                                            error!("Error while sending bundle: {e:?}");
+                                        }
+                                    }
+                                });
                             }
                             Err(e) => {
                                 error!("Error while creating bundle: {e:?}");
@@ -214,7 +275,7 @@ where
 pub fn bundler_service_run<M, S>(
     addr: SocketAddr,
     wallet: Wallet,
-    eps: Vec<Address>,
+    eps: Vec<(Address, EntryPointVersion)>,
     chain: Chain,
     beneficiary: Address,
     min_balance: U256,
@@ -224,14 +285,45 @@ pub fn bundler_service_run<M, S>(
     uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
     enable_metrics: bool,
     enable_access_list: bool,
+    profit_policy: Option<ProfitPolicy>,
+    tracker_config: TransactionTrackerConfig,
+    funding_policy: Option<FundingPolicy>,
+    watch_policy: Option<WatchPolicy>,
 ) where
     M: Middleware + Clone + 'static,
     S: SendBundleOp + Clone + 'static,
 {
+    // the treasury is shared, so that the signers are not refunded once per entry point
+    let treasury = funding_policy.map(|policy| Treasury::new(eth_client.clone(), policy));
+    // the signers and their pending bundles are shared, so that the bundlers of different entry
+    // points (running concurrently) never pick the same signer and nonce
+    let signers = SignerRotation::new(wallet.signers().iter().map(|s| s.address()).collect());
+    let tracker = TransactionTracker::new(eth_client.clone(), tracker_config);
+    let bundling = Arc::<tokio::sync::Mutex<()>>::default();
+    // the deposits and stakes of the watched entities are checked on every entry point
+    if let Some(watch_policy) = watch_policy {
+        for (ep, version) in eps.iter().copied() {
+            let monitor = EntityMonitor::new(eth_client.clone(), ep, version, watch_policy.clone());
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(monitor.policy().interval);
+                loop {
+                    interval.tick().await;
+                    if let Err(err) = monitor.check().await {
+                        warn!(
+                            "Failed to check the watched entities on entry point {ep:?}: {err:?}"
+                        );
+                    }
+                }
+            });
+        }
+    }
+
+    // the sent bundles are reported to the user operation pool before they are mined
+    let (submitted_sd, mut submitted_rv) = tokio::sync::mpsc::unbounded_channel::<BundleRecord>();
     let bundlers: Vec<Bundler<M, S>> = eps
         .into_iter()
-        .map(|ep| {
-            Bundler::new(
+        .map(|(ep, version)| {
+            let bundler = Bundler::new(
                 wallet.clone(),
                 beneficiary,
                 ep,
@@ -241,9 +333,33 @@ pub fn bundler_service_run<M, S>(
                 client.clone(),
                 enable_access_list,
             )
+            .with_entry_point_version(version)
+            .with_shared_signers(signers.clone(), tracker.clone(), bundling.clone())
+            .with_submitted_bundles(submitted_sd.clone());
+            let bundler = match treasury.clone() {
+                Some(treasury) => bundler.with_treasury(treasury),
+                None => bundler,
+            };
+            match profit_policy {
+                Some(profit_policy) => bundler.with_profit_policy(profit_policy),
+                None => bundler,
+            }
         })
         .collect();
 
+    let submitted_uopool_grpc_client = uopool_grpc_client.clone();
+    tokio::spawn(async move {
+        while let Some(record) = submitted_rv.recv().await {
+            let req = Request::new(AddBundleRequest {
+                ep: Some(record.entry_point.into()),
+                record: Some(record.into()),
+            });
+            if let Err(e) = submitted_uopool_grpc_client.clone().bundle_submitted(req).await {
+                error!("Error while reporting the submitted bundle: {e:?}");
+            }
+        }
+    });
+
     let bundler_service = BundlerService::new(bundlers, uopool_grpc_client);
     bundler_service.start_bundling(bundle_interval);
 
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 mod bundler;
+mod mempool_sync;
 mod proto;
 mod uopool;
 mod utils;
@@ -0,0 +1,233 @@
+use alloy_chains::Chain;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, H256, U256},
+};
+use futures::channel::mpsc::UnboundedSender;
+use silius_mempool::Mempool;
+use silius_p2p::{
+    rpc::{
+        methods::{
+            PooledUserOpHashesRequest, PooledUserOpHashesResponse, PooledUserOpsByHashRequest,
+            PooledUserOpsByHashResponse, RPCResponse,
+        },
+        outbound::OutboundRequest,
+        protocol::InboundRequest,
+    },
+    service::{Network, NetworkEvent, UserOperationSource},
+    PeerId,
+};
+use silius_primitives::{
+    constants::p2p::MAX_SYNC_PAGES, entry_point::EntryPointVersion, UserOperation,
+    VerifiedUserOperation,
+};
+use std::{collections::HashMap, sync::Arc};
+use tracing::{debug, info, warn};
+
+/// Synchronization of the mempool with the peers of the p2p network
+///
+/// A newly connected peer (that passed the status handshake) is asked for the hashes of its pooled
+/// user operations (page by page), the missing user operations are fetched by hash and go through
+/// the same validation as the gossiped ones. The requests of the peers are served from the local
+/// mempool.
+pub struct MempoolSync<M: Middleware + 'static> {
+    /// Ethereum execution client
+    eth_client: Arc<M>,
+    /// The chain the mempool is on
+    chain: Chain,
+    /// The mempool shared by the user operation pools
+    mempool: Mempool,
+    /// Versions and channels of the user operations received from the peers (per entry point)
+    uo_senders: HashMap<
+        Address,
+        (EntryPointVersion, UnboundedSender<(UserOperation, U256, UserOperationSource)>),
+    >,
+    /// The last requested offset of the pooled user operation hashes and the number of the
+    /// requested pages (per peer being synchronized)
+    sync_pages: HashMap<PeerId, (u64, usize)>,
+}
+
+impl<M: Middleware + 'static> MempoolSync<M> {
+    pub fn new(
+        eth_client: Arc<M>,
+        chain: Chain,
+        mempool: Mempool,
+        uo_senders: HashMap<
+            Address,
+            (EntryPointVersion, UnboundedSender<(UserOperation, U256, UserOperationSource)>),
+        >,
+    ) -> Self {
+        Self { eth_client, chain, mempool, uo_senders, sync_pages: HashMap::new() }
+    }
+
+    /// Hashes of the pooled user operations (sorted, so the pages are stable)
+    fn pooled_hashes(&self) -> Vec<H256> {
+        let mut hashes = self
+            .mempool
+            .get_all()
+            .map(|uos| uos.into_iter().map(|uo| uo.hash.0).collect::<Vec<_>>())
+            .unwrap_or_default();
+        hashes.sort();
+        hashes
+    }
+
+    /// Entry point the user operation belongs to
+    fn entry_point(&self, uo: &UserOperation) -> Option<Address> {
+        self.uo_senders
+            .iter()
+            .find(|(ep, (version, _))| {
+                uo.user_operation.hash_with_version(ep, self.chain.id(), *version) == uo.hash
+            })
+            .map(|(ep, _)| *ep)
+    }
+
+    /// Pooled user operations with the given hashes
+    async fn pooled_user_ops(&self, hashes: Vec<H256>) -> eyre::Result<Vec<VerifiedUserOperation>> {
+        let block_hash = self
+            .eth_client
+            .get_block(BlockNumber::Latest)
+            .await?
+            .and_then(|b| b.hash)
+            .unwrap_or_default();
+
+        Ok(hashes
+            .into_iter()
+            .filter_map(|h| self.mempool.get(&h.into()).ok().flatten())
+            .filter_map(|uo| {
+                self.entry_point(&uo).map(|ep| {
+                    VerifiedUserOperation::new(
+                        uo.user_operation,
+                        ep,
+                        U256::from_big_endian(block_hash.as_bytes()),
+                    )
+                })
+            })
+            .collect())
+    }
+
+    /// Whether the next page of the pooled user operation hashes is requested from the peer
+    ///
+    /// The offsets have to strictly increase and at most [MAX_SYNC_PAGES] pages are requested per
+    /// connection (a peer can't keep us paging forever).
+    fn next_page(&mut self, peer_id: PeerId, offset: u64) -> bool {
+        let Some((last_offset, pages)) = self.sync_pages.get_mut(&peer_id) else {
+            return false;
+        };
+        if offset <= *last_offset || *pages >= MAX_SYNC_PAGES {
+            debug!("Stopping the synchronization with the peer {peer_id:?} at offset {offset}");
+            self.sync_pages.remove(&peer_id);
+            return false;
+        }
+        *last_offset = offset;
+        *pages += 1;
+        true
+    }
+
+    /// Handles the event of the p2p network related to the mempool synchronization
+    pub async fn handle_event(&mut self, network: &mut Network, event: NetworkEvent) {
+        match event {
+            NetworkEvent::PeerConnected(peer_id) => {
+                debug!("Requesting pooled user operation hashes from the peer {peer_id:?}");
+                self.sync_pages.insert(peer_id, (0, 1));
+                network.send_request(
+                    &peer_id,
+                    OutboundRequest::PooledUserOpHashes(PooledUserOpHashesRequest::new(0)),
+                );
+            }
+            NetworkEvent::PeerDisconnected(peer_id) => {
+                self.sync_pages.remove(&peer_id);
+            }
+            NetworkEvent::RequestMessage { peer_id, request, sender } => match request {
+                InboundRequest::PooledUserOpHashes(req) => {
+                    let res = PooledUserOpHashesResponse::page(&self.pooled_hashes(), req.offset());
+                    if network.send_response(sender, RPCResponse::PooledUserOpHashes(res)).is_err()
+                    {
+                        debug!(
+                            "Failed to send pooled user operation hashes to the peer {peer_id:?}"
+                        );
+                    }
+                }
+                InboundRequest::PooledUserOpsByHash(req) => {
+                    let uos = match self.pooled_user_ops(req.hashes()).await {
+                        Ok(uos) => uos,
+                        Err(e) => {
+                            warn!("Failed to get pooled user operations: {e:?}");
+                            vec![]
+                        }
+                    };
+                    let res = PooledUserOpsByHashResponse::new(uos);
+                    if network.send_response(sender, RPCResponse::PooledUserOpsByHash(res)).is_err()
+                    {
+                        debug!("Failed to send pooled user operations to the peer {peer_id:?}");
+                    }
+                }
+                _ => {}
+            },
+            NetworkEvent::ResponseMessage { peer_id, response } => {
+                match response {
+                    RPCResponse::PooledUserOpHashes(res) => {
+                        let missing = res
+                            .hashes()
+                            .into_iter()
+                            .filter(|h| matches!(self.mempool.get(&(*h).into()), Ok(None)))
+                            .collect::<Vec<_>>();
+                        if !missing.is_empty() {
+                            debug!(
+                                "Requesting {} missing user operations from the peer {peer_id:?}",
+                                missing.len()
+                            );
+                            network.send_request(
+                                &peer_id,
+                                OutboundRequest::PooledUserOpsByHash(
+                                    PooledUserOpsByHashRequest::new(missing),
+                                ),
+                            );
+                        }
+                        match res.next_offset() {
+                            Some(offset) if self.next_page(peer_id, offset) => {
+                                network.send_request(
+                                    &peer_id,
+                                    OutboundRequest::PooledUserOpHashes(
+                                        PooledUserOpHashesRequest::new(offset),
+                                    ),
+                                );
+                            }
+                            // the last page
+                            _ => {
+                                self.sync_pages.remove(&peer_id);
+                            }
+                        }
+                    }
+                    RPCResponse::PooledUserOpsByHash(res) => {
+                        let uos = res.user_operations();
+                        info!(
+                            "Received {} pooled user operations from the peer {peer_id:?}",
+                            uos.len()
+                        );
+                        for uo in uos {
+                            let ep = uo.entry_point();
+                            let Some((version, uo_sender)) = self.uo_senders.get(&ep) else {
+                                warn!("Received unsupported entrypoint user operations {ep:?} from p2p");
+                                continue;
+                            };
+                            let verified_at_block_hash = uo.verified_at_block_hash();
+                            let uo = uo.user_operation();
+                            uo_sender
+                                .unbounded_send((
+                                    UserOperation::from_user_operation_signed(
+                                        uo.hash_with_version(&ep, self.chain.id(), *version),
+                                        uo,
+                                    ),
+                                    verified_at_block_hash,
+                                    UserOperationSource::Sync(peer_id),
+                                ))
+                                .expect("new user operation channel should be open all the time");
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -141,6 +141,26 @@ pub mod types {
         }
     }
 
+    impl From<silius_primitives::UserOperationsPerAggregator> for UserOperationsPerAggregator {
+        fn from(value: silius_primitives::UserOperationsPerAggregator) -> Self {
+            Self {
+                uos: value.user_operations.into_iter().map(Into::into).collect(),
+                aggregator: Some(value.aggregator.into()),
+                signature: prost::bytes::Bytes::copy_from_slice(value.signature.as_ref()),
+            }
+        }
+    }
+
+    impl From<UserOperationsPerAggregator> for silius_primitives::UserOperationsPerAggregator {
+        fn from(value: UserOperationsPerAggregator) -> Self {
+            Self {
+                user_operations: value.uos.into_iter().map(Into::into).collect(),
+                aggregator: value.aggregator.unwrap_or_default().into(),
+                signature: value.signature.into(),
+            }
+        }
+    }
+
     impl From<silius_primitives::UserOperationSigned> for UserOperationSigned {
         fn from(user_operation: silius_primitives::UserOperationSigned) -> Self {
             Self {
@@ -265,6 +285,81 @@ pub mod types {
         }
     }
 
+    impl From<silius_primitives::bundler::BundleRecord> for BundleRecord {
+        fn from(record: silius_primitives::bundler::BundleRecord) -> Self {
+            Self {
+                transaction_hash: Some(record.transaction_hash.into()),
+                entry_point: Some(record.entry_point.into()),
+                block_number: record.block_number,
+                user_operations: record.user_operations.into_iter().map(Into::into).collect(),
+                gas_used: Some(record.gas_used.into()),
+                fees_paid: Some(record.fees_paid.into()),
+                beneficiary: Some(record.beneficiary.into()),
+                revenue: Some(record.revenue.into()),
+                status: match silius_primitives::bundler::BundleStatus::from(record.status) {
+                    silius_primitives::bundler::BundleStatus::Submitted => BundleStatus::Submitted,
+                    silius_primitives::bundler::BundleStatus::Included => BundleStatus::Included,
+                    silius_primitives::bundler::BundleStatus::Reverted => BundleStatus::Reverted,
+                    silius_primitives::bundler::BundleStatus::Cancelled => BundleStatus::Cancelled,
+                    silius_primitives::bundler::BundleStatus::Dropped => BundleStatus::Dropped,
+                } as i32,
+                timestamp: record.timestamp,
+            }
+        }
+    }
+
+    impl From<BundleRecord> for silius_primitives::bundler::BundleRecord {
+        fn from(record: BundleRecord) -> Self {
+            Self {
+                transaction_hash: record.transaction_hash.map(Into::into).unwrap_or_default(),
+                entry_point: record.entry_point.map(Into::into).unwrap_or_default(),
+                block_number: record.block_number,
+                user_operations: record.user_operations.into_iter().map(Into::into).collect(),
+                gas_used: record.gas_used.map(Into::into).unwrap_or_default(),
+                fees_paid: record.fees_paid.map(Into::into).unwrap_or_default(),
+                beneficiary: record.beneficiary.map(Into::into).unwrap_or_default(),
+                revenue: record.revenue.map(Into::into).unwrap_or_default(),
+                status: match BundleStatus::try_from(record.status)
+                    .unwrap_or(BundleStatus::Submitted)
+                {
+                    BundleStatus::Submitted => silius_primitives::bundler::BundleStatus::Submitted,
+                    BundleStatus::Included => silius_primitives::bundler::BundleStatus::Included,
+                    BundleStatus::Reverted => silius_primitives::bundler::BundleStatus::Reverted,
+                    BundleStatus::Cancelled => silius_primitives::bundler::BundleStatus::Cancelled,
+                    BundleStatus::Dropped => silius_primitives::bundler::BundleStatus::Dropped,
+                }
+                .into(),
+                timestamp: record.timestamp,
+            }
+        }
+    }
+
+    impl From<silius_primitives::p2p::PeerRecord> for PeerRecord {
+        fn from(record: silius_primitives::p2p::PeerRecord) -> Self {
+            Self {
+                peer_id: record.peer_id,
+                enr: record.enr.unwrap_or_default(),
+                last_seen: record.last_seen,
+                score: record.score,
+                banned: record.banned,
+                connected: record.connected,
+            }
+        }
+    }
+
+    impl From<PeerRecord> for silius_primitives::p2p::PeerRecord {
+        fn from(record: PeerRecord) -> Self {
+            Self {
+                peer_id: record.peer_id,
+                enr: Some(record.enr).filter(|enr| !enr.is_empty()),
+                last_seen: record.last_seen,
+                score: record.score,
+                banned: record.banned,
+                connected: record.connected,
+            }
+        }
+    }
+
     impl From<ethers::types::TransactionReceipt> for TransactionReceipt {
         fn from(value: ethers::types::TransactionReceipt) -> Self {
             Self {
@@ -1,33 +1,59 @@
 use crate::{
+    mempool_sync::MempoolSync,
     proto::{
         types::{GetChainIdResponse, GetSupportedEntryPointsResponse},
         uopool::*,
     },
-    utils::{parse_addr, parse_hash, parse_uo},
+    utils::{parse_addr, parse_hash, parse_state_override, parse_uo},
 };
 use alloy_chains::Chain;
 use async_trait::async_trait;
 use ethers::{
     providers::Middleware,
-    types::{Address, U256},
+    types::{Address, BlockNumber, H256, U256},
 };
 use eyre::Result;
-use futures::{channel::mpsc::unbounded, StreamExt};
+use futures::{
+    channel::{
+        mpsc::{unbounded, UnboundedSender},
+        oneshot,
+    },
+    stream::BoxStream,
+    StreamExt,
+};
 use parking_lot::RwLock;
 use silius_mempool::{
-    mempool_id, validate::validator::StandardUserOperationValidator, Mempool, MempoolErrorKind,
-    MempoolId, Reputation, SanityCheck, SimulationCheck, SimulationTraceCheck,
-    UoPool as UserOperationPool, UoPoolBuilder,
+    alt_mempool_id, mempool_id, validate::validator::StandardUserOperationValidator,
+    InvalidMempoolUserOperationError, Mempool, MempoolErrorKind, MempoolId, Reputation,
+    ReputationError, SanityCheck, SanityError, SimulationCheck, SimulationError,
+    SimulationTraceCheck, UoPool as UserOperationPool, UoPoolBuilder,
 };
 use silius_metrics::grpc::MetricsLayer;
 use silius_p2p::{
     config::Config,
-    service::{MempoolChannels, Network},
+    peer_manager::peer::score::PeerAction,
+    service::{
+        Enr, MempoolChannels, MessageAcceptance, Network, PeerCommand, PeerId, UserOperationSource,
+    },
 };
-use silius_primitives::{provider::BlockStream, UoPoolMode, UserOperation};
-use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+use silius_primitives::{
+    bundler::{BundleRecord, BundleStatus, BundleStrategy},
+    constants::mempool::USER_OPERATION_EVENTS_CAPACITY,
+    entry_point::EntryPointVersion,
+    mempool::AltMempool,
+    provider::{split_block_stream, BlockStream},
+    UoPoolMode, UserOperation, UserOperationEvent, UserOperationHash, UserOperationStatus,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::broadcast;
 use tonic::{Code, Request, Response, Status};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 type StandardUserPool<M, SanCk, SimCk, SimTrCk> =
     UserOperationPool<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>;
@@ -44,6 +70,15 @@ where
 {
     pub uopools: UoPoolMaps<M, SanCk, SimCk, SimTrCk>,
     pub chain: Chain,
+    /// Peer management commands of the p2p network (if p2p is enabled)
+    pub peer_commands: Option<UnboundedSender<PeerCommand>>,
+    /// Ids of the alternative mempools (per entry point)
+    pub alt_mempools: HashMap<Address, Vec<MempoolId>>,
+    /// Lifecycle events of the user operations (shared with the user operation pools)
+    pub events: broadcast::Sender<UserOperationEvent>,
+    /// Index of the mempool (the canonical one followed by the alternative ones) whose turn it
+    /// is to be bundled (per entry point)
+    pub bundle_turns: Arc<RwLock<HashMap<Address, usize>>>,
 }
 
 impl<M, SanCk, SimCk, SimTrCk> UoPoolService<M, SanCk, SimCk, SimTrCk>
@@ -54,7 +89,47 @@ where
     SimTrCk: SimulationTraceCheck<M> + Clone + 'static,
 {
     pub fn new(uopools: UoPoolMaps<M, SanCk, SimCk, SimTrCk>, chain: Chain) -> Self {
-        Self { uopools, chain }
+        Self {
+            uopools,
+            chain,
+            peer_commands: None,
+            alt_mempools: HashMap::new(),
+            events: broadcast::channel(USER_OPERATION_EVENTS_CAPACITY).0,
+            bundle_turns: Default::default(),
+        }
+    }
+
+    /// Sets the channel the lifecycle events of the user operations are sent to
+    pub fn with_events(mut self, events: broadcast::Sender<UserOperationEvent>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Sends the lifecycle events of the user operations of the bundle to the subscribers
+    fn emit_bundle_events(&self, record: &BundleRecord) {
+        for event in bundle_events(record) {
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Sets the alternative mempools the user operations can fall back to
+    pub fn with_alt_mempools(mut self, alt_mempools: HashMap<Address, Vec<MempoolId>>) -> Self {
+        self.alt_mempools = alt_mempools;
+        self
+    }
+
+    /// Enables the peer management of the p2p network
+    pub fn with_peer_commands(mut self, peer_commands: UnboundedSender<PeerCommand>) -> Self {
+        self.peer_commands = Some(peer_commands);
+        self
+    }
+
+    fn send_peer_command(&self, command: PeerCommand) -> tonic::Result<()> {
+        self.peer_commands
+            .as_ref()
+            .ok_or(Status::new(Code::Unavailable, "P2P network is not enabled"))?
+            .unbounded_send(command)
+            .map_err(|e| Status::internal(format!("Failed to send peer command: {e}")))
     }
 
     #[allow(clippy::type_complexity)]
@@ -69,6 +144,16 @@ where
             .map(|b| b.uopool())
             .ok_or(Status::new(Code::Unavailable, "User operation pool is not available"))
     }
+
+    /// User operation pools of the alternative mempools of the entry point
+    #[allow(clippy::type_complexity)]
+    fn get_alt_uopools(&self, ep: &Address) -> Vec<StandardUserPool<M, SanCk, SimCk, SimTrCk>> {
+        let uopools = self.uopools.read();
+        self.alt_mempools
+            .get(ep)
+            .map(|ids| ids.iter().filter_map(|id| uopools.get(id).map(|b| b.uopool())).collect())
+            .unwrap_or_default()
+    }
 }
 
 #[async_trait]
@@ -82,16 +167,35 @@ where
     async fn add(&self, req: Request<AddRequest>) -> Result<Response<AddResponse>, Status> {
         let req = req.into_inner();
 
-        let uo = parse_uo(req.uo)?;
+        let mut uo = parse_uo(req.uo)?;
         let ep = parse_addr(req.ep)?;
 
-        let res = {
+        let mut res = {
             let uopool = self.get_uopool(&ep)?;
+            // the hash depends on the (configured) version of the entry point
+            uo.hash = uo.user_operation.hash_with_version(
+                &ep,
+                self.chain.id(),
+                uopool.entry_point.version(),
+            );
             uopool.validate_user_operation(&uo).await
         };
 
         let mut uopool = self.get_uopool(&ep)?;
 
+        // user operations which violate the rules of the canonical mempool (that the alternative
+        // mempools relax) go to the first alternative mempool that accepts them
+        if res.as_ref().is_err_and(relaxed_by_alt_mempools) {
+            for alt_uopool in self.get_alt_uopools(&ep) {
+                let alt_res = alt_uopool.validate_user_operation(&uo).await;
+                if alt_res.is_ok() {
+                    res = alt_res;
+                    uopool = alt_uopool;
+                    break;
+                }
+            }
+        }
+
         match uopool.add_user_operation(uo, res).await {
             Ok(uo_hash) => Ok(Response::new(AddResponse {
                 res: AddResult::Added as i32,
@@ -114,9 +218,13 @@ where
         let req = req.into_inner();
 
         let ep = parse_addr(req.ep)?;
-        let mut uopool = self.get_uopool(&ep)?;
+        let uos: Vec<UserOperation> = req.uos.into_iter().map(|uo| uo.into()).collect();
 
-        uopool.remove_user_operations(req.uos.into_iter().map(|uo| uo.into()).collect());
+        let mut uopool = self.get_uopool(&ep)?;
+        uopool.remove_user_operations(uos.clone());
+        for mut alt_uopool in self.get_alt_uopools(&ep) {
+            alt_uopool.remove_user_operations(uos.clone());
+        }
 
         Ok(Response::new(()))
     }
@@ -137,7 +245,10 @@ where
                 .uopools
                 .read()
                 .values()
-                .map(|mempool| mempool.uopool().entry_point.address().into())
+                .map(|mempool| mempool.uopool().entry_point.address())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .map(Into::into)
                 .collect(),
         }))
     }
@@ -151,9 +262,11 @@ where
         let uo = parse_uo(req.uo)?;
         let ep = parse_addr(req.ep)?;
 
+        let state_override = parse_state_override(&req.state_override)?;
+
         let uopool = self.get_uopool(&ep)?;
 
-        Ok(Response::new(match uopool.estimate_user_operation_gas(&uo).await {
+        Ok(Response::new(match uopool.estimate_user_operation_gas(&uo, state_override).await {
             Ok(gas) => EstimateUserOperationGasResponse {
                 res: EstimateUserOperationGasResult::Estimated as i32,
                 data: serde_json::to_string(&gas)
@@ -175,24 +288,41 @@ where
 
         let ep = parse_addr(req.ep)?;
 
-        let uos = {
-            let uopool = self.get_uopool(&ep)?;
-            uopool.get_sorted_user_operations().map_err(|e| {
-                tonic::Status::internal(format!("Get sorted uos internal error: {e:?}"))
-            })?
-        };
+        // a bundle never mixes the rules of different mempools, the canonical and the alternative
+        // mempools of the entry point take turns (the ones with nothing to bundle are skipped)
+        let mut uopools = vec![self.get_uopool(&ep)?];
+        uopools.extend(self.get_alt_uopools(&ep));
+        let turn = self.bundle_turns.read().get(&ep).copied().unwrap_or_default();
 
-        let (uos_valid, storage_map) = {
-            let mut uopool = self.get_uopool(&ep)?;
-            uopool
+        let mut bundle = None;
+        for idx in (0..uopools.len()).map(|i| (turn + i) % uopools.len()) {
+            let uopool = &mut uopools[idx];
+            let uos = uopool.get_sorted_user_operations().map_err(|e| {
+                tonic::Status::internal(format!("Get sorted uos internal error: {e:?}"))
+            })?;
+            if uos.is_empty() {
+                continue;
+            }
+            let (uos_per_aggregator, storage_map) = uopool
                 .bundle_user_operations(uos)
                 .await
-                .map_err(|e| tonic::Status::internal(format!("Bundle uos internal error: {e}")))?
-        };
+                .map_err(|e| tonic::Status::internal(format!("Bundle uos internal error: {e}")))?;
+            if uos_per_aggregator.iter().any(|uos| !uos.user_operations.is_empty()) {
+                self.bundle_turns.write().insert(ep, (idx + 1) % uopools.len());
+                bundle = Some((uos_per_aggregator, storage_map));
+                break;
+            }
+        }
+        let (uos_per_aggregator, storage_map) = bundle.unwrap_or_default();
 
         Ok(Response::new(GetSortedResponse {
-            uos: uos_valid.into_iter().map(Into::into).collect(),
+            uos: uos_per_aggregator
+                .iter()
+                .flat_map(|uos| uos.user_operations.clone())
+                .map(Into::into)
+                .collect(),
             storage_map: Some(storage_map.into()),
+            uos_per_aggregator: uos_per_aggregator.into_iter().map(Into::into).collect(),
         }))
     }
 
@@ -258,6 +388,68 @@ where
         Err(tonic::Status::not_found("User operation receipt not found"))
     }
 
+    async fn get_user_operation_status(
+        &self,
+        req: Request<UserOperationHashRequest>,
+    ) -> Result<Response<GetUserOperationStatusResponse>, Status> {
+        let req = req.into_inner();
+
+        let uo_hash: UserOperationHash = parse_hash(req.hash)?.into();
+        let (uopools, canonical_uopools) = {
+            let uopools_ref = self.uopools.read();
+            let alt_ids: HashSet<&MempoolId> = self.alt_mempools.values().flatten().collect();
+            let uopools = uopools_ref.values().map(|b| b.uopool()).collect::<Vec<_>>();
+            let canonical_uopools = uopools_ref
+                .iter()
+                .filter(|(id, _)| !alt_ids.contains(id))
+                .map(|(_, b)| b.uopool())
+                .collect::<Vec<_>>();
+            (uopools, canonical_uopools)
+        };
+
+        let mut status = uopools
+            .iter()
+            .find_map(|uopool| uopool.get_user_operation_status(&uo_hash).ok().flatten());
+
+        // the alternative mempools share the entry point (and its logs) with the canonical one
+        if status.is_none() {
+            for uopool in canonical_uopools {
+                if let Ok(Some(s)) = uopool.get_included_user_operation_status(&uo_hash).await {
+                    status = Some(s);
+                    break;
+                }
+            }
+        }
+
+        if let Some(status) = status {
+            return Ok(Response::new(GetUserOperationStatusResponse {
+                data: serde_json::to_string(&status).map_err(|err| {
+                    Status::internal(format!("Failed to serialize status: {err}"))
+                })?,
+            }));
+        }
+
+        Err(tonic::Status::not_found("User operation status not found"))
+    }
+
+    async fn get_user_operation_gas_price(
+        &self,
+        req: Request<GetUserOperationGasPriceRequest>,
+    ) -> Result<Response<GetUserOperationGasPriceResponse>, Status> {
+        let req = req.into_inner();
+
+        let ep = parse_addr(req.ep)?;
+        let uopool = self.get_uopool(&ep)?;
+        match uopool.get_user_operation_gas_price().await {
+            Ok(gas_price) => Ok(Response::new(GetUserOperationGasPriceResponse {
+                data: serde_json::to_string(&gas_price).map_err(|err| {
+                    Status::internal(format!("Failed to serialize gas price: {err}"))
+                })?,
+            })),
+            Err(err) => Err(Status::internal(format!("Get gas price internal error: {err:?}"))),
+        }
+    }
+
     async fn get_all(
         &self,
         req: Request<GetAllRequest>,
@@ -337,11 +529,20 @@ where
         let ep = parse_addr(req.ep)?;
         let mut uopool = self.get_uopool(&ep)?;
 
+        // the hashes depend on the (configured) version of the entry point
+        let version = uopool.entry_point.version();
+        let uos = req
+            .uos
+            .into_iter()
+            .map(|uo| {
+                let mut uo: UserOperation = uo.into();
+                uo.hash = uo.user_operation.hash_with_version(&ep, self.chain.id(), version);
+                uo
+            })
+            .collect();
+
         let res = Response::new(AddMempoolResponse {
-            res: match uopool
-                .add_user_operations(req.uos.into_iter().map(|uo| uo.into()).collect())
-                .await
-            {
+            res: match uopool.add_user_operations(uos).await {
                 Ok(_) => AddMempoolResult::AddedMempool as i32,
                 Err(_) => AddMempoolResult::NotAddedMempool as i32,
             },
@@ -369,22 +570,272 @@ where
             is_staked: res.is_staked,
         }))
     }
+
+    async fn add_bundle(&self, req: Request<AddBundleRequest>) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let ep = parse_addr(req.ep)?;
+        let mut uopool = self.get_uopool(&ep)?;
+
+        let record: BundleRecord = req
+            .record
+            .ok_or_else(|| Status::invalid_argument("Bundle record is not provided"))?
+            .into();
+        self.emit_bundle_events(&record);
+        uopool
+            .add_bundle(record)
+            .map_err(|e| tonic::Status::internal(format!("Add bundle internal error: {e}")))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn bundle_submitted(
+        &self,
+        req: Request<AddBundleRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let record: BundleRecord = req
+            .record
+            .ok_or_else(|| Status::invalid_argument("Bundle record is not provided"))?
+            .into();
+        self.emit_bundle_events(&record);
+
+        // the user operations stay in the mempool while the bundle is pending, so the record
+        // tells the status of the user operations apart
+        let mut uopool = self.get_uopool(&record.entry_point)?;
+        uopool
+            .add_bundle(record)
+            .map_err(|e| tonic::Status::internal(format!("Add bundle internal error: {e}")))?;
+
+        Ok(Response::new(()))
+    }
+
+    type SubscribeUserOperationEventsStream =
+        BoxStream<'static, Result<UserOperationEventResponse, Status>>;
+
+    async fn subscribe_user_operation_events(
+        &self,
+        req: Request<SubscribeUserOperationEventsRequest>,
+    ) -> Result<Response<Self::SubscribeUserOperationEventsStream>, Status> {
+        let req = req.into_inner();
+
+        let uo_hash: Option<UserOperationHash> = req.hash.map(Into::into);
+        // the subscription to the events of a single user operation ends with its final status
+        let stream = futures::stream::unfold(
+            Some(self.events.subscribe()),
+            move |events| async move {
+                let mut events = events?;
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            if uo_hash.map_or(false, |h| h != event.user_operation_hash) {
+                                continue;
+                            }
+                            let done = uo_hash.is_some() && event.status.is_final();
+                            let res = serde_json::to_string(&event)
+                                .map(|data| UserOperationEventResponse { data })
+                                .map_err(|e| {
+                                    Status::internal(format!("Failed to serialize the event: {e}"))
+                                });
+                            return Some((res, (!done).then_some(events)));
+                        }
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            warn!("User operation events subscriber lagged behind, {n} events skipped");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_bundle_history(
+        &self,
+        req: Request<GetBundleHistoryRequest>,
+    ) -> Result<Response<GetBundleHistoryResponse>, Status> {
+        let req = req.into_inner();
+
+        let ep = parse_addr(req.ep)?;
+        let uopool = self.get_uopool(&ep)?;
+
+        let records = uopool.get_bundle_history().map_err(|e| {
+            tonic::Status::internal(format!("Get bundle history internal error: {e}"))
+        })?;
+        Ok(Response::new(GetBundleHistoryResponse {
+            records: records.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn add_peer(&self, req: Request<AddPeerRequest>) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let enr = Enr::from_str(&req.enr)
+            .map_err(|e| Status::invalid_argument(format!("Invalid enr: {e}")))?;
+        self.send_peer_command(PeerCommand::AddPeer(enr))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn remove_peer(&self, req: Request<PeerIdRequest>) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let peer_id = PeerId::from_str(&req.peer_id)
+            .map_err(|e| Status::invalid_argument(format!("Invalid peer id: {e}")))?;
+        self.send_peer_command(PeerCommand::RemovePeer(peer_id))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn ban_peer(&self, req: Request<PeerIdRequest>) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let peer_id = PeerId::from_str(&req.peer_id)
+            .map_err(|e| Status::invalid_argument(format!("Invalid peer id: {e}")))?;
+        self.send_peer_command(PeerCommand::BanPeer(peer_id))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn get_peers(&self, _req: Request<()>) -> Result<Response<GetPeersResponse>, Status> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_peer_command(PeerCommand::Peers(sender))?;
+
+        let peers = receiver
+            .await
+            .map_err(|_| Status::internal("P2P network dropped the peers request"))?;
+        Ok(Response::new(GetPeersResponse { peers: peers.into_iter().map(Into::into).collect() }))
+    }
+}
+
+/// Gossipsub acceptance of the user operation received from the p2p network that failed to be added
+/// to the mempool
+///
+/// User operations that are invalid (or use banned entities) are rejected, which penalizes the peer
+/// that propagated them. Failures that depend on the local state of the node (throttling, fees,
+/// provider or database errors) and stale user operations are ignored.
+fn p2p_acceptance(kind: &MempoolErrorKind) -> MessageAcceptance {
+    match kind {
+        MempoolErrorKind::InvalidUserOperation(
+            InvalidMempoolUserOperationError::Reputation(ReputationError::BannedEntity { .. }) |
+            InvalidMempoolUserOperationError::Sanity(
+                SanityError::Reputation(ReputationError::BannedEntity { .. }) |
+                SanityError::VerificationGasLimitTooHigh { .. } |
+                SanityError::PreVerificationGasTooLow { .. } |
+                SanityError::CallGasLimitTooLow { .. } |
+                SanityError::MaxPriorityFeePerGasTooHigh { .. } |
+                SanityError::Paymaster { .. } |
+                SanityError::Sender { .. } |
+                SanityError::EntityRoles { .. },
+            ) |
+            InvalidMempoolUserOperationError::Simulation(
+                SimulationError::Reputation(ReputationError::BannedEntity { .. }) |
+                SimulationError::Signature |
+                SimulationError::Timestamp { .. } |
+                SimulationError::Validation { .. } |
+                SimulationError::Execution { .. } |
+                SimulationError::Opcode { .. } |
+                SimulationError::StorageAccess { .. } |
+                SimulationError::Unstaked { .. } |
+                SimulationError::CallStack { .. } |
+                SimulationError::CodeHashes |
+                SimulationError::OutOfGas,
+            ),
+        ) => MessageAcceptance::Reject,
+        _ => MessageAcceptance::Ignore,
+    }
+}
+
+/// Misbehaviour the peer that relayed the user operation (that failed to be added to the mempool)
+/// is penalized for
+///
+/// User operations verified at an unknown or too old block may have been valid when the peer
+/// verified them, so they are not rejected, but the peers that keep relaying them are banned.
+fn p2p_peer_action(kind: &MempoolErrorKind) -> Option<PeerAction> {
+    match kind {
+        MempoolErrorKind::InvalidUserOperation(InvalidMempoolUserOperationError::Sanity(
+            SanityError::VerifiedBlock { .. },
+        )) => Some(PeerAction::HighToleranceError),
+        _ => None,
+    }
+}
+
+/// Whether the user operation that failed to be validated against the rules of the canonical
+/// mempool may be accepted by an alternative mempool (which relaxes the opcode, storage access and
+/// staking rules)
+///
+/// Failures for other reasons (e.g. fees, gas limits, throttled or banned entities) are not retried
+/// in the alternative mempools.
+fn relaxed_by_alt_mempools(err: &InvalidMempoolUserOperationError) -> bool {
+    matches!(
+        err,
+        InvalidMempoolUserOperationError::Reputation(ReputationError::UnstakedEntity { .. }) |
+            InvalidMempoolUserOperationError::Sanity(SanityError::Reputation(
+                ReputationError::UnstakedEntity { .. }
+            )) |
+            InvalidMempoolUserOperationError::Simulation(
+                SimulationError::Opcode { .. } |
+                    SimulationError::StorageAccess { .. } |
+                    SimulationError::Unstaked { .. } |
+                    SimulationError::Reputation(
+                        ReputationError::UnstakedEntity { .. } |
+                            ReputationError::StakeTooLow { .. } |
+                            ReputationError::UnstakeDelayTooLow { .. }
+                    ),
+            )
+    )
+}
+
+/// Lifecycle events of the user operations of the bundle
+///
+/// The user operations of the included bundles are reported once the block is processed, the user
+/// operations of the released (cancelled or dropped) bundles are pending again.
+fn bundle_events(record: &BundleRecord) -> Vec<UserOperationEvent> {
+    let status = match BundleStatus::from(record.status) {
+        BundleStatus::Submitted => UserOperationStatus::Bundled,
+        BundleStatus::Reverted => UserOperationStatus::Reverted,
+        BundleStatus::Cancelled | BundleStatus::Dropped => UserOperationStatus::Pending,
+        BundleStatus::Included => return vec![],
+    };
+    let block_number = (record.block_number != 0).then(|| record.block_number.into());
+    record
+        .user_operations
+        .iter()
+        .map(|uo_hash| {
+            let event = UserOperationEvent::new((*uo_hash).into(), record.entry_point, status);
+            if status == UserOperationStatus::Pending {
+                event
+            } else {
+                event.with_transaction(record.transaction_hash, block_number)
+            }
+        })
+        .collect()
 }
 
 #[allow(clippy::too_many_arguments)]
 pub async fn uopool_service_run<M, SanCk, SimCk, SimTrCk>(
     addr: SocketAddr,
     mode: UoPoolMode,
-    eps: Vec<Address>,
+    eps: Vec<(Address, EntryPointVersion)>,
     eth_client: Arc<M>,
     block_streams: Vec<BlockStream>,
     chain: Chain,
     max_verification_gas: U256,
+    min_priority_fee_per_gas: U256,
     mempool: Mempool,
     reputation: Reputation,
     validator: StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>,
+    alt_mempools: Vec<(
+        AltMempool,
+        StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>,
+        Mempool,
+    )>,
     p2p_config: Option<Config>,
     enable_metrics: bool,
+    bundle_strategy: BundleStrategy,
+    execution_gas_margin: u64,
 ) -> Result<()>
 where
     M: Middleware + Clone + 'static,
@@ -396,13 +847,53 @@ where
         let mut builder = tonic::transport::Server::builder();
 
         let mut m_map = HashMap::<MempoolId, UoPoolBuilder<M, SanCk, SimCk, SimTrCk>>::new();
+        let mut peer_commands = None;
+        let (events, _) = broadcast::channel(USER_OPERATION_EVENTS_CAPACITY);
+
+        // the mempool partitions (with their validators) of every entry point, the canonical
+        // mempool goes first
+        let mut alt_mempool_ids = HashMap::<Address, Vec<MempoolId>>::new();
+        let mut partitions = Vec::new();
+        for ((ep, version), block_stream) in eps.into_iter().zip(block_streams.into_iter()) {
+            let mut ep_partitions =
+                vec![(mempool_id(&ep, chain.id()), None, mempool.clone(), validator.clone())];
+            for (alt_mempool, alt_validator, alt_partition) in
+                alt_mempools.iter().filter(|(alt_mempool, _, _)| alt_mempool.entry_point == ep)
+            {
+                info!(
+                    "Alternative mempool {} on entry point {ep:?}: {}",
+                    alt_mempool.id, alt_mempool.description
+                );
+                let id = alt_mempool_id(&alt_mempool.id);
+                alt_mempool_ids.entry(ep).or_default().push(id);
+                ep_partitions.push((
+                    id,
+                    Some(alt_mempool.id.clone()),
+                    alt_partition.clone(),
+                    alt_validator.clone(),
+                ));
+            }
+
+            // every partition follows the blocks on its own
+            let block_streams = if ep_partitions.len() > 1 {
+                split_block_stream(block_stream, ep_partitions.len())
+            } else {
+                vec![block_stream]
+            };
+            for ((id, alt_mempool, mempool, validator), block_stream) in
+                ep_partitions.into_iter().zip(block_streams.into_iter())
+            {
+                partitions.push((ep, version, id, alt_mempool, mempool, validator, block_stream));
+            }
+        }
 
         // setup p2p
         if let Some(config) = p2p_config {
             let mut mempool_channels: MempoolChannels = Vec::new();
+            let mut uo_senders = HashMap::new();
+            let mut p2p_consumers = Vec::new();
 
-            for (ep, block_stream) in eps.into_iter().zip(block_streams.into_iter()) {
-                let id = mempool_id(&ep, chain.id());
+            for (ep, version, id, alt_mempool, mempool, validator, block_stream) in partitions {
                 let (waiting_to_pub_sd, waiting_to_pub_rv) = unbounded::<(UserOperation, U256)>();
                 let uo_builder = UoPoolBuilder::new(
                     mode,
@@ -410,28 +901,31 @@ where
                     ep,
                     chain,
                     max_verification_gas,
-                    mempool.clone(),
+                    mempool,
                     reputation.clone(),
-                    validator.clone(),
+                    validator,
                     Some(waiting_to_pub_sd),
-                );
+                )
+                .with_entry_point_version(version)
+                .with_bundle_strategy(bundle_strategy)
+                .with_execution_gas_margin(execution_gas_margin)
+                .with_min_priority_fee_per_gas(min_priority_fee_per_gas)
+                .with_mempool_id(id)
+                .with_events(events.clone());
                 uo_builder.register_block_updates(block_stream);
-                uo_builder.register_reputation_updates();
+                if alt_mempool.is_none() {
+                    uo_builder.register_reputation_updates();
+                }
 
-                let (p2p_userop_sd, mut p2p_userop_rv) = unbounded::<UserOperation>();
-                let mut uo_pool = uo_builder.uopool();
-                // spawn a task which would consume the userop received from p2p network
-                tokio::spawn(async move {
-                    while let Some(user_op) = p2p_userop_rv.next().await {
-                        let res = uo_pool.validate_user_operation(&user_op).await;
-                        match uo_pool.add_user_operation(user_op, res).await {
-                            Ok(_) => {}
-                            Err(e) => error!("Failed to add user operation: {:?} from p2p", e),
-                        }
-                    }
-                });
+                let (p2p_userop_sd, p2p_userop_rv) =
+                    unbounded::<(UserOperation, U256, UserOperationSource)>();
+                p2p_consumers.push((uo_builder.uopool(), p2p_userop_rv));
                 m_map.insert(id, uo_builder);
-                mempool_channels.push((ep, waiting_to_pub_rv, p2p_userop_sd))
+                // only the canonical mempools are synchronized with the peers
+                if alt_mempool.is_none() {
+                    uo_senders.insert(ep, (version, p2p_userop_sd.clone()));
+                }
+                mempool_channels.push((ep, version, alt_mempool, waiting_to_pub_rv, p2p_userop_sd))
             }
 
             if config.bootnodes.is_empty() {
@@ -442,35 +936,102 @@ where
                 .await
                 .expect("p2p network init failed");
 
+            // spawn tasks which would consume the userops received from p2p network and report the
+            // validation results back
+            for (mut uo_pool, mut p2p_userop_rv) in p2p_consumers {
+                let validation_result_sd = p2p_network.validation_result_sender();
+                tokio::spawn(async move {
+                    while let Some((user_op, verified_at_block_hash, source)) =
+                        p2p_userop_rv.next().await
+                    {
+                        let (acceptance, action) =
+                            if matches!(uo_pool.mempool.get(&user_op.hash), Ok(Some(_))) {
+                                (MessageAcceptance::Ignore, None)
+                            } else {
+                                let res = uo_pool
+                                    .validate_p2p_user_operation(
+                                        &user_op,
+                                        H256::from_uint(&verified_at_block_hash),
+                                    )
+                                    .await;
+                                match uo_pool.add_user_operation(user_op, res).await {
+                                    Ok(_) => (MessageAcceptance::Accept, None),
+                                    Err(e) => {
+                                        error!("Failed to add user operation: {:?} from p2p", e);
+                                        (p2p_acceptance(&e.kind), p2p_peer_action(&e.kind))
+                                    }
+                                }
+                            };
+                        let _ = validation_result_sd.unbounded_send((source, acceptance, action));
+                    }
+                });
+            }
+
+            peer_commands = Some(p2p_network.peer_command_sender());
+
+            // keep the head of the local status (exchanged with the peers) up to date
+            let network_globals = p2p_network.network_globals();
+            let head_eth_client = eth_client.clone();
             tokio::spawn(async move {
+                let mut interval = tokio::time::interval(network_globals.chain_spec().block_time);
                 loop {
-                    p2p_network.next_event().await;
+                    interval.tick().await;
+                    match head_eth_client.get_block(BlockNumber::Latest).await {
+                        Ok(Some(block)) => {
+                            if let (Some(hash), Some(number)) = (block.hash, block.number) {
+                                network_globals.set_head(hash, number.as_u64());
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("Failed to get the latest block for the p2p status: {e:?}"),
+                    }
+                }
+            });
+
+            let mut mempool_sync =
+                MempoolSync::new(eth_client.clone(), chain, mempool.clone(), uo_senders);
+
+            tokio::spawn(async move {
+                loop {
+                    let event = p2p_network.next_event().await;
+                    mempool_sync.handle_event(&mut p2p_network, event).await;
                 }
             });
         } else {
-            for (ep, block_stream) in eps.into_iter().zip(block_streams.into_iter()) {
-                let id = mempool_id(&ep, chain.id());
+            for (ep, version, id, alt_mempool, mempool, validator, block_stream) in partitions {
                 let uo_builder = UoPoolBuilder::new(
                     mode,
                     eth_client.clone(),
                     ep,
                     chain,
                     max_verification_gas,
-                    mempool.clone(),
+                    mempool,
                     reputation.clone(),
-                    validator.clone(),
+                    validator,
                     None,
-                );
+                )
+                .with_entry_point_version(version)
+                .with_bundle_strategy(bundle_strategy)
+                .with_execution_gas_margin(execution_gas_margin)
+                .with_min_priority_fee_per_gas(min_priority_fee_per_gas)
+                .with_mempool_id(id)
+                .with_events(events.clone());
                 uo_builder.register_block_updates(block_stream);
-                uo_builder.register_reputation_updates();
+                if alt_mempool.is_none() {
+                    uo_builder.register_reputation_updates();
+                }
                 m_map.insert(id, uo_builder);
             }
         };
 
         let uopool_map = Arc::new(RwLock::new(m_map));
-        let svc = uo_pool_server::UoPoolServer::new(
-            UoPoolService::<M, SanCk, SimCk, SimTrCk>::new(uopool_map, chain),
-        );
+        let mut service = UoPoolService::<M, SanCk, SimCk, SimTrCk>::new(uopool_map, chain)
+            .with_alt_mempools(alt_mempool_ids)
+            .with_events(events);
+        if let Some(peer_commands) = peer_commands {
+            service = service.with_peer_commands(peer_commands);
+        }
+        let svc = uo_pool_server::UoPoolServer::new(service);
 
         if enable_metrics {
             builder.layer(MetricsLayer).add_service(svc).serve(addr).await
@@ -1,4 +1,4 @@
-use ethers::types::{Address, H256};
+use ethers::types::{spoof, Address, H256};
 use silius_primitives::UserOperation;
 use tonic::{Code, Status};
 
@@ -22,3 +22,38 @@ pub fn parse_uo(uo: Option<crate::UserOperation>) -> Result<UserOperation, Statu
         None => Err(Status::new(Code::InvalidArgument, "User operation is not valid")),
     }
 }
+
+/// Parses the state override serialized as JSON (empty if there is no state override)
+pub fn parse_state_override(state_override: &str) -> Result<Option<spoof::State>, Status> {
+    if state_override.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str::<spoof::State>(state_override).map(Some).map_err(|err| {
+        Status::new(Code::InvalidArgument, format!("Failed to parse state override: {err}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Bytes, U256};
+
+    #[test]
+    fn state_override_round_trip() {
+        let mut state = spoof::State::default();
+        state.account(Address::random()).balance(U256::from(1_000_000)).nonce(1.into());
+        state
+            .account(Address::random())
+            .code(Bytes::from(vec![0x60, 0x80]))
+            .store(H256::random(), H256::random());
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(parse_state_override(&json).unwrap(), Some(state));
+        assert_eq!(parse_state_override("").unwrap(), None);
+
+        let err = parse_state_override("{\"0x00\": {\"balance\": 1}}").unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = parse_state_override("not a state override").unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
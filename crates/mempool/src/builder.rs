@@ -1,21 +1,34 @@
 use crate::{
+    bundle_builder::{new_bundle_builder, BundleBuilder},
+    estimate::GasEstimationCache,
+    reorg::{BlockTracker, TrackedBlock},
     validate::{
         validator::StandardUserOperationValidator, SanityCheck, SimulationCheck,
         SimulationTraceCheck,
     },
-    Mempool, Reputation, UoPool,
+    Mempool, MempoolId, Reputation, UoPool,
 };
 use alloy_chains::Chain;
 use ethers::{
     providers::Middleware,
-    types::{Address, H256, U256},
+    types::{Address, Block, Transaction, H256, U256},
 };
+use eyre::format_err;
 use futures::channel::mpsc::UnboundedSender;
 use futures_util::StreamExt;
+use parking_lot::RwLock;
 use silius_contracts::EntryPoint;
-use silius_primitives::{provider::BlockStream, UoPoolMode, UserOperation, UserOperationSigned};
-use std::{sync::Arc, time::Duration};
-use tracing::warn;
+use silius_primitives::{
+    bundler::BundleStrategy,
+    constants::mempool::{EXECUTION_GAS_MARGIN_PERC, REORG_DEPTH},
+    entry_point::EntryPointVersion,
+    get_address,
+    provider::BlockStream,
+    UoPoolMode, UserOperation, UserOperationEvent, UserOperationStatus,
+};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
 
 type StandardUoPool<M, SanCk, SimCk, SimTrCk> =
     UoPool<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>;
@@ -29,6 +42,7 @@ where
     mode: UoPoolMode,
     eth_client: Arc<M>,
     entrypoint_addr: Address,
+    entrypoint_version: EntryPointVersion,
     chain: Chain,
     max_verification_gas: U256,
     mempool: Mempool,
@@ -36,6 +50,13 @@ where
     validator: StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>,
     // It would be None if p2p is not enabled
     publish_sd: Option<UnboundedSender<(UserOperation, U256)>>,
+    bundle_builder: Arc<dyn BundleBuilder>,
+    execution_gas_margin_perc: u64,
+    min_priority_fee_per_gas: U256,
+    estimations: Arc<RwLock<GasEstimationCache>>,
+    // It would be Some for the user operation pools of alternative mempools
+    mempool_id: Option<MempoolId>,
+    events: Option<broadcast::Sender<UserOperationEvent>>,
 }
 
 impl<M, SanCk, SimCk, SimTrCk> UoPoolBuilder<M, SanCk, SimCk, SimTrCk>
@@ -57,45 +78,214 @@ where
         validator: StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>,
         publish_sd: Option<UnboundedSender<(UserOperation, U256)>>,
     ) -> Self {
+        // every mempool validates user operations against its own entry point (v0.6 and v0.7
+        // mempools can run side by side)
+        let validator =
+            validator.with_entry_point(EntryPoint::<M>::new(eth_client.clone(), entrypoint_addr));
+
         Self {
             mode,
             eth_client,
             entrypoint_addr,
+            entrypoint_version: EntryPointVersion::from_address(&entrypoint_addr),
             chain,
             max_verification_gas,
             mempool,
             reputation,
             validator,
             publish_sd,
+            bundle_builder: new_bundle_builder(BundleStrategy::default()),
+            execution_gas_margin_perc: EXECUTION_GAS_MARGIN_PERC,
+            min_priority_fee_per_gas: U256::zero(),
+            estimations: Arc::new(RwLock::new(GasEstimationCache::default())),
+            mempool_id: None,
+            events: None,
         }
     }
 
+    /// Sets the version of the entry point (determined from the entry point address by default)
+    pub fn with_entry_point_version(mut self, version: EntryPointVersion) -> Self {
+        self.entrypoint_version = version;
+        self.validator = self.validator.with_entry_point(EntryPoint::<M>::new_with_version(
+            self.eth_client.clone(),
+            self.entrypoint_addr,
+            version,
+        ));
+        self
+    }
+
+    /// Sets the strategy the user operation pools use to build bundles
+    pub fn with_bundle_strategy(mut self, strategy: BundleStrategy) -> Self {
+        self.bundle_builder = new_bundle_builder(strategy);
+        self
+    }
+
+    /// Sets the percentage added to the gas a user operation used in the simulation when packing
+    /// bundles
+    pub fn with_execution_gas_margin(mut self, execution_gas_margin_perc: u64) -> Self {
+        self.execution_gas_margin_perc = execution_gas_margin_perc;
+        self
+    }
+
+    /// Sets the minimum priority fee per gas the bundler accepts (used for the fee recommendation)
+    pub fn with_min_priority_fee_per_gas(mut self, min_priority_fee_per_gas: U256) -> Self {
+        self.min_priority_fee_per_gas = min_priority_fee_per_gas;
+        self
+    }
+
+    /// Sets the id of the mempool (the canonical mempool id of the entry point is used by default)
+    pub fn with_mempool_id(mut self, mempool_id: MempoolId) -> Self {
+        self.mempool_id = Some(mempool_id);
+        self
+    }
+
+    /// Sets the channel the lifecycle events of the user operations are sent to
+    pub fn with_events(mut self, events: broadcast::Sender<UserOperationEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Decodes the user operations the block included through the entry point, removes them from
+    /// the mempool and increments the `opsIncluded` counters of their entities
+    ///
+    /// The user operations of the reverted bundle transactions were not executed, so they stay in
+    /// the mempool.
+    async fn apply_block(
+        block: Block<Transaction>,
+        uopool: &mut StandardUoPool<M, SanCk, SimCk, SimTrCk>,
+    ) -> eyre::Result<TrackedBlock> {
+        let mut tracked = TrackedBlock {
+            number: block.number.unwrap_or_default().as_u64(),
+            hash: block.hash.unwrap_or_default(),
+            parent_hash: block.parent_hash,
+            ..Default::default()
+        };
+
+        let mut txs = vec![];
+        for tx in block.transactions {
+            if tx.to == Some(uopool.entry_point.address()) {
+                let receipt = uopool
+                    .entry_point
+                    .eth_client()
+                    .get_transaction_receipt(tx.hash)
+                    .await?
+                    .ok_or_else(|| {
+                        format_err!("Receipt of the transaction {:?} is missing", tx.hash)
+                    })?;
+                if receipt.status == Some(1.into()) {
+                    txs.push(tx);
+                } else {
+                    debug!("Bundle transaction {:?} reverted", tx.hash);
+                }
+            }
+        }
+
+        for tx in txs {
+            for aggregator in uopool.entry_point.decode_aggregators(tx.input.clone()) {
+                uopool.reputation.increment_included(&aggregator).ok();
+                tracked.entities.push(aggregator);
+            }
+            if let Some(uos) = uopool.entry_point.decode_handle_ops(tx.input) {
+                let uos = uos
+                    .iter()
+                    .map(|uo| {
+                        UserOperation::from_user_operation_signed(
+                            uo.hash_with_version(
+                                &uopool.entry_point.address(),
+                                uopool.chain.id(),
+                                uopool.entry_point.version(),
+                            ),
+                            uo.clone(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                for uo in uos.iter() {
+                    tracked.entities.push(uo.sender);
+                    tracked.entities.extend(get_address(&uo.paymaster_and_data));
+                    tracked.entities.extend(get_address(&uo.init_code));
+                }
+                uopool.remove_user_operations(uos.clone());
+                for uo in uos.iter() {
+                    uopool.emit_event(
+                        UserOperationEvent::new(
+                            uo.hash,
+                            uopool.entry_point.address(),
+                            UserOperationStatus::Included,
+                        )
+                        .with_transaction(tx.hash, block.number),
+                    );
+                }
+                tracked.user_operations.extend(uos);
+            }
+        }
+
+        Ok(tracked)
+    }
+
     async fn handle_block_update(
         hash: H256,
         uopool: &mut StandardUoPool<M, SanCk, SimCk, SimTrCk>,
+        tracker: &mut BlockTracker,
     ) -> eyre::Result<()> {
-        let txs =
-            uopool.entry_point.eth_client().get_block_with_txs(hash).await?.map(|b| b.transactions);
-
-        if let Some(txs) = txs {
-            for tx in txs {
-                if tx.to == Some(uopool.entry_point.address()) {
-                    let dec: Result<(Vec<UserOperationSigned>, Address), _> =
-                        uopool.entry_point.entry_point_api().decode("handleOps", tx.input);
-
-                    if let Ok((uos, _)) = dec {
-                        uopool.remove_user_operations(
-                            uos.iter()
-                                .map(|uo| {
-                                    UserOperation::from_user_operation_signed(
-                                        uo.hash(&uopool.entry_point.address(), uopool.chain.id()),
-                                        uo.clone(),
-                                    )
-                                })
-                                .collect(),
-                        );
-                    }
-                }
+        if tracker.contains(&hash) {
+            return Ok(());
+        }
+
+        let eth_client = uopool.entry_point.eth_client();
+        let Some(block) = eth_client.get_block_with_txs(hash).await? else {
+            return Ok(());
+        };
+
+        // walk the new chain back until it joins the tracked chain
+        let mut blocks = vec![block];
+        while let Some(last) = blocks.last() {
+            let number = last.number.unwrap_or_default().as_u64();
+            if tracker.contains(&last.parent_hash) ||
+                tracker.oldest_number().map_or(true, |oldest| number <= oldest)
+            {
+                break;
+            }
+            match eth_client.get_block_with_txs(last.parent_hash).await? {
+                Some(parent) => blocks.push(parent),
+                None => break,
+            }
+        }
+
+        let ancestor = blocks.last().map(|b| b.parent_hash).unwrap_or_default();
+        let orphaned =
+            if tracker.extends_head(&ancestor) { vec![] } else { tracker.rewind(&ancestor) };
+        if !orphaned.is_empty() {
+            warn!(
+                "Chain reorganization detected, {} block(s) orphaned, new head {hash:?}",
+                orphaned.len()
+            );
+            for entity in orphaned.iter().flat_map(|b| b.entities.iter()) {
+                uopool.reputation.decrement_included(entity).ok();
+            }
+        }
+
+        let mut included = HashSet::new();
+        for block in blocks.into_iter().rev() {
+            let tracked = Self::apply_block(block, uopool).await?;
+            included.extend(tracked.user_operations.iter().map(|uo| uo.hash));
+            tracker.push(tracked);
+        }
+
+        // user operations of the orphaned blocks go back to the mempool (oldest first) if they
+        // are still valid on the new chain
+        for uo in orphaned
+            .into_iter()
+            .rev()
+            .flat_map(|b| b.user_operations)
+            .filter(|uo| !included.contains(&uo.hash))
+        {
+            let res = uopool.validate_user_operation(&uo).await;
+            match uopool.add_user_operation(uo.clone(), res).await {
+                Ok(_) => info!("{:?} re-added to the mempool after reorganization", uo.hash),
+                Err(e) => debug!(
+                    "{:?} from an orphaned block is not re-added to the mempool: {e:?}",
+                    uo.hash
+                ),
             }
         }
 
@@ -105,10 +295,11 @@ where
     pub fn register_block_updates(&self, mut block_stream: BlockStream) {
         let mut uopool = self.uopool();
         tokio::spawn(async move {
+            let mut tracker = BlockTracker::new(REORG_DEPTH);
             while let Some(hash) = block_stream.next().await {
                 if let Ok(hash) = hash {
                     let h: H256 = hash;
-                    let _ = Self::handle_block_update(h, &mut uopool)
+                    let _ = Self::handle_block_update(h, &mut uopool, &mut tracker)
                         .await
                         .map_err(|e| warn!("Failed to handle block update: {:?}", e));
                 }
@@ -130,9 +321,13 @@ where
     }
 
     pub fn uopool(&self) -> StandardUoPool<M, SanCk, SimCk, SimTrCk> {
-        let entry_point = EntryPoint::<M>::new(self.eth_client.clone(), self.entrypoint_addr);
+        let entry_point = EntryPoint::<M>::new_with_version(
+            self.eth_client.clone(),
+            self.entrypoint_addr,
+            self.entrypoint_version,
+        );
 
-        UoPool::<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>::new(
+        let uopool = UoPool::<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>::new(
             self.mode,
             entry_point,
             self.validator.clone(),
@@ -142,5 +337,18 @@ where
             self.chain,
             self.publish_sd.as_ref().cloned(),
         )
+        .with_bundle_builder(self.bundle_builder.clone())
+        .with_execution_gas_margin(self.execution_gas_margin_perc)
+        .with_min_priority_fee_per_gas(self.min_priority_fee_per_gas)
+        .with_estimation_cache(self.estimations.clone());
+
+        let uopool = match self.mempool_id {
+            Some(id) => uopool.with_id(id),
+            None => uopool,
+        };
+        match self.events {
+            Some(ref events) => uopool.with_events(events.clone()),
+            None => uopool,
+        }
     }
 }
@@ -0,0 +1,606 @@
+//! Strategies that decide which user operations from the mempool go into the bundle
+use crate::mempool::Mempool;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, U256},
+};
+use silius_contracts::Aggregator;
+use silius_primitives::{
+    bundler::BundleStrategy, constants::validation::reputation::THROTTLED_ENTITY_BUNDLE_COUNT,
+    entry_point::EntryPointVersion, simulation::StorageMap, UserOperation,
+    UserOperationsPerAggregator,
+};
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+
+/// Maximum number of gas buckets the knapsack packer works with
+const KNAPSACK_MAX_BUCKETS: u64 = 10_000;
+
+/// Maximum number of candidates the strategies that pack a subset of the candidates collect
+const MAX_BUNDLE_CANDIDATES: usize = 256;
+
+/// How many times the gas budget the candidates of the strategies that pack a subset of the
+/// candidates can take in total
+const BUNDLE_CANDIDATES_GAS_MULTIPLIER: u64 = 2;
+
+/// A user operation that passed the second validation and can be included into the bundle
+#[derive(Debug, Clone)]
+pub struct BundleCandidate {
+    /// The user operation
+    pub uo: UserOperation,
+    /// Gas the user operation takes from the bundle gas budget (`max_verification_gas`)
+    pub gas: U256,
+    /// Gas limits of the user operation (`verification_gas_limit + call_gas_limit`)
+    pub gas_limit: U256,
+    /// Signature aggregator of the user operation (zero address if none)
+    pub aggregator: Address,
+    /// Paymaster of the user operation
+    pub paymaster: Option<Address>,
+    /// Prefund the paymaster deposit has to cover
+    pub pre_fund: U256,
+    /// Entities (factory, paymaster and aggregator) of the user operation
+    pub entities: Vec<Address>,
+    /// Entities of the user operation that are throttled
+    pub throttled: Vec<Address>,
+    /// Signature the aggregator returned from `validateUserOpSignature` (`sigForUserOp`), it
+    /// replaces the signature of the user operation in `handleAggregatedOps`
+    pub sig_for_user_op: Option<Bytes>,
+    /// Storage accessed during the validation of the user operation
+    pub storage_map: StorageMap,
+}
+
+/// The bundle-building strategy used by
+/// [UoPool::bundle_user_operations](crate::UoPool::bundle_user_operations).
+///
+/// Building a bundle goes through four steps:
+/// 1. the user operations from the mempool are ordered with [sort](BundleBuilder::sort),
+/// 2. they are validated one by one (reputation, second simulation, storage conflicts, paymaster
+///    deposits) until the builder reports the bundle [is full](BundleBuilder::is_full),
+/// 3. the builder [packs](BundleBuilder::pack) the valid candidates into the bundle,
+/// 4. the packed candidates are [committed](commit_candidates) against the paymaster deposits and
+///    the limits of the throttled entities.
+pub trait BundleBuilder: Send + Sync {
+    /// Orders the user operations before they are validated for the bundle
+    ///
+    /// # Arguments
+    /// `uos` - The user operations sorted by the mempool
+    /// `mempool` - The [Mempool](Mempool) the user operations come from
+    /// `base_fee` - The current base fee per gas
+    fn sort(
+        &self,
+        uos: Vec<UserOperation>,
+        _mempool: &Mempool,
+        _base_fee: U256,
+    ) -> Vec<UserOperation> {
+        uos
+    }
+
+    /// Whether the candidates collected so far already fill the bundle, in which case the
+    /// remaining user operations are not validated
+    ///
+    /// # Arguments
+    /// `count` - The number of candidates collected so far
+    /// `gas_total` - The gas of all candidates collected so far
+    /// `max_gas` - The gas budget of the bundle
+    fn is_full(&self, _count: usize, _gas_total: U256, _max_gas: U256) -> bool {
+        false
+    }
+
+    /// Picks the candidates that go into the bundle (in bundle order)
+    ///
+    /// # Arguments
+    /// `candidates` - The valid candidates in the order they were validated
+    /// `max_gas` - The gas budget of the bundle
+    /// `base_fee` - The current base fee per gas
+    fn pack(
+        &self,
+        candidates: Vec<BundleCandidate>,
+        max_gas: U256,
+        base_fee: U256,
+    ) -> Vec<BundleCandidate>;
+}
+
+/// Creates the [BundleBuilder](BundleBuilder) for the given strategy
+pub fn new_bundle_builder(strategy: BundleStrategy) -> Arc<dyn BundleBuilder> {
+    match strategy {
+        BundleStrategy::Default => Arc::new(DefaultBundleBuilder),
+        BundleStrategy::MaximizeProfit => Arc::new(MaximizeProfitBundleBuilder),
+        BundleStrategy::Fifo => Arc::new(FifoBundleBuilder),
+        BundleStrategy::Knapsack => Arc::new(KnapsackBundleBuilder),
+    }
+}
+
+/// Tip per gas the bundler receives for the user operation
+pub fn effective_tip(uo: &UserOperation, base_fee: U256) -> U256 {
+    uo.max_priority_fee_per_gas.min(uo.max_fee_per_gas.saturating_sub(base_fee))
+}
+
+/// Whether enough candidates are collected for the strategies that pack a subset of them
+fn candidates_exceeded(count: usize, gas_total: U256, max_gas: U256) -> bool {
+    count >= MAX_BUNDLE_CANDIDATES ||
+        gas_total > max_gas.saturating_mul(BUNDLE_CANDIDATES_GAS_MULTIPLIER.into())
+}
+
+/// Takes the candidates in order until the first one that doesn't fit into the gas budget
+fn pack_prefix(candidates: Vec<BundleCandidate>, max_gas: U256) -> Vec<BundleCandidate> {
+    let mut gas_total = U256::zero();
+    candidates
+        .into_iter()
+        .take_while(|c| {
+            gas_total = gas_total.saturating_add(c.gas);
+            gas_total <= max_gas
+        })
+        .collect()
+}
+
+/// Reorders the user operations of every sender by nonce (keeping the positions the sender
+/// occupies), so that a sender's user operation is never validated before one with a lower nonce
+fn keep_nonce_order(mut uos: Vec<UserOperation>) -> Vec<UserOperation> {
+    let mut positions: HashMap<Address, Vec<usize>> = HashMap::new();
+    for (i, uo) in uos.iter().enumerate() {
+        positions.entry(uo.sender).or_default().push(i);
+    }
+
+    for positions in positions.values().filter(|p| p.len() > 1) {
+        let mut sender_uos = positions.iter().map(|i| uos[*i].clone()).collect::<Vec<_>>();
+        sender_uos.sort_by_key(|uo| uo.nonce);
+        for (i, uo) in positions.iter().zip(sender_uos) {
+            uos[*i] = uo;
+        }
+    }
+
+    uos
+}
+
+/// The default strategy: user operations are validated in the mempool order (highest priority
+/// fee first) and added to the bundle until the gas budget is exceeded
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultBundleBuilder;
+
+impl BundleBuilder for DefaultBundleBuilder {
+    fn is_full(&self, _count: usize, gas_total: U256, max_gas: U256) -> bool {
+        gas_total > max_gas
+    }
+
+    fn pack(
+        &self,
+        candidates: Vec<BundleCandidate>,
+        max_gas: U256,
+        _base_fee: U256,
+    ) -> Vec<BundleCandidate> {
+        pack_prefix(candidates, max_gas)
+    }
+}
+
+/// Greedy strategy that maximizes the bundler profit: user operations are ordered by effective
+/// tip × gas and every user operation that still fits into the gas budget is added
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaximizeProfitBundleBuilder;
+
+impl BundleBuilder for MaximizeProfitBundleBuilder {
+    fn sort(
+        &self,
+        mut uos: Vec<UserOperation>,
+        _mempool: &Mempool,
+        base_fee: U256,
+    ) -> Vec<UserOperation> {
+        uos.sort_by_key(|uo| {
+            std::cmp::Reverse(
+                effective_tip(uo, base_fee).saturating_mul(
+                    uo.call_gas_limit
+                        .saturating_add(uo.verification_gas_limit)
+                        .saturating_add(uo.pre_verification_gas),
+                ),
+            )
+        });
+        keep_nonce_order(uos)
+    }
+
+    fn is_full(&self, count: usize, gas_total: U256, max_gas: U256) -> bool {
+        candidates_exceeded(count, gas_total, max_gas)
+    }
+
+    fn pack(
+        &self,
+        candidates: Vec<BundleCandidate>,
+        max_gas: U256,
+        _base_fee: U256,
+    ) -> Vec<BundleCandidate> {
+        let mut gas_total = U256::zero();
+        candidates
+            .into_iter()
+            .filter(|c| {
+                let gas_total_new = gas_total.saturating_add(c.gas);
+                if gas_total_new > max_gas {
+                    return false;
+                }
+                gas_total = gas_total_new;
+                true
+            })
+            .collect()
+    }
+}
+
+/// Fair strategy: user operations are bundled in the order they arrived into the mempool
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FifoBundleBuilder;
+
+impl BundleBuilder for FifoBundleBuilder {
+    fn sort(
+        &self,
+        mut uos: Vec<UserOperation>,
+        mempool: &Mempool,
+        _base_fee: U256,
+    ) -> Vec<UserOperation> {
+        // user operations without the arrival time (e.g., loaded from the database after a
+        // restart) are the oldest ones
+        uos.sort_by_key(|uo| mempool.get_arrival(&uo.hash).unwrap_or_default());
+        keep_nonce_order(uos)
+    }
+
+    fn is_full(&self, _count: usize, gas_total: U256, max_gas: U256) -> bool {
+        gas_total > max_gas
+    }
+
+    fn pack(
+        &self,
+        candidates: Vec<BundleCandidate>,
+        max_gas: U256,
+        _base_fee: U256,
+    ) -> Vec<BundleCandidate> {
+        pack_prefix(candidates, max_gas)
+    }
+}
+
+/// Strategy that fills the gas budget optimally: the subset of the candidates with the highest
+/// total profit (effective tip × gas) that fits into `max_verification_gas` is picked by solving
+/// the 0/1 knapsack problem
+///
+/// The gas is rounded up to buckets, so that the gas budget is split into at most
+/// [KNAPSACK_MAX_BUCKETS] buckets. At most [MAX_BUNDLE_CANDIDATES] candidates are collected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KnapsackBundleBuilder;
+
+impl BundleBuilder for KnapsackBundleBuilder {
+    fn is_full(&self, count: usize, gas_total: U256, max_gas: U256) -> bool {
+        candidates_exceeded(count, gas_total, max_gas)
+    }
+
+    fn pack(
+        &self,
+        candidates: Vec<BundleCandidate>,
+        max_gas: U256,
+        base_fee: U256,
+    ) -> Vec<BundleCandidate> {
+        let bucket = (max_gas / KNAPSACK_MAX_BUCKETS).max(U256::one());
+        let capacity = (max_gas / bucket).as_usize();
+        let weights = candidates
+            .iter()
+            .map(|c| {
+                let (w, rem) = c.gas.div_mod(bucket);
+                if w > U256::from(capacity) {
+                    usize::MAX
+                } else {
+                    w.as_usize() + usize::from(!rem.is_zero())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // best[w] - highest profit with the total weight at most w
+        let mut best = vec![U256::zero(); capacity + 1];
+        let mut taken = vec![vec![false; capacity + 1]; candidates.len()];
+        for (i, c) in candidates.iter().enumerate() {
+            if weights[i] > capacity {
+                continue;
+            }
+            let profit = effective_tip(&c.uo, base_fee).saturating_mul(c.gas);
+            for w in (weights[i]..=capacity).rev() {
+                let with = best[w - weights[i]].saturating_add(profit);
+                if with > best[w] {
+                    best[w] = with;
+                    taken[i][w] = true;
+                }
+            }
+        }
+
+        let mut picked = vec![false; candidates.len()];
+        let mut w = capacity;
+        for i in (0..candidates.len()).rev() {
+            if taken[i][w] {
+                picked[i] = true;
+                w -= weights[i];
+            }
+        }
+
+        candidates.into_iter().zip(picked).filter(|(_, p)| *p).map(|(c, _)| c).collect()
+    }
+}
+
+/// Commits the packed candidates in bundle order
+/// A candidate is left out of the bundle if the deposit of its paymaster (reduced by the prefunds
+/// of the candidates committed before) doesn't cover its prefund, or if one of its throttled
+/// entities is already included in more than [THROTTLED_ENTITY_BUNDLE_COUNT] committed candidates.
+///
+/// # Arguments
+/// `candidates` - The [BundleCandidates](BundleCandidate) packed into the bundle
+/// `paymaster_deposits` - The deposits of the paymasters of the candidates
+///
+/// # Returns
+/// `Vec<BundleCandidate>` - The committed candidates
+pub fn commit_candidates(
+    candidates: Vec<BundleCandidate>,
+    mut paymaster_deposits: HashMap<Address, U256>,
+) -> Vec<BundleCandidate> {
+    let mut entity_count: HashMap<Address, usize> = HashMap::new();
+    candidates
+        .into_iter()
+        .filter(|c| {
+            if c.throttled
+                .iter()
+                .any(|e| entity_count.get(e).cloned().unwrap_or(0) > THROTTLED_ENTITY_BUNDLE_COUNT)
+            {
+                return false;
+            }
+
+            if let Some(p) = c.paymaster {
+                let deposit = paymaster_deposits.get(&p).cloned().unwrap_or_default();
+                if deposit < c.pre_fund {
+                    return false;
+                }
+                paymaster_deposits.insert(p, deposit.saturating_sub(c.pre_fund));
+            }
+
+            for e in c.entities.iter() {
+                *entity_count.entry(*e).or_insert(0) += 1;
+            }
+            true
+        })
+        .collect()
+}
+
+/// Groups the [UserOperations](UserOperation) per signature aggregator
+/// User operations without an aggregator are grouped under the zero address and come first.
+/// For every aggregator, the signatures are aggregated by calling `aggregateSignatures`, the
+/// signatures of the user operations are replaced with the `sigForUserOp` returned from the
+/// validation and the aggregated signature is checked by calling `validateSignatures` (the way
+/// `handleAggregatedOps` checks it). If any of these calls fails, the user operations of the
+/// aggregator are left out of the bundle.
+///
+/// # Arguments
+/// `eth_client` - Connection to the Ethereum execution client
+/// `version` - The version of the entry point
+/// `candidates` - The [BundleCandidates](BundleCandidate) packed into the bundle
+///
+/// # Returns
+/// `Vec<UserOperationsPerAggregator>` - The [UserOperations](UserOperation) grouped per
+/// aggregator.
+pub async fn aggregate_user_operations<M: Middleware + 'static>(
+    eth_client: Arc<M>,
+    version: EntryPointVersion,
+    candidates: Vec<BundleCandidate>,
+) -> Vec<UserOperationsPerAggregator> {
+    let mut groups: Vec<(Address, Vec<(UserOperation, Option<Bytes>)>)> = vec![];
+    for BundleCandidate { uo, aggregator, sig_for_user_op, .. } in candidates {
+        match groups.iter_mut().find(|(a, _)| *a == aggregator) {
+            Some((_, uos)) => uos.push((uo, sig_for_user_op)),
+            None => groups.push((aggregator, vec![(uo, sig_for_user_op)])),
+        }
+    }
+    groups.sort_by_key(|(aggregator, _)| !aggregator.is_zero());
+
+    let mut res = vec![];
+    for (aggregator, uos) in groups {
+        if aggregator.is_zero() {
+            res.push(UserOperationsPerAggregator {
+                user_operations: uos.into_iter().map(|(uo, _)| uo).collect(),
+                aggregator,
+                signature: Bytes::default(),
+            });
+            continue;
+        }
+
+        let aggregator_api = Aggregator::new(eth_client.clone(), aggregator, version);
+        let signature = match aggregator_api
+            .aggregate_signatures(uos.iter().map(|(uo, _)| uo.user_operation.clone()).collect())
+            .await
+        {
+            Ok(signature) => signature,
+            Err(err) => {
+                warn!(
+                    "Aggregating signatures with aggregator {aggregator:?} failed with error: {err:?}"
+                );
+                continue;
+            }
+        };
+
+        let user_operations: Vec<UserOperation> = uos
+            .into_iter()
+            .map(|(mut uo, sig_for_user_op)| {
+                if let Some(sig_for_user_op) = sig_for_user_op {
+                    uo.user_operation.signature = sig_for_user_op;
+                }
+                uo
+            })
+            .collect();
+
+        if let Err(err) = aggregator_api
+            .validate_signatures(
+                user_operations.iter().map(|uo| uo.user_operation.clone()).collect(),
+                signature.clone(),
+            )
+            .await
+        {
+            warn!(
+                "Validating aggregated signature of aggregator {aggregator:?} failed with error: {err:?}"
+            );
+            continue;
+        }
+
+        res.push(UserOperationsPerAggregator { user_operations, aggregator, signature });
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::H256;
+    use silius_primitives::UserOperationSigned;
+
+    fn candidate(id: u64, gas: u64, tip: u64) -> BundleCandidate {
+        let uo = UserOperation::from_user_operation_signed(
+            H256::from_low_u64_be(id).into(),
+            UserOperationSigned {
+                sender: Address::from_low_u64_be(id),
+                max_fee_per_gas: U256::from(100 + tip),
+                max_priority_fee_per_gas: tip.into(),
+                ..Default::default()
+            },
+        );
+        BundleCandidate {
+            uo,
+            gas: gas.into(),
+            gas_limit: gas.into(),
+            aggregator: Address::zero(),
+            paymaster: None,
+            pre_fund: U256::zero(),
+            entities: vec![],
+            throttled: vec![],
+            sig_for_user_op: None,
+            storage_map: StorageMap::default(),
+        }
+    }
+
+    fn ids(candidates: &[BundleCandidate]) -> Vec<u64> {
+        candidates.iter().map(|c| c.uo.sender.to_low_u64_be()).collect()
+    }
+
+    #[test]
+    fn default_stops_at_first_overflow() {
+        let candidates = vec![candidate(1, 40, 1), candidate(2, 70, 5), candidate(3, 10, 1)];
+        let packed = DefaultBundleBuilder.pack(candidates, 100.into(), 100.into());
+        assert_eq!(ids(&packed), vec![1]);
+    }
+
+    #[test]
+    fn maximize_profit_skips_what_does_not_fit() {
+        let candidates = vec![candidate(1, 70, 5), candidate(2, 40, 3), candidate(3, 30, 1)];
+        let packed = MaximizeProfitBundleBuilder.pack(candidates, 100.into(), 100.into());
+        assert_eq!(ids(&packed), vec![1, 3]);
+    }
+
+    #[test]
+    fn knapsack_fills_gas_budget_optimally() {
+        // greedy picks only 1 (profit 350), the optimum is 2 and 3 (200 + 160)
+        let candidates = vec![candidate(1, 70, 5), candidate(2, 50, 4), candidate(3, 40, 4)];
+        let packed = KnapsackBundleBuilder.pack(candidates, 100.into(), 100.into());
+        assert_eq!(ids(&packed), vec![2, 3]);
+    }
+
+    #[test]
+    fn candidates_are_bounded() {
+        assert!(!MaximizeProfitBundleBuilder.is_full(1, 200.into(), 100.into()));
+        assert!(MaximizeProfitBundleBuilder.is_full(1, 201.into(), 100.into()));
+        assert!(KnapsackBundleBuilder.is_full(MAX_BUNDLE_CANDIDATES, 0.into(), 100.into()));
+        assert!(!FifoBundleBuilder.is_full(MAX_BUNDLE_CANDIDATES, 100.into(), 100.into()));
+    }
+
+    #[test]
+    fn commit_deducts_paymaster_deposit_of_committed_candidates() {
+        let paymaster = Address::random();
+        let candidates = (1..=3)
+            .map(|id| BundleCandidate {
+                paymaster: Some(paymaster),
+                pre_fund: 40.into(),
+                entities: vec![paymaster],
+                ..candidate(id, 10, 1)
+            })
+            .collect();
+        let committed = commit_candidates(candidates, HashMap::from([(paymaster, 100.into())]));
+        assert_eq!(ids(&committed), vec![1, 2]);
+    }
+
+    #[test]
+    fn commit_limits_throttled_entities() {
+        let factory = Address::random();
+        let candidates = (1..=10)
+            .map(|id| BundleCandidate {
+                entities: vec![factory],
+                throttled: if id % 2 == 0 { vec![factory] } else { vec![] },
+                ..candidate(id, 10, 1)
+            })
+            .collect();
+        let committed = commit_candidates(candidates, HashMap::new());
+        // the throttled candidates are left out once the factory is in more than
+        // `THROTTLED_ENTITY_BUNDLE_COUNT` committed candidates
+        assert_eq!(ids(&committed), vec![1, 2, 3, 4, 5, 7, 9]);
+    }
+
+    #[test]
+    fn nonce_order_is_kept_per_sender() {
+        let mut uos = vec![candidate(1, 0, 0).uo, candidate(2, 0, 0).uo, candidate(1, 0, 0).uo];
+        uos[0].user_operation.nonce = 1.into();
+        let uos = keep_nonce_order(uos);
+        assert_eq!(uos[0].nonce, U256::zero());
+        assert_eq!(uos[2].nonce, U256::one());
+    }
+
+    /// Candidate that is validated by the signature aggregator
+    fn aggregated_candidate(id: u64, aggregator: Address, sig: Option<Bytes>) -> BundleCandidate {
+        let mut candidate = candidate(id, 0, 0);
+        candidate.uo.user_operation.signature = vec![id as u8; 65].into();
+        BundleCandidate { aggregator, sig_for_user_op: sig, ..candidate }
+    }
+
+    #[tokio::test]
+    async fn aggregated_user_operations_use_sig_for_user_op() {
+        let (provider, mock) = ethers::providers::Provider::mocked();
+        let signature = Bytes::from(vec![7; 96]);
+        // `aggregateSignatures` returns the aggregated signature, `validateSignatures` returns
+        // nothing (the return data is ignored)
+        for _ in 0..2 {
+            mock.push(Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Bytes(
+                signature.to_vec(),
+            )])))
+            .unwrap();
+        }
+
+        let aggregator = Address::random();
+        let candidates = vec![
+            aggregated_candidate(1, aggregator, Some(Bytes::default())),
+            candidate(2, 0, 0),
+            aggregated_candidate(3, aggregator, None),
+        ];
+        let uos =
+            aggregate_user_operations(Arc::new(provider), EntryPointVersion::V0_6, candidates)
+                .await;
+
+        assert_eq!(uos.len(), 2);
+        assert!(!uos[0].is_aggregated());
+        assert_eq!(uos[0].user_operations.len(), 1);
+        assert_eq!(uos[1].aggregator, aggregator);
+        assert_eq!(uos[1].signature, signature);
+        assert_eq!(
+            uos[1].user_operations.iter().map(|uo| uo.signature.clone()).collect::<Vec<_>>(),
+            vec![Bytes::default(), Bytes::from(vec![3; 65])]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_aggregation_leaves_out_user_operations() {
+        // the mocked provider has no responses, so `aggregateSignatures` fails
+        let (provider, _mock) = ethers::providers::Provider::mocked();
+        let candidates = vec![
+            aggregated_candidate(1, Address::random(), Some(Bytes::default())),
+            candidate(2, 0, 0),
+        ];
+        let uos =
+            aggregate_user_operations(Arc::new(provider), EntryPointVersion::V0_6, candidates)
+                .await;
+
+        assert_eq!(uos.len(), 1);
+        assert!(!uos[0].is_aggregated());
+        assert_eq!(uos[0].user_operations[0].sender, Address::from_low_u64_be(2));
+    }
+}
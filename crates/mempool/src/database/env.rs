@@ -39,7 +39,7 @@ impl<E: EnvironmentKind> Database for Env<E> {
 }
 
 /// Database error
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Error)]
 pub enum DatabaseError {
     /// Internal database error
     #[error(transparent)]
@@ -1,27 +1,31 @@
 use super::{
     env::DatabaseError,
-    tables::{CodeHashes, UserOperations, UserOperationsByEntity, UserOperationsBySender},
+    tables::{
+        BundleHistory, CodeHashes, UserOperations, UserOperationsByEntity, UserOperationsBySender,
+    },
     utils::{
-        WrapAddress, WrapCodeHash, WrapCodeHashVec, WrapUserOpSet, WrapUserOperationHash,
+        WrapAddress, WrapCodeHash, WrapCodeHashVec, WrapH256, WrapUserOpSet, WrapUserOperationHash,
         WrapUserOperationSigned,
     },
     DatabaseTable,
 };
 use crate::{
     mempool::{
-        AddRemoveUserOp, AddRemoveUserOpHash, ClearOp, UserOperationAddrOp,
+        AddRemoveUserOp, AddRemoveUserOpHash, BundleHistoryOp, ClearOp, UserOperationAddrOp,
         UserOperationCodeHashOp, UserOperationOp,
     },
     MempoolErrorKind,
 };
-use ethers::types::Address;
+use ethers::types::{Address, H256};
 use reth_db::{
     cursor::DbCursorRO,
     database::Database,
     mdbx::EnvironmentKind,
     transaction::{DbTx, DbTxMut},
 };
-use silius_primitives::{simulation::CodeHash, UserOperation, UserOperationHash};
+use silius_primitives::{
+    bundler::BundleRecord, simulation::CodeHash, UserOperation, UserOperationHash,
+};
 
 impl<E: EnvironmentKind> AddRemoveUserOp for DatabaseTable<E, UserOperations> {
     fn add(&mut self, uo: UserOperation) -> Result<UserOperationHash, MempoolErrorKind> {
@@ -220,6 +224,45 @@ impl<E: EnvironmentKind> UserOperationCodeHashOp for DatabaseTable<E, CodeHashes
     }
 }
 
+impl<E: EnvironmentKind> BundleHistoryOp for DatabaseTable<E, BundleHistory> {
+    fn add_bundle(&mut self, record: BundleRecord) -> Result<(), MempoolErrorKind> {
+        let tx = self.env.tx_mut()?;
+        tx.put::<BundleHistory>(record.transaction_hash.into(), record.into())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_bundle(&self, tx_hash: &H256) -> Result<Option<BundleRecord>, MempoolErrorKind> {
+        let tx = self.env.tx()?;
+        let res = tx.get::<BundleHistory>((*tx_hash).into())?;
+        tx.commit()?;
+        Ok(res.map(Into::into))
+    }
+
+    fn get_bundles(&self) -> Result<Vec<BundleRecord>, MempoolErrorKind> {
+        let tx = self.env.tx()?;
+        let mut c = tx.cursor_read::<BundleHistory>()?;
+        let res: Vec<BundleRecord> = c
+            .walk(Some(WrapH256::default()))?
+            .map(|a| a.map(|(_, v)| v.into()))
+            .collect::<Result<Vec<_>, _>>()?;
+        tx.commit()?;
+        Ok(res)
+    }
+
+    fn remove_bundle(&mut self, tx_hash: &H256) -> Result<bool, MempoolErrorKind> {
+        let tx_hash_wrap: WrapH256 = (*tx_hash).into();
+        let tx = self.env.tx_mut()?;
+        if tx.get::<BundleHistory>(tx_hash_wrap.clone())?.is_some() {
+            tx.delete::<BundleHistory>(tx_hash_wrap, None)?;
+            tx.commit()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
 macro_rules! impl_clear {
     ($table: ident) => {
         impl<E: EnvironmentKind> ClearOp for DatabaseTable<E, $table> {
@@ -239,16 +282,20 @@ impl_clear!(UserOperations);
 impl_clear!(UserOperationsBySender);
 impl_clear!(UserOperationsByEntity);
 impl_clear!(CodeHashes);
+impl_clear!(BundleHistory);
 
 #[cfg(test)]
 mod tests {
     use crate::{
         database::{
             init_env,
-            tables::{CodeHashes, UserOperations, UserOperationsByEntity, UserOperationsBySender},
+            tables::{
+                BundleHistory, CodeHashes, UserOperations, UserOperationsByEntity,
+                UserOperationsBySender,
+            },
             DatabaseTable,
         },
-        utils::tests::mempool_test_case,
+        utils::tests::{bundle_history_test_case, mempool_test_case},
         Mempool,
     };
     use reth_libmdbx::WriteMap;
@@ -279,4 +326,22 @@ mod tests {
 
         mempool_test_case(mempool);
     }
+
+    #[tokio::test]
+    async fn database_bundle_history() {
+        let dir = TempDir::new("test-silius-db").unwrap();
+
+        let env = init_env::<WriteMap>(dir.into_path()).unwrap();
+        env.create_tables().expect("Create mdbx database tables failed");
+        let env = Arc::new(env);
+        let mempool = Mempool::new(
+            Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
+        )
+        .with_bundle_history(Box::new(DatabaseTable::<WriteMap, BundleHistory>::new(env)));
+
+        bundle_history_test_case(mempool);
+    }
 }
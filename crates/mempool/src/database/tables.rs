@@ -1,6 +1,6 @@
 use super::utils::{
-    WrapAddress, WrapCodeHashVec, WrapReputationEntry, WrapUserOpSet, WrapUserOperationHash,
-    WrapUserOperationSigned,
+    WrapAddress, WrapBundleRecord, WrapCodeHashVec, WrapH256, WrapReputationEntry, WrapUserOpSet,
+    WrapUserOperationHash, WrapUserOperationSigned,
 };
 use reth_db::{table, TableType};
 
@@ -30,11 +30,17 @@ table!(
     ( EntitiesReputation ) WrapAddress | WrapReputationEntry
 );
 
+table!(
+    /// Stores the records of the sent bundles by the hash of the bundle transaction
+    ( BundleHistory ) WrapH256 | WrapBundleRecord
+);
+
 /// Tables that should be present inside database
-pub const TABLES: [(TableType, &str); 5] = [
+pub const TABLES: [(TableType, &str); 6] = [
     (TableType::Table, UserOperations::const_name()),
     (TableType::Table, UserOperationsBySender::const_name()),
     (TableType::Table, UserOperationsByEntity::const_name()),
     (TableType::Table, CodeHashes::const_name()),
     (TableType::Table, EntitiesReputation::const_name()),
+    (TableType::Table, BundleHistory::const_name()),
 ];
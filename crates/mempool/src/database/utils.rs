@@ -2,12 +2,13 @@ use bin_layout::{Decoder, Encoder};
 use ethers::{
     abi::{AbiDecode, AbiEncode},
     prelude::{EthAbiCodec, EthAbiType},
-    types::{Address, Bytes},
+    types::{Address, Bytes, H256},
 };
 use reth_db::table::{Compress, Decode, Decompress, Encode};
 use serde::{Deserialize, Serialize};
 use silius_primitives::{
-    reputation::ReputationEntry, simulation::CodeHash, UserOperationHash, UserOperationSigned,
+    bundler::BundleRecord, reputation::ReputationEntry, simulation::CodeHash, UserOperationHash,
+    UserOperationSigned,
 };
 use std::{collections::HashSet, fmt::Debug};
 
@@ -106,10 +107,12 @@ macro_rules! construct_wrap_struct {
 
 construct_wrap_hash!(Address, WrapAddress, 20);
 construct_wrap_hash!(UserOperationHash, WrapUserOperationHash, 32);
+construct_wrap_hash!(H256, WrapH256, 32);
 
 construct_wrap_struct!(CodeHash, WrapCodeHash);
 construct_wrap_struct!(UserOperationSigned, WrapUserOperationSigned);
 construct_wrap_struct!(ReputationEntry, WrapReputationEntry);
+construct_wrap_struct!(BundleRecord, WrapBundleRecord);
 
 impl<'de> Decoder<'de> for WrapUserOperationHash {
     fn decoder(data: &mut &'de [u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
@@ -0,0 +1,170 @@
+//! Record of the user operations that left the mempool without being included
+use crate::InvalidMempoolUserOperationError;
+use ethers::types::{Address, H256, U64};
+use serde::{Deserialize, Serialize};
+use silius_primitives::{as_checksum_addr, UserOperationHash, UserOperationStatus};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Formatter},
+};
+
+/// Why a user operation was removed from the mempool without being included
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum DropReason {
+    /// Replaced by a user operation of the same sender and nonce (with higher fees)
+    Replaced { by: UserOperationHash },
+    /// Failed the validation (e.g., the second simulation before bundling or a banned entity)
+    Invalid { error: InvalidMempoolUserOperationError },
+}
+
+impl Display for DropReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DropReason::Replaced { by } => write!(f, "replaced by {by:?}"),
+            DropReason::Invalid { error } => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<InvalidMempoolUserOperationError> for DropReason {
+    fn from(error: InvalidMempoolUserOperationError) -> Self {
+        DropReason::Invalid { error }
+    }
+}
+
+/// User operation that left the mempool without being included
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DroppedUserOperation {
+    pub hash: UserOperationHash,
+    pub reason: DropReason,
+    /// Unix timestamp (in seconds) of the removal
+    pub dropped_at: u64,
+}
+
+/// Bounded record of the dropped user operations
+///
+/// The records expire after the TTL, the oldest records are evicted once the capacity is reached.
+#[derive(Debug)]
+pub struct DroppedUserOperations {
+    records: HashMap<UserOperationHash, DroppedUserOperation>,
+    /// Hashes of the records (from the oldest to the newest)
+    order: VecDeque<UserOperationHash>,
+    capacity: usize,
+    /// Time to live (in seconds) of the records
+    ttl: u64,
+}
+
+impl DroppedUserOperations {
+    pub fn new(capacity: usize, ttl: u64) -> Self {
+        Self { records: HashMap::new(), order: VecDeque::new(), capacity: capacity.max(1), ttl }
+    }
+
+    /// Records the dropped user operation (replacing the previous record of the same hash)
+    pub fn insert(&mut self, record: DroppedUserOperation) {
+        self.prune(record.dropped_at);
+        self.remove(&record.hash);
+        while self.order.len() >= self.capacity {
+            if let Some(hash) = self.order.pop_front() {
+                self.records.remove(&hash);
+            }
+        }
+        self.order.push_back(record.hash);
+        self.records.insert(record.hash, record);
+    }
+
+    /// Returns the record of the user operation (if it's not expired)
+    pub fn get(&self, hash: &UserOperationHash, now: u64) -> Option<&DroppedUserOperation> {
+        self.records.get(hash).filter(|r| r.dropped_at.saturating_add(self.ttl) > now)
+    }
+
+    /// Forgets the user operation (e.g., it was added to the mempool again)
+    pub fn remove(&mut self, hash: &UserOperationHash) {
+        if self.records.remove(hash).is_some() {
+            self.order.retain(|h| h != hash);
+        }
+    }
+
+    /// Removes the expired records
+    pub fn prune(&mut self, now: u64) {
+        while let Some(hash) = self.order.front() {
+            match self.records.get(hash) {
+                Some(r) if r.dropped_at.saturating_add(self.ttl) > now => break,
+                _ => {
+                    if let Some(hash) = self.order.pop_front() {
+                        self.records.remove(&hash);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// Status of a user operation known to the bundler
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationStatusResponse {
+    #[serde(rename = "userOpHash")]
+    pub user_operation_hash: UserOperationHash,
+    #[serde(serialize_with = "as_checksum_addr")]
+    pub entry_point: Address,
+    pub status: UserOperationStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<U64>,
+    /// Why the user operation was dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<DropReason>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ReputationError;
+
+    fn record(id: u64, dropped_at: u64) -> DroppedUserOperation {
+        DroppedUserOperation {
+            hash: H256::from_low_u64_be(id).into(),
+            reason: InvalidMempoolUserOperationError::Reputation(ReputationError::BannedEntity {
+                entity: "paymaster".into(),
+                address: Address::from_low_u64_be(id),
+            })
+            .into(),
+            dropped_at,
+        }
+    }
+
+    #[test]
+    fn evicts_oldest_and_expired_records() {
+        let mut dropped = DroppedUserOperations::new(2, 10);
+        dropped.insert(record(1, 100));
+        dropped.insert(record(2, 101));
+        dropped.insert(record(3, 102));
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped.get(&H256::from_low_u64_be(1).into(), 102).is_none());
+        assert!(dropped.get(&H256::from_low_u64_be(2).into(), 102).is_some());
+
+        // expired, but not pruned yet
+        assert!(dropped.get(&H256::from_low_u64_be(2).into(), 111).is_none());
+        dropped.insert(record(4, 112));
+        assert_eq!(dropped.len(), 1);
+        assert!(dropped.get(&H256::from_low_u64_be(3).into(), 112).is_none());
+        assert_eq!(
+            dropped.get(&H256::from_low_u64_be(4).into(), 112).map(|r| r.reason.to_string()),
+            Some(format!("paymaster {:?} is banned", Address::from_low_u64_be(4)))
+        );
+
+        dropped.remove(&H256::from_low_u64_be(4).into());
+        assert!(dropped.is_empty());
+    }
+}
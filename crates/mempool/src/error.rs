@@ -80,7 +80,7 @@ impl From<reth_db::Error> for MempoolErrorKind {
 }
 
 /// Error when validating user operation failed
-#[derive(Debug, Error, Serialize, Deserialize)]
+#[derive(Clone, Debug, Error, Serialize, Deserialize)]
 pub enum InvalidMempoolUserOperationError {
     /// User operation rejected because of the reputation of the entities
     #[error(transparent)]
@@ -94,7 +94,7 @@ pub enum InvalidMempoolUserOperationError {
 }
 
 /// Error related to reputation of the entities
-#[derive(Debug, Error, Serialize, Deserialize)]
+#[derive(Clone, Debug, Error, Serialize, Deserialize)]
 pub enum ReputationError {
     /// Entity is banned
     #[error("{entity} {address:?} is banned")]
@@ -130,7 +130,7 @@ impl From<reth_db::Error> for ReputationError {
 }
 
 /// Error when sanity check fails
-#[derive(Debug, Error, Serialize, Deserialize)]
+#[derive(Clone, Debug, Error, Serialize, Deserialize)]
 pub enum SanityError {
     /// Verification gas limit is too high
     #[error("verificationGasLimit too high: expected at most {verification_gas_limit_expected}")]
@@ -165,6 +165,9 @@ pub enum SanityError {
     /// Entity role validation
     #[error("A {entity} at {address:?} in this user operation is used as a {entity_other} entity in another useroperation currently in mempool")]
     EntityRoles { entity: String, address: Address, entity_other: String },
+    /// Block the user operation was verified at (by the p2p peer) is unknown or too old
+    #[error("{inner}")]
+    VerifiedBlock { inner: String },
     /// Reputation error
     #[error(transparent)]
     Reputation(ReputationError),
@@ -202,7 +205,7 @@ impl From<EntryPointError> for SanityError {
 }
 
 /// Error when simulation fails
-#[derive(Debug, Error, Serialize, Deserialize)]
+#[derive(Clone, Debug, Error, Serialize, Deserialize)]
 pub enum SimulationError {
     /// Signature verification failed
     #[error("Invalid userop signature or paymaster signature")]
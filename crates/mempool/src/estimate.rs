@@ -1,24 +1,31 @@
+use crate::utils::div_ceil;
 use const_hex::hex;
 use core::fmt::Debug;
 use ethers::{
     abi::{Hash, RawLog},
     contract::EthLogDecode,
     providers::Middleware,
-    types::{Bytes, U256},
+    types::{spoof, Bytes, H256, U256},
+    utils::keccak256,
 };
 use silius_contracts::{
     decode_revert_string,
     executor_tracer::{ExecutorTracerResult, LogInfo},
-    EntryPoint, EntryPointError, ExecutionResult, FailedOp, UserOperationEventFilter,
+    EntryPoint, EntryPointError, ExecutionResult, UserOperationEventFilter,
     UserOperationRevertReasonFilter,
 };
-use silius_primitives::UserOperationSigned;
-use std::str::FromStr;
+use silius_primitives::{
+    constants::mempool::PAYMASTER_POST_OP_GAS_LIMIT, entry_point::EntryPointVersion,
+    pack_paymaster_and_data, PackedUserOperation, UserOperationHash, UserOperationSigned,
+    PAYMASTER_DATA_OFFSET,
+};
+use std::{collections::HashMap, future::Future, str::FromStr};
 
-const FALL_BACK_BINARY_SEARCH_CUT_OFF: u64 = 30000;
+const BINARY_SEARCH_CUT_OFF: u64 = 5000;
 const BASE_VGL_BUFFER: u64 = 25;
+/// Upper bound of the verification gas limit bracket (percentage of the gas the traced run used)
+const VGL_BRACKET_PERC: u64 = 200;
 const MAX_CALL_GAS_LIMIT: u64 = 18_000_000;
-const MAX_RETRY: u64 = 7;
 const NON_ZERO_GAS: u64 = 12100; // should be different based on diferrent chain
 const EXECUTION_REVERTED: &str = "execution reverted";
 const EXECUTION_OOG: &str = "execution OOG";
@@ -36,6 +43,7 @@ fn is_validation_oog<T: ToString>(err: T) -> bool {
         s.contains("return data out of bounds") ||
         s.contains("AA40 over verificationGasLimit") ||
         s.contains("AA41 too little verificationGas") ||
+        s.contains("AA36 over paymasterVerificationGasLimit") ||
         s.contains("AA51 prefund below actualGasCost") ||
         s.contains("AA13 initCode failed or OOG") ||
         s.contains("AA23 reverted (or OOG)") ||
@@ -50,6 +58,50 @@ fn is_execution_revert<T: ToString>(err: T) -> bool {
     err.to_string().contains(EXECUTION_REVERTED)
 }
 
+/// Gets the paymaster post-op gas limit of the user operation sent to the entry point v0.7 (the
+/// default is used if it's not set)
+///
+/// # Arguments
+/// * `uo` - The user operation
+/// * `version` - The version of the entry point
+///
+/// # Returns
+/// * `Option<U256>` - The paymaster post-op gas limit, `None` if the user operation has no
+///   paymaster or it's sent to the entry point v0.6
+pub fn paymaster_post_op_gas_limit(
+    uo: &UserOperationSigned,
+    version: EntryPointVersion,
+) -> Option<U256> {
+    if version != EntryPointVersion::V0_7 {
+        return None;
+    }
+    PackedUserOperation::from(uo.clone()).paymaster_gas_limits().map(|(_, _, post_op_gas_limit)| {
+        if post_op_gas_limit.is_zero() {
+            PAYMASTER_POST_OP_GAS_LIMIT.into()
+        } else {
+            post_op_gas_limit
+        }
+    })
+}
+
+/// Packs the paymaster verification and post-op gas limits into `paymasterAndData` of the user
+/// operation (entry point v0.7), the paymaster data are kept
+fn set_paymaster_gas_limits(
+    uo: &mut UserOperationSigned,
+    verification_gas_limit: U256,
+    post_op_gas_limit: U256,
+) {
+    if let Some((paymaster, _, _)) = PackedUserOperation::from(uo.clone()).paymaster_gas_limits() {
+        let paymaster_data = Bytes::from(uo.paymaster_and_data[PAYMASTER_DATA_OFFSET..].to_vec());
+        uo.paymaster_and_data = pack_paymaster_and_data(
+            paymaster,
+            verification_gas_limit,
+            post_op_gas_limit,
+            &paymaster_data,
+        );
+    }
+}
+
 #[derive(Debug, Default)]
 struct TraceOutput {
     tracer_result: ExecutorTracerResult,
@@ -58,23 +110,14 @@ struct TraceOutput {
     user_op_revert_event: Option<UserOperationRevertReasonFilter>,
 }
 
-fn parse_simulate_handle_op_output(output: &str) -> Result<ExecutionResult, EntryPointError> {
+fn parse_simulate_handle_op_output<M: Middleware>(
+    output: &str,
+    entry_point: &EntryPoint<M>,
+) -> Result<ExecutionResult, EntryPointError> {
     let output_b = Bytes::from_str(output).map_err(|e| EntryPointError::Other {
         inner: format!("parse simulate handle op output failed: {e:?}"),
     })?;
-    if let Ok(decoded) =
-        <ExecutionResult as ::ethers::core::abi::AbiDecode>::decode(output_b.as_ref())
-    {
-        return Ok(decoded);
-    };
-
-    if let Ok(decoded) = <FailedOp as ::ethers::core::abi::AbiDecode>::decode(output_b.as_ref()) {
-        return Err(EntryPointError::FailedOp(decoded));
-    };
-
-    Err(EntryPointError::Other {
-        inner: "output of parse simulate handle op is not valid".to_string(),
-    })
+    entry_point.decode_simulate_handle_op_output(&output_b)
 }
 
 fn parse_user_op_event<T: Debug + EthLogDecode>(event: &LogInfo) -> Result<T, EntryPointError> {
@@ -109,15 +152,17 @@ fn parse_user_op_event<T: Debug + EthLogDecode>(event: &LogInfo) -> Result<T, En
 async fn trace_simulate_handle_op<M: Middleware>(
     user_op: &UserOperationSigned,
     entry_point: &EntryPoint<M>,
+    state_override: Option<&spoof::State>,
 ) -> Result<TraceOutput, EntryPointError> {
-    let geth_trace = entry_point.simulate_handle_op_trace(user_op.clone()).await?;
+    let geth_trace = entry_point.simulate_handle_op_trace(user_op.clone(), state_override).await?;
 
     let tracer_result: ExecutorTracerResult =
         ExecutorTracerResult::try_from(geth_trace).map_err(|e| EntryPointError::Other {
             inner: format!("Estimate trace simulate handle op decode error {e:?}"),
         })?;
 
-    let execution_result = parse_simulate_handle_op_output(tracer_result.output.as_str())?;
+    let execution_result =
+        parse_simulate_handle_op_output(tracer_result.output.as_str(), entry_point)?;
 
     let user_op_event = tracer_result.user_op_event.as_ref().ok_or(EntryPointError::Other {
         inner: "Estimate trace simulate handle op user op event not found".to_string(),
@@ -149,128 +194,505 @@ async fn trace_simulate_handle_op<M: Middleware>(
     Ok(TraceOutput { tracer_result, execution_result, user_op_event, user_op_revert_event })
 }
 
-pub async fn estimate_user_op_gas<M: Middleware>(
-    user_op_ori: &UserOperationSigned,
+/// Returns the gas the user operation actually used (`actualGasUsed` of the `UserOperationEvent`)
+/// by tracing `simulateHandleOp` with the executor tracer
+pub async fn trace_user_op_gas_used<M: Middleware>(
+    user_op: &UserOperationSigned,
     entry_point: &EntryPoint<M>,
-) -> Result<(U256, U256), EntryPointError> {
-    let mut iteration: u64 = 0;
+) -> Result<U256, EntryPointError> {
+    let out = trace_simulate_handle_op(user_op, entry_point, None).await?;
+    Ok(out.user_op_event.actual_gas_used)
+}
 
-    let mut user_op = user_op_ori.clone();
-    user_op.verification_gas_limit = 0.into();
-    user_op.call_gas_limit = 0.into();
-    user_op.max_priority_fee_per_gas = user_op_ori.max_fee_per_gas;
+/// Returns the gas the user operation used in `simulateHandleOp` from the fee it paid (the entry
+/// point charges `min(maxFeePerGas, basefee + maxPriorityFeePerGas)` per gas)
+///
+/// # Arguments
+/// * `paid` - The fee the user operation paid (`paid` of the `ExecutionResult`)
+/// * `user_op` - The user operation
+/// * `base_fee` - The base fee of the latest block
+///
+/// # Returns
+/// * `Option<U256>` - The gas used, `None` if the user operation pays no fee
+pub fn gas_used_from_paid(
+    paid: U256,
+    user_op: &UserOperationSigned,
+    base_fee: U256,
+) -> Option<U256> {
+    let gas_price =
+        user_op.max_fee_per_gas.min(base_fee.saturating_add(user_op.max_priority_fee_per_gas));
+    (!gas_price.is_zero()).then(|| div_ceil(paid, gas_price))
+}
 
-    // Binary search
-    let mut l: u64 = 0;
-    let mut r: u64 = u64::MAX;
-    let mut f: u64 = 0;
+/// Returns the gas the user operation takes from the bundle gas budget: the gas it used without
+/// the `pre_verification_gas`, increased by the safety margin and capped by its gas limits
+///
+/// # Arguments
+/// * `gas_used` - The gas the user operation used in `simulateHandleOp`
+/// * `pre_verification_gas` - The `pre_verification_gas` of the user operation
+/// * `margin_perc` - The safety margin (percentage of the gas used)
+/// * `gas_limit` - The verification and call gas limits of the user operation
+///
+/// # Returns
+/// * `U256` - The bundle gas of the user operation
+pub fn bundle_gas_with_margin(
+    gas_used: U256,
+    pre_verification_gas: U256,
+    margin_perc: u64,
+    gas_limit: U256,
+) -> U256 {
+    let gas_used = gas_used.saturating_sub(pre_verification_gas);
+    div_ceil(
+        gas_used.saturating_mul(U256::from(100).saturating_add(margin_perc.into())),
+        U256::from(100),
+    )
+    .min(gas_limit)
+}
+
+/// Key of the [gas estimation cache](GasEstimationCache): hash of the packed user operation with
+/// the gas fields zeroed
+pub type GasEstimationKey = H256;
+
+/// Gas limits (verification and call gas limit) estimated for the user operations and the gas
+/// the user operations used in `simulateHandleOp` when bundled, valid for a single block
+#[derive(Debug, Default)]
+pub struct GasEstimationCache {
+    block_number: u64,
+    entries: HashMap<GasEstimationKey, (U256, U256)>,
+    gas_used: HashMap<UserOperationHash, U256>,
+}
+
+impl GasEstimationCache {
+    /// Key of the user operation in the cache (the gas fields do not change the estimation)
+    pub fn key(uo: &UserOperationSigned) -> GasEstimationKey {
+        let uo = UserOperationSigned {
+            call_gas_limit: U256::zero(),
+            verification_gas_limit: U256::zero(),
+            pre_verification_gas: U256::zero(),
+            max_fee_per_gas: U256::zero(),
+            max_priority_fee_per_gas: U256::zero(),
+            ..uo.clone()
+        };
+        H256::from(keccak256(uo.pack()))
+    }
+
+    /// Gets the gas limits estimated in the block
+    pub fn get(&self, block_number: u64, key: &GasEstimationKey) -> Option<(U256, U256)> {
+        if block_number != self.block_number {
+            return None;
+        }
+        self.entries.get(key).copied()
+    }
+
+    /// Stores the gas limits estimated in the block (the estimations of older blocks are dropped)
+    pub fn insert(&mut self, block_number: u64, key: GasEstimationKey, gas_limits: (U256, U256)) {
+        if block_number < self.block_number {
+            return;
+        }
+        self.advance(block_number);
+        self.entries.insert(key, gas_limits);
+    }
+
+    /// Gets the gas the user operation used in `simulateHandleOp` in the block
+    pub fn get_gas_used(&self, block_number: u64, uo_hash: &UserOperationHash) -> Option<U256> {
+        if block_number != self.block_number {
+            return None;
+        }
+        self.gas_used.get(uo_hash).copied()
+    }
+
+    /// Stores the gas the user operation used in `simulateHandleOp` in the block
+    pub fn insert_gas_used(&mut self, block_number: u64, uo_hash: UserOperationHash, gas: U256) {
+        if block_number < self.block_number {
+            return;
+        }
+        self.advance(block_number);
+        self.gas_used.insert(uo_hash, gas);
+    }
 
+    /// Drops the entries of the older blocks
+    fn advance(&mut self, block_number: u64) {
+        if block_number > self.block_number {
+            self.entries.clear();
+            self.gas_used.clear();
+            self.block_number = block_number;
+        }
+    }
+}
+
+/// Binary searches the lowest verification gas limit the simulation succeeds with, first within
+/// the bracket above the gas used and then up to the maximum if the bracket is too narrow
+///
+/// # Arguments
+/// * `verification_gas_used` - The verification gas the traced run used
+/// * `bracket` - The upper bound of the first bracket
+/// * `max_verification_gas` - The maximum verification gas limit the bundler accepts
+/// * `simulate` - Simulates the user operation with the given verification gas limit
+///
+/// # Returns
+/// * `u64` - The verification gas limit (without the buffer)
+async fn search_verification_gas_limit<F, Fut>(
+    verification_gas_used: u64,
+    bracket: u64,
+    max_verification_gas: u64,
+    simulate: F,
+) -> Result<u64, EntryPointError>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<(), EntryPointError>>,
+{
     let mut veri_err = EntryPointError::Other {
         inner: "Could not find a valid verification gas limit".to_string(),
     };
-
-    while r - l >= FALL_BACK_BINARY_SEARCH_CUT_OFF {
-        let m = (l + r) / 2;
-        user_op.verification_gas_limit = m.into();
-        match entry_point.simulate_handle_op(user_op.clone()).await {
-            // VGL too high
-            Ok(_) => {
-                r = m - 1;
-                f = m;
-                continue;
-            }
-            Err(e) => {
-                veri_err = e.clone();
-                if is_prefund_not_paid(&e) {
+    let mut f: u64 = 0;
+    for (mut l, mut r) in [(verification_gas_used, bracket), (bracket, max_verification_gas)] {
+        while r > l && r - l >= BINARY_SEARCH_CUT_OFF {
+            let m = (l + r) / 2;
+            match simulate(m).await {
+                // VGL too high
+                Ok(_) => {
                     r = m - 1;
-                    continue;
-                } else if is_validation_oog(&e) {
-                    l = m + 1;
-                    continue;
-                } else {
-                    return Err(e);
+                    f = m;
+                }
+                Err(e) => {
+                    veri_err = e.clone();
+                    if is_prefund_not_paid(&e) {
+                        r = m - 1;
+                    } else if is_validation_oog(&e) {
+                        l = m + 1;
+                    } else {
+                        return Err(e);
+                    }
                 }
             }
         }
+        if f != 0 {
+            return Ok(f);
+        }
     }
-    if f == 0 {
-        return Err(veri_err);
+    Err(veri_err)
+}
+
+/// Estimates the verification gas limit and call gas limit of the user operation.
+///
+/// A single traced `simulateHandleOp` with the highest limits the bundler accepts gives the gas
+/// the user operation uses, the limits are then refined by binary searches within narrow brackets
+/// above it. The state override (e.g., balance of a counterfactual account) is applied to every
+/// simulation.
+///
+/// # Arguments
+/// * `user_op_ori` - The user operation
+/// * `entry_point` - The entry point
+/// * `max_verification_gas` - The maximum verification gas limit the bundler accepts
+/// * `block_gas_limit` - The gas limit of the latest block
+/// * `state_override` - Geth-style state override applied to the simulations
+///
+/// # Returns
+/// * `(U256, U256)` - The verification gas limit and the call gas limit
+pub async fn estimate_user_op_gas<M: Middleware>(
+    user_op_ori: &UserOperationSigned,
+    entry_point: &EntryPoint<M>,
+    max_verification_gas: u64,
+    block_gas_limit: u64,
+    state_override: Option<&spoof::State>,
+) -> Result<(U256, U256), EntryPointError> {
+    let max_verification_gas = max_verification_gas.min(block_gas_limit);
+    let max_call_gas = MAX_CALL_GAS_LIMIT.min(block_gas_limit);
+
+    // upper bound: traced run with the highest limits and without fees (no prefund is required)
+    let mut user_op = user_op_ori.clone();
+    user_op.verification_gas_limit = max_verification_gas.into();
+    user_op.call_gas_limit = max_call_gas.into();
+    user_op.max_fee_per_gas = 0u64.into();
+    user_op.max_priority_fee_per_gas = 0u64.into();
+    // the paymaster verification gas limit (entry point v0.7) is searched together with the
+    // verification gas limit
+    let post_op_gas_limit = paymaster_post_op_gas_limit(user_op_ori, entry_point.version());
+    if let Some(post_op_gas_limit) = post_op_gas_limit {
+        set_paymaster_gas_limits(&mut user_op, max_verification_gas.into(), post_op_gas_limit);
     }
-    let out: TraceOutput;
-    let mut res: Result<(U256, U256), EntryPointError> = Ok((0u64.into(), 0u64.into()));
-    loop {
-        if iteration >= MAX_RETRY {
-            return res;
-        }
-        f = (f * (100 + BASE_VGL_BUFFER)) / 100;
-        user_op.verification_gas_limit = f.into();
-        user_op.max_fee_per_gas = 0u64.into();
-        user_op.max_priority_fee_per_gas = 0u64.into();
-        user_op.call_gas_limit = MAX_CALL_GAS_LIMIT.into(); // max block gas limit, better set as a config parameter
-        match trace_simulate_handle_op(&user_op, entry_point).await {
-            Ok(o) => {
-                out = o;
-                break;
-            }
-            Err(e) => {
-                iteration += 1;
-                res = Err(e);
-                continue;
+    let out = trace_simulate_handle_op(&user_op, entry_point, state_override).await?;
+
+    let verification_gas_used = out
+        .execution_result
+        .pre_op_gas
+        .saturating_sub(user_op_ori.pre_verification_gas)
+        .min(max_verification_gas.into())
+        .as_u64();
+    let bracket = (verification_gas_used * VGL_BRACKET_PERC / 100).min(max_verification_gas);
+
+    // refine the verification gas limit within the bracket above the gas used (and up to the
+    // maximum if the bracket is too narrow)
+    user_op.call_gas_limit = 0u64.into();
+    user_op.max_fee_per_gas = user_op_ori.max_fee_per_gas;
+    user_op.max_priority_fee_per_gas = user_op_ori.max_fee_per_gas;
+
+    let f = search_verification_gas_limit(
+        verification_gas_used,
+        bracket,
+        max_verification_gas,
+        |verification_gas_limit| {
+            let mut user_op = user_op.clone();
+            user_op.verification_gas_limit = verification_gas_limit.into();
+            if let Some(post_op_gas_limit) = post_op_gas_limit {
+                set_paymaster_gas_limits(
+                    &mut user_op,
+                    verification_gas_limit.into(),
+                    post_op_gas_limit,
+                );
             }
-        }
-    }
-    let verification_gas_limit = user_op.verification_gas_limit;
-    let mut call_gas_limit = if out.tracer_result.execution_gas_limit < NON_ZERO_GAS {
-        NON_ZERO_GAS
-    } else {
-        out.tracer_result.execution_gas_limit
-    };
+            async move { entry_point.simulate_handle_op(user_op, state_override).await.map(|_| ()) }
+        },
+    )
+    .await?;
+    let verification_gas_limit = f * (100 + BASE_VGL_BUFFER) / 100;
 
+    // the traced run gives the execution gas, refine it only if the execution runs out of gas
+    let mut call_gas_limit = out.tracer_result.execution_gas_limit.max(NON_ZERO_GAS);
     user_op.max_priority_fee_per_gas = user_op_ori.max_priority_fee_per_gas;
     user_op.max_fee_per_gas = user_op_ori.max_fee_per_gas;
-    user_op.verification_gas_limit = verification_gas_limit;
+    user_op.verification_gas_limit = verification_gas_limit.into();
+    if let Some(post_op_gas_limit) = post_op_gas_limit {
+        set_paymaster_gas_limits(&mut user_op, verification_gas_limit.into(), post_op_gas_limit);
+    }
     user_op.call_gas_limit = call_gas_limit.into();
-    loop {
-        match trace_simulate_handle_op(&user_op, entry_point).await {
-            Ok(_) => break,
-            Err(e) => {
-                if is_execution_oog(&e) || is_execution_revert(&e) {
-                    let mut l = call_gas_limit;
-                    let mut r = u64::MAX;
-                    let mut f = 0u64;
-                    while r - l >= FALL_BACK_BINARY_SEARCH_CUT_OFF {
-                        let m = (l + r) / 2;
-                        user_op.call_gas_limit = m.into();
-                        let res = trace_simulate_handle_op(&user_op, entry_point).await;
-                        match res {
-                            Ok(_) => {
-                                r = m - 1;
-                                f = m;
-                                continue;
-                            }
-                            Err(err) => {
-                                if is_prefund_not_paid(&err) {
-                                    r = m - 1;
-                                    continue;
-                                } else if is_execution_oog(&err) || is_execution_revert(&err) {
-                                    l = m + 1;
-                                    continue;
-                                } else {
-                                    return Err(EntryPointError::Other {
-                                        inner: "Could not find a valid call gas limit".to_string(),
-                                    });
-                                }
-                            }
+    match trace_simulate_handle_op(&user_op, entry_point, state_override).await {
+        Ok(_) => {}
+        Err(e) if is_execution_oog(&e) || is_execution_revert(&e) => {
+            let mut l = call_gas_limit;
+            let mut r = max_call_gas;
+            let mut f = 0u64;
+            while r > l && r - l >= BINARY_SEARCH_CUT_OFF {
+                let m = (l + r) / 2;
+                user_op.call_gas_limit = m.into();
+                match trace_simulate_handle_op(&user_op, entry_point, state_override).await {
+                    Ok(_) => {
+                        r = m - 1;
+                        f = m;
+                    }
+                    Err(err) => {
+                        if is_prefund_not_paid(&err) {
+                            r = m - 1;
+                        } else if is_execution_oog(&err) || is_execution_revert(&err) {
+                            l = m + 1;
+                        } else {
+                            return Err(EntryPointError::Other {
+                                inner: "Could not find a valid call gas limit".to_string(),
+                            });
                         }
                     }
-                    call_gas_limit = f;
-                } else {
-                    return Err(EntryPointError::Other {
-                        inner: format!("Trace handle op return unhandled error: {:?}", &e),
-                    });
                 }
             }
+            if f == 0 {
+                return Err(e);
+            }
+            call_gas_limit = f;
+        }
+        Err(e) => {
+            return Err(EntryPointError::Other {
+                inner: format!("Trace handle op return unhandled error: {:?}", &e),
+            });
+        }
+    }
+
+    Ok((verification_gas_limit.into(), call_gas_limit.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    /// Simulation that runs out of verification gas below the given limit
+    async fn simulate_with(
+        verification_gas_limit: u64,
+        required: u64,
+    ) -> Result<(), EntryPointError> {
+        if verification_gas_limit >= required {
+            Ok(())
+        } else {
+            Err(EntryPointError::Other { inner: "AA40 over verificationGasLimit".to_string() })
         }
     }
-    Ok((verification_gas_limit, call_gas_limit.into()))
+
+    #[test]
+    fn gas_estimation_cache_per_block() {
+        let uo = UserOperationSigned { sender: Address::random(), ..Default::default() };
+        let key = GasEstimationCache::key(&uo);
+        let mut cache = GasEstimationCache::default();
+
+        cache.insert(10, key, (1.into(), 2.into()));
+        assert_eq!(cache.get(10, &key), Some((1.into(), 2.into())));
+        assert_eq!(cache.get(11, &key), None);
+
+        // estimations of older blocks are ignored
+        cache.insert(9, key, (3.into(), 4.into()));
+        assert_eq!(cache.get(10, &key), Some((1.into(), 2.into())));
+
+        // a new block drops the estimations of the previous one
+        let other = GasEstimationCache::key(&UserOperationSigned {
+            call_data: vec![1].into(),
+            ..uo.clone()
+        });
+        cache.insert(11, other, (5.into(), 6.into()));
+        assert_eq!(cache.get(11, &key), None);
+        assert_eq!(cache.get(11, &other), Some((5.into(), 6.into())));
+    }
+
+    #[test]
+    fn paymaster_gas_limits_v07() {
+        let paymaster = Address::random();
+        let mut uo = UserOperationSigned {
+            paymaster_and_data: pack_paymaster_and_data(
+                paymaster,
+                0.into(),
+                0.into(),
+                &vec![1, 2, 3].into(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(paymaster_post_op_gas_limit(&uo, EntryPointVersion::V0_6), None);
+        assert_eq!(
+            paymaster_post_op_gas_limit(&uo, EntryPointVersion::V0_7),
+            Some(PAYMASTER_POST_OP_GAS_LIMIT.into())
+        );
+
+        set_paymaster_gas_limits(&mut uo, 100_000.into(), 40_000.into());
+        assert_eq!(
+            PackedUserOperation::from(uo.clone()).paymaster_gas_limits(),
+            Some((paymaster, 100_000.into(), 40_000.into()))
+        );
+        assert_eq!(&uo.paymaster_and_data[PAYMASTER_DATA_OFFSET..], &[1, 2, 3]);
+        assert_eq!(paymaster_post_op_gas_limit(&uo, EntryPointVersion::V0_7), Some(40_000.into()));
+
+        // no paymaster
+        let uo = UserOperationSigned::default();
+        assert_eq!(paymaster_post_op_gas_limit(&uo, EntryPointVersion::V0_7), None);
+    }
+
+    #[test]
+    fn gas_estimation_key() {
+        let uo = UserOperationSigned { sender: Address::random(), ..Default::default() };
+        let key = GasEstimationCache::key(&uo);
+
+        // the gas fields do not change the key
+        assert_eq!(
+            GasEstimationCache::key(&UserOperationSigned {
+                call_gas_limit: 100_000.into(),
+                verification_gas_limit: 200_000.into(),
+                pre_verification_gas: 50_000.into(),
+                max_fee_per_gas: 10.into(),
+                max_priority_fee_per_gas: 1.into(),
+                ..uo.clone()
+            }),
+            key
+        );
+
+        // the init code and the paymaster (and data) do
+        assert_ne!(
+            GasEstimationCache::key(&UserOperationSigned {
+                init_code: Address::random().as_bytes().to_vec().into(),
+                ..uo.clone()
+            }),
+            key
+        );
+        assert_ne!(
+            GasEstimationCache::key(&UserOperationSigned {
+                paymaster_and_data: Address::random().as_bytes().to_vec().into(),
+                ..uo.clone()
+            }),
+            key
+        );
+    }
+
+    #[test]
+    fn gas_used_cached_per_block() {
+        let uo_hash = UserOperationHash::from(H256::random());
+        let mut cache = GasEstimationCache::default();
+
+        cache.insert_gas_used(10, uo_hash, 100_000.into());
+        assert_eq!(cache.get_gas_used(10, &uo_hash), Some(100_000.into()));
+        assert_eq!(cache.get_gas_used(11, &uo_hash), None);
+
+        cache.insert(11, H256::random(), (1.into(), 2.into()));
+        assert_eq!(cache.get_gas_used(10, &uo_hash), None);
+    }
+
+    #[test]
+    fn bundle_gas_standard_mode() {
+        // the traced gas used without the pre verification gas, increased by the margin
+        assert_eq!(
+            bundle_gas_with_margin(150_000.into(), 50_000.into(), 10, 1_000_000.into()),
+            110_000.into()
+        );
+        // capped by the gas limits
+        assert_eq!(
+            bundle_gas_with_margin(150_000.into(), 50_000.into(), 10, 105_000.into()),
+            105_000.into()
+        );
+    }
+
+    #[test]
+    fn bundle_gas_unsafe_mode() {
+        let uo = UserOperationSigned {
+            max_fee_per_gas: 30.into(),
+            max_priority_fee_per_gas: 2.into(),
+            ..Default::default()
+        };
+
+        // the entry point charges base fee + priority fee per gas
+        let paid = U256::from(150_000 * 12);
+        let gas_used = gas_used_from_paid(paid, &uo, 10.into()).unwrap();
+        assert_eq!(gas_used, 150_000.into());
+        assert_eq!(
+            bundle_gas_with_margin(gas_used, 50_000.into(), 10, 1_000_000.into()),
+            110_000.into()
+        );
+
+        // the max fee caps the gas price
+        let paid = U256::from(150_000 * 30);
+        assert_eq!(gas_used_from_paid(paid, &uo, 40.into()), Some(150_000.into()));
+
+        // no fee, no gas used to derive
+        assert_eq!(gas_used_from_paid(paid, &UserOperationSigned::default(), 10.into()), None);
+    }
+
+    #[tokio::test]
+    async fn verification_gas_limit_within_bracket() {
+        let f = search_verification_gas_limit(100_000, 200_000, 1_000_000, |m| {
+            simulate_with(m, 150_000)
+        })
+        .await
+        .unwrap();
+        assert!((150_000..=150_000 + BINARY_SEARCH_CUT_OFF).contains(&f));
+    }
+
+    #[tokio::test]
+    async fn verification_gas_limit_above_bracket() {
+        // the bracket is too narrow, the search goes on up to the maximum
+        let f = search_verification_gas_limit(100_000, 200_000, 1_000_000, |m| {
+            simulate_with(m, 600_000)
+        })
+        .await
+        .unwrap();
+        assert!((600_000..=600_000 + BINARY_SEARCH_CUT_OFF).contains(&f));
+
+        // the bracket is narrower than the cut-off, so only the second bracket is searched
+        let f = search_verification_gas_limit(
+            100_000,
+            100_000 + BINARY_SEARCH_CUT_OFF - 1,
+            1_000_000,
+            |m| simulate_with(m, 100_000),
+        )
+        .await
+        .unwrap();
+        assert!((100_000..100_000 + 2 * BINARY_SEARCH_CUT_OFF).contains(&f));
+
+        // no limit up to the maximum is enough
+        assert!(search_verification_gas_limit(100_000, 200_000, 1_000_000, |m| {
+            simulate_with(m, 2_000_000)
+        })
+        .await
+        .is_err());
+    }
 }
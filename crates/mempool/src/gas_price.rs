@@ -0,0 +1,111 @@
+//! Fee recommendation for the user operations (slow, standard and fast tiers)
+use crate::utils::div_ceil;
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+
+/// Percentile of the tips in the mempool the slow tier pays
+const SLOW_TIP_PERCENTILE: usize = 25;
+/// Percentile of the tips in the mempool the standard tier pays
+const STANDARD_TIP_PERCENTILE: usize = 50;
+/// Percentile of the tips in the mempool the fast tier pays
+const FAST_TIP_PERCENTILE: usize = 90;
+/// Percentage of the current base fee the slow tier covers
+const SLOW_BASE_FEE_PERC: u64 = 110;
+/// Percentage of the current base fee the standard tier covers
+const STANDARD_BASE_FEE_PERC: u64 = 125;
+/// Percentage of the current base fee the fast tier covers
+const FAST_BASE_FEE_PERC: u64 = 200;
+
+/// Fees of a single tier
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceTier {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Fees the bundler currently accepts (slow) and prioritises (standard, fast)
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationGasPrice {
+    pub base_fee_per_gas: U256,
+    pub slow: GasPriceTier,
+    pub standard: GasPriceTier,
+    pub fast: GasPriceTier,
+}
+
+/// Nearest-rank percentile of the sorted values (zero if there are no values)
+fn percentile(sorted: &[U256], perc: usize) -> U256 {
+    if sorted.is_empty() {
+        return U256::zero();
+    }
+    let rank = (perc * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+fn tier(base_fee: U256, base_fee_perc: u64, max_priority_fee_per_gas: U256) -> GasPriceTier {
+    GasPriceTier {
+        max_fee_per_gas: div_ceil(base_fee.saturating_mul(base_fee_perc.into()), 100.into())
+            .saturating_add(max_priority_fee_per_gas),
+        max_priority_fee_per_gas,
+    }
+}
+
+/// Recommends the fees from the current base fee, the minimum priority fee the bundler accepts
+/// and the tips of the user operations in the mempool
+///
+/// # Arguments
+/// `base_fee` - The current base fee per gas
+/// `min_priority_fee_per_gas` - The minimum priority fee per gas the bundler accepts
+/// `tips` - Effective tips of the user operations currently in the mempool
+///
+/// # Returns
+/// The [fee recommendation](UserOperationGasPrice)
+pub fn recommend_gas_price(
+    base_fee: U256,
+    min_priority_fee_per_gas: U256,
+    mut tips: Vec<U256>,
+) -> UserOperationGasPrice {
+    tips.sort();
+
+    let slow = percentile(&tips, SLOW_TIP_PERCENTILE).max(min_priority_fee_per_gas);
+    let standard = percentile(&tips, STANDARD_TIP_PERCENTILE).max(slow);
+    let fast = percentile(&tips, FAST_TIP_PERCENTILE).max(standard);
+
+    UserOperationGasPrice {
+        base_fee_per_gas: base_fee,
+        slow: tier(base_fee, SLOW_BASE_FEE_PERC, slow),
+        standard: tier(base_fee, STANDARD_BASE_FEE_PERC, standard),
+        fast: tier(base_fee, FAST_BASE_FEE_PERC, fast),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recommend_gas_price_tiers() {
+        // empty mempool: every tier pays the minimum priority fee
+        let price = recommend_gas_price(100.into(), 10.into(), vec![]);
+        assert_eq!(
+            price.slow,
+            GasPriceTier { max_fee_per_gas: 120.into(), max_priority_fee_per_gas: 10.into() }
+        );
+        assert_eq!(
+            price.standard,
+            GasPriceTier { max_fee_per_gas: 135.into(), max_priority_fee_per_gas: 10.into() }
+        );
+        assert_eq!(
+            price.fast,
+            GasPriceTier { max_fee_per_gas: 210.into(), max_priority_fee_per_gas: 10.into() }
+        );
+
+        let tips = (1..=10).rev().map(|t| U256::from(t * 10)).collect();
+        let price = recommend_gas_price(100.into(), 5.into(), tips);
+        assert_eq!(price.slow.max_priority_fee_per_gas, 30.into());
+        assert_eq!(price.standard.max_priority_fee_per_gas, 50.into());
+        assert_eq!(price.fast.max_priority_fee_per_gas, 90.into());
+        assert_eq!(price.fast.max_fee_per_gas, 290.into());
+    }
+}
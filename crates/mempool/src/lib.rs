@@ -2,36 +2,46 @@
 #![allow(dead_code)]
 
 mod builder;
+pub mod bundle_builder;
 #[cfg(feature = "mdbx")]
 mod database;
+mod dropped;
 pub mod error;
 mod estimate;
+mod gas_price;
 mod memory;
 mod mempool;
 pub mod metrics;
+mod reorg;
 mod reputation;
 mod uopool;
 mod utils;
 pub mod validate;
 
 pub use builder::UoPoolBuilder;
+pub use bundle_builder::{new_bundle_builder, BundleBuilder, BundleCandidate};
 #[cfg(feature = "mdbx")]
 pub use database::{
     init_env,
     tables::{
-        CodeHashes, EntitiesReputation, UserOperations, UserOperationsByEntity,
+        BundleHistory, CodeHashes, EntitiesReputation, UserOperations, UserOperationsByEntity,
         UserOperationsBySender,
     },
     DatabaseError, DatabaseTable, WriteMap,
 };
+pub use dropped::{
+    DropReason, DroppedUserOperation, DroppedUserOperations, UserOperationStatusResponse,
+};
 pub use error::{
     InvalidMempoolUserOperationError, MempoolError, MempoolErrorKind, ReputationError, SanityError,
     SimulationError,
 };
+pub use estimate::{GasEstimationCache, GasEstimationKey};
+pub use gas_price::{GasPriceTier, UserOperationGasPrice};
 pub use mempool::{
-    mempool_id, AddRemoveUserOp, AddRemoveUserOpHash, ClearOp, Mempool, MempoolId,
-    UserOperationAct, UserOperationAddrAct, UserOperationAddrOp, UserOperationCodeHashAct,
-    UserOperationCodeHashOp, UserOperationOp,
+    alt_mempool_id, mempool_id, AddRemoveUserOp, AddRemoveUserOpHash, BundleHistoryAct,
+    BundleHistoryOp, ClearOp, Mempool, MempoolId, UserOperationAct, UserOperationAddrAct,
+    UserOperationAddrOp, UserOperationCodeHashAct, UserOperationCodeHashOp, UserOperationOp,
 };
 pub use reputation::{HashSetOp, Reputation, ReputationEntryOp};
 pub use uopool::UoPool;
@@ -1,13 +1,14 @@
 use crate::{
     mempool::{
-        AddRemoveUserOp, AddRemoveUserOpHash, ClearOp, UserOperationAddrOp,
+        AddRemoveUserOp, AddRemoveUserOpHash, BundleHistoryOp, ClearOp, UserOperationAddrOp,
         UserOperationCodeHashOp, UserOperationOp,
     },
     MempoolErrorKind,
 };
-use ethers::types::Address;
+use ethers::types::{Address, H256};
 use silius_primitives::{
-    simulation::CodeHash, UserOperation, UserOperationHash, UserOperationSigned,
+    bundler::BundleRecord, simulation::CodeHash, UserOperation, UserOperationHash,
+    UserOperationSigned,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -153,10 +154,38 @@ impl ClearOp for HashMap<Address, HashSet<UserOperationHash>> {
     }
 }
 
+impl BundleHistoryOp for HashMap<H256, BundleRecord> {
+    fn add_bundle(&mut self, record: BundleRecord) -> Result<(), MempoolErrorKind> {
+        self.insert(record.transaction_hash, record);
+        Ok(())
+    }
+
+    fn get_bundle(&self, tx_hash: &H256) -> Result<Option<BundleRecord>, MempoolErrorKind> {
+        Ok(self.get(tx_hash).cloned())
+    }
+
+    fn get_bundles(&self) -> Result<Vec<BundleRecord>, MempoolErrorKind> {
+        Ok(self.values().cloned().collect())
+    }
+
+    fn remove_bundle(&mut self, tx_hash: &H256) -> Result<bool, MempoolErrorKind> {
+        Ok(self.remove(tx_hash).is_some())
+    }
+}
+
+impl ClearOp for HashMap<H256, BundleRecord> {
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{utils::tests::mempool_test_case, Mempool};
+    use crate::{
+        utils::tests::{bundle_history_test_case, mempool_test_case},
+        Mempool,
+    };
 
     #[allow(clippy::unit_cmp)]
     #[tokio::test]
@@ -169,4 +198,16 @@ mod tests {
         );
         mempool_test_case(mempool);
     }
+
+    #[tokio::test]
+    async fn memory_bundle_history() {
+        let mempool = Mempool::new(
+            Box::new(HashMap::<UserOperationHash, UserOperationSigned>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()),
+        )
+        .with_bundle_history(Box::new(HashMap::<H256, BundleRecord>::default()));
+        bundle_history_test_case(mempool);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::MempoolErrorKind;
+use crate::{DropReason, DroppedUserOperation, DroppedUserOperations, MempoolErrorKind};
 use dyn_clone::DynClone;
 use ethers::{
     abi::AbiEncode,
@@ -6,8 +6,17 @@ use ethers::{
     utils::{keccak256, to_checksum},
 };
 use parking_lot::RwLock;
-use silius_primitives::{simulation::CodeHash, UserOperation, UserOperationHash};
-use std::sync::Arc;
+use silius_primitives::{
+    bundler::{BundleRecord, BundleStatus},
+    constants::mempool::{DROPPED_USER_OPERATIONS_CAPACITY, DROPPED_USER_OPERATIONS_TTL},
+    simulation::CodeHash,
+    UserOperation, UserOperationHash,
+};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub type MempoolId = H256;
 
@@ -18,6 +27,11 @@ pub fn mempool_id(ep: &Address, chain_id: u64) -> MempoolId {
     )
 }
 
+/// Returns the id of the alternative mempool with the given IPFS CID
+pub fn alt_mempool_id(cid: &str) -> MempoolId {
+    H256::from_slice(keccak256(cid.as_bytes()).as_slice())
+}
+
 /// AddRemoveUserOp describe the ability to add and remove user operation
 pub trait AddRemoveUserOp {
     /// Adds a [UserOperation](UserOperation) to the mempool
@@ -302,6 +316,66 @@ impl<T: UserOperationCodeHashOp> UserOperationCodeHashOp for Arc<RwLock<T>> {
     }
 }
 
+/// BundleHistoryOp describes the ability to store the records of the sent bundles
+pub trait BundleHistoryOp {
+    /// Adds the [BundleRecord](BundleRecord) (an existing record of the same transaction is
+    /// replaced)
+    ///
+    /// # Arguments
+    /// * `record` - The [BundleRecord](BundleRecord) to add
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the record was added
+    /// * `Err(MempoolErrorKind)` - If the record could not be added
+    fn add_bundle(&mut self, record: BundleRecord) -> Result<(), MempoolErrorKind>;
+
+    /// Gets the [BundleRecord](BundleRecord) by the hash of the bundle transaction
+    ///
+    /// # Arguments
+    /// * `tx_hash` - The hash of the bundle transaction
+    ///
+    /// # Returns
+    /// * `Ok(Option<BundleRecord>)` - The record if it exists
+    /// * `Err(MempoolErrorKind)` - If the record could not be retrieved
+    fn get_bundle(&self, tx_hash: &H256) -> Result<Option<BundleRecord>, MempoolErrorKind>;
+
+    /// Gets all [BundleRecords](BundleRecord)
+    ///
+    /// # Returns
+    /// * `Ok(Vec<BundleRecord>)` - All records (in no particular order)
+    /// * `Err(MempoolErrorKind)` - If the records could not be retrieved
+    fn get_bundles(&self) -> Result<Vec<BundleRecord>, MempoolErrorKind>;
+
+    /// Removes the [BundleRecord](BundleRecord) by the hash of the bundle transaction
+    ///
+    /// # Arguments
+    /// * `tx_hash` - The hash of the bundle transaction
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the record was removed
+    /// * `Ok(false)` - If the record does not exist
+    /// * `Err(MempoolErrorKind)` - If the record could not be removed
+    fn remove_bundle(&mut self, tx_hash: &H256) -> Result<bool, MempoolErrorKind>;
+}
+
+impl<T: BundleHistoryOp> BundleHistoryOp for Arc<RwLock<T>> {
+    fn add_bundle(&mut self, record: BundleRecord) -> Result<(), MempoolErrorKind> {
+        self.write().add_bundle(record)
+    }
+
+    fn get_bundle(&self, tx_hash: &H256) -> Result<Option<BundleRecord>, MempoolErrorKind> {
+        self.read().get_bundle(tx_hash)
+    }
+
+    fn get_bundles(&self) -> Result<Vec<BundleRecord>, MempoolErrorKind> {
+        self.read().get_bundles()
+    }
+
+    fn remove_bundle(&mut self, tx_hash: &H256) -> Result<bool, MempoolErrorKind> {
+        self.write().remove_bundle(tx_hash)
+    }
+}
+
 /// A trait for clearing operation.
 pub trait ClearOp {
     /// Clears the operation.
@@ -344,12 +418,25 @@ impl<T> UserOperationCodeHashAct for T where
 {
 }
 
+pub trait BundleHistoryAct: BundleHistoryOp + ClearOp + Send + Sync + DynClone {}
+dyn_clone::clone_trait_object!(BundleHistoryAct);
+impl<T> BundleHistoryAct for T where T: BundleHistoryOp + ClearOp + Send + Sync + Clone {}
+
 #[derive(Clone)]
 pub struct Mempool {
     user_operations: Box<dyn UserOperationAct>,
     user_operations_by_sender: Box<dyn UserOperationAddrAct>,
     user_operations_by_entity: Box<dyn UserOperationAddrAct>,
     user_operations_code_hashes: Box<dyn UserOperationCodeHashAct>,
+    // records of the sent bundles (not cleared together with the user operations)
+    bundle_history: Box<dyn BundleHistoryAct>,
+    // hashes of the latest bundle transactions of the user operations (index of the bundle
+    // history)
+    bundles_by_user_operation: Arc<RwLock<HashMap<UserOperationHash, H256>>>,
+    // arrival times (unix timestamps in milliseconds) of the user operations, kept in memory only
+    arrivals: Arc<RwLock<HashMap<UserOperationHash, u128>>>,
+    // user operations that left the mempool without being included, kept in memory only
+    dropped: Arc<RwLock<DroppedUserOperations>>,
 }
 
 impl Mempool {
@@ -364,12 +451,42 @@ impl Mempool {
             user_operations_by_sender,
             user_operations_by_entity,
             user_operations_code_hashes,
+            bundle_history: Box::new(Arc::new(RwLock::new(
+                HashMap::<H256, BundleRecord>::default(),
+            ))),
+            bundles_by_user_operation: Default::default(),
+            arrivals: Default::default(),
+            dropped: Arc::new(RwLock::new(DroppedUserOperations::new(
+                DROPPED_USER_OPERATIONS_CAPACITY,
+                DROPPED_USER_OPERATIONS_TTL,
+            ))),
         }
     }
+    /// Sets the storage of the bundle history (kept in memory by default)
+    pub fn with_bundle_history(mut self, bundle_history: Box<dyn BundleHistoryAct>) -> Self {
+        self.bundle_history = bundle_history;
+
+        // the stored records are indexed in the order they were sent
+        let mut records = self.bundle_history.get_bundles().unwrap_or_default();
+        records.sort_by_key(|r| r.timestamp);
+        let mut bundles_by_user_operation = HashMap::new();
+        for record in records {
+            for uo_hash in record.user_operations {
+                bundles_by_user_operation.insert(uo_hash.into(), record.transaction_hash);
+            }
+        }
+        self.bundles_by_user_operation = Arc::new(RwLock::new(bundles_by_user_operation));
+        self
+    }
     pub fn add(&mut self, uo: UserOperation) -> Result<UserOperationHash, MempoolErrorKind> {
         let (sender, factory, paymaster) = uo.get_entities();
         let uo_hash = uo.hash;
         self.user_operations.add(uo)?;
+        self.arrivals.write().insert(
+            uo_hash,
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis(),
+        );
+        self.dropped.write().remove(&uo_hash);
         self.user_operations_by_sender.add(&sender, uo_hash)?;
         if let Some(factory) = factory {
             self.user_operations_by_entity.add(&factory, uo_hash)?;
@@ -408,6 +525,25 @@ impl Mempool {
             .filter(|uo_prev| uo_prev.nonce == uo.nonce)
             .max_by_key(|uo_prev| uo_prev.max_priority_fee_per_gas)
     }
+    /// Returns the time (unix timestamp in milliseconds) the user operation arrived into the
+    /// mempool, `None` if the user operation was added before the bundler started
+    pub fn get_arrival(&self, uo_hash: &UserOperationHash) -> Option<u128> {
+        self.arrivals.read().get(uo_hash).cloned()
+    }
+    /// Records that the user operation left the mempool without being included
+    pub fn add_dropped(&mut self, uo_hash: UserOperationHash, reason: DropReason) {
+        self.dropped.write().insert(DroppedUserOperation {
+            hash: uo_hash,
+            reason,
+            dropped_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        });
+    }
+    /// Returns the record of the user operation that left the mempool without being included
+    /// (if it's not expired)
+    pub fn get_dropped(&self, uo_hash: &UserOperationHash) -> Option<DroppedUserOperation> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.dropped.read().get(uo_hash, now).cloned()
+    }
     pub fn has_code_hashes(&self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
         self.user_operations_code_hashes.has_code_hashes(uo_hash)
     }
@@ -446,17 +582,25 @@ impl Mempool {
         }
 
         self.user_operations_code_hashes.remove_code_hashes(uo_hash)?;
+        self.arrivals.write().remove(uo_hash);
 
         Ok(true)
     }
-    pub fn remove_by_entity(&mut self, entity: &Address) -> Result<(), MempoolErrorKind> {
+    /// Removes the user operations that use the entity, returns the hashes of the removed ones
+    pub fn remove_by_entity(
+        &mut self,
+        entity: &Address,
+    ) -> Result<Vec<UserOperationHash>, MempoolErrorKind> {
         let uos = self.user_operations_by_entity.get_all_by_address(entity);
 
+        let mut removed = vec![];
         for uo_hash in uos {
-            self.remove(&uo_hash)?;
+            if self.remove(&uo_hash)? {
+                removed.push(uo_hash);
+            }
         }
 
-        Ok(())
+        Ok(removed)
     }
     // Get UserOperations sorted by max_priority_fee_per_gas without dup sender
     pub fn get_sorted(&self) -> Result<Vec<UserOperation>, MempoolErrorKind> {
@@ -465,10 +609,54 @@ impl Mempool {
     pub fn get_all(&self) -> Result<Vec<UserOperation>, MempoolErrorKind> {
         self.user_operations.get_all()
     }
+    /// Records the sent bundle
+    ///
+    /// The outcome of a replaced bundle is recorded under the hash of the replacement
+    /// transaction, so the record of the submitted (and replaced) transaction is removed.
+    pub fn add_bundle(&mut self, record: BundleRecord) -> Result<(), MempoolErrorKind> {
+        if BundleStatus::from(record.status) != BundleStatus::Submitted {
+            for replaced in self.bundle_history.get_bundles()?.into_iter().filter(|r| {
+                r.transaction_hash != record.transaction_hash &&
+                    r.entry_point == record.entry_point &&
+                    r.timestamp == record.timestamp &&
+                    r.user_operations == record.user_operations &&
+                    BundleStatus::from(r.status) == BundleStatus::Submitted
+            }) {
+                self.bundle_history.remove_bundle(&replaced.transaction_hash)?;
+            }
+        }
+        let mut bundles_by_user_operation = self.bundles_by_user_operation.write();
+        for uo_hash in record.user_operations.iter() {
+            bundles_by_user_operation.insert((*uo_hash).into(), record.transaction_hash);
+        }
+        self.bundle_history.add_bundle(record)
+    }
+    pub fn get_bundle(&self, tx_hash: &H256) -> Result<Option<BundleRecord>, MempoolErrorKind> {
+        self.bundle_history.get_bundle(tx_hash)
+    }
+    /// Returns the latest bundle the user operation was sent in
+    pub fn get_bundle_by_user_operation(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> Result<Option<BundleRecord>, MempoolErrorKind> {
+        let tx_hash = self.bundles_by_user_operation.read().get(uo_hash).copied();
+        match tx_hash {
+            Some(tx_hash) => self.bundle_history.get_bundle(&tx_hash),
+            None => Ok(None),
+        }
+    }
+    /// Returns the bundles sent to the entry point (the most recent first)
+    pub fn get_bundle_history(&self, ep: &Address) -> Result<Vec<BundleRecord>, MempoolErrorKind> {
+        let mut records = self.bundle_history.get_bundles()?;
+        records.retain(|r| r.entry_point == *ep);
+        records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(records)
+    }
     pub fn clear(&mut self) {
         self.user_operations.clear();
         self.user_operations_by_sender.clear();
         self.user_operations_by_entity.clear();
         self.user_operations_code_hashes.clear();
+        self.arrivals.write().clear();
     }
 }
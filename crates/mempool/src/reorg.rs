@@ -0,0 +1,126 @@
+//! Tracking of the recent blocks to detect chain reorganizations
+use ethers::types::{Address, H256};
+use silius_primitives::UserOperation;
+use std::collections::VecDeque;
+
+/// Recent block with the user operations it included
+#[derive(Clone, Debug, Default)]
+pub struct TrackedBlock {
+    /// Number of the block
+    pub number: u64,
+    /// Hash of the block
+    pub hash: H256,
+    /// Hash of the parent block
+    pub parent_hash: H256,
+    /// User operations included in the block (through the entry point)
+    pub user_operations: Vec<UserOperation>,
+    /// Entities whose `opsIncluded` counter was incremented for the block
+    pub entities: Vec<Address>,
+}
+
+/// Parent-hash chain of the recent blocks
+///
+/// A block whose parent is not the tracked head signals a chain reorganization (or missed blocks).
+/// The blocks after the common ancestor of both chains are orphaned.
+#[derive(Clone, Debug)]
+pub struct BlockTracker {
+    /// Tracked blocks (from the oldest to the head)
+    blocks: VecDeque<TrackedBlock>,
+    /// Maximum number of tracked blocks
+    depth: usize,
+}
+
+impl BlockTracker {
+    /// Creates a new tracker of the given depth
+    pub fn new(depth: usize) -> Self {
+        Self { blocks: VecDeque::with_capacity(depth), depth: depth.max(1) }
+    }
+
+    /// Returns the head of the tracked chain
+    pub fn head(&self) -> Option<&TrackedBlock> {
+        self.blocks.back()
+    }
+
+    /// Returns the number of the oldest tracked block
+    pub fn oldest_number(&self) -> Option<u64> {
+        self.blocks.front().map(|b| b.number)
+    }
+
+    /// Whether the block is tracked
+    pub fn contains(&self, hash: &H256) -> bool {
+        self.blocks.iter().any(|b| b.hash == *hash)
+    }
+
+    /// Whether the block extends the tracked head (or nothing is tracked yet)
+    pub fn extends_head(&self, parent_hash: &H256) -> bool {
+        self.head().map_or(true, |h| h.hash == *parent_hash)
+    }
+
+    /// Appends the block to the tracked chain, dropping the oldest block if the tracker is full
+    pub fn push(&mut self, block: TrackedBlock) {
+        if self.blocks.len() == self.depth {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back(block);
+    }
+
+    /// Rewinds the tracked chain to the common ancestor
+    ///
+    /// # Arguments
+    /// * `ancestor` - Hash of the common ancestor of the tracked chain and the new chain
+    ///
+    /// # Returns
+    /// * `Vec<TrackedBlock>` - The orphaned blocks (from the head to the oldest one), all tracked
+    ///   blocks if the ancestor is not tracked (the reorganization is deeper than the tracker)
+    pub fn rewind(&mut self, ancestor: &H256) -> Vec<TrackedBlock> {
+        let mut orphaned = vec![];
+        while let Some(head) = self.blocks.back() {
+            if head.hash == *ancestor {
+                break;
+            }
+            orphaned.extend(self.blocks.pop_back());
+        }
+        orphaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, fork: u64) -> TrackedBlock {
+        TrackedBlock {
+            number,
+            hash: H256::from_low_u64_be(number * 10 + fork),
+            parent_hash: H256::from_low_u64_be((number - 1) * 10),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rewinds_orphaned_blocks() {
+        let mut tracker = BlockTracker::new(3);
+        for number in 1..=4 {
+            let b = block(number, 0);
+            assert!(tracker.extends_head(&b.parent_hash));
+            tracker.push(b);
+        }
+        assert_eq!(tracker.oldest_number(), Some(2));
+        assert_eq!(tracker.head().map(|b| b.number), Some(4));
+
+        // block 4 is replaced by a block on another fork
+        let fork = TrackedBlock { parent_hash: block(3, 0).hash, ..block(4, 1) };
+        assert!(!tracker.extends_head(&fork.parent_hash));
+        assert!(tracker.contains(&fork.parent_hash));
+        assert_eq!(
+            tracker.rewind(&fork.parent_hash).iter().map(|b| b.hash).collect::<Vec<_>>(),
+            vec![block(4, 0).hash]
+        );
+        assert!(tracker.extends_head(&fork.parent_hash));
+        tracker.push(fork);
+
+        // ancestor is older than the tracked blocks
+        assert_eq!(tracker.rewind(&H256::zero()).len(), 3);
+        assert!(tracker.head().is_none());
+    }
+}
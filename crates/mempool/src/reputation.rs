@@ -279,6 +279,24 @@ impl Reputation {
         Ok(())
     }
 
+    /// Decreases the number of times an entity successfully includes a
+    /// user operation in a block (e.g., the block was reorged out).
+    ///
+    /// # Arguments
+    /// * `addr` - The address to decrement
+    ///
+    /// # Returns
+    /// * `Ok(())` if the address was decremented successfully
+    /// * `Err(ReputationError::NotFound)` if the address does not exist
+    pub fn decrement_included(&mut self, addr: &Address) -> Result<(), ReputationError> {
+        self.set_default(addr)?;
+        if let Some(mut ent) = self.entities.get_entry(addr)? {
+            ent.uo_included = ent.uo_included.saturating_sub(1);
+            self.entities.set_entry(ent)?;
+        }
+        Ok(())
+    }
+
     /// Update an entity's status by hours
     ///
     /// # Returns
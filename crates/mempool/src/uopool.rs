@@ -1,36 +1,52 @@
 use crate::{
-    estimate::estimate_user_op_gas,
+    bundle_builder::{
+        aggregate_user_operations, commit_candidates, effective_tip, BundleBuilder,
+        BundleCandidate, DefaultBundleBuilder,
+    },
+    estimate::{
+        bundle_gas_with_margin, estimate_user_op_gas, gas_used_from_paid,
+        paymaster_post_op_gas_limit, trace_user_op_gas_used, GasEstimationCache,
+    },
+    gas_price::{recommend_gas_price, UserOperationGasPrice},
     mempool::Mempool,
     mempool_id,
-    utils::div_ceil,
+    utils::{div_ceil, p2p_validation_mode},
     validate::{
         utils::merge_storage_maps, UserOperationValidationOutcome, UserOperationValidator,
         UserOperationValidatorMode,
     },
-    InvalidMempoolUserOperationError, MempoolError, MempoolErrorKind, MempoolId, Overhead,
-    Reputation, ReputationError, SanityError, SimulationError,
+    DropReason, InvalidMempoolUserOperationError, MempoolError, MempoolErrorKind, MempoolId,
+    Overhead, Reputation, ReputationError, SanityError, SimulationError,
+    UserOperationStatusResponse,
 };
 use alloy_chains::Chain;
 use ethers::{
     prelude::LogMeta,
     providers::Middleware,
-    types::{Address, BlockNumber, U256},
+    types::{spoof, Address, BlockNumber, H256, U256, U64},
 };
 use eyre::format_err;
 use futures::channel::mpsc::UnboundedSender;
-use silius_contracts::{
-    entry_point::UserOperationEventFilter, utils::parse_from_input_data, EntryPoint,
-    EntryPointError,
-};
+use parking_lot::RwLock;
+use silius_contracts::{entry_point::UserOperationEventFilter, EntryPoint, EntryPointError};
 use silius_primitives::{
-    constants::validation::reputation::THROTTLED_ENTITY_BUNDLE_COUNT,
+    bundler::{BundleRecord, BundleStatus},
+    chain::ChainSpec,
+    constants::{
+        mempool::EXECUTION_GAS_MARGIN_PERC,
+        validation::entities::{AGGREGATOR, FACTORY, PAYMASTER},
+    },
     get_address,
     reputation::{ReputationEntry, StakeInfo, StakeInfoResponse, Status},
     simulation::StorageMap,
-    UoPoolMode, UserOperation, UserOperationByHash, UserOperationGasEstimation, UserOperationHash,
-    UserOperationReceipt,
+    UoPoolMode, UserOperation, UserOperationByHash, UserOperationEvent, UserOperationGasEstimation,
+    UserOperationHash, UserOperationReceipt, UserOperationStatus, UserOperationsPerAggregator,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
 };
-use std::collections::{HashMap, HashSet};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, trace};
 
 const FILTER_MAX_DEPTH: u64 = 10;
@@ -59,6 +75,17 @@ pub struct UoPool<M: Middleware + 'static, V: UserOperationValidator> {
     pub chain: Chain,
     // It would be None if p2p is not enabled
     p2p_channel: Option<UnboundedSender<(UserOperation, U256)>>,
+    /// The [BundleBuilder](BundleBuilder) strategy that picks user operations into the bundle
+    pub bundle_builder: Arc<dyn BundleBuilder>,
+    /// Percentage added to the gas a user operation used in the simulation when packing bundles
+    pub execution_gas_margin_perc: u64,
+    /// Minimum priority fee per gas the bundler accepts (used for the fee recommendation)
+    pub min_priority_fee_per_gas: U256,
+    /// Gas limits estimated in the latest block (shared by the user operation pools of the
+    /// mempool)
+    estimations: Arc<RwLock<GasEstimationCache>>,
+    /// Lifecycle events of the user operations (if anyone listens to them)
+    events: Option<broadcast::Sender<UserOperationEvent>>,
 }
 
 impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
@@ -96,9 +123,74 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
             max_verification_gas,
             chain,
             p2p_channel,
+            bundle_builder: Arc::new(DefaultBundleBuilder),
+            execution_gas_margin_perc: EXECUTION_GAS_MARGIN_PERC,
+            min_priority_fee_per_gas: U256::zero(),
+            estimations: Arc::new(RwLock::new(GasEstimationCache::default())),
+            events: None,
         }
     }
 
+    /// Replaces the id of the mempool (the user operation pools of alternative mempools are
+    /// identified by their own id)
+    pub fn with_id(mut self, id: MempoolId) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Replaces the [BundleBuilder](BundleBuilder) strategy (the
+    /// [DefaultBundleBuilder](DefaultBundleBuilder) is used by default)
+    pub fn with_bundle_builder(mut self, bundle_builder: Arc<dyn BundleBuilder>) -> Self {
+        self.bundle_builder = bundle_builder;
+        self
+    }
+
+    /// Sets the percentage added to the gas a user operation used in the simulation when packing
+    /// bundles
+    pub fn with_execution_gas_margin(mut self, execution_gas_margin_perc: u64) -> Self {
+        self.execution_gas_margin_perc = execution_gas_margin_perc;
+        self
+    }
+
+    /// Sets the minimum priority fee per gas the bundler accepts
+    pub fn with_min_priority_fee_per_gas(mut self, min_priority_fee_per_gas: U256) -> Self {
+        self.min_priority_fee_per_gas = min_priority_fee_per_gas;
+        self
+    }
+
+    /// Sets the cache of the gas estimations (shared by the user operation pools of the mempool)
+    pub fn with_estimation_cache(mut self, estimations: Arc<RwLock<GasEstimationCache>>) -> Self {
+        self.estimations = estimations;
+        self
+    }
+
+    /// Sets the channel the lifecycle events of the user operations are sent to
+    pub fn with_events(mut self, events: broadcast::Sender<UserOperationEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Sends the lifecycle event of a user operation to the subscribers (if there are any)
+    pub fn emit_event(&self, event: UserOperationEvent) {
+        if let Some(ref events) = self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Records the user operation that left the mempool without being included and sends its
+    /// [dropped](UserOperationStatus::Dropped) event
+    fn record_dropped(&mut self, uo_hash: UserOperationHash, reason: DropReason) {
+        self.emit_event(
+            UserOperationEvent::new(
+                uo_hash,
+                self.entry_point.address(),
+                UserOperationStatus::Dropped,
+            )
+            .with_reason(reason.to_string()),
+        );
+        self.mempool.add_dropped(uo_hash, reason);
+    }
+
     /// Returns all of the [UserOperations](UserOperation) in the mempool
     ///
     /// # Returns
@@ -109,6 +201,30 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         })
     }
 
+    /// Records a bundle sent to the [EntryPoint](EntryPoint)
+    ///
+    /// # Arguments
+    /// `record` - The [BundleRecord](BundleRecord) (replaces an existing record of the same
+    /// transaction)
+    ///
+    /// # Returns
+    /// `Result<(), eyre::Error>` - Ok if the record was stored
+    pub fn add_bundle(&mut self, record: BundleRecord) -> eyre::Result<()> {
+        self.mempool.add_bundle(record).map_err(|err| {
+            format_err!("Adding bundle record into the bundle history failed with error: {err:?}",)
+        })
+    }
+
+    /// Returns the bundles sent to the [EntryPoint](EntryPoint) (the most recent first)
+    ///
+    /// # Returns
+    /// `Result<Vec<BundleRecord>, eyre::Error>` - An array of [BundleRecords](BundleRecord)
+    pub fn get_bundle_history(&self) -> eyre::Result<Vec<BundleRecord>> {
+        self.mempool
+            .get_bundle_history(&self.entry_point.address())
+            .map_err(|err| format_err!("Getting the bundle history failed with error: {err:?}",))
+    }
+
     /// Returns an array of [ReputationEntry](ReputationEntry) for entities.
     ///
     /// # Returns
@@ -202,6 +318,36 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
             .await
     }
 
+    /// Validates a single [UserOperation](UserOperation) received from the p2p network
+    ///
+    /// The block the peer verified the [UserOperation](UserOperation) at has to be known and at
+    /// most `MAX_VERIFIED_BLOCK_AGE` blocks old. If the code of the entities didn't change since
+    /// that block, the validation skips the simulation trace (it's done again when bundling).
+    ///
+    /// # Arguments
+    /// `uo` - The [UserOperation](UserOperation) to validate
+    /// `verified_at_block_hash` - The hash of the block the peer verified the
+    /// [UserOperation](UserOperation) at
+    ///
+    /// # Returns
+    /// `Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError>` - The validation
+    /// outcome
+    pub async fn validate_p2p_user_operation(
+        &self,
+        uo: &UserOperation,
+        verified_at_block_hash: H256,
+    ) -> Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError> {
+        let eth_client = self.entry_point.eth_client();
+        let mode = p2p_validation_mode(eth_client.as_ref(), uo, verified_at_block_hash).await?;
+        if !mode.contains(UserOperationValidatorMode::SimulationTrace) {
+            debug!(
+                "{:?} verified at recent block with unchanged entities code, skipping trace",
+                uo.hash
+            );
+        }
+        self.validator.validate_user_operation(uo, &self.mempool, &self.reputation, mode).await
+    }
+
     /// Adds a single validated user operation into the pool
     /// Indirectly invoked by RPC API via gRPC sevice to add a [UserOperation](UserOperation) into
     /// the mempool The function first validates the [UserOperation](UserOperation) by calling
@@ -229,7 +375,7 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                     ReputationError::BannedEntity { address, entity: _ },
                 )) = err
                 {
-                    self.remove_user_operation_by_entity(&address);
+                    self.remove_user_operation_by_entity(&address, err.clone());
                 }
                 return Err(MempoolError { hash: uo.hash, kind: err.into() });
             }
@@ -237,6 +383,7 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
 
         if let Some(uo_hash) = res.prev_hash {
             self.remove_user_operation(&uo_hash);
+            self.record_dropped(uo_hash, DropReason::Replaced { by: uo.hash });
         }
         if let Some(ref sd) = self.p2p_channel {
             sd.unbounded_send((uo.clone(), res.verified_block))
@@ -253,6 +400,14 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                 }
                 info!("{uo_hash:?} added to the mempool {:?}", self.id);
                 trace!("{uo:?} added to the mempool {:?}", self.id);
+                self.emit_event(
+                    UserOperationEvent::new(
+                        uo_hash,
+                        self.entry_point.address(),
+                        UserOperationStatus::Pending,
+                    )
+                    .with_user_operation(uo.user_operation.clone()),
+                );
 
                 // update reputation
                 self.reputation
@@ -268,6 +423,11 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                         .increment_seen(&p_addr)
                         .map_err(|e| MempoolError { hash: uo_hash, kind: e.into() })?;
                 }
+                if let Some(aggregator) = res.aggregator {
+                    self.reputation
+                        .increment_seen(&aggregator.address)
+                        .map_err(|e| MempoolError { hash: uo_hash, kind: e.into() })?;
+                }
 
                 Ok(uo_hash)
             }
@@ -287,32 +447,48 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
     }
 
     /// Bundles an array of [UserOperations](UserOperation)
-    /// The function first checks the reputations of the entities, then validate each
-    /// [UserOperation](UserOperation) by calling
+    /// The [UserOperations](UserOperation) are first ordered by the
+    /// [BundleBuilder](BundleBuilder). The function then checks the reputations of the entities and
+    /// validates each [UserOperation](UserOperation) by calling
     /// [UoPool::validate_user_operation](UoPool::validate_user_operation).
-    /// If the [UserOperations](UserOperation) passes the validation, push it into the `uos_valid`
-    /// array.
+    /// If the [UserOperations](UserOperation) passes the validation, it becomes a bundle candidate.
+    /// The [BundleBuilder](BundleBuilder) packs the candidates into the bundle. Only the packed
+    /// candidates are committed against the paymaster deposits and the limits of the throttled
+    /// entities, and the committed [UserOperations](UserOperation) are grouped per signature
+    /// aggregator.
     ///
     /// # Arguments
     /// `uos` - An array of [UserOperations](UserOperation) to bundle
     ///
     /// # Returns
-    /// `Result<(Vec<UserOperation>, StorageMap), eyre::Error>` - The bundled
-    /// [UserOperations](UserOperation).
+    /// `Result<(Vec<UserOperationsPerAggregator>, StorageMap), eyre::Error>` - The bundled
+    /// [UserOperations](UserOperation) grouped per aggregator.
     pub async fn bundle_user_operations(
         &mut self,
         uos: Vec<UserOperation>,
-    ) -> eyre::Result<(Vec<UserOperation>, StorageMap)> {
-        let mut uos_valid = vec![];
+    ) -> eyre::Result<(Vec<UserOperationsPerAggregator>, StorageMap)> {
+        let mut candidates = vec![];
         let mut senders = HashSet::new();
         let mut gas_total = U256::zero();
         let mut paymaster_dep = HashMap::new();
-        let mut staked_entity_c = HashMap::new();
-        let mut storage_maps: Vec<StorageMap> = Vec::new();
 
         let senders_all = uos.iter().map(|uo| uo.sender).collect::<HashSet<_>>();
 
+        let block = self
+            .entry_point
+            .eth_client()
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or(format_err!("No block found"))?;
+        let block_number = block.number.unwrap_or_default().as_u64();
+        let base_fee = block.base_fee_per_gas.ok_or(format_err!("No base fee found"))?;
+        let uos = self.bundle_builder.sort(uos, &self.mempool, base_fee);
+
         'uos: for uo in uos {
+            if self.bundle_builder.is_full(candidates.len(), gas_total, self.max_verification_gas) {
+                break;
+            }
+
             if senders.contains(&uo.sender) {
                 continue;
             }
@@ -329,27 +505,38 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                 |err| format_err!("Error getting reputation status with error: {err:?}"),
             )?);
 
-            let p_c = p_opt.map(|p| staked_entity_c.get(&p).cloned().unwrap_or(0)).unwrap_or(0);
-            let f_c = f_opt.map(|f| staked_entity_c.get(&f).cloned().unwrap_or(0)).unwrap_or(0);
+            // the throttled entities are limited when the candidates are committed into the bundle
+            let mut throttled = vec![];
+            if let (Some(p), Status::THROTTLED) = (p_opt, &p_st) {
+                throttled.push(p);
+            }
+            if let (Some(f), Status::THROTTLED) = (f_opt, &f_st) {
+                throttled.push(f);
+            }
 
-            match (p_st, f_st) {
-                (Status::BANNED, _) | (_, Status::BANNED) => {
-                    self.mempool.remove(&uo.hash).map_err(|err| {
-                        format_err!(
-                            "Removing a banned user operation {:?} failed with error: {err:?}",
-                            uo.hash,
-                        )
-                    })?;
-                    continue;
-                }
-                (Status::THROTTLED, _) if p_c > THROTTLED_ENTITY_BUNDLE_COUNT => {
-                    continue;
-                }
-                (_, Status::THROTTLED) if f_c > THROTTLED_ENTITY_BUNDLE_COUNT => {
-                    continue;
-                }
-                _ => (),
-            };
+            let p_banned = p_st == Status::BANNED;
+            if p_banned || f_st == Status::BANNED {
+                self.mempool.remove(&uo.hash).map_err(|err| {
+                    format_err!(
+                        "Removing a banned user operation {:?} failed with error: {err:?}",
+                        uo.hash,
+                    )
+                })?;
+                let (entity, address) = if p_banned {
+                    (PAYMASTER, p_opt.unwrap_or_default())
+                } else {
+                    (FACTORY, f_opt.unwrap_or_default())
+                };
+                self.record_dropped(
+                    uo.hash,
+                    InvalidMempoolUserOperationError::Reputation(ReputationError::BannedEntity {
+                        entity: entity.into(),
+                        address,
+                    })
+                    .into(),
+                );
+                continue;
+            }
 
             let val_out = self
                 .validator
@@ -369,6 +556,37 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                         continue;
                     }
 
+                    let a_opt = val_out.aggregator.map(|a| a.address);
+                    if let Some(a) = a_opt {
+                        let a_st = Status::from(self.reputation.get_status(&a).map_err(|err| {
+                            format_err!("Error getting reputation status with error: {err:?}")
+                        })?);
+
+                        match a_st {
+                            Status::BANNED => {
+                                self.mempool.remove(&uo.hash).map_err(|err| {
+                                    format_err!(
+                                        "Removing a banned user operation {:?} failed with error: {err:?}",
+                                        uo.hash,
+                                    )
+                                })?;
+                                self.record_dropped(
+                                    uo.hash,
+                                    InvalidMempoolUserOperationError::Reputation(
+                                        ReputationError::BannedEntity {
+                                            entity: AGGREGATOR.into(),
+                                            address: a,
+                                        },
+                                    )
+                                    .into(),
+                                );
+                                continue;
+                            }
+                            Status::THROTTLED => throttled.push(a),
+                            _ => (),
+                        }
+                    }
+
                     for addr in val_out.storage_map.root_hashes.keys() {
                         if *addr != uo.sender && senders_all.contains(addr) {
                             continue 'uos;
@@ -380,18 +598,17 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                         }
                     }
 
-                    storage_maps.push(val_out.storage_map);
+                    let gas_limit =
+                        val_out.verification_gas_limit.saturating_add(uo.call_gas_limit);
+                    let gas_cost = self.bundle_gas(&uo, gas_limit, block_number, base_fee).await;
 
-                    // TODO
-                    // it would be better to use estimate_gas instead of call_gas_limit
-                    // The result of call_gas_limit is usesally higher and less user op would be
-                    // included
-                    let gas_cost = val_out.verification_gas_limit.saturating_add(uo.call_gas_limit);
-                    let gas_total_new = gas_total.saturating_add(gas_cost);
-                    if gas_total_new.gt(&self.max_verification_gas) {
-                        break;
-                    }
+                    // the prefund includes the paymaster gas limits packed into
+                    // `paymasterAndData` (entry point v0.7)
+                    let pre_fund =
+                        val_out.pre_fund.max(uo.required_prefund(self.entry_point.version()));
 
+                    // the deposit is only deducted once the candidate is committed into the
+                    // bundle, here the user operation has to be covered by the whole deposit
                     if let Some(p) = p_opt {
                         let balance = match paymaster_dep.get(&p) {
                             Some(n) => *n,
@@ -401,36 +618,133 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                                 )
                             })?,
                         };
+                        paymaster_dep.insert(p, balance);
 
-                        if balance.lt(&val_out.pre_fund) {
+                        if balance.lt(&pre_fund) {
                             continue;
                         }
-
-                        staked_entity_c.entry(p).and_modify(|c| *c += 1).or_insert(1);
-                        paymaster_dep.insert(p, balance.saturating_sub(val_out.pre_fund));
                     }
 
-                    if let Some(f) = f_opt {
-                        staked_entity_c.entry(f).and_modify(|c| *c += 1).or_insert(1);
-                    }
-
-                    gas_total = gas_total_new;
+                    candidates.push(BundleCandidate {
+                        uo: uo.clone(),
+                        gas: gas_cost,
+                        gas_limit,
+                        aggregator: a_opt.unwrap_or_default(),
+                        paymaster: p_opt,
+                        pre_fund,
+                        entities: [p_opt, f_opt, a_opt].into_iter().flatten().collect(),
+                        throttled,
+                        sig_for_user_op: val_out.sig_for_user_op,
+                        storage_map: val_out.storage_map,
+                    });
+                    gas_total = gas_total.saturating_add(gas_cost);
                 }
-                Err(_) => {
+                Err(err) => {
                     self.mempool.remove(&uo.hash).map_err(|err| {
                         format_err!(
                             "Removing a user operation {:?} with 2nd failed simulation failed with error: {err:?}", uo.hash,
                         )
                     })?;
+                    self.record_dropped(uo.hash, err.into());
                     continue;
                 }
             }
 
-            uos_valid.push(uo.clone());
             senders.insert(uo.sender);
         }
 
-        Ok((uos_valid, merge_storage_maps(storage_maps)))
+        let candidates = commit_candidates(
+            self.bundle_builder.pack(candidates, self.max_verification_gas, base_fee),
+            paymaster_dep,
+        );
+        debug!(
+            "Bundle gas budget: {:?} (gas limits: {:?}), max verification gas: {:?}",
+            candidates.iter().fold(U256::zero(), |acc, c| acc.saturating_add(c.gas)),
+            candidates.iter().fold(U256::zero(), |acc, c| acc.saturating_add(c.gas_limit)),
+            self.max_verification_gas
+        );
+        let storage_map =
+            merge_storage_maps(candidates.iter().map(|c| c.storage_map.clone()).collect());
+        let uos_per_aggregator = aggregate_user_operations(
+            self.entry_point.eth_client(),
+            self.entry_point.version(),
+            candidates,
+        )
+        .await;
+
+        Ok((uos_per_aggregator, storage_map))
+    }
+
+    /// Gas the [UserOperation](UserOperation) takes from the bundle gas budget
+    /// The gas the user operation actually used in `simulateHandleOp` (traced with the executor
+    /// tracer in the standard mode, derived from the paid fee in the unsafe mode), without the
+    /// `pre_verification_gas`, is increased by the safety margin. The gas used is cached for the
+    /// block. If the simulation fails, the gas limits of the user operation are used.
+    ///
+    /// # Arguments
+    /// `uo` - The [UserOperation](UserOperation) that passed the second validation
+    /// `gas_limit` - The verification and call gas limits of the user operation
+    /// `block_number` - The number of the latest block
+    /// `base_fee` - The base fee of the latest block
+    ///
+    /// # Returns
+    /// `U256` - The gas of the user operation
+    async fn bundle_gas(
+        &self,
+        uo: &UserOperation,
+        gas_limit: U256,
+        block_number: u64,
+        base_fee: U256,
+    ) -> U256 {
+        let cached = self.estimations.read().get_gas_used(block_number, &uo.hash);
+        let gas_used = match cached {
+            Some(gas_used) => Ok(gas_used),
+            None => {
+                let gas_used = match self.mode {
+                    UoPoolMode::Standard => {
+                        trace_user_op_gas_used(&uo.user_operation, &self.entry_point).await
+                    }
+                    UoPoolMode::Unsafe => self
+                        .entry_point
+                        .simulate_handle_op(uo.user_operation.clone(), None)
+                        .await
+                        .and_then(|ret| {
+                            gas_used_from_paid(ret.paid, uo, base_fee).ok_or_else(|| {
+                                EntryPointError::Other {
+                                    inner: "User operation pays no fee".to_string(),
+                                }
+                            })
+                        }),
+                };
+                if let Ok(gas_used) = gas_used {
+                    self.estimations.write().insert_gas_used(block_number, uo.hash, gas_used);
+                }
+                gas_used
+            }
+        };
+
+        match gas_used {
+            Ok(gas_used) => {
+                let gas = bundle_gas_with_margin(
+                    gas_used,
+                    uo.pre_verification_gas,
+                    self.execution_gas_margin_perc,
+                    gas_limit,
+                );
+                debug!(
+                    "Bundle gas of userop {:?}: {gas:?} (gas limits: {gas_limit:?}, gas used: {gas_used:?})",
+                    uo.hash
+                );
+                gas
+            }
+            Err(err) => {
+                debug!(
+                    "Simulating userop {:?} for the bundle gas failed with error: {err:?}, using gas limits: {gas_limit:?}",
+                    uo.hash
+                );
+                gas_limit
+            }
+        }
     }
 
     /// Gets the block base fee per gas
@@ -447,12 +761,27 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         block.base_fee_per_gas.ok_or(format_err!("No base fee found"))
     }
 
+    /// Recommends the fees of user operations (slow, standard and fast tiers) from the current
+    /// base fee, the minimum priority fee the bundler accepts and the tips of the user operations
+    /// in the mempool
+    ///
+    /// # Returns
+    /// `Result<UserOperationGasPrice, eyre::Error>` - The fee recommendation
+    pub async fn get_user_operation_gas_price(&self) -> eyre::Result<UserOperationGasPrice> {
+        let base_fee = self.base_fee_per_gas().await?;
+        let tips = self.get_all()?.iter().map(|uo| effective_tip(uo, base_fee)).collect();
+        Ok(recommend_gas_price(base_fee, self.min_priority_fee_per_gas, tips))
+    }
+
     /// Estimates the `verification_gas_limit`, `call_gas_limit` and `pre_verification_gas` for a
-    /// user operation. The function is indirectly invoked by the `estimate_user_operation_gas`
-    /// JSON RPC method.
+    /// user operation (and the paymaster gas limits for a user operation with a paymaster sent to
+    /// the entry point v0.7). The function is indirectly invoked by the
+    /// `estimate_user_operation_gas` JSON RPC method.
     ///
     /// # Arguments
     /// * `uo` - The [UserOperation](UserOperation) to estimate the gas for.
+    /// * `state_override` - Optional geth-style state override (balance, code, storage per address)
+    ///   applied to the simulations, e.g., to estimate for an unfunded account.
     ///
     /// # Returns
     /// `Result<UserOperationGasEstimation, MempoolError>` - The gas estimation result,
@@ -460,72 +789,131 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
     pub async fn estimate_user_operation_gas(
         &self,
         uo: &UserOperation,
+        state_override: Option<spoof::State>,
     ) -> Result<UserOperationGasEstimation, MempoolError> {
+        let pre_verification_gas = Overhead::default()
+            .calculate_pre_verification_gas_with_l1_fee(
+                uo,
+                &self.entry_point,
+                ChainSpec::from_chain_id(self.chain.id()).l1_gas_oracle,
+            )
+            .await
+            .map_err(|e| match e {
+                EntryPointError::Provider { inner } => {
+                    MempoolError { hash: uo.hash, kind: MempoolErrorKind::Provider { inner } }
+                }
+                _ => MempoolError {
+                    hash: uo.hash,
+                    kind: MempoolErrorKind::Other { inner: format!("{e:?}") },
+                },
+            })?;
         let pre_verification_gas = div_ceil(
-            Overhead::default().calculate_pre_verification_gas(uo).saturating_mul(
+            pre_verification_gas.saturating_mul(
                 U256::from(100).saturating_add(PRE_VERIFICATION_SAFE_RESERVE_PERC.into()),
             ),
             U256::from(100),
         );
 
+        let block = self
+            .entry_point
+            .eth_client()
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|err| MempoolError {
+                hash: uo.hash,
+                kind: MempoolErrorKind::Provider { inner: err.to_string() },
+            })?
+            .ok_or(MempoolError {
+                hash: uo.hash,
+                kind: MempoolErrorKind::Other { inner: "No block found".into() },
+            })?;
+        let block_number = block.number.unwrap_or_default().as_u64();
+
+        // the estimations with state overrides are not cached (the state differs)
+        let key = GasEstimationCache::key(uo);
+        let cached = match state_override {
+            Some(_) => None,
+            None => self.estimations.read().get(block_number, &key),
+        };
+        // the paymaster verification gas limit (entry point v0.7) is estimated together with the
+        // verification gas limit
+        let post_op_gas_limit =
+            paymaster_post_op_gas_limit(&uo.user_operation, self.entry_point.version());
+        if let Some((verification_gas_limit, call_gas_limit)) = cached {
+            return Ok(UserOperationGasEstimation {
+                pre_verification_gas,
+                verification_gas_limit,
+                call_gas_limit,
+                paymaster_verification_gas_limit: post_op_gas_limit.map(|_| verification_gas_limit),
+                paymaster_post_op_gas_limit: post_op_gas_limit,
+            });
+        }
+
         let (verification_gas_limit, call_gas_limit) = match self.mode {
-            UoPoolMode::Standard => estimate_user_op_gas(&uo.user_operation, &self.entry_point)
-                .await
-                .map_err(|e| match e {
-                    EntryPointError::FailedOp(f) => MempoolError {
-                        hash: uo.hash,
-                        kind: MempoolErrorKind::InvalidUserOperation(
-                            InvalidMempoolUserOperationError::Simulation(
-                                SimulationError::Validation { inner: format!("{f:?}") },
-                            ),
-                        ),
-                    },
-                    EntryPointError::ExecutionReverted(e) => MempoolError {
-                        hash: uo.hash,
-                        kind: MempoolErrorKind::InvalidUserOperation(
-                            InvalidMempoolUserOperationError::Simulation(
-                                SimulationError::Execution { inner: e },
-                            ),
-                        ),
-                    },
-                    EntryPointError::Provider { inner } => {
-                        MempoolError { hash: uo.hash, kind: MempoolErrorKind::Provider { inner } }
-                    }
-                    _ => MempoolError {
-                        hash: uo.hash,
-                        kind: MempoolErrorKind::Other { inner: format!("{e:?}") },
-                    },
-                })?,
+            UoPoolMode::Standard => estimate_user_op_gas(
+                &uo.user_operation,
+                &self.entry_point,
+                self.max_verification_gas.min(u64::MAX.into()).as_u64(),
+                block.gas_limit.low_u64(),
+                state_override.as_ref(),
+            )
+            .await
+            .map_err(|e| match e {
+                EntryPointError::FailedOp(f) => MempoolError {
+                    hash: uo.hash,
+                    kind: MempoolErrorKind::InvalidUserOperation(
+                        InvalidMempoolUserOperationError::Simulation(SimulationError::Validation {
+                            inner: format!("{f:?}"),
+                        }),
+                    ),
+                },
+                EntryPointError::ExecutionReverted(e) => MempoolError {
+                    hash: uo.hash,
+                    kind: MempoolErrorKind::InvalidUserOperation(
+                        InvalidMempoolUserOperationError::Simulation(SimulationError::Execution {
+                            inner: e,
+                        }),
+                    ),
+                },
+                EntryPointError::Provider { inner } => {
+                    MempoolError { hash: uo.hash, kind: MempoolErrorKind::Provider { inner } }
+                }
+                _ => MempoolError {
+                    hash: uo.hash,
+                    kind: MempoolErrorKind::Other { inner: format!("{e:?}") },
+                },
+            })?,
             UoPoolMode::Unsafe => {
-                let ret =
-                    self.entry_point.simulate_handle_op(uo.clone().user_operation).await.map_err(
-                        |e| match e {
-                            EntryPointError::FailedOp(f) => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::InvalidUserOperation(
-                                    InvalidMempoolUserOperationError::Simulation(
-                                        SimulationError::Validation { inner: format!("{f:?}") },
-                                    ),
+                let ret = self
+                    .entry_point
+                    .simulate_handle_op(uo.clone().user_operation, state_override.as_ref())
+                    .await
+                    .map_err(|e| match e {
+                        EntryPointError::FailedOp(f) => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::InvalidUserOperation(
+                                InvalidMempoolUserOperationError::Simulation(
+                                    SimulationError::Validation { inner: format!("{f:?}") },
                                 ),
-                            },
-                            EntryPointError::ExecutionReverted(e) => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::InvalidUserOperation(
-                                    InvalidMempoolUserOperationError::Simulation(
-                                        SimulationError::Execution { inner: e },
-                                    ),
+                            ),
+                        },
+                        EntryPointError::ExecutionReverted(e) => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::InvalidUserOperation(
+                                InvalidMempoolUserOperationError::Simulation(
+                                    SimulationError::Execution { inner: e },
                                 ),
-                            },
-                            EntryPointError::Provider { inner } => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::Provider { inner },
-                            },
-                            _ => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::Other { inner: format!("{e:?}") },
-                            },
+                            ),
+                        },
+                        EntryPointError::Provider { inner } => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Provider { inner },
                         },
-                    )?;
+                        _ => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Other { inner: format!("{e:?}") },
+                        },
+                    })?;
 
                 let verification_gas_limit = div_ceil(
                     ret.pre_op_gas.saturating_sub(pre_verification_gas).saturating_mul(3.into()),
@@ -539,10 +927,20 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
             }
         };
 
+        if state_override.is_none() {
+            self.estimations.write().insert(
+                block_number,
+                key,
+                (verification_gas_limit, call_gas_limit),
+            );
+        }
+
         Ok(UserOperationGasEstimation {
             pre_verification_gas,
             verification_gas_limit,
             call_gas_limit,
+            paymaster_verification_gas_limit: post_op_gas_limit.map(|_| verification_gas_limit),
+            paymaster_post_op_gas_limit: post_op_gas_limit,
         })
     }
 
@@ -597,7 +995,7 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                 .get_transaction(log_meta.transaction_hash)
                 .await?
                 .and_then(|tx| {
-                    let uos = parse_from_input_data(tx.input)?;
+                    let uos = self.entry_point.decode_handle_ops(tx.input)?;
                     let ep = tx.to?;
                     uos.iter()
                         .find(|uo| uo.sender == event.sender && uo.nonce == event.nonce)
@@ -658,6 +1056,110 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         Err(format_err!("No user operation found"))
     }
 
+    /// Gets the status of the [UserOperation](UserOperation) by hash.
+    /// The function is indirectly invoked by the `silius_getUserOperationStatus` JSON RPC method.
+    ///
+    /// The bundle history is checked first (the user operations stay in the mempool while their
+    /// bundle is pending), then the mempool and the record of the dropped user operations. The
+    /// `UserOperationEvent` logs of the entry point are not queried (see
+    /// [get_included_user_operation_status](Self::get_included_user_operation_status)).
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to get the status for.
+    ///
+    /// # Returns
+    /// `Result<Option<UserOperationStatusResponse>, eyre::Error>` - The status of the user
+    /// operation, `None` if the user operation is unknown to the mempool.
+    pub fn get_user_operation_status(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> eyre::Result<Option<UserOperationStatusResponse>> {
+        // the user operations of the released bundles went back to the mempool
+        if let Some(record) = self
+            .mempool
+            .get_bundle_by_user_operation(uo_hash)?
+            .filter(|r| !r.is_released() && r.entry_point == self.entry_point.address())
+        {
+            let block_number = (record.block_number != 0).then(|| record.block_number.into());
+            let uo_status = match BundleStatus::from(record.status) {
+                BundleStatus::Included => UserOperationStatus::Included,
+                BundleStatus::Reverted => UserOperationStatus::Reverted,
+                _ => UserOperationStatus::Bundled,
+            };
+            return Ok(Some(self.status_response(
+                uo_hash,
+                uo_status,
+                Some(record.transaction_hash),
+                block_number,
+                None,
+            )));
+        }
+
+        if self.mempool.get(uo_hash)?.is_some() {
+            return Ok(Some(self.status_response(
+                uo_hash,
+                UserOperationStatus::Pending,
+                None,
+                None,
+                None,
+            )));
+        }
+
+        if let Some(dropped) = self.mempool.get_dropped(uo_hash) {
+            return Ok(Some(self.status_response(
+                uo_hash,
+                UserOperationStatus::Dropped,
+                None,
+                None,
+                Some(dropped.reason),
+            )));
+        }
+
+        Ok(None)
+    }
+
+    /// Gets the status of the [UserOperation](UserOperation) included by another bundler
+    /// from the `UserOperationEvent` logs of the entry point.
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to get the status for.
+    ///
+    /// # Returns
+    /// `Result<Option<UserOperationStatusResponse>, eyre::Error>` - The status of the user
+    /// operation, `None` if there is no `UserOperationEvent` of the user operation.
+    pub async fn get_included_user_operation_status(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> eyre::Result<Option<UserOperationStatusResponse>> {
+        Ok(self.get_user_operation_event_meta(uo_hash).await?.map(|(_, log_meta)| {
+            self.status_response(
+                uo_hash,
+                UserOperationStatus::Included,
+                Some(log_meta.transaction_hash),
+                Some(log_meta.block_number),
+                None,
+            )
+        }))
+    }
+
+    fn status_response(
+        &self,
+        uo_hash: &UserOperationHash,
+        status: UserOperationStatus,
+        transaction_hash: Option<H256>,
+        block_number: Option<U64>,
+        reason: Option<DropReason>,
+    ) -> UserOperationStatusResponse {
+        UserOperationStatusResponse {
+            user_operation_hash: *uo_hash,
+            entry_point: self.entry_point.address(),
+            status,
+            transaction_hash,
+            block_number,
+            reason,
+        }
+    }
+
     /// Removes the [UserOperation](UserOperation) from the user operation mempool
     /// given the [UserOperationHash](UserOperationHash).
     ///
@@ -671,8 +1173,22 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         None
     }
 
-    pub fn remove_user_operation_by_entity(&mut self, entity: &Address) -> Option<()> {
-        self.mempool.remove_by_entity(entity).ok();
+    /// Removes the [UserOperations](UserOperation) that use the entity (e.g., it got banned)
+    ///
+    /// # Arguments
+    /// * `entity` - The address of the entity
+    /// * `reason` - Why the user operations of the entity are removed
+    ///
+    /// # Returns
+    /// `Option<()>` - None
+    pub fn remove_user_operation_by_entity(
+        &mut self,
+        entity: &Address,
+        reason: InvalidMempoolUserOperationError,
+    ) -> Option<()> {
+        for uo_hash in self.mempool.remove_by_entity(entity).unwrap_or_default() {
+            self.record_dropped(uo_hash, reason.clone().into());
+        }
         None
     }
 
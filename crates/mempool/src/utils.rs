@@ -1,5 +1,15 @@
-use ethers::types::{Address, H256, U256};
-use silius_primitives::{simulation::CodeHash, UserOperationSigned};
+use crate::{validate::UserOperationValidatorMode, SanityError};
+use enumset::EnumSet;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, H256, U256},
+    utils::keccak256,
+};
+use silius_contracts::{EntryPoint, EntryPointError};
+use silius_primitives::{
+    chain::L1GasOracle, constants::mempool::MAX_VERIFIED_BLOCK_AGE, get_address,
+    simulation::CodeHash, UserOperationSigned,
+};
 use std::{collections::HashMap, ops::Deref};
 
 pub fn equal_code_hashes(hashes: &[CodeHash], hashes_prev: &Vec<CodeHash>) -> bool {
@@ -23,6 +33,101 @@ pub fn equal_code_hashes(hashes: &[CodeHash], hashes_prev: &Vec<CodeHash>) -> bo
     true
 }
 
+/// Checks whether the code of the entities (sender, factory and paymaster) of a user operation is
+/// the same as at the block the user operation was verified at
+///
+/// # Arguments
+/// `eth_client` - Connection to the Ethereum execution client
+/// `uo` - The user operation to check the entities of
+/// `verified_at_block_hash` - The hash of the block the user operation was verified at
+///
+/// # Returns
+/// `true` if the code of all entities is unchanged
+pub async fn entities_code_unchanged<M: Middleware>(
+    eth_client: &M,
+    uo: &UserOperationSigned,
+    verified_at_block_hash: H256,
+) -> Result<bool, M::Error> {
+    let addrs = [Some(uo.sender), get_address(&uo.init_code), get_address(&uo.paymaster_and_data)];
+
+    let mut hashes_prev = vec![];
+    let mut hashes = vec![];
+    for addr in addrs.into_iter().flatten() {
+        let code_prev = eth_client.get_code(addr, Some(verified_at_block_hash.into())).await?;
+        hashes_prev.push(CodeHash { address: addr, hash: keccak256(&code_prev).into() });
+        let code = eth_client.get_code(addr, None).await?;
+        hashes.push(CodeHash { address: addr, hash: keccak256(&code).into() });
+    }
+    Ok(equal_code_hashes(&hashes, &hashes_prev))
+}
+
+/// Determines the validation mode of a user operation received from the p2p network
+///
+/// The block the user operation was verified at has to be recent (see [check_verified_block]).
+/// If the code of the entities didn't change since that block, the simulation trace is skipped
+/// (it's done again when bundling).
+///
+/// # Arguments
+/// `eth_client` - Connection to the Ethereum execution client
+/// `uo` - The user operation received from the p2p network
+/// `verified_at_block_hash` - The hash of the block the peer verified the user operation at
+///
+/// # Returns
+/// The [UserOperationValidatorMode](UserOperationValidatorMode)s to validate the user operation
+/// with, otherwise a [SanityError](SanityError)
+pub async fn p2p_validation_mode<M: Middleware>(
+    eth_client: &M,
+    uo: &UserOperationSigned,
+    verified_at_block_hash: H256,
+) -> Result<EnumSet<UserOperationValidatorMode>, SanityError> {
+    check_verified_block(eth_client, verified_at_block_hash).await?;
+
+    let unchanged = entities_code_unchanged(eth_client, uo, verified_at_block_hash)
+        .await
+        .map_err(|e| SanityError::Provider { inner: e.to_string() })?;
+
+    Ok(if unchanged {
+        UserOperationValidatorMode::Sanity | UserOperationValidatorMode::Simulation
+    } else {
+        UserOperationValidatorMode::Sanity |
+            UserOperationValidatorMode::Simulation |
+            UserOperationValidatorMode::SimulationTrace
+    })
+}
+
+/// Checks the block a user operation received from the p2p network was verified at, the block has
+/// to be known and at most [MAX_VERIFIED_BLOCK_AGE] blocks old
+///
+/// # Arguments
+/// `eth_client` - Connection to the Ethereum execution client
+/// `verified_at_block_hash` - The hash of the block the peer verified the user operation at
+///
+/// # Returns
+/// Nothing if the block is recent, otherwise a [SanityError](SanityError)
+pub async fn check_verified_block<M: Middleware>(
+    eth_client: &M,
+    verified_at_block_hash: H256,
+) -> Result<(), SanityError> {
+    let provider_err = |e: M::Error| SanityError::Provider { inner: e.to_string() };
+
+    let block = eth_client.get_block(verified_at_block_hash).await.map_err(provider_err)?;
+    let Some(block_number) = block.and_then(|b| b.number) else {
+        return Err(SanityError::VerifiedBlock {
+            inner: format!("user operation verified at unknown block {verified_at_block_hash:?}"),
+        });
+    };
+    let latest = eth_client.get_block_number().await.map_err(provider_err)?;
+    if latest.saturating_sub(block_number) > MAX_VERIFIED_BLOCK_AGE.into() {
+        return Err(SanityError::VerifiedBlock {
+            inner: format!(
+                "user operation verified at block {block_number} older than {MAX_VERIFIED_BLOCK_AGE} blocks"
+            ),
+        });
+    }
+
+    Ok(())
+}
+
 /// Struct to calculate the pre-verification gas of a user operation
 // https://github.com/eth-infinitism/bundler/blob/main/packages/sdk/src/calcPreVerificationGas.ts#L44-L51
 pub struct Overhead {
@@ -84,6 +189,70 @@ impl Overhead {
             .saturating_add(self.per_user_op)
             .saturating_add(word_cost)
     }
+
+    /// Calculates the pre-verification gas of a [UserOperation](UserOperationSigned) including
+    /// the L1 data fee charged by L2 chains (OP Stack, Arbitrum).
+    ///
+    /// # Arguments
+    /// `uo` - The [UserOperation](UserOperationSigned) to calculate the pre-verification gas for
+    /// `entry_point` - The entry point the user operation is bundled to
+    /// `oracle` - The [oracle](L1GasOracle) of the L1 data fee (from the chain spec)
+    ///
+    /// # Returns
+    /// The pre-verification gas of the [UserOperation](UserOperationSigned)
+    pub async fn calculate_pre_verification_gas_with_l1_fee<M: Middleware + 'static>(
+        &self,
+        uo: &UserOperationSigned,
+        entry_point: &EntryPoint<M>,
+        oracle: L1GasOracle,
+    ) -> Result<U256, EntryPointError> {
+        let pre_verification_gas = self.calculate_pre_verification_gas(uo);
+
+        let gas_price = match oracle {
+            L1GasOracle::None => return Ok(pre_verification_gas),
+            L1GasOracle::OpStack(_) => {
+                let block = entry_point
+                    .eth_client()
+                    .get_block(BlockNumber::Latest)
+                    .await
+                    .map_err(|err| EntryPointError::Provider { inner: err.to_string() })?
+                    .ok_or(EntryPointError::Other { inner: "No block found".into() })?;
+                l1_fee_gas_price(
+                    uo.max_fee_per_gas,
+                    uo.max_priority_fee_per_gas,
+                    block.base_fee_per_gas.unwrap_or_default(),
+                )
+            }
+            L1GasOracle::Arbitrum(_) => U256::zero(),
+        };
+
+        let l1_gas = entry_point.l1_gas(uo.clone(), oracle, gas_price).await?;
+        Ok(pre_verification_gas.saturating_add(l1_gas))
+    }
+}
+
+/// Gas price the L1 data fee is converted to L2 gas with. The user operation pays at most the
+/// `base_fee + max_priority_fee_per_gas`; when the fees are not set yet (gas estimation), the
+/// current base fee is used.
+///
+/// # Arguments
+/// `max_fee_per_gas` - The max fee per gas of the user operation
+/// `max_priority_fee_per_gas` - The max priority fee per gas of the user operation
+/// `base_fee` - The current base fee per gas
+///
+/// # Returns
+/// The gas price
+pub fn l1_fee_gas_price(
+    max_fee_per_gas: U256,
+    max_priority_fee_per_gas: U256,
+    base_fee: U256,
+) -> U256 {
+    let gas_price = base_fee.saturating_add(max_priority_fee_per_gas);
+    if max_fee_per_gas.is_zero() {
+        gas_price
+    } else {
+        max_fee_per_gas.min(gas_price)
+    }
 }
 
 /// Helper function to calculate the valid gas of a [UserOperation](UserOperation)
@@ -150,8 +319,12 @@ pub fn div_ceil(numerator: U256, denominator: U256) -> U256 {
 pub mod tests {
     use super::*;
     use crate::{mempool::Mempool, Reputation};
-    use ethers::types::{Address, Bytes, H256, U256};
+    use ethers::{
+        providers::{MockProvider, Provider},
+        types::{Address, Block, Bytes, H256, U256, U64},
+    };
     use silius_primitives::{
+        bundler::{BundleRecord, BundleStatus},
         reputation::{ReputationEntry, Status},
         UserOperation, UserOperationHash, UserOperationSigned,
     };
@@ -176,6 +349,85 @@ pub mod tests {
         assert_eq!(gas_oh.calculate_pre_verification_gas(&uo), 45340.into());
     }
 
+    /// Mocked provider that knows the block the user operation was verified at
+    fn provider_with_blocks(verified_at: u64, latest: u64) -> Provider<MockProvider> {
+        let (provider, mock) = Provider::mocked();
+        // the mocked provider returns the responses in the reverse order
+        mock.push(U64::from(latest)).unwrap();
+        mock.push(Block::<H256> { number: Some(verified_at.into()), ..Default::default() })
+            .unwrap();
+        provider
+    }
+
+    #[tokio::test]
+    async fn verified_block_has_to_be_recent() {
+        assert!(check_verified_block(&provider_with_blocks(95, 100), H256::random()).await.is_ok());
+        assert!(matches!(
+            check_verified_block(&provider_with_blocks(80, 100), H256::random()).await,
+            Err(SanityError::VerifiedBlock { .. })
+        ));
+
+        // unknown block
+        let (provider, mock) = Provider::mocked();
+        mock.push(Option::<Block<H256>>::None).unwrap();
+        assert!(matches!(
+            check_verified_block(&provider, H256::random()).await,
+            Err(SanityError::VerifiedBlock { .. })
+        ));
+    }
+
+    /// Mocked provider that knows the block the user operation was verified at and returns the
+    /// code of the sender at that block and now
+    fn provider_with_code(code_prev: Bytes, code: Bytes) -> Provider<MockProvider> {
+        let (provider, mock) = Provider::mocked();
+        // the mocked provider returns the responses in the reverse order
+        mock.push(code).unwrap();
+        mock.push(code_prev).unwrap();
+        mock.push(U64::from(100)).unwrap();
+        mock.push(Block::<H256> { number: Some(99.into()), ..Default::default() }).unwrap();
+        provider
+    }
+
+    #[tokio::test]
+    async fn p2p_validation_skips_trace_with_unchanged_code() {
+        let uo = UserOperationSigned { sender: Address::random(), ..Default::default() };
+        let code = Bytes::from(vec![0x60, 0x80]);
+
+        let mode = p2p_validation_mode(
+            &provider_with_code(code.clone(), code.clone()),
+            &uo,
+            H256::random(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            mode,
+            UserOperationValidatorMode::Sanity | UserOperationValidatorMode::Simulation
+        );
+
+        let mode = p2p_validation_mode(
+            &provider_with_code(code, Bytes::from(vec![0x60, 0x81])),
+            &uo,
+            H256::random(),
+        )
+        .await
+        .unwrap();
+        assert!(mode.contains(UserOperationValidatorMode::SimulationTrace));
+
+        // the block has to be recent before comparing the code
+        assert!(matches!(
+            p2p_validation_mode(&provider_with_blocks(80, 100), &uo, H256::random()).await,
+            Err(SanityError::VerifiedBlock { .. })
+        ));
+    }
+
+    #[test]
+    fn l1_fee_gas_price_calculation() {
+        assert_eq!(l1_fee_gas_price(100.into(), 10.into(), 50.into()), 60.into());
+        assert_eq!(l1_fee_gas_price(55.into(), 10.into(), 50.into()), 55.into());
+        assert_eq!(l1_fee_gas_price(U256::zero(), U256::zero(), 50.into()), 50.into());
+    }
+
     #[test]
     fn pre_verification_gas_calculation_with_large_user_operation() {
         let gas_oh = Overhead::default();
@@ -415,6 +667,67 @@ pub mod tests {
         assert_eq!(code_hashes, code_hashes_get);
     }
 
+    pub fn bundle_history_test_case(mut mempool: Mempool) {
+        let ep = Address::random();
+        let records = (0..3)
+            .map(|i| BundleRecord {
+                transaction_hash: H256::random(),
+                entry_point: ep,
+                user_operations: vec![H256::random(), H256::random()],
+                status: BundleStatus::Submitted.into(),
+                timestamp: i,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        for record in records.iter() {
+            mempool.add_bundle(record.clone()).unwrap();
+        }
+        mempool
+            .add_bundle(BundleRecord {
+                transaction_hash: H256::random(),
+                entry_point: Address::random(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let history = mempool.get_bundle_history(&ep).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0], records[2]);
+        assert_eq!(history[2], records[0]);
+
+        let included = BundleRecord {
+            block_number: 10,
+            gas_used: 100_000.into(),
+            status: BundleStatus::Included.into(),
+            ..records[1].clone()
+        };
+        mempool.add_bundle(included.clone()).unwrap();
+        assert_eq!(mempool.get_bundle(&records[1].transaction_hash).unwrap(), Some(included));
+        assert_eq!(mempool.get_bundle_history(&ep).unwrap().len(), 3);
+
+        // the outcome of a replaced bundle replaces the record of the submitted transaction
+        let replaced = BundleRecord {
+            transaction_hash: H256::random(),
+            block_number: 11,
+            status: BundleStatus::Included.into(),
+            ..records[2].clone()
+        };
+        mempool.add_bundle(replaced.clone()).unwrap();
+        assert_eq!(mempool.get_bundle(&records[2].transaction_hash).unwrap(), None);
+        let history = mempool.get_bundle_history(&ep).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0], replaced);
+
+        // the user operations are indexed by the latest bundle they were sent in
+        let uo_hash = records[2].user_operations[0].into();
+        assert_eq!(mempool.get_bundle_by_user_operation(&uo_hash).unwrap(), Some(replaced));
+        assert_eq!(mempool.get_bundle_by_user_operation(&H256::random().into()).unwrap(), None);
+
+        // the bundle history is kept when the mempool is cleared
+        mempool.clear();
+        assert_eq!(mempool.get_bundle_history(&ep).unwrap().len(), 3);
+    }
+
     pub fn reputation_test_case(mut reputation: Reputation) {
         let mut addrs: Vec<Address> = vec![];
 
@@ -460,6 +773,10 @@ pub mod tests {
         assert_eq!(reputation.increment_included(&addrs[2]).unwrap(), ());
         assert_eq!(reputation.increment_included(&addrs[2]).unwrap(), ());
         assert_eq!(reputation.increment_included(&addrs[3]).unwrap(), ());
+        assert_eq!(reputation.decrement_included(&addrs[2]).unwrap(), ());
+        assert_eq!(reputation.decrement_included(&addrs[4]).unwrap(), ());
+        assert_eq!(reputation.get(&addrs[2]).unwrap().uo_included, 1);
+        assert_eq!(reputation.get(&addrs[4]).unwrap().uo_included, 0);
 
         assert_eq!(reputation.update_handle_ops_reverted(&addrs[3]).unwrap(), ());
 
@@ -4,12 +4,16 @@ use crate::{
 };
 use alloy_chains::Chain;
 use enumset::{EnumSet, EnumSetType};
-use ethers::{providers::Middleware, types::U256};
+use ethers::{
+    providers::Middleware,
+    types::{Bytes, U256},
+};
 use silius_contracts::{entry_point::SimulateValidationResult, tracer::JsTracerFrame, EntryPoint};
 use silius_primitives::{
     constants::validation::entities::NUMBER_OF_LEVELS,
     reputation::StakeInfo,
     simulation::{CodeHash, StorageMap},
+    validation::ValidationRules,
     UserOperation, UserOperationHash,
 };
 
@@ -25,6 +29,10 @@ pub struct UserOperationValidationOutcome {
     pub prev_hash: Option<UserOperationHash>,
     pub pre_fund: U256,
     pub verification_gas_limit: U256,
+    // the signature aggregator (if the user operation uses one)
+    pub aggregator: Option<StakeInfo>,
+    // the signature the aggregator returned for the user operation (`sigForUserOp`)
+    pub sig_for_user_op: Option<Bytes>,
     // Simulation
     pub valid_after: Option<U256>,
     // Simulation trace
@@ -61,6 +69,7 @@ pub trait UserOperationValidator: Send + Sync {
 pub struct SanityHelper<'a, M: Middleware + 'static> {
     entry_point: &'a EntryPoint<M>,
     chain: Chain,
+    rules: &'a ValidationRules,
 }
 
 #[async_trait::async_trait]
@@ -150,6 +159,7 @@ sanity_check_impls! { A B C D F G I J K L }
 
 /// The [UserOperation] simulation check helper trait.
 pub struct SimulationHelper<'a> {
+    rules: &'a ValidationRules,
     simulate_validation_result: &'a SimulateValidationResult,
     valid_after: Option<U256>,
 }
@@ -212,6 +222,7 @@ simulation_check_impls! { A B C D F G I J K L }
 pub struct SimulationTraceHelper<'a, M: Middleware + Send + Sync + 'static> {
     entry_point: &'a EntryPoint<M>,
     chain: Chain,
+    rules: &'a ValidationRules,
     simulate_validation_result: &'a SimulateValidationResult,
     js_trace: &'a JsTracerFrame,
     stake_info: Option<[StakeInfo; NUMBER_OF_LEVELS]>,
@@ -4,7 +4,7 @@ use crate::{
     Reputation, SanityError,
 };
 use ethers::{providers::Middleware, types::U256};
-use silius_primitives::UserOperation;
+use silius_primitives::{validation::ValidationRule, UserOperation};
 
 #[derive(Clone)]
 pub struct CallGas;
@@ -24,8 +24,12 @@ impl<M: Middleware> SanityCheck<M> for CallGas {
         uo: &UserOperation,
         _mempool: &Mempool,
         _reputation: &Reputation,
-        _helper: &SanityHelper<M>,
+        helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
+        if !helper.rules.is_enabled(ValidationRule::CallGas) {
+            return Ok(());
+        }
+
         // call gas limit is at least the cost of a CALL with non-zero value
         // https://github.com/wolflo/evm-opcodes/blob/main/gas.md#aa-1-call
         // gas_cost = 100 + 9000
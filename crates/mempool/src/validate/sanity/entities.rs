@@ -11,6 +11,7 @@ use silius_primitives::{
         reputation::THROTTLED_ENTITY_MEMPOOL_COUNT,
     },
     reputation::Status,
+    validation::ValidationRule,
     UserOperation,
 };
 
@@ -29,13 +30,14 @@ impl Entities {
     }
 
     /// [SREP-020] - a BANNED address is not allowed into the mempool.
-    fn check_banned(
+    fn check_banned<M: Middleware>(
         &self,
         entity: &str,
         addr: &Address,
         status: &Status,
+        helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
-        if *status == Status::BANNED {
+        if *status == Status::BANNED && helper.rules.is_enabled(ValidationRule::Srep020) {
             return Err(
                 ReputationError::BannedEntity { entity: entity.into(), address: *addr }.into()
             );
@@ -51,11 +53,12 @@ impl Entities {
         entity: &str,
         addr: &Address,
         status: &Status,
-        _helper: &SanityHelper<M>,
+        helper: &SanityHelper<M>,
         mempool: &Mempool,
         _reputation: &Reputation,
     ) -> Result<(), SanityError> {
         if *status == Status::THROTTLED &&
+            helper.rules.is_enabled(ValidationRule::Srep030) &&
             (mempool.get_number_by_sender(addr) + mempool.get_number_by_entity(addr)) >=
                 THROTTLED_ENTITY_MEMPOOL_COUNT
         {
@@ -92,20 +95,20 @@ impl<M: Middleware> SanityCheck<M> for Entities {
 
         // sender
         let status = self.get_status(&sender, helper, reputation)?;
-        self.check_banned(SENDER, &sender, &status)?;
+        self.check_banned(SENDER, &sender, &status, helper)?;
         self.check_throttled(SENDER, &sender, &status, helper, mempool, reputation)?;
 
         // factory
         if let Some(factory) = factory {
             let status = self.get_status(&factory, helper, reputation)?;
-            self.check_banned(FACTORY, &factory, &status)?;
+            self.check_banned(FACTORY, &factory, &status, helper)?;
             self.check_throttled(FACTORY, &factory, &status, helper, mempool, reputation)?;
         }
 
         // paymaster
         if let Some(paymaster) = paymaster {
             let status = self.get_status(&paymaster, helper, reputation)?;
-            self.check_banned(PAYMASTER, &paymaster, &status)?;
+            self.check_banned(PAYMASTER, &paymaster, &status, helper)?;
             self.check_throttled(PAYMASTER, &paymaster, &status, helper, mempool, reputation)?;
         }
 
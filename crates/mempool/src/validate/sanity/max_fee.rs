@@ -7,7 +7,7 @@ use ethers::{
     providers::Middleware,
     types::{BlockNumber, U256},
 };
-use silius_primitives::UserOperation;
+use silius_primitives::{validation::ValidationRule, UserOperation};
 
 #[derive(Clone)]
 pub struct MaxFee {
@@ -31,6 +31,10 @@ impl<M: Middleware> SanityCheck<M> for MaxFee {
         _reputation: &Reputation,
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
+        if !helper.rules.is_enabled(ValidationRule::MaxFee) {
+            return Ok(());
+        }
+
         if uo.max_priority_fee_per_gas > uo.max_fee_per_gas {
             return Err(SanityError::MaxPriorityFeePerGasTooHigh {
                 max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
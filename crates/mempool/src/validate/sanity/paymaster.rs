@@ -4,7 +4,7 @@ use crate::{
     Reputation, SanityError,
 };
 use ethers::{providers::Middleware, types::U256};
-use silius_primitives::{get_address, UserOperation};
+use silius_primitives::{get_address, validation::ValidationRule, UserOperation};
 
 #[derive(Clone)]
 pub struct Paymaster;
@@ -27,6 +27,10 @@ impl<M: Middleware> SanityCheck<M> for Paymaster {
         _reputation: &Reputation,
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
+        if !helper.rules.is_enabled(ValidationRule::Paymaster) {
+            return Ok(());
+        }
+
         if !uo.paymaster_and_data.is_empty() {
             if let Some(addr) = get_address(&uo.paymaster_and_data) {
                 let code = helper
@@ -39,7 +43,10 @@ impl<M: Middleware> SanityCheck<M> for Paymaster {
                 if !code.is_empty() {
                     let deposit_info = helper.entry_point.get_deposit_info(&addr).await?;
 
-                    if U256::from(deposit_info.deposit) >= uo.max_fee_per_gas {
+                    // the prefund includes the paymaster gas limits packed into `paymasterAndData`
+                    // (entry point v0.7)
+                    let prefund = uo.required_prefund(helper.entry_point.version());
+                    if U256::from(deposit_info.deposit) >= prefund {
                         return Ok(());
                     }
                 }
@@ -5,7 +5,9 @@ use crate::{
     Reputation, SanityError,
 };
 use ethers::providers::Middleware;
-use silius_primitives::{constants::mempool::GAS_INCREASE_PERC, UserOperation};
+use silius_primitives::{
+    constants::mempool::GAS_INCREASE_PERC, validation::ValidationRule, UserOperation,
+};
 
 #[derive(Clone)]
 pub struct Sender;
@@ -30,6 +32,10 @@ impl<M: Middleware> SanityCheck<M> for Sender {
         _reputation: &Reputation,
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
+        if !helper.rules.is_enabled(ValidationRule::Sender) {
+            return Ok(());
+        }
+
         let code = helper
             .entry_point
             .eth_client()
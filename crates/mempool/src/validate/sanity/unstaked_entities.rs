@@ -16,14 +16,24 @@ use silius_primitives::{
         },
     },
     reputation::{ReputationEntry, StakeInfo},
+    validation::ValidationRule,
     UserOperation,
 };
-use std::cmp;
+use std::{cmp, collections::HashSet};
 
-#[derive(Clone)]
-pub struct UnstakedEntities;
+#[derive(Clone, Default)]
+pub struct UnstakedEntities {
+    /// Entities which are treated as staked (by an alternative mempool)
+    pub allowed_entities: HashSet<Address>,
+}
 
 impl UnstakedEntities {
+    /// Checks whether the entity is staked (or treated as staked).
+    fn is_staked(&self, entity: &str, stake: StakeInfo, reputation: &Reputation) -> bool {
+        self.allowed_entities.contains(&stake.address) ||
+            reputation.verify_stake(entity, Some(stake)).is_ok()
+    }
+
     /// Gets the deposit info for entity.
     async fn get_stake<'a, M: Middleware>(
         &self,
@@ -81,6 +91,8 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
         let (sender, factory, paymaster) = uo.get_entities();
+        let sto_040 = helper.rules.is_enabled(ValidationRule::Sto040);
+        let urep_020 = helper.rules.is_enabled(ValidationRule::Urep020);
 
         // [SREP-010] - the "canonical mempool" defines a staked entity if it has MIN_STAKE_VALUE
         // and unstake delay of MIN_UNSTAKE_DELAY
@@ -88,7 +100,7 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
         // sender
         // [STO-040] - UserOperation may not use an entity address (factory/paymaster/aggregator)
         // that is used as an "account" in another UserOperation in the mempool
-        if mempool.get_number_by_entity(&sender) > 0 {
+        if sto_040 && mempool.get_number_by_entity(&sender) > 0 {
             return Err(SanityError::EntityRoles {
                 entity: SENDER.into(),
                 address: sender,
@@ -98,9 +110,9 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
 
         // [UREP-010] - UserOperation with unstaked sender are only allowed up to
         // SAME_SENDER_MEMPOOL_COUNT times in the mempool
-        let sender_stake = self.get_stake(&sender, helper).await?;
-        if reputation.verify_stake(SENDER, Some(sender_stake)).is_err() &&
-            mempool.get_number_by_sender(&uo.sender) >= SAME_SENDER_MEMPOOL_COUNT
+        if helper.rules.is_enabled(ValidationRule::Urep010) &&
+            mempool.get_number_by_sender(&uo.sender) >= SAME_SENDER_MEMPOOL_COUNT &&
+            !self.is_staked(SENDER, self.get_stake(&sender, helper).await?, reputation)
         {
             return Err(ReputationError::UnstakedEntity {
                 entity: SENDER.into(),
@@ -114,7 +126,7 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
             // [STO-040] - UserOperation may not use an entity address
             // (factory/paymaster/aggregator) that is used as an "account" in another UserOperation
             // in the mempool
-            if mempool.get_number_by_sender(&factory) > 0 {
+            if sto_040 && mempool.get_number_by_sender(&factory) > 0 {
                 return Err(SanityError::EntityRoles {
                     entity: FACTORY.into(),
                     address: sender,
@@ -122,8 +134,9 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
                 });
             }
 
-            let factory_stake = self.get_stake(&factory, helper).await?;
-            if reputation.verify_stake(FACTORY, Some(factory_stake)).is_err() {
+            if urep_020 &&
+                !self.is_staked(FACTORY, self.get_stake(&factory, helper).await?, reputation)
+            {
                 // [UREP-020] - for other entities
                 let entity = self.get_entity(&factory, helper, reputation)?;
                 let uos_allowed = Self::calculate_allowed_user_operations(entity);
@@ -142,7 +155,7 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
             // [STO-040] - UserOperation may not use an entity address
             // (factory/paymaster/aggregator) that is used as an "account" in another UserOperation
             // in the mempool
-            if mempool.get_number_by_sender(&paymaster) > 0 {
+            if sto_040 && mempool.get_number_by_sender(&paymaster) > 0 {
                 return Err(SanityError::EntityRoles {
                     entity: PAYMASTER.into(),
                     address: sender,
@@ -150,8 +163,9 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
                 });
             }
 
-            let paymaster_stake = self.get_stake(&paymaster, helper).await?;
-            if reputation.verify_stake(PAYMASTER, Some(paymaster_stake)).is_err() {
+            if urep_020 &&
+                !self.is_staked(PAYMASTER, self.get_stake(&paymaster, helper).await?, reputation)
+            {
                 // [UREP-020] - for other entities
                 let entity = self.get_entity(&paymaster, helper, reputation)?;
                 let uos_allowed = Self::calculate_allowed_user_operations(entity);
@@ -5,7 +5,7 @@ use crate::{
     Overhead, Reputation, SanityError,
 };
 use ethers::{providers::Middleware, types::U256};
-use silius_primitives::UserOperation;
+use silius_primitives::{chain::ChainSpec, validation::ValidationRule, UserOperation};
 
 #[derive(Clone)]
 pub struct VerificationGas {
@@ -29,8 +29,12 @@ impl<M: Middleware> SanityCheck<M> for VerificationGas {
         uo: &UserOperation,
         _mempool: &Mempool,
         _reputation: &Reputation,
-        _helper: &SanityHelper<M>,
+        helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
+        if !helper.rules.is_enabled(ValidationRule::VerificationGas) {
+            return Ok(());
+        }
+
         if uo.verification_gas_limit > self.max_verification_gas {
             return Err(SanityError::VerificationGasLimitTooHigh {
                 verification_gas_limit: uo.verification_gas_limit,
@@ -39,10 +43,20 @@ impl<M: Middleware> SanityCheck<M> for VerificationGas {
         }
 
         // calculate the pvg and allow 10 % deviation
-        let pre_gas = div_ceil(
-            Overhead::default().calculate_pre_verification_gas(uo).saturating_mul(U256::from(90)),
-            U256::from(100),
-        );
+        let pre_gas = Overhead::default().calculate_pre_verification_gas(uo);
+        let pre_gas = div_ceil(pre_gas.saturating_mul(U256::from(90)), U256::from(100));
+
+        // the L1 data fee (on L2 chains) is converted to L2 gas with the max fee per gas, so the
+        // check doesn't fail when the base fee changes after the gas estimation
+        let l1_gas = helper
+            .entry_point
+            .l1_gas(
+                uo.user_operation.clone(),
+                ChainSpec::from_chain_id(helper.chain.id()).l1_gas_oracle,
+                uo.max_fee_per_gas,
+            )
+            .await?;
+        let pre_gas = pre_gas.saturating_add(l1_gas);
         if uo.pre_verification_gas < pre_gas {
             return Err(SanityError::PreVerificationGasTooLow {
                 pre_verification_gas: uo.pre_verification_gas,
@@ -3,7 +3,7 @@ use crate::{
     SimulationError,
 };
 use silius_contracts::entry_point::SimulateValidationResult;
-use silius_primitives::UserOperation;
+use silius_primitives::{validation::ValidationRule, UserOperation};
 
 #[derive(Clone)]
 pub struct Signature;
@@ -22,6 +22,10 @@ impl SimulationCheck for Signature {
         _uo: &UserOperation,
         helper: &mut SimulationHelper,
     ) -> Result<(), SimulationError> {
+        if !helper.rules.is_enabled(ValidationRule::Signature) {
+            return Ok(());
+        }
+
         let sig_check = match helper.simulate_validation_result {
             SimulateValidationResult::ValidationResult(res) => res.return_info.2,
             SimulateValidationResult::ValidationResultWithAggregation(res) => res.return_info.2,
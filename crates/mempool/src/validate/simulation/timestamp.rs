@@ -3,7 +3,9 @@ use crate::{
     SimulationError,
 };
 use ethers::types::U256;
-use silius_primitives::{simulation::EXPIRATION_TIMESTAMP_DIFF, UserOperation};
+use silius_primitives::{
+    simulation::EXPIRATION_TIMESTAMP_DIFF, validation::ValidationRule, UserOperation,
+};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone)]
@@ -23,6 +25,10 @@ impl SimulationCheck for Timestamp {
         _uo: &UserOperation,
         helper: &mut SimulationHelper,
     ) -> Result<(), SimulationError> {
+        if !helper.rules.is_enabled(ValidationRule::Timestamp) {
+            return Ok(());
+        }
+
         let (valid_after, valid_until) = extract_timestamps(helper.simulate_validation_result);
 
         let now = U256::from(
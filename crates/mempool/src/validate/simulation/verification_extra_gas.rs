@@ -3,7 +3,9 @@ use crate::{
     SimulationError,
 };
 use silius_contracts::entry_point::SimulateValidationResult;
-use silius_primitives::{constants::validation::simulation::MIN_EXTRA_GAS, UserOperation};
+use silius_primitives::{
+    constants::validation::simulation::MIN_EXTRA_GAS, validation::ValidationRule, UserOperation,
+};
 
 #[derive(Clone)]
 pub struct VerificationExtraGas;
@@ -22,6 +24,10 @@ impl SimulationCheck for VerificationExtraGas {
         uo: &UserOperation,
         helper: &mut SimulationHelper,
     ) -> Result<(), SimulationError> {
+        if !helper.rules.is_enabled(ValidationRule::VerificationExtraGas) {
+            return Ok(());
+        }
+
         let pre_op_gas = match helper.simulate_validation_result {
             SimulateValidationResult::ValidationResult(res) => res.return_info.0,
             SimulateValidationResult::ValidationResultWithAggregation(res) => res.return_info.0,
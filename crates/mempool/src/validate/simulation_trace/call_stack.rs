@@ -13,6 +13,7 @@ use silius_primitives::{
     simulation::{
         CREATE_OPCODE, RETURN_OPCODE, REVERT_OPCODE, VALIDATE_PAYMASTER_USER_OP_FUNCTION,
     },
+    validation::ValidationRule,
     UserOperation,
 };
 
@@ -119,7 +120,8 @@ impl<M: Middleware> SimulationTraceCheck<M> for CallStack {
             // [OP-052] - may call depositTo(sender) with any value from either the sender or
             // factory [OP-053] - may call the fallback function from the sender with
             // any value
-            if call.to.unwrap_or_default() == helper.entry_point.address() &&
+            if helper.rules.is_enabled(ValidationRule::Op054) &&
+                call.to.unwrap_or_default() == helper.entry_point.address() &&
                 call.from.unwrap_or_default() != helper.entry_point.address() &&
                 (call.method.is_some() &&
                     call.method.clone().unwrap_or_default() != *"depositTo")
@@ -132,7 +134,8 @@ impl<M: Middleware> SimulationTraceCheck<M> for CallStack {
 
             // [OP-061] - CALL with value is forbidden. The only exception is a call to the
             // EntryPoint described above
-            if call.to.unwrap_or_default() != helper.entry_point.address() &&
+            if helper.rules.is_enabled(ValidationRule::Op061) &&
+                call.to.unwrap_or_default() != helper.entry_point.address() &&
                 !call.value.unwrap_or_default().is_zero()
             {
                 return Err(SimulationError::CallStack { inner: "Illegal call {call:?}".into() });
@@ -153,7 +156,8 @@ impl<M: Middleware> SimulationTraceCheck<M> for CallStack {
 
                         // [EREP-050] - an unstaked paymaster may not return a context
                         // This will be removed in the future
-                        if !context.is_empty() &&
+                        if helper.rules.is_enabled(ValidationRule::Erep050) &&
+                            !context.is_empty() &&
                             reputation.verify_stake(PAYMASTER, Some(*stake_info)).is_err()
                         {
                             return Err(SimulationError::Unstaked {
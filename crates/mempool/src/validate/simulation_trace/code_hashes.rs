@@ -9,7 +9,7 @@ use ethers::{
     types::{Address, H256},
     utils::keccak256,
 };
-use silius_primitives::{simulation::CodeHash, UserOperation};
+use silius_primitives::{simulation::CodeHash, validation::ValidationRule, UserOperation};
 use std::sync::Arc;
 use tokio::task::JoinSet;
 use tracing::debug;
@@ -101,7 +101,9 @@ impl<M: Middleware> SimulationTraceCheck<M> for CodeHashes {
                     "Veryfing {:?} code hashes in 2nd simulation: {:?} vs {:?}",
                     uo.hash, hashes, hashes_prev
                 );
-                if !equal_code_hashes(hashes, &hashes_prev) {
+                if helper.rules.is_enabled(ValidationRule::Cod010) &&
+                    !equal_code_hashes(hashes, &hashes_prev)
+                {
                     return Err(SimulationError::CodeHashes {});
                 } else {
                     helper.code_hashes = Some(hashes.to_vec());
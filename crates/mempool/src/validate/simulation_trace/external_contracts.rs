@@ -6,7 +6,8 @@ use crate::{
 use ethers::providers::Middleware;
 use silius_contracts::entry_point::SELECTORS_INDICES;
 use silius_primitives::{
-    constants::validation::entities::LEVEL_TO_ENTITY, simulation::CREATE2_OPCODE, UserOperation,
+    constants::validation::entities::LEVEL_TO_ENTITY, simulation::CREATE2_OPCODE,
+    validation::ValidationRule, UserOperation,
 };
 
 #[derive(Clone)]
@@ -21,6 +22,9 @@ impl<M: Middleware> SimulationTraceCheck<M> for ExternalContracts {
         _reputation: &Reputation,
         helper: &mut SimulationTraceHelper<M>,
     ) -> Result<(), SimulationError> {
+        let op_041 = helper.rules.is_enabled(ValidationRule::Op041);
+        let op_054 = helper.rules.is_enabled(ValidationRule::Op054);
+
         for call_info in helper.js_trace.calls_from_entry_point.iter() {
             let level = SELECTORS_INDICES.get(call_info.top_level_method_sig.as_ref()).cloned();
 
@@ -28,7 +32,7 @@ impl<M: Middleware> SimulationTraceCheck<M> for ExternalContracts {
                 // [OP-041] - access to an address without a deployed code is forbidden for EXTCODE*
                 // and *CALL opcodes
                 for (addr, size) in call_info.contract_size.iter() {
-                    if *addr != uo.sender // [OP-042] - exception: access to "sender" address is allowed
+                    if op_041 && *addr != uo.sender // [OP-042] - exception: access to "sender" address is allowed
                         && size.contract_size <= 2
                         && size.opcode != CREATE2_OPCODE.to_string()
                     {
@@ -40,7 +44,8 @@ impl<M: Middleware> SimulationTraceCheck<M> for ExternalContracts {
                 }
 
                 for (addr, info) in call_info.ext_code_access_info.iter() {
-                    if *addr == helper.entry_point.address() {
+                    // [OP-054] - access to the entry point is forbidden
+                    if op_054 && *addr == helper.entry_point.address() {
                         return Err(SimulationError::Opcode {
                             entity: LEVEL_TO_ENTITY[l].into(),
                             opcode: info.clone(),
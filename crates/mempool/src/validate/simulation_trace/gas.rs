@@ -4,7 +4,7 @@ use crate::{
     Reputation, SimulationError,
 };
 use ethers::providers::Middleware;
-use silius_primitives::UserOperation;
+use silius_primitives::{validation::ValidationRule, UserOperation};
 
 #[derive(Clone)]
 pub struct Gas;
@@ -29,6 +29,10 @@ impl<M: Middleware> SimulationTraceCheck<M> for Gas {
     ) -> Result<(), SimulationError> {
         // [OP-020] - revert on "out of gas" is forbidden as it can "leak" the gas limit or the
         // current call stack depth
+        if !helper.rules.is_enabled(ValidationRule::Op020) {
+            return Ok(());
+        }
+
         for call_info in helper.js_trace.calls_from_entry_point.iter() {
             if call_info.oog.unwrap_or(false) {
                 return Err(SimulationError::OutOfGas {});
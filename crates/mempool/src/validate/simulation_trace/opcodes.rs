@@ -7,11 +7,16 @@ use silius_contracts::entry_point::SELECTORS_INDICES;
 use silius_primitives::{
     constants::validation::entities::{FACTORY, LEVEL_TO_ENTITY},
     simulation::{CREATE2_OPCODE, FORBIDDEN_OPCODES},
+    validation::ValidationRule,
     UserOperation,
 };
+use std::collections::HashSet;
 
-#[derive(Clone)]
-pub struct Opcodes;
+#[derive(Clone, Default)]
+pub struct Opcodes {
+    /// Forbidden opcodes which are allowed anyway (by an alternative mempool)
+    pub allowed_opcodes: HashSet<String>,
+}
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for Opcodes {
@@ -30,13 +35,19 @@ impl<M: Middleware> SimulationTraceCheck<M> for Opcodes {
         _reputation: &Reputation,
         helper: &mut SimulationTraceHelper<M>,
     ) -> Result<(), SimulationError> {
+        let op_011 = helper.rules.is_enabled(ValidationRule::Op011);
+        let op_031 = helper.rules.is_enabled(ValidationRule::Op031);
+
         for call_info in helper.js_trace.calls_from_entry_point.iter() {
             let level = SELECTORS_INDICES.get(call_info.top_level_method_sig.as_ref()).cloned();
 
             if let Some(l) = level {
                 // [OP-011] - block opcodes
                 for op in call_info.opcodes.keys() {
-                    if FORBIDDEN_OPCODES.contains(op) {
+                    if op_011 &&
+                        FORBIDDEN_OPCODES.contains(op) &&
+                        !self.allowed_opcodes.contains(op)
+                    {
                         return Err(SimulationError::Opcode {
                             entity: LEVEL_TO_ENTITY[l].to_string(),
                             opcode: op.clone(),
@@ -47,7 +58,7 @@ impl<M: Middleware> SimulationTraceCheck<M> for Opcodes {
                 // [OP-031] - CREATE2 is allowed exactly once in the deployment phase and must
                 // deploy code for the "sender" address
                 if let Some(c) = call_info.opcodes.get(&*CREATE2_OPCODE) {
-                    if LEVEL_TO_ENTITY[l] == FACTORY && *c == 1 {
+                    if !op_031 || (LEVEL_TO_ENTITY[l] == FACTORY && *c == 1) {
                         continue;
                     }
                     return Err(SimulationError::Opcode {
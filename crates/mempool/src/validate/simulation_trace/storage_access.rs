@@ -12,12 +12,18 @@ use silius_contracts::entry_point::SELECTORS_INDICES;
 use silius_primitives::{
     constants::validation::entities::{FACTORY_LEVEL, LEVEL_TO_ENTITY, NUMBER_OF_LEVELS},
     reputation::StakeInfo,
+    validation::ValidationRule,
     UserOperation,
 };
 use std::collections::{HashMap, HashSet};
 
-#[derive(Clone)]
-pub struct StorageAccess;
+#[derive(Clone, Default)]
+pub struct StorageAccess {
+    /// Contracts whose storage can be accessed by any entity (by an alternative mempool)
+    pub allowed_storage: HashSet<Address>,
+    /// Entities which are treated as staked (by an alternative mempool)
+    pub allowed_entities: HashSet<Address>,
+}
 
 impl StorageAccess {
     /// The helper method that parses the slots from the JS trace.
@@ -121,6 +127,11 @@ impl<M: Middleware> SimulationTraceCheck<M> for StorageAccess {
             &mut slots,
         );
 
+        let sto_022 = helper.rules.is_enabled(ValidationRule::Sto022);
+        let sto_031 = helper.rules.is_enabled(ValidationRule::Sto031);
+        let sto_032 = helper.rules.is_enabled(ValidationRule::Sto032);
+        let sto_033 = helper.rules.is_enabled(ValidationRule::Sto033);
+
         let mut slot_staked = String::new();
         let stake_info = helper.stake_info.unwrap_or_default();
 
@@ -136,6 +147,10 @@ impl<M: Middleware> SimulationTraceCheck<M> for StorageAccess {
                         continue;
                     }
 
+                    if self.allowed_storage.contains(addr) {
+                        continue;
+                    }
+
                     slot_staked.clear();
 
                     for slot in [
@@ -149,25 +164,39 @@ impl<M: Middleware> SimulationTraceCheck<M> for StorageAccess {
                             // an external (non-entity contract) is allowed if either The account
                             // already exists or There is an initCode and the factory contract is
                             // staked
-                            if !(uo.init_code.is_empty() ||
-                                uo.sender == stake_info_l.address &&
-                                    stake_info[FACTORY_LEVEL].is_staked())
+                            if sto_022 &&
+                                !(uo.init_code.is_empty() ||
+                                    uo.sender == stake_info_l.address &&
+                                        stake_info[FACTORY_LEVEL].is_staked())
                             {
                                 slot_staked = slot.clone();
                             }
-                        } else if *addr == stake_info_l.address // [STO-031] - access the entity's own storage (if entity staked)
-                            || self.associated_with_slot(&stake_info_l.address, &slot, &slots)? // [STO-032] - read/write Access to storage slots that is associated with the entity, in any non-entity contract (if entity staked)
-                            || !acc.writes.contains_key(&slot)
-                        // [STO-033] - read-only access to any storage in non-entity contract (if
-                        // entity staked)
-                        {
-                            slot_staked = slot.clone();
-                        } else {
+                        } else if *addr == stake_info_l.address {
+                            // [STO-031] - access the entity's own storage (if entity staked)
+                            if sto_031 {
+                                slot_staked = slot.clone();
+                            }
+                        } else if self.associated_with_slot(&stake_info_l.address, &slot, &slots)? {
+                            // [STO-032] - read/write Access to storage slots that is associated
+                            // with the entity, in any non-entity contract (if entity staked)
+                            if sto_032 {
+                                slot_staked = slot.clone();
+                            }
+                        } else if !acc.writes.contains_key(&slot) {
+                            // [STO-033] - read-only access to any storage in non-entity contract
+                            // (if entity staked)
+                            if sto_033 {
+                                slot_staked = slot.clone();
+                            }
+                        } else if sto_033 {
                             return Err(SimulationError::StorageAccess { slot });
                         }
                     }
 
-                    if !slot_staked.is_empty() && !stake_info_l.is_staked() {
+                    if !slot_staked.is_empty() &&
+                        !stake_info_l.is_staked() &&
+                        !self.allowed_entities.contains(&stake_info_l.address)
+                    {
                         return Err(SimulationError::Unstaked {
                             entity: LEVEL_TO_ENTITY[l].into(),
                             address: stake_info_l.address,
@@ -58,7 +58,7 @@ pub fn extract_timestamps(sim_res: &SimulateValidationResult) -> (U256, U256) {
 /// `sim_res` - The [simulation result](SimulateValidationResult) from the simulation
 ///
 /// # Returns
-/// The stake info for the factory, account and paymaster
+/// The stake info for the factory, account, paymaster and aggregator
 pub fn extract_stake_info(
     uo: &UserOperation,
     sim_res: &SimulateValidationResult,
@@ -87,9 +87,28 @@ pub fn extract_stake_info(
             stake: p_info.0,
             unstake_delay: p_info.1,
         },
+        // aggregator
+        extract_aggregator(sim_res).unwrap_or_default(),
     ]
 }
 
+/// Helper function to extract the aggregator from the simulation result
+///
+/// # Arguments
+/// `sim_res` - The [simulation result](SimulateValidationResult) from the simulation
+///
+/// # Returns
+/// The stake info of the aggregator if the user operation uses a signature aggregator
+pub fn extract_aggregator(sim_res: &SimulateValidationResult) -> Option<StakeInfo> {
+    match sim_res {
+        SimulateValidationResult::ValidationResult(_) => None,
+        SimulateValidationResult::ValidationResultWithAggregation(res) => {
+            let (address, (stake, unstake_delay)) = res.aggregator_info;
+            Some(StakeInfo { address, stake, unstake_delay })
+        }
+    }
+}
+
 /// Helper function to extract the storage map from the simulation result
 ///
 /// # Arguments
@@ -10,26 +10,35 @@ use super::{
         call_stack::CallStack, code_hashes::CodeHashes, external_contracts::ExternalContracts,
         gas::Gas, opcodes::Opcodes, storage_access::StorageAccess,
     },
-    utils::{extract_pre_fund, extract_storage_map, extract_verification_gas_limit},
+    utils::{
+        extract_aggregator, extract_pre_fund, extract_storage_map, extract_verification_gas_limit,
+    },
     SanityCheck, SanityHelper, SimulationCheck, SimulationHelper, SimulationTraceCheck,
     SimulationTraceHelper, UserOperationValidationOutcome, UserOperationValidator,
     UserOperationValidatorMode,
 };
 use crate::{
-    mempool::Mempool, InvalidMempoolUserOperationError, Reputation, SanityError, SimulationError,
+    mempool::Mempool, InvalidMempoolUserOperationError, Reputation, ReputationError, SanityError,
+    SimulationError,
 };
 use alloy_chains::Chain;
 use enumset::EnumSet;
 use ethers::{
     providers::Middleware,
-    types::{BlockNumber, GethTrace, U256},
+    types::{BlockNumber, Bytes, GethTrace, U256},
 };
 use silius_contracts::{
     entry_point::{EntryPointError, SimulateValidationResult},
     tracer::JsTracerFrame,
-    EntryPoint,
+    Aggregator, EntryPoint,
+};
+use silius_primitives::{
+    constants::validation::entities::AGGREGATOR,
+    mempool::AltMempool,
+    reputation::{StakeInfo, Status},
+    validation::ValidationRules,
+    UserOperation,
 };
-use silius_primitives::UserOperation;
 use tracing::debug;
 
 pub type StandardValidator<M> = StandardUserOperationValidator<
@@ -63,6 +72,8 @@ where
     simulation_checks: SimCk,
     /// An array of [SimulationTraceChecks](SimulationTraceCheck).
     simulation_trace_checks: SimTrCk,
+    /// The [validation rules](ValidationRules) the checks enforce.
+    rules: ValidationRules,
 }
 
 impl<M: Middleware + Clone + 'static, SanCk, SimCk, SimTrCk> Clone
@@ -79,6 +90,7 @@ where
             sanity_checks: self.sanity_checks.clone(),
             simulation_checks: self.simulation_checks.clone(),
             simulation_trace_checks: self.simulation_trace_checks.clone(),
+            rules: self.rules.clone(),
         }
     }
 }
@@ -113,10 +125,64 @@ pub fn new_canonical<M: Middleware + 'static>(
             MaxFee { min_priority_fee_per_gas },
             Paymaster,
             Entities,
-            UnstakedEntities,
+            UnstakedEntities::default(),
+        ),
+        (Signature, Timestamp, VerificationExtraGas),
+        (
+            Gas,
+            Opcodes::default(),
+            ExternalContracts,
+            StorageAccess::default(),
+            CallStack,
+            CodeHashes,
+        ),
+    )
+}
+
+/// Creates a new [StandardUserOperationValidator]
+/// with the sanity checks and simulation checks relaxed by the rules of an alternative mempool.
+///
+/// # Arguments
+/// `entry_point` - [EntryPoint] object.
+/// `chain` - A [EIP-155](https://eips.ethereum.org/EIPS/eip-155) chain ID.
+/// `max_verification_gas` - max verification gas that bundler would accept for one user operation
+/// `min_priority_fee_per_gas` - min priority fee per gas that bundler would accept for one user
+/// operation
+/// `alt_mempool` - The [alternative mempool](AltMempool) definition
+///
+/// # Returns
+/// A new [StandardUserOperationValidator].
+pub fn new_alternative<M: Middleware + 'static>(
+    entry_point: EntryPoint<M>,
+    chain: Chain,
+    max_verification_gas: U256,
+    min_priority_fee_per_gas: U256,
+    alt_mempool: &AltMempool,
+) -> StandardValidator<M> {
+    StandardUserOperationValidator::new(
+        entry_point,
+        chain,
+        (
+            Sender,
+            VerificationGas { max_verification_gas },
+            CallGas,
+            MaxFee { min_priority_fee_per_gas },
+            Paymaster,
+            Entities,
+            UnstakedEntities { allowed_entities: alt_mempool.allowed_entities.clone() },
         ),
         (Signature, Timestamp, VerificationExtraGas),
-        (Gas, Opcodes, ExternalContracts, StorageAccess, CallStack, CodeHashes),
+        (
+            Gas,
+            Opcodes { allowed_opcodes: alt_mempool.allowed_opcodes.clone() },
+            ExternalContracts,
+            StorageAccess {
+                allowed_storage: alt_mempool.allowed_storage.clone(),
+                allowed_entities: alt_mempool.allowed_entities.clone(),
+            },
+            CallStack,
+            CodeHashes,
+        ),
     )
 }
 
@@ -136,7 +202,7 @@ pub fn new_canonical_unsafe<M: Middleware + Clone + 'static>(
             MaxFee { min_priority_fee_per_gas },
             Paymaster,
             Entities,
-            UnstakedEntities,
+            UnstakedEntities::default(),
         ),
         (Signature, Timestamp, VerificationExtraGas),
         (),
@@ -157,7 +223,27 @@ where
         simulation_checks: SimCk,
         simulation_trace_checks: SimTrCk,
     ) -> Self {
-        Self { entry_point, chain, sanity_checks, simulation_checks, simulation_trace_checks }
+        Self {
+            entry_point,
+            chain,
+            sanity_checks,
+            simulation_checks,
+            simulation_trace_checks,
+            rules: ValidationRules::default(),
+        }
+    }
+
+    /// Replaces the [validation rules](ValidationRules) the checks enforce (all rules are enabled
+    /// by default).
+    pub fn with_rules(mut self, rules: ValidationRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Replaces the [EntryPoint](EntryPoint) the user operations are validated against.
+    pub fn with_entry_point(mut self, entry_point: EntryPoint<M>) -> Self {
+        self.entry_point = entry_point;
+        self
     }
 
     /// Simulates validation of a [UserOperation](UserOperation) via the
@@ -184,6 +270,55 @@ where
         }
     }
 
+    /// Checks the reputation and the stake of the signature aggregator and validates the signature
+    /// of the [UserOperation](UserOperation) via the `validateUserOpSignature` method of the
+    /// aggregator.
+    ///
+    /// # Arguments
+    /// `uo` - [UserOperation](UserOperation) that uses the aggregator.
+    /// `reputation` - [Reputation](Reputation) object.
+    /// `aggregator` - [StakeInfo](StakeInfo) of the aggregator returned from the simulation.
+    ///
+    /// # Returns
+    /// The signature to put into the user operation in the bundle (`sigForUserOp`) if the
+    /// aggregator accepts the user operation, otherwise a
+    /// [SimulationError](crate::error::SimulationError).
+    async fn check_aggregator(
+        &self,
+        uo: &UserOperation,
+        reputation: &Reputation,
+        aggregator: StakeInfo,
+    ) -> Result<Bytes, SimulationError> {
+        if Status::from(reputation.get_status(&aggregator.address)?) == Status::BANNED {
+            return Err(ReputationError::BannedEntity {
+                entity: AGGREGATOR.into(),
+                address: aggregator.address,
+            }
+            .into());
+        }
+
+        if !aggregator.is_staked() {
+            return Err(ReputationError::UnstakedEntity {
+                entity: AGGREGATOR.into(),
+                address: aggregator.address,
+            }
+            .into());
+        }
+        reputation.verify_stake(AGGREGATOR, Some(aggregator))?;
+
+        Aggregator::new(
+            self.entry_point.eth_client(),
+            aggregator.address,
+            self.entry_point.version(),
+        )
+        .validate_user_op_signature(uo.user_operation.clone())
+        .await
+        .map_err(|err| match err {
+            EntryPointError::Provider { inner } => SimulationError::Provider { inner },
+            _ => SimulationError::Signature,
+        })
+    }
+
     /// Simulates validation of a [UserOperation](UserOperation) via the
     /// [simulate_validation_trace](crate::entry_point::EntryPoint::simulate_validation_trace)
     /// method of the [entry_point](crate::entry_point::EntryPoint)
@@ -243,7 +378,11 @@ where
         let mut out: UserOperationValidationOutcome = Default::default();
 
         if mode.contains(UserOperationValidatorMode::Sanity) {
-            let sanity_helper = SanityHelper { entry_point: &self.entry_point, chain: self.chain };
+            let sanity_helper = SanityHelper {
+                entry_point: &self.entry_point,
+                chain: self.chain,
+                rules: &self.rules,
+            };
 
             self.sanity_checks
                 .check_user_operation(uo, mempool, reputation, &sanity_helper)
@@ -257,14 +396,25 @@ where
         let sim_res = self.simulate_validation(uo).await?;
 
         if mode.contains(UserOperationValidatorMode::Simulation) {
-            let mut sim_helper =
-                SimulationHelper { simulate_validation_result: &sim_res, valid_after: None };
+            let mut sim_helper = SimulationHelper {
+                rules: &self.rules,
+                simulate_validation_result: &sim_res,
+                valid_after: None,
+            };
 
             self.simulation_checks.check_user_operation(uo, &mut sim_helper)?;
 
             out.valid_after = sim_helper.valid_after;
         }
 
+        out.aggregator = extract_aggregator(&sim_res);
+        if let Some(aggregator) = out.aggregator {
+            if mode.contains(UserOperationValidatorMode::Simulation) {
+                out.sig_for_user_op =
+                    Some(self.check_aggregator(uo, reputation, aggregator).await?);
+            }
+        }
+
         out.pre_fund = extract_pre_fund(&sim_res);
         out.verification_gas_limit = extract_verification_gas_limit(&sim_res);
 
@@ -286,6 +436,7 @@ where
             let mut sim_helper = SimulationTraceHelper {
                 entry_point: &self.entry_point,
                 chain: self.chain,
+                rules: &self.rules,
                 simulate_validation_result: &sim_res,
                 js_trace: &js_trace,
                 stake_info: None,
@@ -303,3 +454,178 @@ where
         Ok(out)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::Token,
+        providers::{MockProvider, Provider},
+        types::{Address, Block, H256},
+    };
+    use silius_primitives::{
+        constants::validation::reputation::{
+            BAN_SLACK, MIN_INCLUSION_RATE_DENOMINATOR, THROTTLING_SLACK,
+        },
+        entry_point::EntryPointVersion,
+        reputation::ReputationEntry,
+        simulation::CodeHash,
+        validation::ValidationRule,
+        UserOperationHash, UserOperationSigned,
+    };
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+    };
+
+    /// Address the account accesses during the validation
+    const EXTERNAL_CONTRACT: &str = "0x1111111111111111111111111111111111111111";
+
+    fn mempool() -> Mempool {
+        Mempool::new(
+            Box::new(HashMap::<UserOperationHash, UserOperationSigned>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()),
+        )
+    }
+
+    fn reputation() -> Reputation {
+        Reputation::new(
+            MIN_INCLUSION_RATE_DENOMINATOR,
+            THROTTLING_SLACK,
+            BAN_SLACK,
+            U256::from(1),
+            U256::from(0),
+            Arc::new(parking_lot::RwLock::new(HashSet::<Address>::default())),
+            Arc::new(parking_lot::RwLock::new(HashSet::<Address>::default())),
+            Box::new(HashMap::<Address, ReputationEntry>::default()),
+        )
+    }
+
+    fn user_operation(call_gas_limit: u64) -> UserOperation {
+        UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned {
+                sender: Address::random(),
+                call_gas_limit: call_gas_limit.into(),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Queues the responses of `simulateValidation` (entry point v0.7), of the latest block and of
+    /// the validation trace, in which the account accesses [EXTERNAL_CONTRACT] with the given code
+    /// size
+    fn push_validation_responses(mock: &MockProvider, contract_size: u64) {
+        // the mocked provider returns the responses in the reverse order
+        mock.push(serde_json::json!({
+            "callsFromEntryPoint": [{
+                "topLevelMethodSig": "0x19822f7c",
+                "topLevelTargetAddress": "0x",
+                "access": {},
+                "opcodes": {},
+                "contractSize": {
+                    EXTERNAL_CONTRACT: { "opcode": "EXTCODESIZE", "contractSize": contract_size },
+                },
+                "extCodeAccessInfo": {},
+            }],
+            "keccak": [],
+            "logs": [],
+            "calls": [],
+            "debug": [],
+        }))
+        .unwrap();
+        mock.push(Block::<H256> { hash: Some(H256::random()), ..Default::default() }).unwrap();
+
+        let stake_info = Token::Tuple(vec![Token::Uint(U256::zero()), Token::Uint(U256::zero())]);
+        mock.push(Bytes::from(ethers::abi::encode(&[Token::Tuple(vec![
+            Token::Tuple(vec![
+                Token::Uint(100_000.into()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Bytes(vec![]),
+            ]),
+            stake_info.clone(),
+            stake_info.clone(),
+            stake_info.clone(),
+            Token::Tuple(vec![Token::Address(Address::zero()), stake_info]),
+        ])])))
+        .unwrap();
+    }
+
+    fn validator(
+        provider: Provider<MockProvider>,
+        rules: ValidationRules,
+    ) -> StandardUserOperationValidator<
+        Provider<MockProvider>,
+        (CallGas,),
+        (Signature,),
+        (ExternalContracts,),
+    > {
+        StandardUserOperationValidator::new(
+            EntryPoint::new_with_version(
+                Arc::new(provider),
+                Address::random(),
+                EntryPointVersion::V0_7,
+            ),
+            Chain::from_id(1337),
+            (CallGas,),
+            (Signature,),
+            (ExternalContracts,),
+        )
+        .with_rules(rules)
+    }
+
+    #[tokio::test]
+    async fn disabled_trace_rule_lets_user_operation_through() {
+        let mode = UserOperationValidatorMode::Sanity | UserOperationValidatorMode::SimulationTrace;
+        let uo = user_operation(100_000);
+
+        // [OP-041] - the account accesses an address without a deployed code
+        let (provider, mock) = Provider::mocked();
+        push_validation_responses(&mock, 0);
+        let res = validator(provider, ValidationRules::default())
+            .validate_user_operation(&uo, &mempool(), &reputation(), mode)
+            .await;
+        assert!(matches!(
+            res,
+            Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { .. }))
+        ));
+
+        let mut rules = ValidationRules::default();
+        rules.disable(ValidationRule::Op041);
+        let (provider, mock) = Provider::mocked();
+        push_validation_responses(&mock, 0);
+        let res = validator(provider, rules)
+            .validate_user_operation(&uo, &mempool(), &reputation(), mode)
+            .await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn disabled_sanity_check_lets_user_operation_through() {
+        let mode = UserOperationValidatorMode::Sanity | UserOperationValidatorMode::SimulationTrace;
+        let uo = user_operation(0);
+
+        // the call gas limit doesn't cover a CALL with non-zero value
+        let (provider, _mock) = Provider::mocked();
+        let res = validator(provider, ValidationRules::default())
+            .validate_user_operation(&uo, &mempool(), &reputation(), mode)
+            .await;
+        assert!(matches!(
+            res,
+            Err(InvalidMempoolUserOperationError::Sanity(SanityError::CallGasLimitTooLow { .. }))
+        ));
+
+        let mut rules = ValidationRules::default();
+        rules.disable(ValidationRule::CallGas);
+        let (provider, mock) = Provider::mocked();
+        push_validation_responses(&mock, 100);
+        let res = validator(provider, rules)
+            .validate_user_operation(&uo, &mempool(), &reputation(), mode)
+            .await;
+        assert!(res.is_ok());
+    }
+}
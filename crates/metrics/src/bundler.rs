@@ -0,0 +1,147 @@
+use ethers::{
+    types::{Address, U256},
+    utils::format_ether,
+};
+use metrics::{counter, describe_counter, describe_gauge, gauge};
+
+const SIGNER_BALANCE: &str = "silius_bundler_signer_balance";
+const SIGNER_REFUND: &str = "silius_bundler_signer_refund";
+const TREASURY_BALANCE: &str = "silius_bundler_treasury_balance";
+const TREASURY_LOW_BALANCE: &str = "silius_bundler_treasury_low_balance";
+const TREASURY_INSUFFICIENT_BALANCE: &str = "silius_bundler_treasury_insufficient_balance";
+const ENTITY_DEPOSIT: &str = "silius_bundler_entity_deposit";
+const ENTITY_STAKE: &str = "silius_bundler_entity_stake";
+const ENTITY_UNSTAKE_DELAY: &str = "silius_bundler_entity_unstake_delay";
+const ENTITY_LOW_DEPOSIT: &str = "silius_bundler_entity_low_deposit";
+const ENTITY_LOW_STAKE: &str = "silius_bundler_entity_low_stake";
+const ENTITY_LOW_UNSTAKE_DELAY: &str = "silius_bundler_entity_low_unstake_delay";
+const ENTITY_UNSTAKING: &str = "silius_bundler_entity_unstaking";
+
+fn ether(value: U256) -> f64 {
+    format_ether(value).parse().unwrap_or_default()
+}
+
+/// Records the balance of the bundler signer
+pub fn record_signer_balance(signer: Address, balance: U256) {
+    gauge!(SIGNER_BALANCE, "signer" => format!("{signer:?}")).set(ether(balance));
+}
+
+/// Records the refund of the bundler signer from the treasury (the balance of the signer is
+/// recorded once the refund transfer is mined)
+pub fn record_signer_refund(signer: Address) {
+    counter!(SIGNER_REFUND, "signer" => format!("{signer:?}")).increment(1);
+}
+
+/// Records the balance of the treasury and whether it's below the minimum treasury balance
+pub fn record_treasury_balance(treasury: Address, balance: U256, low: bool) {
+    gauge!(TREASURY_BALANCE, "treasury" => format!("{treasury:?}")).set(ether(balance));
+    gauge!(TREASURY_LOW_BALANCE, "treasury" => format!("{treasury:?}"))
+        .set(f64::from(u8::from(low)));
+}
+
+/// Records that the treasury couldn't refund the bundler signer
+pub fn record_treasury_insufficient_balance(treasury: Address, signer: Address) {
+    counter!(
+        TREASURY_INSUFFICIENT_BALANCE,
+        "treasury" => format!("{treasury:?}"),
+        "signer" => format!("{signer:?}")
+    )
+    .increment(1);
+}
+
+/// Records the deposit, stake and unstake delay (in seconds) of a watched entity
+pub fn record_entity_deposit(
+    entry_point: Address,
+    entity: Address,
+    deposit: U256,
+    stake: U256,
+    unstake_delay: u64,
+) {
+    gauge!(
+        ENTITY_DEPOSIT,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(ether(deposit));
+    gauge!(
+        ENTITY_STAKE,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(ether(stake));
+    gauge!(
+        ENTITY_UNSTAKE_DELAY,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(unstake_delay as f64);
+}
+
+/// Records whether the deposit, stake and unstake delay of a watched entity are below the
+/// thresholds and whether the entity is unstaking
+pub fn record_entity_alerts(
+    entry_point: Address,
+    entity: Address,
+    low_deposit: bool,
+    low_stake: bool,
+    low_unstake_delay: bool,
+    unstaking: bool,
+) {
+    gauge!(
+        ENTITY_LOW_DEPOSIT,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(f64::from(u8::from(low_deposit)));
+    gauge!(
+        ENTITY_LOW_STAKE,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(f64::from(u8::from(low_stake)));
+    gauge!(
+        ENTITY_LOW_UNSTAKE_DELAY,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(f64::from(u8::from(low_unstake_delay)));
+    gauge!(
+        ENTITY_UNSTAKING,
+        "entry_point" => format!("{entry_point:?}"),
+        "entity" => format!("{entity:?}")
+    )
+    .set(f64::from(u8::from(unstaking)));
+}
+
+pub fn describe_bundler_metrics() {
+    describe_gauge!(SIGNER_BALANCE, "The balance of the bundler signer (in ether)");
+    describe_counter!(
+        SIGNER_REFUND,
+        "The number of refunds of the bundler signer from the treasury"
+    );
+    describe_gauge!(TREASURY_BALANCE, "The balance of the treasury (in ether)");
+    describe_gauge!(
+        TREASURY_LOW_BALANCE,
+        "Whether the treasury balance is below the minimum treasury balance"
+    );
+    describe_counter!(
+        TREASURY_INSUFFICIENT_BALANCE,
+        "The number of refunds the treasury couldn't afford"
+    );
+    describe_gauge!(ENTITY_DEPOSIT, "The deposit of the watched entity (in ether)");
+    describe_gauge!(ENTITY_STAKE, "The stake of the watched entity (in ether)");
+    describe_gauge!(ENTITY_UNSTAKE_DELAY, "The unstake delay of the watched entity (in seconds)");
+    describe_gauge!(
+        ENTITY_LOW_DEPOSIT,
+        "Whether the deposit of the watched entity is below the minimum deposit"
+    );
+    describe_gauge!(
+        ENTITY_LOW_STAKE,
+        "Whether the stake of the watched entity is below the minimum stake"
+    );
+    describe_gauge!(
+        ENTITY_LOW_UNSTAKE_DELAY,
+        "Whether the unstake delay of the watched entity is below the minimum unstake delay"
+    );
+    describe_gauge!(ENTITY_UNSTAKING, "Whether the watched entity unlocked its stake");
+}
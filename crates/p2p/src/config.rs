@@ -1,11 +1,16 @@
-use crate::listen_addr::{ListenAddr, ListenAddress};
+use crate::{
+    listen_addr::{ListenAddr, ListenAddress},
+    types::topics::topic,
+};
 use discv5::{Enr, ListenConfig};
-use libp2p::gossipsub;
+use libp2p::gossipsub::{self, PeerScoreParams, PeerScoreThresholds, TopicScoreParams};
 use sha2::{Digest, Sha256};
 use silius_primitives::{
     chain::ChainSpec,
     constants::p2p::{
-        IPV4_ADDRESS, MESSAGE_DOMAIN_VALID_SNAPPY, NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME,
+        GOSSIP_THRESHOLD, GRAYLIST_THRESHOLD, INVALID_MESSAGE_DELIVERIES_DECAY,
+        INVALID_MESSAGE_DELIVERIES_WEIGHT, IPV4_ADDRESS, MESSAGE_DOMAIN_VALID_SNAPPY,
+        NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME, NODE_PEERS_FILE_NAME, PUBLISH_THRESHOLD,
         TARGET_PEERS, TCP_PORT, UDP_PORT,
     },
 };
@@ -22,6 +27,9 @@ pub struct Config {
     /// File to store the node's enr.
     pub node_enr_file: PathBuf,
 
+    /// File to store the known peers.
+    pub peers_file: PathBuf,
+
     /// The listening address for p2p.
     pub listen_addr: ListenAddress,
 
@@ -69,6 +77,7 @@ impl Default for Config {
         Self {
             node_key_file: PathBuf::from(NODE_KEY_FILE_NAME),
             node_enr_file: PathBuf::from(NODE_ENR_FILE_NAME),
+            peers_file: PathBuf::from(NODE_PEERS_FILE_NAME),
             listen_addr: ListenAddress::V4(ListenAddr {
                 addr: IPV4_ADDRESS,
                 udp_port: UDP_PORT,
@@ -123,6 +132,12 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set the known peers file.
+    pub fn peers_file(mut self, peers_file: PathBuf) -> Self {
+        self.config.peers_file = peers_file;
+        self
+    }
+
     /// Set the listen address.
     pub fn listen_addr(mut self, listen_addr: ListenAddress) -> Self {
         self.config.listen_addr = listen_addr;
@@ -223,3 +238,36 @@ pub fn gossipsub_config() -> gossipsub::Config {
         .build()
         .expect("valid config")
 }
+
+/// Create the gossipsub peer score parameters of the mempool topics.
+///
+/// Peers are penalized for the invalid user operations (reported by the mempool after the
+/// validation). The mesh delivery penalties are disabled since the user operations topics can be
+/// quiet for a long time.
+pub fn gossipsub_peer_score_params(mempool_ids: &[String]) -> PeerScoreParams {
+    let mut params = PeerScoreParams::default();
+    for mempool_id in mempool_ids {
+        params.topics.insert(
+            topic(mempool_id).hash(),
+            TopicScoreParams {
+                topic_weight: 1.0,
+                mesh_message_deliveries_weight: 0.0,
+                mesh_failure_penalty_weight: 0.0,
+                invalid_message_deliveries_weight: INVALID_MESSAGE_DELIVERIES_WEIGHT,
+                invalid_message_deliveries_decay: INVALID_MESSAGE_DELIVERIES_DECAY,
+                ..Default::default()
+            },
+        );
+    }
+    params
+}
+
+/// Create the gossipsub peer score thresholds.
+pub fn gossipsub_peer_score_thresholds() -> PeerScoreThresholds {
+    PeerScoreThresholds {
+        gossip_threshold: GOSSIP_THRESHOLD,
+        publish_threshold: PUBLISH_THRESHOLD,
+        graylist_threshold: GRAYLIST_THRESHOLD,
+        ..Default::default()
+    }
+}
@@ -2,20 +2,33 @@ pub mod network_behaviour;
 pub mod peer;
 pub mod peerdb;
 
-use self::peer::peer_info::ConnectionDirection;
+use self::peer::{peer_info::ConnectionDirection, score::PeerAction};
 use crate::{
     discovery::enr_ext::EnrExt,
-    rpc::methods::{GoodbyeReason, MetaData},
+    rpc::{
+        methods::{GoodbyeReason, MetaData},
+        InboundFailure, OutboundFailure,
+    },
+    service::utils::save_peers_to_file,
     types::globals::NetworkGlobals,
 };
 use delay_map::HashSetDelay;
 use discv5::Enr;
 use libp2p::{Multiaddr, PeerId};
-use silius_primitives::constants::p2p::{
-    HEARTBEAT_INTERVAL, PING_INTERVAL_INBOUND, PING_INTERVAL_OUTBOUND, TARGET_PEERS,
+use silius_primitives::{
+    constants::p2p::{
+        HANDSHAKE_TIMEOUT, HEARTBEAT_INTERVAL, MIN_SCORE_BEFORE_BAN, PING_INTERVAL_INBOUND,
+        PING_INTERVAL_OUTBOUND, TARGET_PEERS,
+    },
+    p2p::PeerRecord,
 };
-use std::{collections::VecDeque, sync::Arc, time::Duration};
-use tracing::debug;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+use tracing::{debug, warn};
 
 /// The events that the `PeerManager` outputs (requests).
 #[derive(Debug)]
@@ -51,24 +64,33 @@ pub struct PeerManager {
     inbound_ping_peers: HashSetDelay<PeerId>,
     /// List of outbound peers we need to ping.
     outbound_ping_peers: HashSetDelay<PeerId>,
+    /// Connected peers that haven't exchanged their status yet (disconnected once expired).
+    handshake_peers: HashSetDelay<PeerId>,
     /// the target peers we want to connect,
     target_peers: usize,
     /// Peers needs to be dialed.
     peers_to_dial: Vec<Enr>,
     /// The heartbeat interval for peer management.
     heartbeat: tokio::time::Interval,
+    /// Writes the known peers to the file (off the event loop).
+    peers_writer: mpsc::Sender<Vec<PeerRecord>>,
+    /// Peer ids, ENRs and bans of the last persisted peers.
+    persisted_peers: Vec<(String, Option<String>, bool)>,
 }
 
 impl PeerManager {
-    pub fn new(network_globals: Arc<NetworkGlobals>) -> Self {
+    pub fn new(network_globals: Arc<NetworkGlobals>, peers_file: PathBuf) -> Self {
         Self {
             network_globals,
             events: Default::default(),
             inbound_ping_peers: HashSetDelay::new(Duration::from_secs(PING_INTERVAL_INBOUND)),
             outbound_ping_peers: HashSetDelay::new(Duration::from_secs(PING_INTERVAL_OUTBOUND)),
+            handshake_peers: HashSetDelay::new(Duration::from_secs(HANDSHAKE_TIMEOUT)),
             target_peers: TARGET_PEERS,
             peers_to_dial: Vec::new(),
             heartbeat: tokio::time::interval(Duration::from_secs(HEARTBEAT_INTERVAL)),
+            peers_writer: spawn_peers_writer(peers_file),
+            persisted_peers: Vec::new(),
         }
     }
 
@@ -146,6 +168,25 @@ impl PeerManager {
         }
     }
 
+    /// The peer has exchanged its status.
+    pub fn handshake_completed(&mut self, peer_id: &PeerId) {
+        self.handshake_peers.remove(peer_id);
+    }
+
+    /// Disconnects the peer if it hasn't exchanged its status in time.
+    fn handshake_expired(&mut self, peer_id: PeerId) {
+        let handshaked = self
+            .network_globals
+            .peers
+            .read()
+            .peer_info(&peer_id)
+            .map_or(true, |info| info.status().is_some());
+        if !handshaked {
+            debug!("Peer {peer_id} didn't exchange its status in time, disconnecting");
+            self.disconnect_peer(peer_id, GoodbyeReason::Error);
+        }
+    }
+
     /// The peer has responded with metadata.
     pub fn metadata_response(&mut self, peer_id: &PeerId, metadata: MetaData) {
         if let Some(peer_info) = self.network_globals.peers.write().peer_info_mut(peer_id) {
@@ -176,6 +217,7 @@ impl PeerManager {
 
         self.inbound_ping_peers.remove(peer_id);
         self.outbound_ping_peers.remove(peer_id);
+        self.handshake_peers.remove(peer_id);
     }
 
     fn inject_peer_connection(
@@ -194,22 +236,141 @@ impl PeerManager {
             ConnectingType::IngoingConnected { multiaddr } => {
                 peer_db.connect_ingoing(peer_id, multiaddr, enr);
                 self.inbound_ping_peers.insert(*peer_id);
+                self.handshake_peers.insert(*peer_id);
             }
             ConnectingType::OutgoingConnected { multiaddr } => {
                 peer_db.connect_outgoing(peer_id, multiaddr, enr);
                 self.outbound_ping_peers.insert(*peer_id);
+                self.handshake_peers.insert(*peer_id);
             }
         }
 
         true
     }
 
-    fn _disconnect_peer(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
+    /// Disconnects the peer (with a goodbye) if it's connected.
+    pub fn disconnect_peer(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
+        if !self.is_connected(&peer_id) {
+            return;
+        }
         self.events.push_back(PeerManagerEvent::DisconnectPeer(peer_id, reason));
         self.network_globals.peers.write().notify_disconnecting(&peer_id);
     }
 
+    /// Adds the peer (lifting its ban) and dials it.
+    pub fn add_peer(&mut self, enr: Enr) -> bool {
+        self.network_globals.peers.write().unban(&enr.peer_id());
+        let dialing = self.dial_peer(enr);
+        self.persist_peers();
+        dialing
+    }
+
+    /// Bans the peer permanently (the ban is persisted) and disconnects it.
+    pub fn ban_peer(&mut self, peer_id: PeerId) {
+        self.disconnect_peer(peer_id, GoodbyeReason::Error);
+        self.network_globals.peers.write().ban(&peer_id);
+        self.persist_peers();
+    }
+
+    /// Disconnects the peer and forgets it.
+    pub fn remove_peer(&mut self, peer_id: PeerId) {
+        self.disconnect_peer(peer_id, GoodbyeReason::ClientShutdown);
+        self.peers_to_dial.retain(|enr| enr.peer_id() != peer_id);
+        self.network_globals.peers.write().remove(&peer_id);
+        self.persist_peers();
+    }
+
+    /// Penalizes the peer for the misbehaviour, the peer is banned once its score drops to the ban
+    /// threshold (until its score decays back above
+    /// [MIN_SCORE_BEFORE_UNBAN](silius_primitives::constants::p2p::MIN_SCORE_BEFORE_UNBAN)).
+    pub fn report_peer(&mut self, peer_id: &PeerId, action: PeerAction, msg: &str) {
+        let Some(score) = self.network_globals.peers.write().report_peer(peer_id, action) else {
+            return;
+        };
+        debug!("Peer {peer_id} reported for {msg} ({action:?}), score: {score}");
+
+        if score <= MIN_SCORE_BEFORE_BAN && !self.network_globals.peers.read().is_banned(peer_id) {
+            warn!(
+                "Peer {peer_id} score {score} dropped to the ban threshold, banning until it recovers"
+            );
+            self.disconnect_peer(*peer_id, GoodbyeReason::Error);
+            self.network_globals.peers.write().ban_for_score(peer_id);
+        }
+    }
+
+    /// Penalizes the peer for the failed inbound request.
+    pub fn handle_inbound_failure(&mut self, peer_id: &PeerId, failure: &InboundFailure) {
+        let action = match failure {
+            InboundFailure::Timeout => PeerAction::HighToleranceError,
+            InboundFailure::Error(_) => PeerAction::LowToleranceError,
+            // not caused by the peer
+            InboundFailure::ConnectionClosed |
+            InboundFailure::UnsupportedProtocols |
+            InboundFailure::ResponseOmission => return,
+        };
+        self.report_peer(peer_id, action, &format!("inbound request failure {failure:?}"));
+    }
+
+    /// Penalizes the peer for the failed outbound request.
+    pub fn handle_outbound_failure(&mut self, peer_id: &PeerId, failure: &OutboundFailure) {
+        let action = match failure {
+            OutboundFailure::Timeout => PeerAction::MidToleranceError,
+            OutboundFailure::UnsupportedProtocols | OutboundFailure::Error(_) => {
+                PeerAction::LowToleranceError
+            }
+            // not caused by the peer
+            OutboundFailure::DialFailure | OutboundFailure::ConnectionClosed => return,
+        };
+        self.report_peer(peer_id, action, &format!("outbound request failure {failure:?}"));
+    }
+
+    /// Disconnects the lowest-scored peers above the target number of peers.
+    fn prune_excess_peers(&mut self) {
+        let peers = self.network_globals.peers.read().connected_peers_by_score();
+        let excess = peers.len().saturating_sub(self.target_peers);
+        for (peer_id, score) in peers.into_iter().take(excess) {
+            debug!("Pruning excess peer {peer_id} with score {score}");
+            self.disconnect_peer(peer_id, GoodbyeReason::ClientShutdown);
+        }
+    }
+
+    /// Persists the known peers if the peer set (or a ban) changed since the last write.
+    pub fn persist_peers(&mut self) {
+        let records = self.network_globals.peers.read().records();
+        let mut peers = records
+            .iter()
+            .map(|r| (r.peer_id.clone(), r.enr.clone(), r.banned))
+            .collect::<Vec<_>>();
+        peers.sort();
+        if peers == self.persisted_peers {
+            return;
+        }
+        self.persisted_peers = peers;
+        if self.peers_writer.send(records).is_err() {
+            warn!("Known peers writer is gone, the known peers are not persisted");
+        }
+    }
+
     fn heartbeat(&mut self) {
         // TODO: optionally run discovery
+        let unbanned = self.network_globals.peers.write().decay_scores(self.heartbeat.period());
+        for peer_id in unbanned {
+            debug!("Peer {peer_id} score recovered, lifting its ban");
+        }
+        self.prune_excess_peers();
+        self.persist_peers();
     }
 }
+
+/// Spawns the thread that writes the known peers to the file (in the order they are sent).
+fn spawn_peers_writer(peers_file: PathBuf) -> mpsc::Sender<Vec<PeerRecord>> {
+    let (tx, rx) = mpsc::channel::<Vec<PeerRecord>>();
+    std::thread::spawn(move || {
+        for records in rx {
+            if let Err(e) = save_peers_to_file(&records, &peers_file) {
+                warn!("Failed to persist known peers to {peers_file:?}: {e:?}");
+            }
+        }
+    });
+    tx
+}
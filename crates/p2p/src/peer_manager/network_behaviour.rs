@@ -7,7 +7,7 @@ use libp2p::{
         behaviour::ConnectionEstablished,
         dial_opts::{DialOpts, PeerCondition},
         dummy::ConnectionHandler,
-        ConnectionClosed, DialFailure, FromSwarm, NetworkBehaviour, ToSwarm,
+        ConnectionClosed, ConnectionDenied, DialFailure, FromSwarm, NetworkBehaviour, ToSwarm,
     },
     PeerId,
 };
@@ -50,20 +50,22 @@ impl NetworkBehaviour for PeerManager {
     fn handle_established_inbound_connection(
         &mut self,
         _connection_id: libp2p::swarm::ConnectionId,
-        _peer: PeerId,
+        peer: PeerId,
         _local_addr: &libp2p::Multiaddr,
         _remote_addr: &libp2p::Multiaddr,
     ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
+        self.deny_banned_peer(&peer)?;
         Ok(ConnectionHandler)
     }
 
     fn handle_established_outbound_connection(
         &mut self,
         _connection_id: libp2p::swarm::ConnectionId,
-        _peer: PeerId,
+        peer: PeerId,
         _addr: &libp2p::Multiaddr,
         _role_override: libp2p::core::Endpoint,
     ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
+        self.deny_banned_peer(&peer)?;
         Ok(ConnectionHandler)
     }
 
@@ -120,6 +122,16 @@ impl NetworkBehaviour for PeerManager {
             }
         }
 
+        loop {
+            match self.handshake_peers.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(peer_id))) => self.handshake_expired(peer_id),
+                Poll::Ready(Some(Err(e))) => {
+                    error!("Failed to check handshake peer with {e:?}");
+                }
+                Poll::Ready(None) | Poll::Pending => break,
+            }
+        }
+
         if !self.events.is_empty() {
             if let Some(event) = self.events.pop_front() {
                 return Poll::Ready(ToSwarm::GenerateEvent(event));
@@ -142,6 +154,13 @@ impl NetworkBehaviour for PeerManager {
 }
 
 impl PeerManager {
+    fn deny_banned_peer(&self, peer_id: &PeerId) -> Result<(), ConnectionDenied> {
+        if self.network_globals.peers.read().is_banned(peer_id) {
+            return Err(ConnectionDenied::new(format!("Peer {peer_id} is banned")));
+        }
+        Ok(())
+    }
+
     fn on_connection_established(
         &mut self,
         peer_id: PeerId,
@@ -1 +1,2 @@
 pub mod peer_info;
+pub mod score;
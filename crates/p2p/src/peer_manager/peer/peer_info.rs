@@ -1,7 +1,9 @@
-use crate::rpc::methods::MetaData;
+use super::score::{self, PeerAction};
+use crate::rpc::methods::{MetaData, StatusMessage};
 use discv5::Enr;
 use eyre::Result;
 use libp2p::Multiaddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Information about a peer.
 #[derive(Default, Debug, Clone)]
@@ -14,6 +16,18 @@ pub struct PeerInfo {
     metadata: Option<MetaData>,
     /// Connection direction (ingoing or outgoing).
     connection_direction: Option<ConnectionDirection>,
+    /// Unix timestamp (in seconds) of the last connection.
+    last_seen: u64,
+    /// Score of the peer.
+    score: f64,
+    /// Whether the peer is banned (through the admin API, the ban is persisted).
+    banned: bool,
+    /// Whether the peer is banned for its low score (the ban is lifted once the score recovers).
+    score_banned: bool,
+    /// Status of the peer (once the status handshake succeeded).
+    status: Option<StatusMessage>,
+    /// Whether the peer is far behind us (according to its status).
+    far_behind: bool,
 }
 
 impl PeerInfo {
@@ -33,10 +47,69 @@ impl PeerInfo {
         self.metadata = Some(metadata);
     }
 
+    pub fn status(&self) -> &Option<StatusMessage> {
+        &self.status
+    }
+
+    pub fn set_status(&mut self, status: Option<StatusMessage>, far_behind: bool) {
+        self.status = status;
+        self.far_behind = far_behind;
+    }
+
+    pub fn is_far_behind(&self) -> bool {
+        self.far_behind
+    }
+
     pub fn connection_direction(&self) -> &Option<ConnectionDirection> {
         &self.connection_direction
     }
 
+    pub fn last_seen(&self) -> u64 {
+        self.last_seen
+    }
+
+    pub fn set_last_seen(&mut self, last_seen: u64) {
+        self.last_seen = last_seen;
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    pub fn set_score(&mut self, score: f64) {
+        self.score = score;
+    }
+
+    /// Penalizes the peer for the misbehaviour.
+    pub fn apply_action(&mut self, action: PeerAction) {
+        self.score = score::apply_action(self.score, action);
+    }
+
+    /// Decays the score towards zero.
+    pub fn decay_score(&mut self, elapsed: Duration) {
+        self.score = score::decay(self.score, elapsed);
+    }
+
+    pub fn is_banned(&self) -> bool {
+        self.banned || self.score_banned
+    }
+
+    pub fn is_admin_banned(&self) -> bool {
+        self.banned
+    }
+
+    pub fn set_banned(&mut self, banned: bool) {
+        self.banned = banned;
+    }
+
+    pub fn is_score_banned(&self) -> bool {
+        self.score_banned
+    }
+
+    pub fn set_score_banned(&mut self, score_banned: bool) {
+        self.score_banned = score_banned;
+    }
+
     pub fn is_connected(&self) -> bool {
         matches!(self.connection_status, PeerConnectionStatus::Connected)
     }
@@ -88,6 +161,7 @@ impl PeerInfo {
             PeerConnectionStatus::Unknown => {
                 self.connection_status = PeerConnectionStatus::Connected;
                 self.connection_direction = Some(ConnectionDirection::Incoming);
+                self.seen();
             }
         }
     }
@@ -101,9 +175,16 @@ impl PeerInfo {
             PeerConnectionStatus::Unknown => {
                 self.connection_status = PeerConnectionStatus::Connected;
                 self.connection_direction = Some(ConnectionDirection::Outgoing);
+                self.seen();
             }
         }
     }
+
+    /// Marks the peer as seen now.
+    fn seen(&mut self) {
+        self.last_seen =
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,51 @@
+use silius_primitives::constants::p2p::{MAX_PEER_SCORE, MIN_PEER_SCORE, SCORE_HALFLIFE};
+use std::time::Duration;
+
+/// Misbehaviour of a peer, the peer is penalized according to how many times such misbehaviour is
+/// tolerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerAction {
+    /// Tolerated a few times (e.g. invalid responses).
+    LowToleranceError,
+    /// Tolerated about a dozen times (e.g. unanswered requests).
+    MidToleranceError,
+    /// Tolerated many times (e.g. slow requests).
+    HighToleranceError,
+}
+
+impl PeerAction {
+    /// Score change of the peer for the misbehaviour.
+    pub fn penalty(&self) -> f64 {
+        match self {
+            PeerAction::LowToleranceError => -10.0,
+            PeerAction::MidToleranceError => -5.0,
+            PeerAction::HighToleranceError => -1.0,
+        }
+    }
+}
+
+/// Applies the penalty of the misbehaviour to the score (bounded by the minimum score).
+pub fn apply_action(score: f64, action: PeerAction) -> f64 {
+    (score + action.penalty()).clamp(MIN_PEER_SCORE, MAX_PEER_SCORE)
+}
+
+/// Decays the score towards zero, the score is halved every [SCORE_HALFLIFE] seconds.
+pub fn decay(score: f64, elapsed: Duration) -> f64 {
+    score * 0.5f64.powf(elapsed.as_secs_f64() / SCORE_HALFLIFE as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use silius_primitives::constants::p2p::MIN_SCORE_BEFORE_BAN;
+
+    #[test]
+    fn penalizes_and_decays_score() {
+        let score = (0..5).fold(0.0, |score, _| apply_action(score, PeerAction::LowToleranceError));
+        assert_eq!(score, MIN_SCORE_BEFORE_BAN);
+        assert_eq!(apply_action(MIN_PEER_SCORE, PeerAction::HighToleranceError), MIN_PEER_SCORE);
+
+        assert_eq!(decay(-40.0, Duration::from_secs(SCORE_HALFLIFE)), -20.0);
+        assert_eq!(decay(-40.0, Duration::ZERO), -40.0);
+    }
+}
@@ -1,8 +1,15 @@
-use super::peer::peer_info::{ConnectionDirection, PeerConnectionStatus, PeerInfo};
+use super::peer::{
+    peer_info::{ConnectionDirection, PeerConnectionStatus, PeerInfo},
+    score::PeerAction,
+};
 use discv5::Enr;
 use libp2p::{Multiaddr, PeerId};
-use std::collections::HashMap;
-use tracing::error;
+use silius_primitives::{
+    constants::p2p::{MIN_SCORE_BEFORE_BAN, MIN_SCORE_BEFORE_UNBAN},
+    p2p::PeerRecord,
+};
+use std::{collections::HashMap, str::FromStr, time::Duration};
+use tracing::{error, warn};
 
 #[derive(Default)]
 pub struct PeerDB {
@@ -56,10 +63,127 @@ impl PeerDB {
     }
 
     pub fn should_dial(&self, peer_id: &PeerId) -> bool {
-        matches!(
-            self.connection_status(peer_id),
-            Some(PeerConnectionStatus::Disconnected | PeerConnectionStatus::Unknown) | None
-        )
+        !self.is_banned(peer_id) &&
+            matches!(
+                self.connection_status(peer_id),
+                Some(PeerConnectionStatus::Disconnected | PeerConnectionStatus::Unknown) | None
+            )
+    }
+
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.peer_info(peer_id).map_or(false, |info| info.is_banned())
+    }
+
+    /// Bans the peer permanently (it's not dialed and its connections are denied).
+    pub fn ban(&mut self, peer_id: &PeerId) {
+        self.peers.entry(*peer_id).or_default().set_banned(true);
+    }
+
+    /// Bans the peer for its low score, the ban is lifted once the score decays above
+    /// [MIN_SCORE_BEFORE_UNBAN].
+    pub fn ban_for_score(&mut self, peer_id: &PeerId) {
+        if let Some(info) = self.peers.get_mut(peer_id) {
+            info.set_score_banned(true);
+        }
+    }
+
+    /// Lifts the ban of the peer.
+    pub fn unban(&mut self, peer_id: &PeerId) {
+        if let Some(info) = self.peers.get_mut(peer_id) {
+            info.set_banned(false);
+            info.set_score_banned(false);
+        }
+    }
+
+    /// Forgets the peer (including its ban).
+    pub fn remove(&mut self, peer_id: &PeerId) -> Option<PeerInfo> {
+        self.peers.remove(peer_id)
+    }
+
+    /// Penalizes the peer for the misbehaviour.
+    ///
+    /// # Returns
+    /// * `Option<f64>` - The new score of the peer (if it's known)
+    pub fn report_peer(&mut self, peer_id: &PeerId, action: PeerAction) -> Option<f64> {
+        self.peers.get_mut(peer_id).map(|info| {
+            info.apply_action(action);
+            info.score()
+        })
+    }
+
+    /// Decays the scores of all peers towards zero.
+    ///
+    /// # Returns
+    /// * `Vec<PeerId>` - The peers whose score recovered, so that their ban was lifted
+    pub fn decay_scores(&mut self, elapsed: Duration) -> Vec<PeerId> {
+        let mut unbanned = vec![];
+        for (peer_id, info) in self.peers.iter_mut() {
+            info.decay_score(elapsed);
+            if info.is_score_banned() && info.score() > MIN_SCORE_BEFORE_UNBAN {
+                info.set_score_banned(false);
+                unbanned.push(*peer_id);
+            }
+        }
+        unbanned
+    }
+
+    /// Connected peers sorted by their score (the lowest first).
+    pub fn connected_peers_by_score(&self) -> Vec<(PeerId, f64)> {
+        let mut peers = self
+            .peers
+            .iter()
+            .filter(|(_, info)| info.is_connected())
+            .map(|(peer_id, info)| (*peer_id, info.score()))
+            .collect::<Vec<_>>();
+        peers.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        peers
+    }
+
+    /// Records of the peers worth persisting (the peers that can be redialed and the banned ones).
+    ///
+    /// Only the bans through the admin API are persisted, the peers banned for their score are
+    /// banned again on restore if their score is still at the ban threshold.
+    pub fn records(&self) -> Vec<PeerRecord> {
+        self.peers
+            .iter()
+            .filter(|(_, info)| info.enr().is_some() || info.is_admin_banned())
+            .map(|(peer_id, info)| PeerRecord {
+                peer_id: peer_id.to_string(),
+                enr: info.enr().as_ref().map(|enr| enr.to_base64()),
+                last_seen: info.last_seen(),
+                score: info.score(),
+                banned: info.is_admin_banned(),
+                connected: info.is_connected(),
+            })
+            .collect()
+    }
+
+    /// Restores the persisted peers as disconnected.
+    ///
+    /// # Returns
+    /// * `Vec<Enr>` - ENRs of the restored peers that are not banned (to be redialed)
+    pub fn restore(&mut self, records: Vec<PeerRecord>) -> Vec<Enr> {
+        let mut enrs = vec![];
+        for record in records {
+            let Ok(peer_id) = PeerId::from_str(&record.peer_id) else {
+                warn!("Skipping persisted peer with invalid peer id {:?}", record.peer_id);
+                continue;
+            };
+            let enr = record.enr.as_deref().and_then(|enr| Enr::from_str(enr).ok());
+
+            let info = self.peers.entry(peer_id).or_default();
+            info.set_connection_status(PeerConnectionStatus::Disconnected);
+            info.set_enr(enr.clone());
+            info.set_last_seen(record.last_seen);
+            info.set_score(record.score);
+            info.set_banned(record.banned);
+            info.set_score_banned(record.score <= MIN_SCORE_BEFORE_BAN);
+
+            if !info.is_banned() {
+                enrs.extend(enr);
+            }
+        }
+        enrs
     }
 
     pub fn update_connection_state(&mut self, peer_id: &PeerId, new_state: NewConnectionState) {
@@ -67,7 +191,10 @@ impl PeerDB {
 
         match (info.connection_status().clone(), new_state) {
             (_current_state, NewConnectionState::Connected { enr, direction, seen_address }) => {
-                info.set_enr(enr);
+                // keep the enr the peer was dialed with
+                if enr.is_some() {
+                    info.set_enr(enr);
+                }
 
                 match direction {
                     ConnectionDirection::Incoming => info.connect_ingoing(seen_address),
@@ -82,6 +209,8 @@ impl PeerDB {
                 }
             }
             (_old_state, NewConnectionState::Disconnected) => {
+                // the status handshake is repeated on the next connection
+                info.set_status(None, false);
                 info.set_connection_status(PeerConnectionStatus::Disconnected)
             }
             (_old_state, NewConnectionState::Disconnecting) => {
@@ -137,3 +266,103 @@ pub enum NewConnectionState {
     Disconnecting,
     Dialing { enr: Option<Enr> },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        discovery::{enr::build_enr, enr_ext::EnrExt},
+    };
+    use discv5::enr::CombinedKey;
+    use silius_primitives::constants::p2p::SCORE_HALFLIFE;
+
+    #[test]
+    fn restores_persisted_peers() {
+        let enr = build_enr(&CombinedKey::generate_secp256k1(), &Config::default()).unwrap();
+        let peer_id = enr.peer_id();
+        let banned_peer_id = PeerId::random();
+        let unknown_peer_id = PeerId::random();
+
+        let mut peer_db = PeerDB::default();
+        peer_db.dialing_peer(&peer_id, Some(enr.clone()));
+        peer_db.connect_outgoing(&peer_id, Multiaddr::empty(), None);
+        peer_db.connect_ingoing(&unknown_peer_id, Multiaddr::empty(), None);
+        peer_db.ban(&banned_peer_id);
+        assert!(!peer_db.should_dial(&banned_peer_id));
+
+        // peers without enr can't be redialed, so they are not persisted
+        let mut records = peer_db.records();
+        records.sort_by_key(|r| r.banned);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].enr, Some(enr.to_base64()));
+        assert!(records[0].connected && records[0].last_seen > 0);
+        assert_eq!(records[1].peer_id, banned_peer_id.to_string());
+        assert!(records[1].banned);
+
+        let mut restored = PeerDB::default();
+        assert_eq!(restored.restore(records), vec![enr]);
+        assert!(restored.should_dial(&peer_id));
+        assert!(restored.is_banned(&banned_peer_id));
+
+        restored.unban(&banned_peer_id);
+        assert!(restored.should_dial(&banned_peer_id));
+        assert!(restored.remove(&banned_peer_id).is_some());
+        assert_eq!(restored.records().len(), 1);
+    }
+
+    #[test]
+    fn sorts_connected_peers_by_score() {
+        let peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+        let mut peer_db = PeerDB::default();
+        for peer_id in peers.iter() {
+            peer_db.connect_ingoing(peer_id, Multiaddr::empty(), None);
+        }
+        assert_eq!(peer_db.report_peer(&peers[1], PeerAction::LowToleranceError), Some(-10.0));
+        assert_eq!(peer_db.report_peer(&peers[2], PeerAction::HighToleranceError), Some(-1.0));
+        assert_eq!(peer_db.report_peer(&PeerId::random(), PeerAction::LowToleranceError), None);
+        peer_db.inject_disconnect(&peers[0]);
+
+        assert_eq!(peer_db.connected_peers_by_score(), vec![(peers[1], -10.0), (peers[2], -1.0)]);
+
+        peer_db.decay_scores(Duration::from_secs(SCORE_HALFLIFE));
+        assert_eq!(peer_db.connected_peers_by_score(), vec![(peers[1], -5.0), (peers[2], -0.5)]);
+    }
+
+    #[test]
+    fn lifts_score_bans_once_score_recovers() {
+        let enr = build_enr(&CombinedKey::generate_secp256k1(), &Config::default()).unwrap();
+        let peer_id = enr.peer_id();
+
+        let mut peer_db = PeerDB::default();
+        peer_db.dialing_peer(&peer_id, Some(enr.clone()));
+        peer_db.connect_outgoing(&peer_id, Multiaddr::empty(), None);
+        for _ in 0..5 {
+            peer_db.report_peer(&peer_id, PeerAction::LowToleranceError);
+        }
+        peer_db.ban_for_score(&peer_id);
+        assert!(peer_db.is_banned(&peer_id));
+
+        // the score ban is not persisted as a permanent ban, but it's restored until the score
+        // recovers
+        let records = peer_db.records();
+        assert!(!records[0].banned);
+        let mut restored = PeerDB::default();
+        assert!(restored.restore(records).is_empty());
+        assert!(restored.is_banned(&peer_id));
+
+        // -50 -> -25
+        assert!(peer_db.decay_scores(Duration::from_secs(SCORE_HALFLIFE)).is_empty());
+        assert!(peer_db.is_banned(&peer_id));
+        // -25 -> -12.5
+        assert_eq!(peer_db.decay_scores(Duration::from_secs(SCORE_HALFLIFE)), vec![peer_id]);
+        assert!(!peer_db.is_banned(&peer_id));
+
+        // admin bans stay
+        peer_db.ban(&peer_id);
+        peer_db.decay_scores(Duration::from_secs(SCORE_HALFLIFE));
+        assert!(peer_db.is_banned(&peer_id));
+        assert!(peer_db.records()[0].banned);
+    }
+}
@@ -1,5 +1,9 @@
+use ethers::types::{H256, U256};
 use silius_primitives::{
-    constants::p2p::{MAX_IPFS_CID_LENGTH, MAX_OPS_PER_REQUEST, MAX_SUPPORTED_MEMPOOLS},
+    constants::p2p::{
+        FAR_BEHIND_BLOCKS, FINALITY_DEPTH, MAX_IPFS_CID_LENGTH, MAX_OPS_PER_REQUEST,
+        MAX_SUPPORTED_MEMPOOLS,
+    },
     VerifiedUserOperation,
 };
 use ssz_rs::{List, Serialize, Vector};
@@ -13,6 +17,13 @@ pub struct MetaData {
     pub supported_mempools: List<Vector<u8, MAX_IPFS_CID_LENGTH>, MAX_SUPPORTED_MEMPOOLS>,
 }
 
+impl MetaData {
+    /// Whether the peer supports any of our mempools.
+    pub fn shares_mempool(&self, other: &MetaData) -> bool {
+        self.supported_mempools.iter().any(|mempool| other.supported_mempools.contains(mempool))
+    }
+}
+
 #[derive(ssz_rs_derive::Serializable, Clone, Debug, PartialEq, Default)]
 pub struct StatusMessage {
     chain_id: u64,
@@ -20,6 +31,58 @@ pub struct StatusMessage {
     block_number: u64,
 }
 
+impl StatusMessage {
+    pub fn new(chain_id: u64, block_hash: H256, block_number: u64) -> Self {
+        Self { chain_id, block_hash: block_hash.to_fixed_bytes(), block_number }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn block_hash(&self) -> H256 {
+        H256::from(self.block_hash)
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// Why the peer with the status is irrelevant to us (on another chain or fork).
+    ///
+    /// The head of the peer is only compared with our block at the same height if that block is
+    /// final (at least [FINALITY_DEPTH] blocks behind our head), the recent blocks may still be
+    /// reorganized.
+    ///
+    /// # Arguments
+    /// * `remote` - The status of the peer
+    /// * `local_block_hash` - The hash of our block at the height of the head of the peer (if
+    ///   known)
+    pub fn irrelevance(
+        &self,
+        remote: &StatusMessage,
+        local_block_hash: Option<H256>,
+    ) -> Option<String> {
+        if self.chain_id != remote.chain_id {
+            return Some(format!("chain id {} doesn't match {}", remote.chain_id, self.chain_id));
+        }
+        let is_final = remote.block_number.saturating_add(FINALITY_DEPTH) <= self.block_number;
+        match local_block_hash {
+            Some(hash) if is_final && hash != remote.block_hash() => Some(format!(
+                "block hash {:?} doesn't match {hash:?} at block {}",
+                remote.block_hash(),
+                remote.block_number
+            )),
+            _ => None,
+        }
+    }
+
+    /// Whether the peer with the status is more than [FAR_BEHIND_BLOCKS] blocks behind us.
+    pub fn is_far_behind(&self, remote: &StatusMessage) -> bool {
+        self.block_number.saturating_sub(remote.block_number) > FAR_BEHIND_BLOCKS
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub enum GoodbyeReason {
     #[default]
@@ -85,16 +148,49 @@ impl Ping {
     }
 }
 
+/// Request of a page of the user operation hashes in the peer's mempool.
 #[derive(ssz_rs_derive::Serializable, Clone, Debug, PartialEq, Default)]
 pub struct PooledUserOpHashesRequest {
     cursor: [u8; 32],
 }
 
+impl PooledUserOpHashesRequest {
+    /// Creates a request of the page starting at the offset (the cursor of the previous page).
+    pub fn new(offset: u64) -> Self {
+        let mut cursor = [0; 32];
+        U256::from(offset).to_big_endian(&mut cursor);
+        Self { cursor }
+    }
+
+    /// The offset of the requested page.
+    pub fn offset(&self) -> u64 {
+        U256::from_big_endian(&self.cursor).low_u64()
+    }
+}
+
+/// Request of the user operations with the given hashes from the peer's mempool.
 #[derive(ssz_rs_derive::Serializable, Clone, Debug, PartialEq, Default)]
 pub struct PooledUserOpsByHashRequest {
     hashes: List<Vector<u8, 32>, MAX_OPS_PER_REQUEST>,
 }
 
+impl PooledUserOpsByHashRequest {
+    /// Creates a request of the user operations (at most `MAX_OPS_PER_REQUEST`).
+    pub fn new(hashes: Vec<H256>) -> Self {
+        let hashes = hashes
+            .into_iter()
+            .take(MAX_OPS_PER_REQUEST)
+            .map(|h| Vector::try_from(h.as_bytes().to_vec()).expect("hash is 32 bytes"))
+            .collect::<Vec<_>>();
+        Self { hashes: List::try_from(hashes).expect("number of hashes is limited") }
+    }
+
+    /// The hashes of the requested user operations.
+    pub fn hashes(&self) -> Vec<H256> {
+        self.hashes.iter().map(|h| H256::from_slice(h)).collect()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct MetaDataRequest;
 
@@ -135,17 +231,63 @@ pub enum RPCResponse {
     PooledUserOpsByHash(PooledUserOpsByHashResponse),
 }
 
+/// Page of the user operation hashes in the mempool.
 #[derive(ssz_rs_derive::Serializable, Clone, Debug, PartialEq, Default)]
 pub struct PooledUserOpHashesResponse {
     hashes: List<[u8; 32], MAX_OPS_PER_REQUEST>,
     next_cursor: [u8; 32],
 }
 
+impl PooledUserOpHashesResponse {
+    /// Creates the page of the hashes starting at the offset. The next cursor is zero if it's
+    /// the last page.
+    pub fn page(hashes: &[H256], offset: u64) -> Self {
+        let start = (offset as usize).min(hashes.len());
+        let end = (start + MAX_OPS_PER_REQUEST).min(hashes.len());
+        let page = hashes[start..end].iter().map(|h| h.to_fixed_bytes()).collect::<Vec<_>>();
+
+        let mut next_cursor = [0; 32];
+        if end < hashes.len() {
+            U256::from(end).to_big_endian(&mut next_cursor);
+        }
+
+        Self { hashes: List::try_from(page).expect("page size is limited"), next_cursor }
+    }
+
+    /// The hashes of the page.
+    pub fn hashes(&self) -> Vec<H256> {
+        self.hashes.iter().map(H256::from).collect()
+    }
+
+    /// The offset of the next page (none if it's the last page).
+    pub fn next_offset(&self) -> Option<u64> {
+        let next = U256::from_big_endian(&self.next_cursor);
+        (!next.is_zero()).then(|| next.low_u64())
+    }
+}
+
+/// User operations from the mempool (with the entry point and the block they were verified at).
 #[derive(ssz_rs_derive::Serializable, Clone, Debug, PartialEq, Default)]
 pub struct PooledUserOpsByHashResponse {
     hashes: List<VerifiedUserOperation, MAX_OPS_PER_REQUEST>,
 }
 
+impl PooledUserOpsByHashResponse {
+    /// Creates the response (at most `MAX_OPS_PER_REQUEST` user operations).
+    pub fn new(user_operations: Vec<VerifiedUserOperation>) -> Self {
+        let user_operations =
+            user_operations.into_iter().take(MAX_OPS_PER_REQUEST).collect::<Vec<_>>();
+        Self {
+            hashes: List::try_from(user_operations).expect("number of user operations is limited"),
+        }
+    }
+
+    /// The user operations of the response.
+    pub fn user_operations(&self) -> Vec<VerifiedUserOperation> {
+        self.hashes.iter().cloned().collect()
+    }
+}
+
 impl RPCResponse {
     pub fn serialize(self) -> Result<Vec<u8>, ssz_rs::SerializeError> {
         let mut buffer = Vec::new();
@@ -167,10 +309,38 @@ impl RPCResponse {
 
 #[cfg(test)]
 mod tests {
-    use super::Ping;
-    use ssz_rs::Serialize;
+    use super::{
+        Ping, PooledUserOpHashesRequest, PooledUserOpHashesResponse, PooledUserOpsByHashRequest,
+        StatusMessage,
+    };
+    use ethers::types::H256;
+    use silius_primitives::constants::p2p::MAX_OPS_PER_REQUEST;
+    use ssz_rs::{Deserialize, Serialize};
     use std::io::Write;
 
+    #[test]
+    fn pooled_user_op_hashes_paging() {
+        let hashes =
+            (1..=(MAX_OPS_PER_REQUEST + 10) as u64).map(H256::from_low_u64_be).collect::<Vec<_>>();
+
+        let req = PooledUserOpHashesRequest::new(0);
+        let first = PooledUserOpHashesResponse::page(&hashes, req.offset());
+        assert_eq!(first.hashes(), hashes[..MAX_OPS_PER_REQUEST].to_vec());
+        assert_eq!(first.next_offset(), Some(MAX_OPS_PER_REQUEST as u64));
+
+        let mut buffer = Vec::new();
+        PooledUserOpHashesRequest::new(first.next_offset().unwrap())
+            .serialize(&mut buffer)
+            .unwrap();
+        let req = PooledUserOpHashesRequest::deserialize(&buffer).unwrap();
+        let last = PooledUserOpHashesResponse::page(&hashes, req.offset());
+        assert_eq!(last.hashes(), hashes[MAX_OPS_PER_REQUEST..].to_vec());
+        assert_eq!(last.next_offset(), None);
+
+        let req = PooledUserOpsByHashRequest::new(last.hashes());
+        assert_eq!(req.hashes(), hashes[MAX_OPS_PER_REQUEST..].to_vec());
+    }
+
     #[test]
     fn serialize() {
         let ping = Ping::new(1);
@@ -187,4 +357,39 @@ mod tests {
         let compress2 = wtr.into_inner().unwrap();
         assert_eq!(compress, compress2);
     }
+
+    #[test]
+    fn status_relevance() {
+        let local = StatusMessage::new(1, H256::repeat_byte(1), 100);
+
+        assert!(local
+            .irrelevance(&StatusMessage::new(1, H256::repeat_byte(2), 99), None)
+            .is_none());
+        assert!(local
+            .irrelevance(&StatusMessage::new(5, H256::repeat_byte(1), 100), None)
+            .is_some());
+        // the heads at the same height may differ (the recent blocks may be reorganized)
+        assert!(local
+            .irrelevance(
+                &StatusMessage::new(1, H256::repeat_byte(2), 100),
+                Some(local.block_hash())
+            )
+            .is_none());
+        assert!(StatusMessage::new(1, H256::zero(), 0)
+            .irrelevance(&StatusMessage::new(1, H256::repeat_byte(2), 0), None)
+            .is_none());
+
+        // the final blocks may not
+        let remote = StatusMessage::new(1, H256::repeat_byte(2), 36);
+        assert!(local.irrelevance(&remote, Some(H256::repeat_byte(3))).is_some());
+        assert!(local.irrelevance(&remote, Some(H256::repeat_byte(2))).is_none());
+        assert!(local.irrelevance(&remote, None).is_none());
+        assert!(local
+            .irrelevance(&StatusMessage::new(1, H256::repeat_byte(2), 37), Some(H256::zero()))
+            .is_none());
+
+        assert!(!local.is_far_behind(&StatusMessage::new(1, H256::zero(), 36)));
+        assert!(local.is_far_behind(&StatusMessage::new(1, H256::zero(), 35)));
+        assert!(!local.is_far_behind(&StatusMessage::new(1, H256::zero(), 200)));
+    }
 }
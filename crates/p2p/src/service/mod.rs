@@ -4,7 +4,9 @@ pub mod utils;
 
 use self::{
     behaviour::Behaviour,
-    utils::{load_enr_from_file, load_private_key_from_file, save_enr_to_file},
+    utils::{
+        load_enr_from_file, load_peers_from_file, load_private_key_from_file, save_enr_to_file,
+    },
 };
 use crate::{
     config::Config,
@@ -13,9 +15,11 @@ use crate::{
         enr_ext::{CombinedPublicKeyExt, EnrExt},
         DiscoveredPeers, Discovery,
     },
-    peer_manager::{PeerManager, PeerManagerEvent},
+    peer_manager::{peer::score::PeerAction, PeerManager, PeerManagerEvent},
     rpc::{
-        methods::{MetaData, MetaDataRequest, Ping, RPCResponse, RequestId},
+        methods::{
+            GoodbyeReason, MetaData, MetaDataRequest, Ping, RPCResponse, RequestId, StatusMessage,
+        },
         outbound::OutboundRequest,
         protocol::InboundRequest,
         RPCEvent, RPC,
@@ -27,10 +31,10 @@ use crate::{
         topics::topic,
     },
 };
-use discv5::Enr;
+pub use discv5::Enr;
 use ethers::types::{Address, U256};
 use futures::channel::{
-    mpsc::{UnboundedReceiver, UnboundedSender},
+    mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     oneshot::Sender,
 };
 use libp2p::{
@@ -40,11 +44,17 @@ use libp2p::{
     identity::{secp256k1, Keypair},
     noise,
     swarm::SwarmEvent,
-    Multiaddr, PeerId, Swarm, SwarmBuilder,
+    Multiaddr, Swarm, SwarmBuilder,
 };
+pub use libp2p::{gossipsub::MessageAcceptance, PeerId};
 use libp2p_mplex::{MaxBufferBehaviour, MplexConfig};
 use silius_primitives::{
-    constants::p2p::{FIND_NODE_QUERY_CLOSEST_PEERS, MAX_IPFS_CID_LENGTH, MAX_SUPPORTED_MEMPOOLS},
+    constants::p2p::{
+        FIND_NODE_QUERY_CLOSEST_PEERS, GRAYLIST_THRESHOLD, MAX_IPFS_CID_LENGTH,
+        MAX_SUPPORTED_MEMPOOLS,
+    },
+    entry_point::EntryPointVersion,
+    p2p::PeerRecord,
     UserOperation, VerifiedUserOperation,
 };
 use ssz_rs::{Deserialize, List, Serialize, Vector};
@@ -55,8 +65,55 @@ use std::{
 };
 use tracing::{debug, error, info, warn};
 
-pub type MempoolChannels =
-    Vec<(Address, UnboundedReceiver<(UserOperation, U256)>, UnboundedSender<UserOperation>)>; // entrypoint ...
+pub type MempoolChannels = Vec<(
+    Address,
+    EntryPointVersion,
+    Option<String>,
+    UnboundedReceiver<(UserOperation, U256)>,
+    UnboundedSender<(UserOperation, U256, UserOperationSource)>,
+)>; // entrypoint, entrypoint version, alternative mempool id (None for the canonical mempools) ...
+
+/// Source of a user operation received from the p2p network
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UserOperationSource {
+    /// Gossipsub message, its validation result is reported back to gossipsub
+    Gossip {
+        /// The peer that forwarded us the message.
+        peer_id: PeerId,
+        /// The gossipsub message id.
+        message_id: MessageId,
+    },
+    /// Response of the peer to the pooled user operations request
+    Sync(PeerId),
+}
+
+impl UserOperationSource {
+    /// The peer the user operation was received from
+    pub fn peer_id(&self) -> PeerId {
+        match self {
+            UserOperationSource::Gossip { peer_id, .. } | UserOperationSource::Sync(peer_id) => {
+                *peer_id
+            }
+        }
+    }
+}
+
+/// Validation result of a user operation received from the p2p network, with the misbehaviour the
+/// peer is penalized for (if any)
+pub type ValidationResult = (UserOperationSource, MessageAcceptance, Option<PeerAction>);
+
+/// Peer management command (issued through the admin API)
+#[derive(Debug)]
+pub enum PeerCommand {
+    /// Adds the peer (lifting its ban) and dials it.
+    AddPeer(Enr),
+    /// Disconnects the peer and forgets it.
+    RemovePeer(PeerId),
+    /// Bans the peer and disconnects it.
+    BanPeer(PeerId),
+    /// Returns the known peers.
+    Peers(Sender<Vec<PeerRecord>>),
+}
 
 #[derive(Debug)]
 pub enum NetworkEvent {
@@ -66,7 +123,7 @@ pub enum NetworkEvent {
     PeerConnectedIncoming(PeerId),
     /// A peer was disconnected.
     PeerDisconnected(PeerId),
-    /// A peer successfully connected with us.
+    /// A peer successfully connected with us (and passed the status handshake).
     PeerConnected(PeerId),
     /// Gossipsub message from the network
     PubsubMessage {
@@ -110,6 +167,10 @@ pub struct Network {
     swarm: Swarm<Behaviour>,
     network_globals: Arc<NetworkGlobals>,
     mempool_channels: MempoolChannels,
+    /// Validation results of the received user operations (reported by the mempools)
+    validation_results: (UnboundedSender<ValidationResult>, UnboundedReceiver<ValidationResult>),
+    /// Peer management commands
+    peer_commands: (UnboundedSender<PeerCommand>, UnboundedReceiver<PeerCommand>),
 }
 
 impl From<Network> for Swarm<Behaviour> {
@@ -153,7 +214,15 @@ impl Network {
 
         info!("Enr: {}", enr);
 
-        let canonical_mempools = config.chain_spec.canonical_mempools.clone();
+        // canonical mempools of the chain followed by the alternative mempools
+        let mut mempool_ids = config.chain_spec.canonical_mempools.clone();
+        for (_, _, alt_mempool, _, _) in mempool_channels.iter() {
+            if let Some(id) = alt_mempool {
+                if !mempool_ids.contains(id) {
+                    mempool_ids.push(id.clone());
+                }
+            }
+        }
         let trusted_peers = config.bootnodes.iter().map(|x| x.public_key().as_peer_id()).collect();
 
         let network_globals = {
@@ -162,13 +231,12 @@ impl Network {
                 MAX_SUPPORTED_MEMPOOLS,
             > = List::default();
 
-            for canonical_mempool in canonical_mempools.iter() {
-                let mut mempool_id = canonical_mempool.as_bytes().to_vec();
+            for mempool_id in mempool_ids.iter() {
+                let mut mempool_id = mempool_id.as_bytes().to_vec();
                 mempool_id.resize_with(MAX_IPFS_CID_LENGTH, Default::default);
 
                 supported_mempools.push(
-                    Vector::try_from(mempool_id)
-                        .expect("canonical mempool id should be equal to 256 bytes"),
+                    Vector::try_from(mempool_id).expect("mempool id should be equal to 256 bytes"),
                 );
             }
 
@@ -183,14 +251,22 @@ impl Network {
             ))
         };
 
-        let mut gossipsub = create_gossipsub(canonical_mempools).map_err(|e| eyre::anyhow!(e))?;
+        let mut gossipsub = create_gossipsub(mempool_ids).map_err(|e| eyre::anyhow!(e))?;
         for bootnode in &config.bootnodes {
             gossipsub.add_explicit_peer(&bootnode.peer_id());
         }
 
         let rpc = RPC::new();
 
-        let peer_manager = PeerManager::new(network_globals.clone());
+        let mut peer_manager = PeerManager::new(network_globals.clone(), config.peers_file.clone());
+
+        // redial the known peers
+        let known_peers =
+            network_globals.peers.write().restore(load_peers_from_file(&config.peers_file));
+        info!("Redialing {} known peers", known_peers.len());
+        for enr in known_peers {
+            peer_manager.dial_peer(enr);
+        }
 
         let mut discovery =
             Discovery::new(combined_key, config.clone(), network_globals.clone()).await?;
@@ -215,7 +291,13 @@ impl Network {
             .expect("building p2p behaviour failed")
             .build();
 
-        let mut network = Network { swarm, network_globals, mempool_channels };
+        let mut network = Network {
+            swarm,
+            network_globals,
+            mempool_channels,
+            validation_results: unbounded(),
+            peer_commands: unbounded(),
+        };
 
         network.start(&config).await?;
 
@@ -250,31 +332,82 @@ impl Network {
     }
 
     /// handle gossipsub event
-    fn handle_gossipsub_event(&self, event: Box<gossipsub::Event>) -> Option<NetworkEvent> {
+    ///
+    /// Messages that can't be deserialized are rejected and messages without a matching mempool
+    /// (entry point and topic) are ignored, the other messages are validated by the mempools.
+    fn handle_gossipsub_event(&mut self, event: Box<gossipsub::Event>) -> Option<NetworkEvent> {
         match *event {
             gossipsub::Event::Message { propagation_source, message_id, message } => {
+                // the peers that haven't exchanged their status yet may be on another network
+                let handshaked = self
+                    .network_globals
+                    .peers
+                    .read()
+                    .peer_info(&propagation_source)
+                    .is_some_and(|info| info.status().is_some());
+                if !handshaked {
+                    debug!("Dropping gossip from peer {propagation_source:?} before the handshake");
+                    self.report_validation_result(
+                        UserOperationSource::Gossip { peer_id: propagation_source, message_id },
+                        MessageAcceptance::Ignore,
+                    );
+                    return None;
+                }
                 let user_op = match VerifiedUserOperation::deserialize(message.data.as_ref()) {
                     Ok(user_op) => user_op,
                     Err(e) => {
                         debug!("Failed to deserialize user operations: {:?}", e);
+                        self.report_validation_result(
+                            UserOperationSource::Gossip { peer_id: propagation_source, message_id },
+                            MessageAcceptance::Reject,
+                        );
                         return None;
                     }
                 };
-                self.mempool_channels.iter().find_map(|(ep, _, new_coming_uos_ch)| {
-                    if *ep == user_op.entry_point() {
-                        let uo = user_op.clone().user_operation();
-                        new_coming_uos_ch
-                            .unbounded_send(UserOperation::from_user_operation_signed(
-                                uo.hash(ep, self.network_globals.chain_spec().chain.id()),
-                                uo,
-                            ))
-                            .expect("new user operation channel should be open all the time");
-                        Some(())
-                    } else {
-                        warn!("Received unsupported entrypoint user operations {ep:?} from p2p");
-                        None
-                    }
+                // user operations of an alternative mempool go to its own channel
+                let canonical_mempools = self.network_globals.chain_spec().canonical_mempools;
+                let channel = self.mempool_channels.iter().find(|(ep, _, alt_mempool, _, _)| {
+                    *ep == user_op.entry_point() &&
+                        match alt_mempool {
+                            Some(id) => message.topic == topic(id).hash(),
+                            None => canonical_mempools
+                                .iter()
+                                .any(|id| message.topic == topic(id).hash()),
+                        }
                 });
+                if let Some((ep, version, _, _, new_coming_uos_ch)) = channel {
+                    let uo = user_op.clone().user_operation();
+                    new_coming_uos_ch
+                        .unbounded_send((
+                            UserOperation::from_user_operation_signed(
+                                uo.hash_with_version(
+                                    ep,
+                                    self.network_globals.chain_spec().chain.id(),
+                                    *version,
+                                ),
+                                uo,
+                            ),
+                            user_op.verified_at_block_hash(),
+                            UserOperationSource::Gossip {
+                                peer_id: propagation_source,
+                                message_id: message_id.clone(),
+                            },
+                        ))
+                        .expect("new user operation channel should be open all the time");
+                } else {
+                    warn!(
+                        "Received unsupported entrypoint user operations {:?} on topic {:?} from p2p",
+                        user_op.entry_point(),
+                        message.topic
+                    );
+                    self.report_validation_result(
+                        UserOperationSource::Gossip {
+                            peer_id: propagation_source,
+                            message_id: message_id.clone(),
+                        },
+                        MessageAcceptance::Ignore,
+                    );
+                }
                 let message = PubsubMessage::UserOperation(user_op);
 
                 Some(NetworkEvent::PubsubMessage {
@@ -294,6 +427,98 @@ impl Network {
         }
     }
 
+    /// Reports the validation result of the user operation to gossipsub
+    ///
+    /// Invalid user operations lower the gossipsub score of the peer, the peer is disconnected once
+    /// it's graylisted.
+    fn report_validation_result(
+        &mut self,
+        source: UserOperationSource,
+        acceptance: MessageAcceptance,
+    ) {
+        match source {
+            UserOperationSource::Gossip { peer_id, message_id } => {
+                let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+                if let Err(e) =
+                    gossipsub.report_message_validation_result(&message_id, &peer_id, acceptance)
+                {
+                    debug!("Failed to report validation result of message {message_id:?}: {e:?}");
+                }
+
+                if let Some(score) = gossipsub.peer_score(&peer_id) {
+                    if score < GRAYLIST_THRESHOLD {
+                        warn!("Peer {peer_id:?} is graylisted with score {score}, disconnecting");
+                        self.swarm
+                            .behaviour_mut()
+                            .peer_manager
+                            .disconnect_peer(peer_id, GoodbyeReason::Error);
+                    }
+                }
+            }
+            UserOperationSource::Sync(peer_id) => {
+                if matches!(acceptance, MessageAcceptance::Reject) {
+                    debug!("Peer {peer_id:?} sent an invalid pooled user operation");
+                }
+            }
+        }
+    }
+
+    /// Handles the peer management command
+    fn handle_peer_command(&mut self, command: PeerCommand) {
+        let peer_manager = &mut self.swarm.behaviour_mut().peer_manager;
+        match command {
+            PeerCommand::AddPeer(enr) => {
+                info!("Adding peer {:?}", enr.peer_id());
+                peer_manager.add_peer(enr);
+            }
+            PeerCommand::RemovePeer(peer_id) => {
+                info!("Removing peer {peer_id:?}");
+                peer_manager.remove_peer(peer_id);
+            }
+            PeerCommand::BanPeer(peer_id) => {
+                info!("Banning peer {peer_id:?}");
+                peer_manager.ban_peer(peer_id);
+            }
+            PeerCommand::Peers(sender) => {
+                let _ = sender.send(self.network_globals.peers.read().records());
+            }
+        }
+    }
+
+    /// Handles the status of the peer (received as a request or a response)
+    ///
+    /// Peers on another chain (or fork) are disconnected, peers far behind us are flagged. The
+    /// peer is reported as connected once its first status is accepted (peers that don't exchange
+    /// their status in time are disconnected by the peer manager).
+    fn on_status(&mut self, peer_id: PeerId, status: StatusMessage) -> Option<NetworkEvent> {
+        let local_status = self.network_globals.local_status();
+        let local_block_hash = self.network_globals.block_hash_at(status.block_number());
+        if let Some(reason) = local_status.irrelevance(&status, local_block_hash) {
+            debug!("Peer {peer_id:?} is on an irrelevant network ({reason}), disconnecting");
+            self.swarm
+                .behaviour_mut()
+                .peer_manager
+                .disconnect_peer(peer_id, GoodbyeReason::IrrelevantNetwork);
+            return None;
+        }
+
+        let far_behind = local_status.is_far_behind(&status);
+        if far_behind {
+            warn!(
+                "Peer {peer_id:?} is far behind at block {} (local head {})",
+                status.block_number(),
+                local_status.block_number()
+            );
+        }
+
+        self.swarm.behaviour_mut().peer_manager.handshake_completed(&peer_id);
+        let mut peers = self.network_globals.peers.write();
+        let info = peers.peer_info_mut(&peer_id)?;
+        let handshake = info.status().is_none();
+        info.set_status(Some(status), far_behind);
+        handshake.then_some(NetworkEvent::PeerConnected(peer_id))
+    }
+
     /// handle reqrep event
     fn handle_rpc_event(&mut self, event: RPCEvent) -> Option<NetworkEvent> {
         match event {
@@ -311,20 +536,44 @@ impl Network {
                         .expect("channel should exist");
                     None
                 }
+                InboundRequest::Status(status) => {
+                    sender
+                        .send(RPCResponse::Status(self.network_globals.local_status()))
+                        .expect("channel should exist");
+                    self.on_status(peer_id, status)
+                }
                 InboundRequest::Goodbye(_) => None,
                 _ => Some(NetworkEvent::RequestMessage { peer_id, request, sender }),
             },
             RPCEvent::Response { peer_id, response, .. } => match response {
+                RPCResponse::Status(status) => self.on_status(peer_id, status),
                 RPCResponse::Pong(ping) => {
                     self.swarm.behaviour_mut().peer_manager.pong_response(&peer_id, ping.data);
                     None
                 }
                 RPCResponse::MetaData(metadata) => {
+                    if !self.metadata().shares_mempool(&metadata) {
+                        debug!(
+                            "Peer {peer_id:?} doesn't support any of our mempools, disconnecting"
+                        );
+                        self.swarm
+                            .behaviour_mut()
+                            .peer_manager
+                            .disconnect_peer(peer_id, GoodbyeReason::IrrelevantNetwork);
+                    }
                     self.swarm.behaviour_mut().peer_manager.metadata_response(&peer_id, metadata);
                     None
                 }
                 _ => Some(NetworkEvent::ResponseMessage { peer_id, response }),
             },
+            RPCEvent::InboundFailure { peer_id, err, .. } => {
+                self.swarm.behaviour_mut().peer_manager.handle_inbound_failure(&peer_id, &err);
+                None
+            }
+            RPCEvent::OutboundFailure { peer_id, err, .. } => {
+                self.swarm.behaviour_mut().peer_manager.handle_outbound_failure(&peer_id, &err);
+                None
+            }
             _ => None,
         }
     }
@@ -339,9 +588,17 @@ impl Network {
     fn handler_peer_manager_event(&mut self, event: PeerManagerEvent) -> Option<NetworkEvent> {
         match event {
             PeerManagerEvent::PeerConnectedIncoming(peer_id) => {
+                self.send_request(
+                    &peer_id,
+                    OutboundRequest::Status(self.network_globals.local_status()),
+                );
                 Some(NetworkEvent::PeerConnectedIncoming(peer_id))
             }
             PeerManagerEvent::PeerConnectedOutgoing(peer_id) => {
+                self.send_request(
+                    &peer_id,
+                    OutboundRequest::Status(self.network_globals.local_status()),
+                );
                 Some(NetworkEvent::PeerConnectedOutgoing(peer_id))
             }
             PeerManagerEvent::PeerDisconnected(peer_id) => {
@@ -362,26 +619,55 @@ impl Network {
                 self.send_request(&peer_id, OutboundRequest::MetaData(MetaDataRequest));
                 None
             }
+            PeerManagerEvent::DisconnectPeer(peer_id, reason) => {
+                // the goodbye is sent on a best-effort basis
+                self.send_request(&peer_id, OutboundRequest::Goodbye(reason));
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+                None
+            }
             _ => None,
         }
     }
 
     pub fn poll_network(&mut self, cx: &mut Context) -> Poll<NetworkEvent> {
-        let mut uos_publish: Vec<VerifiedUserOperation> = Vec::new();
+        let mut uos_publish: Vec<(VerifiedUserOperation, Option<String>)> = Vec::new();
 
-        for (ep, waiting_to_publish_ch, _) in self.mempool_channels.iter_mut() {
+        for (ep, _, alt_mempool, waiting_to_publish_ch, _) in self.mempool_channels.iter_mut() {
             while let Ok(Some((user_op, verified_at_block_hash))) = waiting_to_publish_ch.try_next()
             {
                 info!("Got user operation {user_op:?} from ep {ep:?} verified in {verified_at_block_hash:?} to publish to p2p network!");
                 let user_op =
                     VerifiedUserOperation::new(user_op.user_operation, *ep, verified_at_block_hash);
-                uos_publish.push(user_op);
+                uos_publish.push((user_op, alt_mempool.clone()));
+            }
+        }
+
+        // polling registers the waker, so the validation results and the commands are handled as
+        // soon as they are sent
+        while let Poll::Ready(Some((source, acceptance, action))) =
+            self.validation_results.1.poll_next_unpin(cx)
+        {
+            if let Some(action) = action {
+                self.swarm.behaviour_mut().peer_manager.report_peer(
+                    &source.peer_id(),
+                    action,
+                    "relayed user operation",
+                );
             }
+            self.report_validation_result(source, acceptance);
+        }
+
+        while let Poll::Ready(Some(command)) = self.peer_commands.1.poll_next_unpin(cx) {
+            self.handle_peer_command(command);
         }
 
-        for uo in uos_publish.into_iter() {
-            for canonical_mempool in self.network_globals.chain_spec().canonical_mempools.iter() {
-                let topic_hash: TopicHash = topic(canonical_mempool).into();
+        for (uo, alt_mempool) in uos_publish.into_iter() {
+            let mempool_ids = match alt_mempool {
+                Some(id) => vec![id],
+                None => self.network_globals.chain_spec().canonical_mempools.clone(),
+            };
+            for mempool_id in mempool_ids.iter() {
+                let topic_hash: TopicHash = topic(mempool_id).into();
                 match self.publish(uo.clone(), topic_hash) {
                     Ok(_) => {}
                     Err(err) => match err {
@@ -429,6 +715,21 @@ impl Network {
         futures::future::poll_fn(|cx| self.poll_network(cx)).await
     }
 
+    /// Returns the sender of the validation results of the received user operations.
+    pub fn validation_result_sender(&self) -> UnboundedSender<ValidationResult> {
+        self.validation_results.0.clone()
+    }
+
+    /// Returns the network globals (shared with the other components).
+    pub fn network_globals(&self) -> Arc<NetworkGlobals> {
+        self.network_globals.clone()
+    }
+
+    /// Returns the sender of the peer management commands.
+    pub fn peer_command_sender(&self) -> UnboundedSender<PeerCommand> {
+        self.peer_commands.0.clone()
+    }
+
     pub fn local_peer_id(&self) -> &PeerId {
         self.swarm.local_peer_id()
     }
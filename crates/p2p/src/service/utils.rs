@@ -1,6 +1,8 @@
 use discv5::Enr;
 use libp2p::identity::Keypair;
+use silius_primitives::p2p::PeerRecord;
 use std::{os::unix::fs::PermissionsExt, path::PathBuf, str::FromStr};
+use tracing::warn;
 
 /// Load ENR from file
 pub fn load_enr_from_file(path: &PathBuf) -> Option<Enr> {
@@ -43,3 +45,56 @@ pub fn save_private_key_to_file(key: &Keypair, path: &PathBuf) {
     std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
         .expect("Setting key file permission failed");
 }
+
+/// Load known peers from file (a corrupted file is skipped)
+pub fn load_peers_from_file(path: &PathBuf) -> Vec<PeerRecord> {
+    if !path.exists() {
+        return vec![];
+    }
+
+    match std::fs::read(path).map_err(eyre::Error::from).and_then(|content| {
+        serde_json::from_slice::<Vec<PeerRecord>>(&content).map_err(eyre::Error::from)
+    }) {
+        Ok(peers) => peers,
+        Err(e) => {
+            warn!("Failed to load known peers from {path:?}: {e:?}");
+            vec![]
+        }
+    }
+}
+
+/// Save known peers to file
+pub fn save_peers_to_file(peers: &[PeerRecord], path: &PathBuf) -> eyre::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(peers)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn known_peers_round_trip() {
+        let dir = TempDir::new("peers").unwrap();
+        let path = dir.path().join("p2p/peers.json");
+        assert!(load_peers_from_file(&path).is_empty());
+
+        let peers = vec![PeerRecord {
+            peer_id: "16Uiu2HAm7yqxVZTLnz1wAAJgVeqzVULzLaggo5bYMnp6vCrXwr2V".into(),
+            enr: None,
+            last_seen: 1700000000,
+            score: -10.0,
+            banned: true,
+            connected: false,
+        }];
+        save_peers_to_file(&peers, &path).unwrap();
+        assert_eq!(load_peers_from_file(&path), peers);
+
+        std::fs::write(&path, "corrupted").unwrap();
+        assert!(load_peers_from_file(&path).is_empty());
+    }
+}
@@ -1,8 +1,14 @@
-use crate::{discovery::enr_ext::EnrExt, peer_manager::peerdb::PeerDB, rpc::methods::MetaData};
+use crate::{
+    discovery::enr_ext::EnrExt,
+    peer_manager::peerdb::PeerDB,
+    rpc::methods::{MetaData, StatusMessage},
+};
 use discv5::Enr;
+use ethers::types::H256;
 use libp2p::{Multiaddr, PeerId};
 use parking_lot::RwLock;
-use silius_primitives::chain::ChainSpec;
+use silius_primitives::{chain::ChainSpec, constants::p2p::FINALITY_DEPTH};
+use std::collections::BTreeMap;
 
 pub struct NetworkGlobals {
     /// The local ENR of the node.
@@ -17,6 +23,10 @@ pub struct NetworkGlobals {
     pub local_metadata: RwLock<MetaData>,
     /// Chain information.
     pub chain_spec: RwLock<ChainSpec>,
+    /// The local status of the node (exchanged with the peers on connection).
+    pub local_status: RwLock<StatusMessage>,
+    /// Hashes of the recent local blocks (by number), to compare with the heads of the peers.
+    pub recent_blocks: RwLock<BTreeMap<u64, H256>>,
 }
 
 impl NetworkGlobals {
@@ -35,6 +45,8 @@ impl NetworkGlobals {
             listen_multiaddrs: RwLock::new(multiaddrs),
             peers: RwLock::new(PeerDB::new(trusted_peers)),
             local_metadata: RwLock::new(local_metadata),
+            local_status: RwLock::new(StatusMessage::new(chain_spec.chain.id(), H256::zero(), 0)),
+            recent_blocks: RwLock::new(BTreeMap::new()),
             chain_spec: RwLock::new(chain_spec),
         }
     }
@@ -55,6 +67,28 @@ impl NetworkGlobals {
         self.local_metadata.read().clone()
     }
 
+    pub fn local_status(&self) -> StatusMessage {
+        self.local_status.read().clone()
+    }
+
+    /// Updates the head block of the local status.
+    pub fn set_head(&self, block_hash: H256, block_number: u64) {
+        let chain_id = self.local_status.read().chain_id();
+        *self.local_status.write() = StatusMessage::new(chain_id, block_hash, block_number);
+
+        // the blocks above the new head were reorganized away
+        let mut recent_blocks = self.recent_blocks.write();
+        recent_blocks.split_off(&block_number);
+        recent_blocks.insert(block_number, block_hash);
+        let oldest = block_number.saturating_sub(2 * FINALITY_DEPTH);
+        *recent_blocks = recent_blocks.split_off(&oldest);
+    }
+
+    /// Returns the hash of the recent local block.
+    pub fn block_hash_at(&self, block_number: u64) -> Option<H256> {
+        self.recent_blocks.read().get(&block_number).copied()
+    }
+
     pub fn chain_spec(&self) -> ChainSpec {
         self.chain_spec.read().clone()
     }
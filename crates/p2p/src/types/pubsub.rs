@@ -1,5 +1,8 @@
 use super::topics::{create_whitelist_filter, topic};
-use crate::{config::gossipsub_config, service::behaviour::Gossipsub};
+use crate::{
+    config::{gossipsub_config, gossipsub_peer_score_params, gossipsub_peer_score_thresholds},
+    service::behaviour::Gossipsub,
+};
 use libp2p::gossipsub::{DataTransform, Message, MessageAuthenticity, RawMessage, TopicHash};
 use silius_primitives::{constants::p2p::GOSSIP_MAX_SIZE, VerifiedUserOperation};
 use snap::raw::{decompress_len, Decoder, Encoder};
@@ -83,6 +86,10 @@ pub fn create_gossipsub(mempool_ids: Vec<String>) -> Result<Gossipsub, String> {
         filter,
         snappy_transform,
     )?;
+    gossipsub.with_peer_score(
+        gossipsub_peer_score_params(&mempool_ids),
+        gossipsub_peer_score_thresholds(),
+    )?;
 
     for mempool_id in mempool_ids {
         let _ = gossipsub.subscribe(&topic(&mempool_id)).map_err(|_| "subscribe error")?;
@@ -34,6 +34,7 @@ async fn build_p2p_instance(bootnode: Option<Enr>) -> eyre::Result<Network> {
     let dir = TempDir::new("test-silius-p2p").unwrap();
     let node_key_file = dir.path().join("node_key");
     let node_enr_file = dir.path().join("node_enr");
+    let peers_file = dir.path().join("peers.json");
 
     let available_port = get_available_port().unwrap();
     let listen_addr = ListenAddress::V4(ListenAddr {
@@ -47,6 +48,7 @@ async fn build_p2p_instance(bootnode: Option<Enr>) -> eyre::Result<Network> {
     let config = Config {
         node_key_file,
         node_enr_file,
+        peers_file,
         listen_addr: listen_addr.clone(),
         ipv4_addr: Some(Ipv4Addr::LOCALHOST),
         ipv6_addr: None,
@@ -64,7 +66,8 @@ async fn build_p2p_instance(bootnode: Option<Enr>) -> eyre::Result<Network> {
     let (_, rv) = unbounded();
     let (sd, _) = unbounded();
 
-    let network = Network::new(config, vec![(Default::default(), rv, sd)]).await?;
+    let network =
+        Network::new(config, vec![(Default::default(), Default::default(), None, rv, sd)]).await?;
 
     Ok(network)
 }
@@ -99,3 +102,36 @@ pub async fn build_connnected_p2p_pair() -> eyre::Result<(Network, Network)> {
 
     Ok((peer1, peer2))
 }
+
+/// Runs the two connected nodes until both of them completed the status handshake with the other
+/// one (gossip received before the handshake is dropped)
+pub async fn handshake_p2p_pair(peer1: &mut Network, peer2: &mut Network) {
+    let peer1_id = *peer1.local_peer_id();
+    let peer2_id = *peer2.local_peer_id();
+
+    let peer1_fut = async {
+        loop {
+            if let NetworkEvent::PeerConnected(peer_id) = peer1.next_event().await {
+                if peer_id == peer2_id {
+                    return;
+                }
+            }
+        }
+    };
+    let peer2_fut = async {
+        loop {
+            if let NetworkEvent::PeerConnected(peer_id) = peer2.next_event().await {
+                if peer_id == peer1_id {
+                    return;
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = futures::future::join(peer1_fut, peer2_fut) => {}
+        _ = tokio::time::sleep(Duration::from_secs(20)) => {
+            panic!("Handshake timed out");
+        }
+    }
+}
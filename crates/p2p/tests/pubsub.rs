@@ -1,6 +1,6 @@
 mod common;
 
-use crate::common::build_connnected_p2p_pair;
+use crate::common::{build_connnected_p2p_pair, handshake_p2p_pair};
 use ethers::types::H160;
 use silius_p2p::{
     service::NetworkEvent,
@@ -13,7 +13,9 @@ use std::{str::FromStr, time::Duration};
 async fn pubsub_msg() -> eyre::Result<()> {
     let chain_spec = ChainSpec::dev();
     let (mut peer1, mut peer2) = build_connnected_p2p_pair().await?;
+    handshake_p2p_pair(&mut peer1, &mut peer2).await;
 
+    // subscribe only after the handshake, so the publishing on subscription isn't dropped
     let mempool_id = chain_spec.canonical_mempools.first().unwrap();
     let res1 = peer1.subscribe(&mempool_id)?;
     let res2 = peer2.subscribe(&mempool_id)?;
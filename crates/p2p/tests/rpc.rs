@@ -1,13 +1,15 @@
 mod common;
 
-use crate::common::build_connnected_p2p_pair;
+use crate::common::{build_connnected_p2p_pair, handshake_p2p_pair};
+use ethers::types::H256;
 use silius_p2p::{
     rpc::{
         methods::{RPCResponse, StatusMessage},
         outbound::OutboundRequest,
     },
-    service::NetworkEvent,
+    service::{Network, NetworkEvent},
 };
+use silius_primitives::constants::p2p::FINALITY_DEPTH;
 use std::time::Duration;
 
 async fn rpc_case(request_case: OutboundRequest, response_case: RPCResponse) -> eyre::Result<()> {
@@ -73,13 +75,76 @@ async fn rpc_case(request_case: OutboundRequest, response_case: RPCResponse) ->
     Ok(())
 }
 
+/// Runs the two peers until the first one disconnects the second one, the status handshake must
+/// not complete
+async fn assert_handshake_rejected(peer1: &mut Network, peer2: &mut Network) {
+    let peer2_id = *peer2.local_peer_id();
+
+    let peer1_fut = async {
+        loop {
+            match peer1.next_event().await {
+                NetworkEvent::PeerConnected(peer_id) if peer_id == peer2_id => {
+                    panic!("Unexpected handshake")
+                }
+                NetworkEvent::PeerDisconnected(peer_id) if peer_id == peer2_id => return,
+                _ => {}
+            }
+        }
+    };
+
+    let peer2_fut = async {
+        loop {
+            peer2.next_event().await;
+        }
+    };
+
+    tokio::select! {
+        _ = peer1_fut => {}
+        _ = peer2_fut => {}
+        _ = tokio::time::sleep(Duration::from_secs(20)) => {
+            panic!("Future timed out");
+        }
+    }
+}
+
 #[tokio::test]
 async fn rpc_status() -> eyre::Result<()> {
-    rpc_case(
-        OutboundRequest::Status(StatusMessage::default()),
-        RPCResponse::Status(StatusMessage::default()),
-    )
-    .await?;
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair().await?;
+    let peer2_id = *peer2.local_peer_id();
+    let peer2_status =
+        StatusMessage::new(peer2.network_globals().local_status().chain_id(), H256::random(), 10);
+    peer2.network_globals().set_head(peer2_status.block_hash(), peer2_status.block_number());
+
+    // the status is exchanged (and stored) by the network itself on connection
+    handshake_p2p_pair(&mut peer1, &mut peer2).await;
+    let network_globals = peer1.network_globals();
+    let peers = network_globals.peers.read();
+    let peer_info = peers.peer_info(&peer2_id).expect("peer should be known");
+    assert_eq!(peer_info.status(), &Some(peer2_status));
+    Ok(())
+}
+
+#[tokio::test]
+async fn rpc_status_mismatched_chain_id() -> eyre::Result<()> {
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair().await?;
+    let chain_id = peer2.network_globals().local_status().chain_id();
+    *peer2.network_globals().local_status.write() =
+        StatusMessage::new(chain_id + 1, H256::zero(), 0);
+
+    assert_handshake_rejected(&mut peer1, &mut peer2).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn rpc_status_mismatched_finalized_block() -> eyre::Result<()> {
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair().await?;
+
+    // the head of the second peer is final for the first peer, but on another fork
+    peer1.network_globals().set_head(H256::random(), 10);
+    peer1.network_globals().set_head(H256::random(), 10 + FINALITY_DEPTH);
+    peer2.network_globals().set_head(H256::random(), 10);
+
+    assert_handshake_rejected(&mut peer1, &mut peer2).await;
     Ok(())
 }
 
@@ -1,6 +1,11 @@
 //! Bundler-related primitives
 
-use serde::Deserialize;
+use crate::utils::as_hex_string;
+use ethers::{
+    prelude::{EthAbiCodec, EthAbiType},
+    types::{Address, H256, U256},
+};
+use serde::{Deserialize, Serialize};
 use strum_macros::{EnumString, EnumVariantNames};
 
 /// Bundler modes
@@ -25,3 +30,109 @@ pub enum SendStrategy {
     /// Send the bundle to the Ethereum execution client over conditional RPC method
     Conditional,
 }
+
+/// Determines how the bundler picks user operations from the mempool into the bundle
+#[derive(Clone, Copy, Debug, Default, EnumString, EnumVariantNames, PartialEq, Eq)]
+#[strum(serialize_all = "kebab_case")]
+pub enum BundleStrategy {
+    /// User operations with the highest priority fee first, until the gas budget is exceeded
+    #[default]
+    Default,
+    /// Greedy by effective tip × gas
+    MaximizeProfit,
+    /// User operations in the order they arrived into the mempool
+    Fifo,
+    /// Fills the gas budget optimally (0/1 knapsack)
+    Knapsack,
+}
+
+pub type BundleStatusCode = u64;
+
+/// Outcome of a sent bundle
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleStatus {
+    /// The bundle was sent, but its outcome is not known (e.g., it was sent to a relay)
+    #[default]
+    Submitted,
+    /// The bundle transaction was mined and succeeded
+    Included,
+    /// The bundle transaction was mined, but reverted
+    Reverted,
+    /// The bundle transaction was replaced by a zero-value self-transfer
+    Cancelled,
+    /// The bundle transaction was dropped (another transaction with the same nonce was mined)
+    Dropped,
+}
+
+impl From<BundleStatus> for BundleStatusCode {
+    fn from(status: BundleStatus) -> Self {
+        match status {
+            BundleStatus::Submitted => 0,
+            BundleStatus::Included => 1,
+            BundleStatus::Reverted => 2,
+            BundleStatus::Cancelled => 3,
+            BundleStatus::Dropped => 4,
+        }
+    }
+}
+
+impl From<BundleStatusCode> for BundleStatus {
+    fn from(status: BundleStatusCode) -> Self {
+        match status {
+            1 => BundleStatus::Included,
+            2 => BundleStatus::Reverted,
+            3 => BundleStatus::Cancelled,
+            4 => BundleStatus::Dropped,
+            _ => BundleStatus::Submitted,
+        }
+    }
+}
+
+/// Record of a bundle sent by the bundler
+#[derive(
+    Default,
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    EthAbiCodec,
+    EthAbiType,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleRecord {
+    /// Hash of the bundle transaction (the mined one if the bundle was replaced)
+    pub transaction_hash: H256,
+    /// Entry point the bundle was sent to
+    pub entry_point: Address,
+    /// Number of the block the bundle transaction was mined in (zero if it wasn't mined)
+    #[serde(serialize_with = "as_hex_string")]
+    pub block_number: u64,
+    /// Hashes of the user operations in the bundle
+    pub user_operations: Vec<H256>,
+    /// Gas used by the bundle transaction
+    pub gas_used: U256,
+    /// Fees the bundler paid for the bundle transaction
+    pub fees_paid: U256,
+    /// Beneficiary of the bundle
+    pub beneficiary: Address,
+    /// Fees the beneficiary received from the user operations
+    pub revenue: U256,
+    /// Outcome of the bundle (see [BundleStatus](BundleStatus))
+    #[serde(serialize_with = "as_hex_string")]
+    pub status: BundleStatusCode,
+    /// Time the bundle was sent (unix timestamp in seconds)
+    #[serde(serialize_with = "as_hex_string")]
+    pub timestamp: u64,
+}
+
+impl BundleRecord {
+    /// Whether the bundle was cancelled or dropped (its user operations stay in the mempool)
+    pub fn is_released(&self) -> bool {
+        matches!(BundleStatus::from(self.status), BundleStatus::Cancelled | BundleStatus::Dropped)
+    }
+}
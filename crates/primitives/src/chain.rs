@@ -1,8 +1,59 @@
 //! Chain information
 
 use alloy_chains::{Chain, NamedChain};
+use ethers::types::Address;
 use std::{fmt::Debug, time::Duration};
 
+/// Address of the OP Stack `GasPriceOracle` predeploy
+pub const OP_STACK_GAS_PRICE_ORACLE: &str = "0x420000000000000000000000000000000000000F";
+/// Address of the Arbitrum `NodeInterface` precompile
+pub const ARBITRUM_NODE_INTERFACE: &str = "0x00000000000000000000000000000000000000C8";
+
+/// Oracle of the L1 data fee that L2 chains charge for posting transactions to L1
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum L1GasOracle {
+    /// No L1 data fee (L1 or sidechain)
+    #[default]
+    None,
+    /// OP Stack `GasPriceOracle` (`getL1Fee`)
+    OpStack(Address),
+    /// Arbitrum `NodeInterface` (`gasEstimateL1Component`)
+    Arbitrum(Address),
+}
+
+impl L1GasOracle {
+    /// The OP Stack `GasPriceOracle` predeploy
+    pub fn op_stack() -> Self {
+        Self::OpStack(OP_STACK_GAS_PRICE_ORACLE.parse().expect("valid address"))
+    }
+
+    /// The Arbitrum `NodeInterface` precompile
+    pub fn arbitrum() -> Self {
+        Self::Arbitrum(ARBITRUM_NODE_INTERFACE.parse().expect("valid address"))
+    }
+
+    /// Selects the oracle of the L1 data fee by chain id
+    pub fn from_chain_id(chain_id: u64) -> Self {
+        match Chain::from_id(chain_id).named() {
+            Some(
+                NamedChain::Optimism |
+                NamedChain::OptimismGoerli |
+                NamedChain::OptimismSepolia |
+                NamedChain::Base |
+                NamedChain::BaseGoerli |
+                NamedChain::BaseSepolia,
+            ) => Self::op_stack(),
+            Some(
+                NamedChain::Arbitrum |
+                NamedChain::ArbitrumNova |
+                NamedChain::ArbitrumGoerli |
+                NamedChain::ArbitrumSepolia,
+            ) => Self::arbitrum(),
+            _ => Self::None,
+        }
+    }
+}
+
 /// Chain specification structure
 #[derive(PartialEq, Debug, Clone)]
 pub struct ChainSpec {
@@ -12,6 +63,8 @@ pub struct ChainSpec {
     pub block_time: Duration,
     /// List of canonicam mempools
     pub canonical_mempools: Vec<String>,
+    /// Oracle of the L1 data fee (included in the pre-verification gas)
+    pub l1_gas_oracle: L1GasOracle,
 }
 
 impl ChainSpec {
@@ -35,6 +88,7 @@ impl ChainSpec {
             chain: Chain::from(NamedChain::Mainnet),
             block_time: Duration::from_secs(12),
             canonical_mempools: vec![],
+            l1_gas_oracle: L1GasOracle::None,
         }
     }
 
@@ -44,6 +98,7 @@ impl ChainSpec {
             chain: Chain::from(NamedChain::Dev),
             block_time: Duration::from_secs(1),
             canonical_mempools: vec!["Qmf7P3CuhzSbpJa8LqXPwRzfPqsvoQ6RG7aXvthYTzGxb2".into()],
+            l1_gas_oracle: L1GasOracle::None,
         }
     }
 
@@ -53,6 +108,7 @@ impl ChainSpec {
             chain: Chain::from(NamedChain::Goerli),
             block_time: Duration::from_secs(12),
             canonical_mempools: vec!["QmTmj4cizhWpEFCCqk5dP67yws7R2PPgCtb2bd2RgVPCbF".into()],
+            l1_gas_oracle: L1GasOracle::None,
         }
     }
 
@@ -62,6 +118,7 @@ impl ChainSpec {
             chain: Chain::from(NamedChain::Sepolia),
             block_time: Duration::from_secs(12),
             canonical_mempools: vec!["QmdDwVFoEEcgv5qnaTB8ncnXGMnqrhnA5nYpRr4ouWe4AT".into()],
+            l1_gas_oracle: L1GasOracle::None,
         }
     }
 
@@ -74,6 +131,7 @@ impl ChainSpec {
                 "QmRJ1EPhmRDb8SKrPLRXcUBi2weUN8VJ8X9zUtXByC7eJg".into(),
                 "QmaHG3xiRYhxTth7vSTyZCyodBDrtj5hmEMz5DuzaJVKHH".into(),
             ],
+            l1_gas_oracle: L1GasOracle::None,
         }
     }
 
@@ -83,6 +141,7 @@ impl ChainSpec {
             chain: Chain::from(NamedChain::PolygonMumbai),
             block_time: Duration::from_secs(2),
             canonical_mempools: vec!["QmQfRyE9iVTBqZ17hPSP4tuMzaez83Y5wD874ymyRtj9VE".into()],
+            l1_gas_oracle: L1GasOracle::None,
         }
     }
 
@@ -92,6 +151,7 @@ impl ChainSpec {
             chain: Chain::from(NamedChain::ArbitrumSepolia),
             block_time: Duration::from_millis(250),
             canonical_mempools: vec!["QmVwhF77aVNzRUkMJNLDkeF9BtQMHLnfDY5ePpZ81uKLzA".into()],
+            l1_gas_oracle: L1GasOracle::arbitrum(),
         }
     }
 
@@ -101,6 +161,21 @@ impl ChainSpec {
             chain: Chain::from_id(chain_id),
             block_time: Duration::from_secs(2), // Use default block time
             canonical_mempools: vec![],
+            l1_gas_oracle: L1GasOracle::from_chain_id(chain_id),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn l1_gas_oracle_selection() {
+        assert_eq!(ChainSpec::mainnet().l1_gas_oracle, L1GasOracle::None);
+        assert_eq!(ChainSpec::from_chain_id(10).l1_gas_oracle, L1GasOracle::op_stack());
+        assert_eq!(ChainSpec::from_chain_id(84532).l1_gas_oracle, L1GasOracle::op_stack());
+        assert_eq!(ChainSpec::from_chain_id(42161).l1_gas_oracle, L1GasOracle::arbitrum());
+        assert_eq!(ChainSpec::arbitrum_sepolia().l1_gas_oracle, L1GasOracle::arbitrum());
+    }
+}
@@ -6,12 +6,29 @@ pub mod entry_point {
     pub const ADDRESS: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
     /// Version of the entry point smart contract
     pub const VERSION: &str = "0.6.0";
+    /// Address of the entry point smart contract (v0.7)
+    pub const ADDRESS_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+    /// Version of the entry point smart contract (v0.7)
+    pub const VERSION_V07: &str = "0.7.0";
 }
 
 /// Bundler
 pub mod bundler {
     /// Default time interval for auto bundling mode (in seconds)
     pub const BUNDLE_INTERVAL: u64 = 10;
+    /// Number of blocks after which a pending bundle transaction is replaced with higher fees
+    pub const BUMP_AFTER_BLOCKS: u64 = 3;
+    /// Percentage the fees of a pending bundle transaction are increased by when it's replaced
+    /// (execution clients usually require at least 10%)
+    pub const FEE_BUMP_PERC: u64 = 10;
+    /// Maximum number of fee bumps of a bundle transaction
+    pub const MAX_FEE_BUMPS: u64 = 5;
+    /// Number of consecutive failed checks of a pending bundle transaction after which it's given
+    /// up
+    pub const MAX_TRACKER_ERRORS: u64 = 100;
+    /// Default time interval of checking the deposits and stakes of the watched entities (in
+    /// seconds)
+    pub const WATCH_INTERVAL: u64 = 60;
 }
 
 /// User operation mempool
@@ -20,24 +37,42 @@ pub mod mempool {
     pub const GAS_INCREASE_PERC: u64 = 10;
     /// Depth scan when searching for previous user operations
     pub const LATEST_SCAN_DEPTH: u64 = 1000;
+    /// Percentage added to the gas a user operation used in the simulation when packing bundles
+    pub const EXECUTION_GAS_MARGIN_PERC: u64 = 10;
+    /// Number of recent blocks tracked to detect chain reorganizations
+    pub const REORG_DEPTH: usize = 64;
+    /// Maximum age (in blocks) of the block a user operation from the p2p network was verified at
+    pub const MAX_VERIFIED_BLOCK_AGE: u64 = 10;
+    /// Paymaster post-op gas limit estimated for the user operations that don't set it (entry
+    /// point v0.7)
+    pub const PAYMASTER_POST_OP_GAS_LIMIT: u64 = 50_000;
+    /// Number of user operation events buffered for the slowest subscriber
+    pub const USER_OPERATION_EVENTS_CAPACITY: usize = 1024;
+    /// Maximum number of remembered user operations that left the mempool without being included
+    pub const DROPPED_USER_OPERATIONS_CAPACITY: usize = 4096;
+    /// How long (in seconds) a user operation that left the mempool is remembered
+    pub const DROPPED_USER_OPERATIONS_TTL: u64 = 3600;
 }
 
 /// User operation validation
 pub mod validation {
     /// Entities (factory, sender/account, paymaster, aggregator)
     pub mod entities {
-        // 0 - factory, 1 - sender/account, 2 - paymaster
-        pub const NUMBER_OF_LEVELS: usize = 3;
+        // 0 - factory, 1 - sender/account, 2 - paymaster, 3 - aggregator
+        pub const NUMBER_OF_LEVELS: usize = 4;
 
         pub const FACTORY: &str = "factory";
         pub const SENDER: &str = "account";
         pub const PAYMASTER: &str = "paymaster";
+        pub const AGGREGATOR: &str = "aggregator";
 
         pub const FACTORY_LEVEL: usize = 0;
         pub const SENDER_LEVEL: usize = 1;
         pub const PAYMASTER_LEVEL: usize = 2;
+        pub const AGGREGATOR_LEVEL: usize = 3;
 
-        pub const LEVEL_TO_ENTITY: [&str; NUMBER_OF_LEVELS] = [FACTORY, SENDER, PAYMASTER];
+        pub const LEVEL_TO_ENTITY: [&str; NUMBER_OF_LEVELS] =
+            [FACTORY, SENDER, PAYMASTER, AGGREGATOR];
     }
 
     /// Reputation
@@ -144,12 +179,31 @@ pub mod p2p {
     pub const NODE_KEY_FILE_NAME: &str = "p2p/node-key";
     /// The default path for storing the node enr
     pub const NODE_ENR_FILE_NAME: &str = "p2p/node-enr";
+    /// The default path for storing the known peers
+    pub const NODE_PEERS_FILE_NAME: &str = "p2p/peers.json";
     /// Default number of peers to find on the initial discovery.
     pub const FIND_NODE_QUERY_CLOSEST_PEERS: usize = 16;
     /// Default target peers.
     pub const TARGET_PEERS: usize = 50;
     /// Default heartbeat interval (how often we perform discovery and peer management).
     pub const HEARTBEAT_INTERVAL: u64 = 30;
+    /// Maximum score of a peer.
+    pub const MAX_PEER_SCORE: f64 = 100.0;
+    /// Minimum score of a peer.
+    pub const MIN_PEER_SCORE: f64 = -100.0;
+    /// Score at (or below) which a peer is disconnected and banned (until its score recovers).
+    pub const MIN_SCORE_BEFORE_BAN: f64 = -50.0;
+    /// Score above which the ban of a peer banned for its score is lifted (as the score decays).
+    pub const MIN_SCORE_BEFORE_UNBAN: f64 = -20.0;
+    /// Time after which the score of a peer is halved (towards zero).
+    pub const SCORE_HALFLIFE: u64 = 600; // seconds
+    /// Number of blocks a peer can be behind before it's flagged as far behind.
+    pub const FAR_BEHIND_BLOCKS: u64 = 64;
+    /// Number of blocks after which a block is final (the head of a peer is compared with our
+    /// block at the same height only if that block is final).
+    pub const FINALITY_DEPTH: u64 = 64;
+    /// Time a connected peer has to exchange its status before it's disconnected.
+    pub const HANDSHAKE_TIMEOUT: u64 = 10; // seconds
     /// Default outbound ping interval.
     pub const PING_INTERVAL_OUTBOUND: u64 = 15;
     /// Default inbound ping interval.
@@ -170,8 +224,21 @@ pub mod p2p {
     pub const GOSSIP_MAX_SIZE: usize = 1048576; // bytes
     /// The maximum number of user operations in a single request
     pub const MAX_OPS_PER_REQUEST: usize = 4096;
+    /// The maximum number of pages of the pooled user operation hashes requested from a peer on
+    /// connection
+    pub const MAX_SYNC_PAGES: usize = 16;
     /// The maximum time for complete response transfer
     pub const RESP_TIMEOUT: u64 = 10; // seconds
+    /// Gossipsub score below which the gossip of the peer is not emitted
+    pub const GOSSIP_THRESHOLD: f64 = -4000.0;
+    /// Gossipsub score below which the messages are not published to the peer
+    pub const PUBLISH_THRESHOLD: f64 = -8000.0;
+    /// Gossipsub score below which the peer is graylisted (and disconnected)
+    pub const GRAYLIST_THRESHOLD: f64 = -16000.0;
+    /// Gossipsub penalty weight of the invalid user operations (squared number of the messages)
+    pub const INVALID_MESSAGE_DELIVERIES_WEIGHT: f64 = -100.0;
+    /// Decay of the number of the invalid user operations per decay interval (1 second)
+    pub const INVALID_MESSAGE_DELIVERIES_DECAY: f64 = 0.999;
     /// The maximum time to wait for first byte of request response (time-to-first-byte)
     pub const TTFB_TIMEOUT: usize = 5; // seconds
     /// The amount of time to maintain a request context of pooled hashes
@@ -0,0 +1,71 @@
+//! Entry point-related primitives
+
+use crate::constants::entry_point::{ADDRESS_V07, VERSION, VERSION_V07};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use strum_macros::{EnumString, EnumVariantNames};
+
+/// Versions of the entry point smart contract supported by the bundler
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    EnumString,
+    EnumVariantNames,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum EntryPointVersion {
+    /// Entry point v0.6 (user operations are not packed)
+    #[default]
+    #[strum(serialize = "v0.6")]
+    V0_6,
+    /// Entry point v0.7 (user operations are packed - `PackedUserOperation`)
+    #[strum(serialize = "v0.7")]
+    V0_7,
+}
+
+impl EntryPointVersion {
+    /// Determines the version of the entry point from its (canonical) address.
+    ///
+    /// Addresses that are not known to be v0.7 deployments are treated as v0.6.
+    pub fn from_address(address: &Address) -> Self {
+        if *address == Address::from_str(ADDRESS_V07).expect("address should be valid") {
+            Self::V0_7
+        } else {
+            Self::V0_6
+        }
+    }
+
+    /// Returns the semantic version of the entry point
+    pub fn version(&self) -> &'static str {
+        match self {
+            Self::V0_6 => VERSION,
+            Self::V0_7 => VERSION_V07,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::entry_point::ADDRESS;
+
+    #[test]
+    fn entry_point_version_from_address() {
+        assert_eq!(
+            EntryPointVersion::from_address(&ADDRESS.parse().unwrap()),
+            EntryPointVersion::V0_6
+        );
+        assert_eq!(
+            EntryPointVersion::from_address(&ADDRESS_V07.parse().unwrap()),
+            EntryPointVersion::V0_7
+        );
+        assert_eq!(EntryPointVersion::from_str("v0.7").unwrap(), EntryPointVersion::V0_7);
+    }
+}
@@ -5,6 +5,7 @@
 pub mod bundler;
 pub mod chain;
 pub mod constants;
+pub mod entry_point;
 pub mod mempool;
 pub mod p2p;
 pub mod provider;
@@ -12,14 +13,17 @@ pub mod reputation;
 pub mod simulation;
 mod user_operation;
 mod utils;
+pub mod validation;
 mod wallet;
 
 pub use bundler::Mode as BundlerMode;
 pub use mempool::Mode as UoPoolMode;
 pub use p2p::VerifiedUserOperation;
 pub use user_operation::{
-    UserOperation, UserOperationByHash, UserOperationGasEstimation, UserOperationHash,
-    UserOperationReceipt, UserOperationRequest, UserOperationSigned,
+    pack_paymaster_and_data, PackedUserOperation, UserOperation, UserOperationByHash,
+    UserOperationEvent, UserOperationGasEstimation, UserOperationHash, UserOperationReceipt,
+    UserOperationRequest, UserOperationSigned, UserOperationStatus, UserOperationsPerAggregator,
+    PAYMASTER_DATA_OFFSET,
 };
-pub use utils::get_address;
+pub use utils::{as_checksum_addr, get_address};
 pub use wallet::Wallet;
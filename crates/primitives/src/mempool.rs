@@ -1,5 +1,8 @@
 //! Mempool/related primitives
 
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::Path};
 use strum_macros::{EnumString, EnumVariantNames};
 
 /// Verification modes for user operation mempool
@@ -9,3 +12,91 @@ pub enum Mode {
     Standard,
     Unsafe,
 }
+
+/// Alternative mempool (ERC-7562) which relaxes some of the validation rules of the canonical
+/// mempool
+///
+/// User operations of an alternative mempool are validated with its own rules, kept in their own
+/// mempool partition and propagated on its own gossip topic.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AltMempool {
+    /// IPFS CID of the mempool definition (also the id of the gossip topic)
+    pub id: String,
+    /// Chain the mempool is on
+    pub chain_id: u64,
+    /// Entry point the mempool is for
+    pub entry_point: Address,
+    /// Human readable description of the mempool
+    #[serde(default)]
+    pub description: String,
+    /// Opcodes (e.g. `TIMESTAMP`) which are allowed even if the canonical mempool forbids them
+    #[serde(default)]
+    pub allowed_opcodes: HashSet<String>,
+    /// Contracts whose storage can be accessed by any entity
+    #[serde(default)]
+    pub allowed_storage: HashSet<Address>,
+    /// Entities which are treated as staked by the validation rules
+    #[serde(default)]
+    pub allowed_entities: HashSet<Address>,
+}
+
+/// Loads the definitions of the alternative mempools from a YAML file
+///
+/// # Arguments
+/// * `path` - Path of the YAML file with the list of the mempool definitions
+///
+/// # Returns
+/// * `Vec<AltMempool>` - The definitions of the alternative mempools
+pub fn load_alt_mempools(path: &Path) -> eyre::Result<Vec<AltMempool>> {
+    let content = std::fs::read_to_string(path)?;
+    let alt_mempools: Vec<AltMempool> = serde_yaml::from_str(&content)?;
+
+    let mut ids = HashSet::new();
+    for alt_mempool in alt_mempools.iter() {
+        if !ids.insert(alt_mempool.id.clone()) {
+            return Err(eyre::eyre!("Duplicate alternative mempool {}", alt_mempool.id));
+        }
+    }
+
+    Ok(alt_mempools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn parses_alt_mempools() {
+        let alt_mempools: Vec<AltMempool> = serde_yaml::from_str(
+            r#"
+- id: QmTmj4cizhWpEFCCqk5dP67yws7R2PPgCtb2bd2RgVPCbF
+  chainId: 11155111
+  entryPoint: "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"
+  description: Accounts that read the block timestamp
+  allowedOpcodes: [TIMESTAMP, NUMBER]
+  allowedStorage: ["0x0000000000000000000000000000000000000001"]
+- id: QmdDwVFoEEcgv5qnaTB8ncnXGMnqrhnA5nYpRr4ouWe4AT
+  chainId: 11155111
+  entryPoint: "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"
+  allowedEntities: ["0x0000000000000000000000000000000000000002"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(alt_mempools.len(), 2);
+        assert_eq!(
+            alt_mempools[0].entry_point,
+            Address::from_str("0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789").unwrap()
+        );
+        assert_eq!(
+            alt_mempools[0].allowed_opcodes,
+            HashSet::from(["TIMESTAMP".to_string(), "NUMBER".to_string()])
+        );
+        assert_eq!(alt_mempools[0].allowed_storage, HashSet::from([Address::from_low_u64_be(1)]));
+        assert!(alt_mempools[1].description.is_empty());
+        assert!(alt_mempools[1].allowed_opcodes.is_empty());
+        assert_eq!(alt_mempools[1].allowed_entities, HashSet::from([Address::from_low_u64_be(2)]));
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::UserOperationSigned;
 use ethers::types::{Address, U256 as EthersU256};
+use serde::{Deserialize, Serialize};
 use ssz_rs::{Vector, U256};
 use ssz_rs_derive::Serializable;
 
@@ -38,4 +39,27 @@ impl VerifiedUserOperation {
     pub fn entry_point(&self) -> Address {
         Address::from_slice(&self.entry_point)
     }
+
+    pub fn verified_at_block_hash(&self) -> EthersU256 {
+        EthersU256::from_little_endian(&self.verified_at_block_hash.to_bytes_le())
+    }
+}
+
+/// Known peer of the p2p network (persisted across restarts)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerRecord {
+    /// Peer id of the peer
+    pub peer_id: String,
+    /// ENR of the peer (base64 encoded), used to redial the peer
+    pub enr: Option<String>,
+    /// Unix timestamp (in seconds) of the last connection to the peer
+    pub last_seen: u64,
+    /// Score of the peer
+    pub score: f64,
+    /// Whether the peer is banned
+    pub banned: bool,
+    /// Whether the peer is connected (ignored when the known peers are restored)
+    #[serde(default)]
+    pub connected: bool,
 }
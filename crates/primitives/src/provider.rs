@@ -77,3 +77,32 @@ where
     }
     streams
 }
+
+/// Splits the block stream into multiple block streams which yield the same blocks
+pub fn split_block_stream(mut block_stream: BlockStream, n: usize) -> Vec<BlockStream> {
+    let (senders, receivers): (Vec<_>, Vec<_>) =
+        (0..n).map(|_| tokio::sync::mpsc::unbounded_channel::<eyre::Result<H256>>()).unzip();
+
+    tokio::spawn(async move {
+        while let Some(hash) = block_stream.next().await {
+            for sender in senders.iter() {
+                let hash = match &hash {
+                    Ok(hash) => Ok(*hash),
+                    Err(e) => Err(eyre::eyre!("{e}")),
+                };
+                let _ = sender.send(hash);
+            }
+        }
+    });
+
+    receivers
+        .into_iter()
+        .map(|mut receiver| -> BlockStream {
+            Box::pin(stream! {
+                while let Some(hash) = receiver.recv().await {
+                    yield hash;
+                }
+            })
+        })
+        .collect()
+}
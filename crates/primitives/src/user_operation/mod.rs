@@ -1,9 +1,10 @@
 //! Basic transaction type for account abstraction (ERC-4337)
 
 mod hash;
+mod packed;
 mod request;
 
-use crate::{get_address, utils::as_checksum_addr};
+use crate::{entry_point::EntryPointVersion, get_address, utils::as_checksum_addr};
 use derive_more::{AsRef, Deref};
 use ethers::{
     abi::AbiEncode,
@@ -12,6 +13,9 @@ use ethers::{
     utils::keccak256,
 };
 pub use hash::UserOperationHash;
+pub use packed::{
+    pack_paymaster_and_data, pack_uints, unpack_uints, PackedUserOperation, PAYMASTER_DATA_OFFSET,
+};
 pub use request::UserOperationRequest;
 use serde::{Deserialize, Serialize};
 use ssz_rs::List;
@@ -44,6 +48,26 @@ impl From<UserOperation> for UserOperationSigned {
     }
 }
 
+/// User operations that share the same signature aggregator (`UserOpsPerAggregator` of the entry
+/// point)
+///
+/// User operations that don't use an aggregator are grouped under the zero address and carry an
+/// empty aggregated signature.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationsPerAggregator {
+    pub user_operations: Vec<UserOperation>,
+    pub aggregator: Address,
+    pub signature: Bytes,
+}
+
+impl UserOperationsPerAggregator {
+    /// Whether the user operations are validated by a signature aggregator
+    pub fn is_aggregated(&self) -> bool {
+        !self.aggregator.is_zero()
+    }
+}
+
 /// User operation
 #[derive(
     Default,
@@ -142,11 +166,31 @@ impl UserOperationSigned {
     }
 
     /// Calculates the hash of the user operation
+    ///
+    /// The way the user operation is packed depends on the version of the entry point (see
+    /// [EntryPointVersion::from_address]).
     pub fn hash(&self, entry_point: &Address, chain_id: u64) -> UserOperationHash {
+        self.hash_with_version(entry_point, chain_id, EntryPointVersion::from_address(entry_point))
+    }
+
+    /// Calculates the hash of the user operation for the given entry point version
+    pub fn hash_with_version(
+        &self,
+        entry_point: &Address,
+        chain_id: u64,
+        version: EntryPointVersion,
+    ) -> UserOperationHash {
+        let packed = match version {
+            EntryPointVersion::V0_6 => self.pack_without_signature(),
+            EntryPointVersion::V0_7 => {
+                PackedUserOperation::from(self.clone()).pack_without_signature()
+            }
+        };
+
         H256::from_slice(
             keccak256(
                 [
-                    keccak256(self.pack_without_signature().deref()).to_vec(),
+                    keccak256(packed.deref()).to_vec(),
                     entry_point.encode(),
                     U256::from(chain_id).encode(),
                 ]
@@ -157,6 +201,36 @@ impl UserOperationSigned {
         .into()
     }
 
+    /// Calculates the prefund the entry point requires from the paymaster (or the sender) to
+    /// validate the user operation
+    ///
+    /// The verification gas limit of the entry point v0.6 counts three times if the user operation
+    /// has a paymaster, the entry point v0.7 adds the paymaster verification and post-op gas
+    /// limits packed into `paymasterAndData` instead.
+    pub fn required_prefund(&self, version: EntryPointVersion) -> U256 {
+        let required_gas = match version {
+            EntryPointVersion::V0_6 => {
+                let mul: u64 = if self.paymaster_and_data.is_empty() { 1 } else { 3 };
+                self.call_gas_limit
+                    .saturating_add(self.verification_gas_limit.saturating_mul(mul.into()))
+                    .saturating_add(self.pre_verification_gas)
+            }
+            EntryPointVersion::V0_7 => {
+                let (paymaster_verification_gas_limit, paymaster_post_op_gas_limit) =
+                    PackedUserOperation::from(self.clone())
+                        .paymaster_gas_limits()
+                        .map(|(_, verification, post_op)| (verification, post_op))
+                        .unwrap_or_default();
+                self.verification_gas_limit
+                    .saturating_add(self.call_gas_limit)
+                    .saturating_add(paymaster_verification_gas_limit)
+                    .saturating_add(paymaster_post_op_gas_limit)
+                    .saturating_add(self.pre_verification_gas)
+            }
+        };
+        required_gas.saturating_mul(self.max_fee_per_gas)
+    }
+
     // Builder pattern helpers
 
     /// Sets the sender of the user operation
@@ -459,6 +533,95 @@ pub struct UserOperationGasEstimation {
     pub pre_verification_gas: U256,
     pub verification_gas_limit: U256,
     pub call_gas_limit: U256,
+    /// Paymaster verification gas limit (only for the user operations with a paymaster sent to
+    /// the entry point v0.7)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// Paymaster post-op gas limit (only for the user operations with a paymaster sent to the
+    /// entry point v0.7)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+}
+
+/// Stage of the lifecycle of a user operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UserOperationStatus {
+    /// The user operation was admitted to the mempool (or returned to it)
+    Pending,
+    /// The user operation was sent in a bundle transaction
+    Bundled,
+    /// The user operation was included in a block
+    Included,
+    /// The bundle transaction with the user operation reverted
+    Reverted,
+    /// The user operation was removed from the mempool without being included
+    Dropped,
+}
+
+impl UserOperationStatus {
+    /// Whether no more updates of the status follow
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Included | Self::Reverted | Self::Dropped)
+    }
+}
+
+/// Update of the status of a user operation (pushed to the subscribers of `eth_subscribe`)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationEvent {
+    #[serde(rename = "userOpHash")]
+    pub user_operation_hash: UserOperationHash,
+    #[serde(serialize_with = "as_checksum_addr")]
+    pub entry_point: Address,
+    pub status: UserOperationStatus,
+    /// The user operation (only for the user operations admitted to the mempool)
+    #[serde(rename = "userOperation", default, skip_serializing_if = "Option::is_none")]
+    pub user_operation: Option<UserOperationSigned>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<U64>,
+    /// Why the user operation was dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl UserOperationEvent {
+    pub fn new(
+        user_operation_hash: UserOperationHash,
+        entry_point: Address,
+        status: UserOperationStatus,
+    ) -> Self {
+        Self {
+            user_operation_hash,
+            entry_point,
+            status,
+            user_operation: None,
+            transaction_hash: None,
+            block_number: None,
+            reason: None,
+        }
+    }
+
+    /// Attaches the user operation
+    pub fn with_user_operation(mut self, user_operation: UserOperationSigned) -> Self {
+        self.user_operation = Some(user_operation);
+        self
+    }
+
+    /// Attaches the transaction (and the block) the user operation was sent in
+    pub fn with_transaction(mut self, transaction_hash: H256, block_number: Option<U64>) -> Self {
+        self.transaction_hash = Some(transaction_hash);
+        self.block_number = block_number;
+        self
+    }
+
+    /// Attaches the reason the user operation was dropped
+    pub fn with_reason(mut self, reason: String) -> Self {
+        self.reason = Some(reason);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -466,6 +629,25 @@ mod tests {
     use super::*;
     use std::str::FromStr;
 
+    #[test]
+    fn user_operation_event_serde() {
+        let event = UserOperationEvent::new(
+            UserOperationHash::from(H256::from_low_u64_be(1)),
+            "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789".parse().unwrap(),
+            UserOperationStatus::Included,
+        )
+        .with_transaction(H256::from_low_u64_be(2), Some(10.into()));
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["userOpHash"], format!("{:?}", H256::from_low_u64_be(1)));
+        assert_eq!(json["entryPoint"], "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
+        assert_eq!(json["status"], "included");
+        assert_eq!(json["blockNumber"], "0xa");
+        assert!(json.get("userOperation").is_none());
+        assert!(json.get("reason").is_none());
+        assert_eq!(serde_json::from_value::<UserOperationEvent>(json).unwrap(), event);
+    }
+
     #[test]
     fn user_operation_signed_pack() {
         let uos =  vec![
@@ -532,6 +714,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn user_operation_signed_hash_v07() {
+        let uo = UserOperationSigned {
+            sender: "0x9c5754De1443984659E1b3a8d1931D83475ba29C".parse().unwrap(),
+            nonce: U256::zero(),
+            init_code: "0x9406cc6185a346906296840746125a0e449764545fbfb9cf000000000000000000000000ce0fefa6f7979c4c9b5373e0f5105b7259092c6d0000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+            call_data: "0xb61d27f60000000000000000000000009c5754de1443984659e1b3a8d1931d83475ba29c00000000000000000000000000000000000000000000000000005af3107a400000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+            call_gas_limit: 33_100.into(),
+            verification_gas_limit: 361_460.into(),
+            pre_verification_gas: 44_980.into(),
+            max_fee_per_gas: 1_695_000_030_u64.into(),
+            max_priority_fee_per_gas: 1_695_000_000.into(),
+            paymaster_and_data: Bytes::default(),
+            signature: "0xebfd4657afe1f1c05c1ec65f3f9cc992a3ac083c424454ba61eab93152195e1400d74df01fc9fa53caadcb83a891d478b713016bcc0c64307c1ad3d7ea2e2d921b".parse().unwrap(),
+        };
+        let entry_point = "0x0000000071727De22E5E9d8BAf0edAc6f37da032".parse().unwrap();
+        assert_eq!(
+            uo.hash(&entry_point, 80_001),
+            "0x00d03fbd97b3b38e7f980ebaaddbf0dda2c0b1bf8a33539b8b8b08fe8baa3b09"
+                .parse::<H256>()
+                .unwrap()
+                .into()
+        );
+
+        // the paymaster verification and post-op gas limits are packed into `paymasterAndData`
+        let uo = uo.paymaster_and_data("0xa9d2d5d1fb3d8a9c1f1f7e7b0bd1b1de1f0e3c53000000000000000000000000000186a00000000000000000000000000000c3501234".parse().unwrap());
+        assert_eq!(
+            uo.hash_with_version(&entry_point, 80_001, EntryPointVersion::V0_7),
+            "0x9d23b69129e69d780bbcb2ca2a3a50d7bc1c9e3f0cb2977b887124823ad78493"
+                .parse::<H256>()
+                .unwrap()
+                .into()
+        );
+    }
+
+    #[test]
+    fn user_operation_signed_required_prefund() {
+        let uo = UserOperationSigned::default()
+            .call_gas_limit(10_000.into())
+            .verification_gas_limit(100_000.into())
+            .pre_verification_gas(21_000.into())
+            .max_fee_per_gas(2.into());
+        assert_eq!(uo.required_prefund(EntryPointVersion::V0_6), U256::from(262_000));
+        assert_eq!(uo.required_prefund(EntryPointVersion::V0_7), U256::from(262_000));
+
+        let uo = uo.paymaster_and_data(pack_paymaster_and_data(
+            Address::random(),
+            50_000.into(),
+            30_000.into(),
+            &Bytes::default(),
+        ));
+        assert_eq!(uo.required_prefund(EntryPointVersion::V0_6), U256::from(662_000));
+        assert_eq!(uo.required_prefund(EntryPointVersion::V0_7), U256::from(422_000));
+    }
+
     #[test]
     fn user_operation_signed_ssz() {
         let uo = UserOperationSigned {
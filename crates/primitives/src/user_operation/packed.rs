@@ -0,0 +1,203 @@
+//! Packed user operation (entry point v0.7)
+
+use super::UserOperationSigned;
+use crate::get_address;
+use ethers::{
+    abi::AbiEncode,
+    contract::{EthAbiCodec, EthAbiType},
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+/// Length of the paymaster address and paymaster gas limits at the beginning of
+/// `paymaster_and_data` (20 + 16 + 16 bytes)
+pub const PAYMASTER_DATA_OFFSET: usize = 52;
+
+/// User operation as it is passed to the entry point v0.7
+#[derive(Default, Clone, Debug, PartialEq, Eq, EthAbiCodec, EthAbiType, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedUserOperation {
+    /// Sender of the user operation
+    pub sender: Address,
+
+    /// Nonce (anti replay protection)
+    pub nonce: U256,
+
+    /// Factory address followed by the factory data (can be empty)
+    pub init_code: Bytes,
+
+    /// The data that is passed to the sender during the main execution call
+    pub call_data: Bytes,
+
+    /// Verification gas limit (high 128 bits) and call gas limit (low 128 bits)
+    pub account_gas_limits: [u8; 32],
+
+    /// The amount of gas to pay bundler to compensate for the pre-verification execution and
+    /// calldata
+    pub pre_verification_gas: U256,
+
+    /// Maximum priority fee per gas (high 128 bits) and maximum fee per gas (low 128 bits)
+    pub gas_fees: [u8; 32],
+
+    /// Paymaster address, paymaster verification gas limit, paymaster post-op gas limit, followed
+    /// by extra data to send to the paymaster (can be empty)
+    pub paymaster_and_data: Bytes,
+
+    /// Data passed to the account along with the nonce during the verification step
+    pub signature: Bytes,
+}
+
+/// Packed user operation without signature (helper for calculating the hash)
+#[derive(EthAbiCodec, EthAbiType)]
+struct PackedUserOperationNoSignature {
+    pub sender: Address,
+    pub nonce: U256,
+    pub init_code: H256,
+    pub call_data: H256,
+    pub account_gas_limits: [u8; 32],
+    pub pre_verification_gas: U256,
+    pub gas_fees: [u8; 32],
+    pub paymaster_and_data: H256,
+}
+
+impl From<PackedUserOperation> for PackedUserOperationNoSignature {
+    fn from(value: PackedUserOperation) -> Self {
+        Self {
+            sender: value.sender,
+            nonce: value.nonce,
+            init_code: keccak256(value.init_code.deref()).into(),
+            call_data: keccak256(value.call_data.deref()).into(),
+            account_gas_limits: value.account_gas_limits,
+            pre_verification_gas: value.pre_verification_gas,
+            gas_fees: value.gas_fees,
+            paymaster_and_data: keccak256(value.paymaster_and_data.deref()).into(),
+        }
+    }
+}
+
+/// Packs two 128-bit values into a 32-byte word (`high` in the upper 16 bytes)
+pub fn pack_uints(high: U256, low: U256) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    buf[0..16].copy_from_slice(&high.low_u128().to_be_bytes());
+    buf[16..32].copy_from_slice(&low.low_u128().to_be_bytes());
+    buf
+}
+
+/// Unpacks a 32-byte word into two 128-bit values (`(high, low)`)
+pub fn unpack_uints(buf: &[u8; 32]) -> (U256, U256) {
+    (U256::from_big_endian(&buf[0..16]), U256::from_big_endian(&buf[16..32]))
+}
+
+/// Builds the v0.7 `paymaster_and_data` from its unpacked parts
+pub fn pack_paymaster_and_data(
+    paymaster: Address,
+    paymaster_verification_gas_limit: U256,
+    paymaster_post_op_gas_limit: U256,
+    paymaster_data: &Bytes,
+) -> Bytes {
+    [
+        paymaster.as_bytes(),
+        &paymaster_verification_gas_limit.low_u128().to_be_bytes(),
+        &paymaster_post_op_gas_limit.low_u128().to_be_bytes(),
+        paymaster_data.as_ref(),
+    ]
+    .concat()
+    .into()
+}
+
+impl PackedUserOperation {
+    /// Packs the user operation without signature to bytes (used for calculating the hash)
+    pub fn pack_without_signature(&self) -> Bytes {
+        PackedUserOperationNoSignature::from(self.clone()).encode().into()
+    }
+
+    /// Gets the paymaster (if present) together with the paymaster verification and post-op gas
+    /// limits
+    pub fn paymaster_gas_limits(&self) -> Option<(Address, U256, U256)> {
+        if self.paymaster_and_data.len() < PAYMASTER_DATA_OFFSET {
+            return None;
+        }
+        let paymaster = get_address(&self.paymaster_and_data)?;
+        Some((
+            paymaster,
+            U256::from_big_endian(&self.paymaster_and_data[20..36]),
+            U256::from_big_endian(&self.paymaster_and_data[36..52]),
+        ))
+    }
+}
+
+impl From<UserOperationSigned> for PackedUserOperation {
+    fn from(uo: UserOperationSigned) -> Self {
+        Self {
+            sender: uo.sender,
+            nonce: uo.nonce,
+            init_code: uo.init_code,
+            call_data: uo.call_data,
+            account_gas_limits: pack_uints(uo.verification_gas_limit, uo.call_gas_limit),
+            pre_verification_gas: uo.pre_verification_gas,
+            gas_fees: pack_uints(uo.max_priority_fee_per_gas, uo.max_fee_per_gas),
+            paymaster_and_data: uo.paymaster_and_data,
+            signature: uo.signature,
+        }
+    }
+}
+
+impl From<PackedUserOperation> for UserOperationSigned {
+    fn from(uo: PackedUserOperation) -> Self {
+        let (verification_gas_limit, call_gas_limit) = unpack_uints(&uo.account_gas_limits);
+        let (max_priority_fee_per_gas, max_fee_per_gas) = unpack_uints(&uo.gas_fees);
+        Self {
+            sender: uo.sender,
+            nonce: uo.nonce,
+            init_code: uo.init_code,
+            call_data: uo.call_data,
+            call_gas_limit,
+            verification_gas_limit,
+            pre_verification_gas: uo.pre_verification_gas,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            paymaster_and_data: uo.paymaster_and_data,
+            signature: uo.signature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_user_operation_round_trip() {
+        let uo = UserOperationSigned::default()
+            .sender("0x9c5754De1443984659E1b3a8d1931D83475ba29C".parse().unwrap())
+            .call_gas_limit(200_000.into())
+            .verification_gas_limit(100_000.into())
+            .pre_verification_gas(21_000.into())
+            .max_fee_per_gas(3_000_000_000_u64.into())
+            .max_priority_fee_per_gas(1_000_000_000.into())
+            .paymaster_and_data(pack_paymaster_and_data(
+                "0x0000000000000000000000000000000000000001".parse().unwrap(),
+                50_000.into(),
+                10_000.into(),
+                &Bytes::from(vec![1, 2, 3]),
+            ));
+
+        let packed = PackedUserOperation::from(uo.clone());
+        assert_eq!(unpack_uints(&packed.account_gas_limits), (100_000.into(), 200_000.into()));
+        assert_eq!(
+            unpack_uints(&packed.gas_fees),
+            (1_000_000_000.into(), 3_000_000_000_u64.into())
+        );
+        assert_eq!(
+            packed.paymaster_gas_limits(),
+            Some((
+                "0x0000000000000000000000000000000000000001".parse().unwrap(),
+                50_000.into(),
+                10_000.into()
+            ))
+        );
+        assert_eq!(UserOperationSigned::from(packed), uo);
+    }
+}
@@ -1,6 +1,6 @@
 //! User operation request (optional fields)
 
-use super::UserOperationSigned;
+use super::{packed::pack_paymaster_and_data, UserOperationSigned};
 use crate::utils::{as_checksum_addr, as_checksum_bytes};
 use ethers::types::{Address, Bytes, U256};
 use serde::{Deserialize, Serialize};
@@ -31,6 +31,21 @@ pub struct UserOperationRequest {
     pub paymaster_and_data: Bytes,
     #[serde(default)]
     pub signature: Option<Bytes>,
+
+    // Unpacked fields of the entry point v0.7 user operation (`init_code` and
+    // `paymaster_and_data` are built from them if present)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory_data: Option<Bytes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
 }
 
 impl From<UserOperationRequest> for UserOperationSigned {
@@ -38,7 +53,14 @@ impl From<UserOperationRequest> for UserOperationSigned {
         Self {
             sender: user_operation.sender,
             nonce: user_operation.nonce,
-            init_code: user_operation.init_code,
+            init_code: match user_operation.factory {
+                Some(factory) => {
+                    [factory.as_bytes(), user_operation.factory_data.unwrap_or_default().as_ref()]
+                        .concat()
+                        .into()
+                }
+                None => user_operation.init_code,
+            },
             call_data: user_operation.call_data,
             call_gas_limit: {
                 if let Some(call_gas_limit) = user_operation.call_gas_limit {
@@ -75,7 +97,15 @@ impl From<UserOperationRequest> for UserOperationSigned {
                     U256::zero()
                 }
             },
-            paymaster_and_data: user_operation.paymaster_and_data,
+            paymaster_and_data: match user_operation.paymaster {
+                Some(paymaster) => pack_paymaster_and_data(
+                    paymaster,
+                    user_operation.paymaster_verification_gas_limit.unwrap_or_default(),
+                    user_operation.paymaster_post_op_gas_limit.unwrap_or_default(),
+                    &user_operation.paymaster_data.unwrap_or_default(),
+                ),
+                None => user_operation.paymaster_and_data,
+            },
             signature: {
                 if let Some(signature) = user_operation.signature {
                     signature
@@ -101,6 +131,12 @@ impl From<UserOperationSigned> for UserOperationRequest {
             max_priority_fee_per_gas: Some(user_operation.max_priority_fee_per_gas),
             paymaster_and_data: user_operation.paymaster_and_data,
             signature: Some(user_operation.signature),
+            factory: None,
+            factory_data: None,
+            paymaster: None,
+            paymaster_verification_gas_limit: None,
+            paymaster_post_op_gas_limit: None,
+            paymaster_data: None,
         }
     }
 }
@@ -0,0 +1,201 @@
+//! Validation rules (ERC-7562) and checks (ERC-4337) of the sanity, simulation and simulation
+//! trace checks that can be enabled or disabled at runtime
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+};
+use strum_macros::{Display, EnumString};
+
+/// Validation rule enforced by the sanity, simulation and simulation trace checks
+///
+/// The ERC-7562 rules are identified by their ids, the other checks of the ERC-4337 validation
+/// (which have no ERC-7562 id) by their names.
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Hash)]
+pub enum ValidationRule {
+    /// Forbidden opcodes
+    #[strum(serialize = "OP-011")]
+    Op011,
+    /// Revert on "out of gas"
+    #[strum(serialize = "OP-020")]
+    Op020,
+    /// CREATE2 outside of the deployment of the sender
+    #[strum(serialize = "OP-031")]
+    Op031,
+    /// Access to an address without a deployed code
+    #[strum(serialize = "OP-041")]
+    Op041,
+    /// Access to the entry point (other than `depositTo`)
+    #[strum(serialize = "OP-054")]
+    Op054,
+    /// CALL with value
+    #[strum(serialize = "OP-061")]
+    Op061,
+    /// Change of the code hashes between the first and the second validation
+    #[strum(serialize = "COD-010")]
+    Cod010,
+    /// Access to the associated storage of the account during its deployment requires a staked
+    /// factory
+    #[strum(serialize = "STO-022")]
+    Sto022,
+    /// Access to the entity's own storage requires the entity to be staked
+    #[strum(serialize = "STO-031")]
+    Sto031,
+    /// Access to the storage associated with the entity requires the entity to be staked
+    #[strum(serialize = "STO-032")]
+    Sto032,
+    /// Access to any other storage of non-entity contracts (read-only and requires the entity to
+    /// be staked)
+    #[strum(serialize = "STO-033")]
+    Sto033,
+    /// Entity address used as an account in another user operation in the mempool
+    #[strum(serialize = "STO-040")]
+    Sto040,
+    /// Banned entities
+    #[strum(serialize = "SREP-020")]
+    Srep020,
+    /// Throttled entities
+    #[strum(serialize = "SREP-030")]
+    Srep030,
+    /// Limit of the user operations of an unstaked sender in the mempool
+    #[strum(serialize = "UREP-010")]
+    Urep010,
+    /// Limit of the user operations of other unstaked entities in the mempool
+    #[strum(serialize = "UREP-020")]
+    Urep020,
+    /// Context returned by an unstaked paymaster
+    #[strum(serialize = "EREP-050")]
+    Erep050,
+    /// The sender is deployed or the init code is provided (but not both)
+    #[strum(serialize = "SENDER")]
+    Sender,
+    /// Verification gas limit and pre-verification gas
+    #[strum(serialize = "VERIFICATION-GAS")]
+    VerificationGas,
+    /// Call gas limit covers a CALL with non-zero value
+    #[strum(serialize = "CALL-GAS")]
+    CallGas,
+    /// Max fee and max priority fee per gas
+    #[strum(serialize = "MAX-FEE")]
+    MaxFee,
+    /// The paymaster is deployed and its deposit covers the prefund
+    #[strum(serialize = "PAYMASTER")]
+    Paymaster,
+    /// Signature validation result of the simulation
+    #[strum(serialize = "SIGNATURE")]
+    Signature,
+    /// Validity time range of the user operation
+    #[strum(serialize = "TIMESTAMP")]
+    Timestamp,
+    /// Verification gas limit leaves the extra gas over the gas used in the validation
+    #[strum(serialize = "VERIFICATION-EXTRA-GAS")]
+    VerificationExtraGas,
+}
+
+/// Configuration file of the validation rules, e.g.
+///
+/// ```yaml
+/// rules:
+///   OP-041: false
+///   STO-040: false
+///   SIGNATURE: false
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRulesConfig {
+    /// Whether the rule (by its id) is enabled
+    #[serde(default)]
+    pub rules: HashMap<String, bool>,
+}
+
+/// Validation rules the checks enforce (all rules are enabled by default)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationRules {
+    disabled: HashSet<ValidationRule>,
+}
+
+impl ValidationRules {
+    /// Whether the rule is enforced
+    pub fn is_enabled(&self, rule: ValidationRule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+
+    /// Stops enforcing the rule
+    pub fn disable(&mut self, rule: ValidationRule) {
+        self.disabled.insert(rule);
+    }
+
+    /// Enforces the rule again
+    pub fn enable(&mut self, rule: ValidationRule) {
+        self.disabled.remove(&rule);
+    }
+
+    /// The rules that are not enforced
+    pub fn disabled(&self) -> &HashSet<ValidationRule> {
+        &self.disabled
+    }
+}
+
+impl TryFrom<ValidationRulesConfig> for ValidationRules {
+    type Error = eyre::Error;
+
+    fn try_from(config: ValidationRulesConfig) -> Result<Self, Self::Error> {
+        let mut rules = Self::default();
+        for (id, enabled) in config.rules {
+            let rule = ValidationRule::from_str(&id)
+                .map_err(|_| eyre::eyre!("Unknown validation rule {id}"))?;
+            if !enabled {
+                rules.disable(rule);
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// Loads the validation rules from a YAML file (unknown rule ids are rejected)
+///
+/// # Arguments
+/// * `path` - Path of the YAML file with the [configuration](ValidationRulesConfig)
+///
+/// # Returns
+/// * `ValidationRules` - The validation rules
+pub fn load_validation_rules(path: &Path) -> eyre::Result<ValidationRules> {
+    let content = std::fs::read_to_string(path)?;
+    let config: ValidationRulesConfig = serde_yaml::from_str(&content)?;
+    config.try_into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_validation_rules() {
+        let config: ValidationRulesConfig = serde_yaml::from_str(
+            r#"
+rules:
+  OP-041: false
+  STO-040: false
+  OP-011: true
+  CALL-GAS: false
+"#,
+        )
+        .unwrap();
+        let rules = ValidationRules::try_from(config).unwrap();
+
+        assert!(!rules.is_enabled(ValidationRule::Op041));
+        assert!(!rules.is_enabled(ValidationRule::Sto040));
+        assert!(rules.is_enabled(ValidationRule::Op011));
+        assert!(rules.is_enabled(ValidationRule::Cod010));
+        assert!(!rules.is_enabled(ValidationRule::CallGas));
+        assert!(rules.is_enabled(ValidationRule::Signature));
+        assert_eq!(ValidationRule::VerificationExtraGas.to_string(), "VERIFICATION-EXTRA-GAS");
+        assert_eq!(ValidationRule::Sto040.to_string(), "STO-040");
+
+        let config: ValidationRulesConfig =
+            serde_yaml::from_str("rules:\n  OP-999: false\n").unwrap();
+        assert!(ValidationRules::try_from(config).is_err());
+    }
+}
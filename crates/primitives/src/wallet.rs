@@ -7,7 +7,14 @@ use ethers::{
     types::Address,
 };
 use expanded_pathbuf::ExpandedPathBuf;
-use std::fs;
+use std::{fs, ops::Range};
+
+/// Derivation path index of the Flashbots signing key
+const FLASHBOTS_KEY_INDEX: u32 = 1;
+
+fn derivation_path(index: u32) -> String {
+    format!("m/44'/60'/0'/0/{index}")
+}
 
 /// Wrapper around ethers wallet
 #[derive(Clone, Debug)]
@@ -16,6 +23,8 @@ pub struct Wallet {
     pub signer: ethers::signers::Wallet<SigningKey>,
     /// Flashbots signing key of the wallet
     pub flashbots_signer: Option<ethers::signers::Wallet<SigningKey>>,
+    /// Additional signing keys the bundles are sent with (next to `signer`)
+    pub additional_signers: Vec<ethers::signers::Wallet<SigningKey>>,
 }
 
 impl Wallet {
@@ -59,9 +68,14 @@ impl Wallet {
             Ok(Self {
                 signer: wallet.with_chain_id(chain_id),
                 flashbots_signer: Some(flashbots_wallet.with_chain_id(chain_id)),
+                additional_signers: vec![],
             })
         } else {
-            Ok(Self { signer: wallet.with_chain_id(chain_id), flashbots_signer: None })
+            Ok(Self {
+                signer: wallet.with_chain_id(chain_id),
+                flashbots_signer: None,
+                additional_signers: vec![],
+            })
         }
     }
 
@@ -81,29 +95,28 @@ impl Wallet {
         chain_id: u64,
         flashbots_key: bool,
     ) -> eyre::Result<Self> {
-        let mnemonic_phrase = fs::read_to_string(path)?;
-
-        let wallet_builder = MnemonicBuilder::<English>::default().phrase(mnemonic_phrase.trim());
-
-        let wallet = wallet_builder
-            .clone()
-            .derivation_path("m/44'/60'/0'/0/0")
-            .expect("Failed to derive wallet")
-            .build()?;
-
-        if flashbots_key {
-            let flashbots_wallet = wallet_builder
-                .derivation_path("m/44'/60'/0'/0/1")
-                .expect("Failed to derive wallet")
-                .build()?;
+        Self::from_file_with_indices(path, 0..1, chain_id, flashbots_key)
+    }
 
-            Ok(Self {
-                signer: wallet.with_chain_id(chain_id),
-                flashbots_signer: Some(flashbots_wallet.with_chain_id(chain_id)),
-            })
-        } else {
-            Ok(Self { signer: wallet.with_chain_id(chain_id), flashbots_signer: None })
-        }
+    /// Create a new wallet with several signing keys from the given file containing the mnemonic
+    /// phrase
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file where the mnemonic phrase is stored
+    /// * `indices` - The derivation path indices of the signing keys (the first one is `signer`)
+    /// * `chain_id` - The chain id of the blockchain network to be used
+    /// * `flashbots_key` - Whether to create a Flashbots key
+    ///
+    /// # Returns
+    /// * `Self` - A new `Wallet` instance
+    pub fn from_file_with_indices(
+        path: ExpandedPathBuf,
+        indices: Range<u32>,
+        chain_id: u64,
+        flashbots_key: bool,
+    ) -> eyre::Result<Self> {
+        let mnemonic_phrase = fs::read_to_string(path)?;
+        Self::from_phrase_with_indices(mnemonic_phrase.trim(), indices, chain_id, flashbots_key)
     }
 
     /// Create a new wallet from the given mnemonic phrase
@@ -118,26 +131,92 @@ impl Wallet {
     /// # Returns
     /// * `Self` - A new `Wallet` instance
     pub fn from_phrase(phrase: &str, chain_id: u64, flashbots_key: bool) -> eyre::Result<Self> {
-        let wallet_builder = MnemonicBuilder::<English>::default().phrase(phrase);
+        Self::from_phrase_with_indices(phrase, 0..1, chain_id, flashbots_key)
+    }
 
-        let wallet = wallet_builder
-            .clone()
-            .derivation_path("m/44'/60'/0'/0/0")
-            .expect("Failed to derive wallet")
-            .build()?;
+    /// Create a new wallet with several signing keys from the given mnemonic phrase
+    /// The Flashbots key is derived at index 1, so the index can't be used for signing keys if
+    /// `flashbots_key` is true.
+    ///
+    /// # Arguments
+    /// * `phrase` - The mnemonic phrase
+    /// * `indices` - The derivation path indices of the signing keys (the first one is `signer`)
+    /// * `chain_id` - The chain id of the blockchain network to be used
+    /// * `flashbots_key` - Whether to create a Flashbots key
+    ///
+    /// # Returns
+    /// * `Self` - A new `Wallet` instance
+    pub fn from_phrase_with_indices(
+        phrase: &str,
+        indices: Range<u32>,
+        chain_id: u64,
+        flashbots_key: bool,
+    ) -> eyre::Result<Self> {
+        if indices.is_empty() {
+            return Err(eyre::eyre!("No derivation path indices provided"));
+        }
+        if flashbots_key && indices.contains(&FLASHBOTS_KEY_INDEX) {
+            return Err(eyre::eyre!(
+                "Derivation path index {FLASHBOTS_KEY_INDEX} is reserved for the Flashbots key"
+            ));
+        }
 
-        if flashbots_key {
-            let flashbots_wallet = wallet_builder
-                .derivation_path("m/44'/60'/0'/0/1")
-                .expect("Failed to derive wallet")
-                .build()?;
-            Ok(Self {
-                signer: wallet.with_chain_id(chain_id),
-                flashbots_signer: Some(flashbots_wallet.with_chain_id(chain_id)),
+        let wallet_builder = MnemonicBuilder::<English>::default().phrase(phrase);
+
+        let mut signers = indices
+            .map(|i| {
+                Ok(wallet_builder
+                    .clone()
+                    .derivation_path(&derivation_path(i))?
+                    .build()?
+                    .with_chain_id(chain_id))
             })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let signer = signers.remove(0);
+
+        let flashbots_signer = if flashbots_key {
+            Some(
+                wallet_builder
+                    .derivation_path(&derivation_path(FLASHBOTS_KEY_INDEX))?
+                    .build()?
+                    .with_chain_id(chain_id),
+            )
         } else {
-            Ok(Self { signer: wallet.with_chain_id(chain_id), flashbots_signer: None })
+            None
+        };
+
+        Ok(Self { signer, flashbots_signer, additional_signers: signers })
+    }
+
+    /// Adds signing keys from the given files, each containing a hex-encoded private key
+    ///
+    /// # Arguments
+    /// * `paths` - The paths to the files where the private keys are stored
+    /// * `chain_id` - The chain id of the blockchain network to be used
+    ///
+    /// # Returns
+    /// * `Self` - The `Wallet` instance with the additional signing keys
+    pub fn with_key_files(
+        mut self,
+        paths: Vec<ExpandedPathBuf>,
+        chain_id: u64,
+    ) -> eyre::Result<Self> {
+        for path in paths {
+            let key = fs::read_to_string(path)?;
+            let signer =
+                key.trim().parse::<ethers::signers::Wallet<SigningKey>>()?.with_chain_id(chain_id);
+            if !self.signers().iter().any(|s| s.address() == signer.address()) {
+                self.additional_signers.push(signer);
+            }
         }
+        Ok(self)
+    }
+
+    /// Returns all signing keys the bundles are sent with (`signer` first)
+    pub fn signers(&self) -> Vec<ethers::signers::Wallet<SigningKey>> {
+        std::iter::once(self.signer.clone())
+            .chain(self.additional_signers.iter().cloned())
+            .collect()
     }
 
     /// Signs the user operation
@@ -163,3 +242,28 @@ impl Wallet {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn wallet_with_indices() {
+        let wallet = Wallet::from_phrase_with_indices(KEY_PHRASE, 2..5, 1, true).unwrap();
+        assert_eq!(wallet.signers().len(), 3);
+        assert_eq!(
+            wallet.signer.address(),
+            "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC".parse::<Address>().unwrap()
+        );
+        assert!(wallet.flashbots_signer.is_some());
+
+        // index 1 is reserved for the Flashbots key
+        assert!(Wallet::from_phrase_with_indices(KEY_PHRASE, 0..3, 1, true).is_err());
+        assert_eq!(
+            Wallet::from_phrase_with_indices(KEY_PHRASE, 0..3, 1, false).unwrap().signers().len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{admin_api::AdminApiServer, debug_api::ResponseSuccess, error::JsonRpcError};
+use async_trait::async_trait;
+use jsonrpsee::core::RpcResult;
+use silius_grpc::{uo_pool_client::UoPoolClient, AddPeerRequest, PeerIdRequest};
+use silius_primitives::p2p::PeerRecord;
+use tonic::Request;
+
+/// AdminApiServerImpl implements the `admin` namespace rpc methods trait
+/// [AdminApiServer](AdminApiServer).
+pub struct AdminApiServerImpl {
+    pub uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
+}
+
+#[async_trait]
+impl AdminApiServer for AdminApiServerImpl {
+    /// Adds the peer (lifting its ban) and dials it via the [AddPeerRequest](AddPeerRequest).
+    ///
+    /// # Arguments
+    /// * `enr: String` - The ENR of the peer.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    async fn add_peer(&self, enr: String) -> RpcResult<ResponseSuccess> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        uopool_grpc_client
+            .add_peer(Request::new(AddPeerRequest { enr }))
+            .await
+            .map_err(JsonRpcError::from)?;
+
+        Ok(ResponseSuccess::Ok)
+    }
+
+    /// Disconnects the peer and forgets it via the [PeerIdRequest](PeerIdRequest).
+    ///
+    /// # Arguments
+    /// * `peer_id: String` - The peer id of the peer.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    async fn remove_peer(&self, peer_id: String) -> RpcResult<ResponseSuccess> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        uopool_grpc_client
+            .remove_peer(Request::new(PeerIdRequest { peer_id }))
+            .await
+            .map_err(JsonRpcError::from)?;
+
+        Ok(ResponseSuccess::Ok)
+    }
+
+    /// Bans the peer and disconnects it via the [PeerIdRequest](PeerIdRequest).
+    ///
+    /// # Arguments
+    /// * `peer_id: String` - The peer id of the peer.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    async fn ban_peer(&self, peer_id: String) -> RpcResult<ResponseSuccess> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        uopool_grpc_client
+            .ban_peer(Request::new(PeerIdRequest { peer_id }))
+            .await
+            .map_err(JsonRpcError::from)?;
+
+        Ok(ResponseSuccess::Ok)
+    }
+
+    /// Returns the known peers.
+    ///
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<PeerRecord>>` - An array of [PeerRecords](PeerRecord)
+    async fn peers(&self) -> RpcResult<Vec<PeerRecord>> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let res = uopool_grpc_client
+            .get_peers(Request::new(()))
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(res.peers.into_iter().map(Into::into).collect())
+    }
+}
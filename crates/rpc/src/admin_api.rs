@@ -0,0 +1,46 @@
+pub use crate::admin::AdminApiServerImpl;
+use crate::debug_api::ResponseSuccess;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use silius_primitives::p2p::PeerRecord;
+
+/// The `admin` namespace RPC methods trait (peer management of the p2p network)
+#[rpc(server, namespace = "admin")]
+pub trait AdminApi {
+    /// Adds the peer (lifting its ban) and dials it.
+    ///
+    /// # Arguments
+    /// * `enr: String` - The ENR of the peer.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    #[method(name = "addPeer")]
+    async fn add_peer(&self, enr: String) -> RpcResult<ResponseSuccess>;
+
+    /// Disconnects the peer and forgets it (including its ban).
+    ///
+    /// # Arguments
+    /// * `peer_id: String` - The peer id of the peer.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    #[method(name = "removePeer")]
+    async fn remove_peer(&self, peer_id: String) -> RpcResult<ResponseSuccess>;
+
+    /// Bans the peer and disconnects it. The ban is kept across restarts.
+    ///
+    /// # Arguments
+    /// * `peer_id: String` - The peer id of the peer.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    #[method(name = "banPeer")]
+    async fn ban_peer(&self, peer_id: String) -> RpcResult<ResponseSuccess>;
+
+    /// Returns the known peers.
+    ///
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<PeerRecord>>` - An array of [PeerRecords](PeerRecord)
+    #[method(name = "peers")]
+    async fn peers(&self) -> RpcResult<Vec<PeerRecord>>;
+}
@@ -10,10 +10,11 @@ use jsonrpsee::{
 };
 use silius_grpc::{
     bundler_client::BundlerClient, uo_pool_client::UoPoolClient, AddMempoolRequest,
-    GetAllReputationRequest, GetAllRequest, GetStakeInfoRequest, Mode as GrpcMode, SetModeRequest,
-    SetReputationRequest, SetReputationResult,
+    GetAllReputationRequest, GetAllRequest, GetBundleHistoryRequest, GetStakeInfoRequest,
+    Mode as GrpcMode, SetModeRequest, SetReputationRequest, SetReputationResult,
 };
 use silius_primitives::{
+    bundler::BundleRecord,
     constants::bundler::BUNDLE_INTERVAL,
     reputation::{ReputationEntry, StakeInfoResponse},
     BundlerMode, UserOperation, UserOperationRequest, UserOperationSigned,
@@ -268,4 +269,26 @@ impl DebugApiServer for DebugApiServerImpl {
             Err(s) => Err(JsonRpcError::from(s).into()),
         }
     }
+
+    /// Returns the bundles sent to the entry point (the most recent first) via the
+    /// [GetBundleHistoryRequest](GetBundleHistoryRequest).
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<BundleRecord>>` - An array of [BundleRecords](BundleRecord)
+    async fn get_bundle_history(&self, ep: Address) -> RpcResult<Vec<BundleRecord>> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let req = Request::new(GetBundleHistoryRequest { ep: Some(ep.into()) });
+
+        let res = uopool_grpc_client
+            .get_bundle_history(req)
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(res.records.into_iter().map(Into::into).collect())
+    }
 }
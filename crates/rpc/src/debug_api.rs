@@ -3,6 +3,7 @@ use ethers::types::{Address, H256};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use serde::{Deserialize, Serialize};
 use silius_primitives::{
+    bundler::BundleRecord,
     reputation::{ReputationEntry, StakeInfoResponse},
     BundlerMode, UserOperationRequest,
 };
@@ -127,4 +128,14 @@ pub trait DebugApi {
         address: Address,
         entry_point: Address,
     ) -> RpcResult<StakeInfoResponse>;
+
+    /// Returns the bundles sent to the entry point (the most recent first).
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<BundleRecord>>` - An array of [BundleRecords](BundleRecord)
+    #[method(name = "getBundleHistory")]
+    async fn get_bundle_history(&self, entry_point: Address) -> RpcResult<Vec<BundleRecord>>;
 }
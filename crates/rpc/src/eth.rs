@@ -1,7 +1,7 @@
 use crate::{codes::USER_OPERATION_HASH, error::JsonRpcError, eth_api::EthApiServer};
 use async_trait::async_trait;
 use ethers::{
-    types::{Address, U64},
+    types::{spoof, Address, U64},
     utils::to_checksum,
 };
 use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned};
@@ -111,6 +111,8 @@ impl EthApiServer for EthApiServerImpl {
     /// * `user_operation: [UserOperation](UserOperationRequest)` - User operation for which to
     ///   estimate the gas.
     /// * `entry_point: Address` - The address of the entry point.
+    /// * `state_override: Option<spoof::State>` - Geth-style state override applied to the
+    ///   simulations.
     ///
     /// # Returns
     /// * `RpcResult<UserOperationGasEstimation>` - The
@@ -119,6 +121,7 @@ impl EthApiServer for EthApiServerImpl {
         &self,
         uo: UserOperationRequest,
         ep: Address,
+        state_override: Option<spoof::State>,
     ) -> RpcResult<UserOperationGasEstimation> {
         let mut uopool_grpc_client = self.uopool_grpc_client.clone();
 
@@ -140,6 +143,10 @@ impl EthApiServer for EthApiServerImpl {
                     .into(),
                 ),
                 ep: Some(ep.into()),
+                state_override: match state_override {
+                    Some(state) => serde_json::to_string(&state).map_err(JsonRpcError::from)?,
+                    None => String::new(),
+                },
             });
 
         let res = uopool_grpc_client
@@ -1,5 +1,5 @@
 pub use crate::eth::EthApiServerImpl;
-use ethers::types::{Address, U64};
+use ethers::types::{spoof, Address, U64};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use silius_primitives::{
     UserOperationByHash, UserOperationGasEstimation, UserOperationHash, UserOperationReceipt,
@@ -47,6 +47,8 @@ pub trait EthApi {
     /// * `user_operation: [UserOperation](UserOperationRequest)` - User operation for which to
     ///   estimate the gas.
     /// * `entry_point: Address` - The address of the entry point.
+    /// * `state_override: Option<spoof::State>` - Geth-style state override (balance, code, storage
+    ///   per address), e.g., to estimate for an unfunded or undeployed account.
     ///
     /// # Returns
     /// * `RpcResult<UserOperationGasEstimation>` - The estimated gas for the user operation.
@@ -55,6 +57,7 @@ pub trait EthApi {
         &self,
         user_operation: UserOperationRequest,
         entry_point: Address,
+        state_override: Option<spoof::State>,
     ) -> RpcResult<UserOperationGasEstimation>;
 
     /// Retrieve the receipt of a user operation.
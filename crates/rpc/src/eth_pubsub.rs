@@ -0,0 +1,93 @@
+use crate::{error::JsonRpcError, eth_pubsub_api::EthPubSubApiServer};
+use async_trait::async_trait;
+use jsonrpsee::{
+    core::SubscriptionResult,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject},
+    PendingSubscriptionSink, SubscriptionMessage,
+};
+use silius_grpc::{uo_pool_client::UoPoolClient, SubscribeUserOperationEventsRequest};
+use silius_primitives::{UserOperationEvent, UserOperationHash, UserOperationStatus};
+use tonic::Request;
+
+/// Subscription to the user operations added to the mempool
+const NEW_USER_OPERATIONS: &str = "newUserOperations";
+/// Subscription to all lifecycle events of a single user operation
+const USER_OPERATION_STATUS: &str = "userOperationStatus";
+
+/// EthPubSubApiServerImpl implements the `eth` namespace subscriptions trait
+/// [EthPubSubApiServer](EthPubSubApiServer).
+pub struct EthPubSubApiServerImpl {
+    /// The [UoPool gRPC client](UoPoolClient).
+    pub uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
+}
+
+#[async_trait]
+impl EthPubSubApiServer for EthPubSubApiServerImpl {
+    /// Subscribe to the lifecycle events of the user operations.
+    ///
+    /// # Arguments
+    /// * `pending: PendingSubscriptionSink` - The pending subscription.
+    /// * `kind: String` - The kind of the subscription.
+    /// * `hash: Option<UserOperationHash>` - The hash of the user operation.
+    ///
+    /// # Returns
+    /// * `SubscriptionResult` - Ok once the subscriber or the user operation pool is gone.
+    async fn subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: String,
+        hash: Option<UserOperationHash>,
+    ) -> SubscriptionResult {
+        let hash = match (kind.as_str(), hash) {
+            (NEW_USER_OPERATIONS, _) => None,
+            (USER_OPERATION_STATUS, Some(hash)) => Some(hash),
+            (USER_OPERATION_STATUS, None) => {
+                pending
+                    .reject(ErrorObject::owned(
+                        INVALID_PARAMS_CODE,
+                        "User operation hash is required",
+                        None::<bool>,
+                    ))
+                    .await;
+                return Ok(());
+            }
+            _ => {
+                pending
+                    .reject(ErrorObject::owned(
+                        INVALID_PARAMS_CODE,
+                        format!("Unsupported subscription: {kind}"),
+                        None::<bool>,
+                    ))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+        let req = Request::new(SubscribeUserOperationEventsRequest { hash: hash.map(Into::into) });
+        let mut stream = match uopool_grpc_client.subscribe_user_operation_events(req).await {
+            Ok(res) => res.into_inner(),
+            Err(s) => {
+                pending.reject(JsonRpcError::from(s)).await;
+                return Ok(());
+            }
+        };
+
+        let sink = pending.accept().await?;
+        while let Some(res) = stream.message().await? {
+            let event: UserOperationEvent = serde_json::from_str(&res.data)?;
+            // the user operations of the released bundles are pending again, but they are not new
+            if hash.is_none() &&
+                (event.status != UserOperationStatus::Pending || event.user_operation.is_none())
+            {
+                continue;
+            }
+            if sink.send(SubscriptionMessage::from_json(&event)?).await.is_err() {
+                // the subscriber is gone
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
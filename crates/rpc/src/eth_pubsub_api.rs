@@ -0,0 +1,22 @@
+pub use crate::eth_pubsub::EthPubSubApiServerImpl;
+use jsonrpsee::{core::SubscriptionResult, proc_macros::rpc};
+use silius_primitives::{UserOperationEvent, UserOperationHash};
+
+/// The `eth` namespace subscriptions to the lifecycle events of the user operations (available only
+/// over WebSocket)
+#[rpc(server, namespace = "eth")]
+pub trait EthPubSubApi {
+    /// Subscribe to the lifecycle events of the user operations.
+    ///
+    /// # Arguments
+    /// * `kind: String` - The kind of the subscription: `newUserOperations` (user operations added
+    ///   to the mempool) or `userOperationStatus` (all events of a single user operation).
+    /// * `hash: Option<UserOperationHash>` - The hash of the user operation (required for the
+    ///   `userOperationStatus` subscription).
+    ///
+    /// # Returns
+    /// * `SubscriptionResult` - The [events](UserOperationEvent) are sent as subscription
+    ///   notifications.
+    #[subscription(name = "subscribe" => "subscription", unsubscribe = "unsubscribe", item = UserOperationEvent)]
+    async fn subscribe(&self, kind: String, hash: Option<UserOperationHash>) -> SubscriptionResult;
+}
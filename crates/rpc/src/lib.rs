@@ -1,14 +1,20 @@
 //! Silius RPC crate provides an interface for handling RPC methods according to the ERC-4337 spec.
 #![allow(dead_code)]
 
+mod admin;
+pub mod admin_api;
 pub mod codes;
 mod debug;
 pub mod debug_api;
 mod error;
 mod eth;
 pub mod eth_api;
+mod eth_pubsub;
+pub mod eth_pubsub_api;
 pub mod middleware;
 mod rpc;
+mod silius;
+pub mod silius_api;
 mod web3;
 pub mod web3_api;
 
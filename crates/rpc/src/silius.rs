@@ -0,0 +1,71 @@
+use crate::{error::JsonRpcError, silius_api::SiliusApiServer};
+use async_trait::async_trait;
+use ethers::types::Address;
+use jsonrpsee::core::RpcResult;
+use silius_grpc::{
+    uo_pool_client::UoPoolClient, GetUserOperationGasPriceRequest, UserOperationHashRequest,
+};
+use silius_mempool::{UserOperationGasPrice, UserOperationStatusResponse};
+use silius_primitives::UserOperationHash;
+use tonic::Request;
+
+/// SiliusApiServerImpl implements the `silius` namespace rpc methods trait
+/// [SiliusApiServer](SiliusApiServer).
+pub struct SiliusApiServerImpl {
+    /// The [UoPool gRPC client](UoPoolClient).
+    pub uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
+}
+
+#[async_trait]
+impl SiliusApiServer for SiliusApiServerImpl {
+    /// Get the status of a user operation via the
+    /// [UserOperationHashRequest](UserOperationHashRequest).
+    ///
+    /// # Arguments
+    /// * `user_operation_hash: UserOperationHash` - The hash of the user operation.
+    ///
+    /// # Returns
+    /// * `RpcResult<Option<UserOperationStatusResponse>>` - The status of the user operation, null
+    ///   if the user operation is unknown.
+    async fn get_user_operation_status(
+        &self,
+        user_operation_hash: UserOperationHash,
+    ) -> RpcResult<Option<UserOperationStatusResponse>> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let req = Request::new(UserOperationHashRequest { hash: Some(user_operation_hash.into()) });
+        match uopool_grpc_client.get_user_operation_status(req).await {
+            Ok(res) => Ok(Some(
+                serde_json::from_str::<UserOperationStatusResponse>(&res.into_inner().data)
+                    .map_err(JsonRpcError::from)?,
+            )),
+            Err(s) => match s.code() {
+                tonic::Code::NotFound => Ok(None),
+                _ => Err(JsonRpcError::from(s).into()),
+            },
+        }
+    }
+
+    /// Get the fee recommendation (slow, standard and fast tiers) of the entry point's mempool.
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<UserOperationGasPrice>` - The slow, standard and fast fee tiers.
+    async fn get_user_operation_gas_price(
+        &self,
+        entry_point: Address,
+    ) -> RpcResult<UserOperationGasPrice> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let req = Request::new(GetUserOperationGasPriceRequest { ep: Some(entry_point.into()) });
+        let res = uopool_grpc_client
+            .get_user_operation_gas_price(req)
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(serde_json::from_str::<UserOperationGasPrice>(&res.data).map_err(JsonRpcError::from)?)
+    }
+}
@@ -0,0 +1,39 @@
+pub use crate::silius::SiliusApiServerImpl;
+use ethers::types::Address;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use silius_mempool::{UserOperationGasPrice, UserOperationStatusResponse};
+use silius_primitives::UserOperationHash;
+
+/// The `silius` namespace RPC methods trait (bundler specific extensions)
+#[rpc(server, namespace = "silius")]
+pub trait SiliusApi {
+    /// Get the status of a user operation: pending, bundled, included, reverted or dropped (with
+    /// the reason it was removed from the mempool).
+    ///
+    /// # Arguments
+    /// * `user_operation_hash: UserOperationHash` - The hash of the user operation.
+    ///
+    /// # Returns
+    /// * `RpcResult<Option<UserOperationStatusResponse>>` - The status of the user operation, null
+    ///   if the user operation is unknown.
+    #[method(name = "getUserOperationStatus")]
+    async fn get_user_operation_status(
+        &self,
+        user_operation_hash: UserOperationHash,
+    ) -> RpcResult<Option<UserOperationStatusResponse>>;
+
+    /// Get the fees of user operations the bundler currently accepts (slow) and prioritises
+    /// (standard, fast), based on the current base fee, the minimum priority fee of the bundler
+    /// and the fees of the user operations in the mempool.
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<UserOperationGasPrice>` - The slow, standard and fast fee tiers.
+    #[method(name = "getUserOperationGasPrice")]
+    async fn get_user_operation_gas_price(
+        &self,
+        entry_point: Address,
+    ) -> RpcResult<UserOperationGasPrice>;
+}
@@ -98,7 +98,7 @@ async fn estimate_with_zero() -> eyre::Result<()> {
     let uo_wallet = UoWallet::from_phrase(SEED_PHRASE, chain_id, false)?;
     let user_op = uo_wallet.sign_user_operation(&user_op, &entry_point.address, chain_id).await?;
 
-    let estimate = uopool.estimate_user_operation_gas(&user_op).await.expect("estimate done");
+    let estimate = uopool.estimate_user_operation_gas(&user_op, None).await.expect("estimate done");
     let user_op = UserOperationSigned {
         verification_gas_limit: estimate.verification_gas_limit,
         call_gas_limit: estimate.call_gas_limit,